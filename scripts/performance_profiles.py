@@ -255,7 +255,7 @@ if __name__ == '__main__':
     algos = commons.infer_algorithms_from_dataframe(df)
     instances = df.instance.unique()
     colors = commons.construct_new_color_mapping(algos)
-    final_portfolio_name = set(algos).intersection(set(["final_portfolio", "final_portfolio_opt"])).pop()
+    final_portfolio_name = next(algo for algo in algos if algo.startswith("final_portfolio"))
     for algo in algos:
         if algo == final_portfolio_name: continue
         plot_name_prefix = f"{plot_name}_{final_portfolio_name}_{algo}_{load_field}"
@@ -1,14 +1,17 @@
 use std::ops::Range;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use polars::prelude::*;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use portfolio_solver::csv_parser;
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
-use rand_distr::Normal;
+use rand_distr::{LogNormal, Normal};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -32,18 +35,42 @@ struct DataGeneratorConfig {
     out_path: PathBuf,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Preset {
+    /// Sequential and parallel algorithms with different strengths on three
+    /// instance families and a portfolio_solver config for them
+    Showcase,
+}
+
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Args {
     /// Path to the json config
-    #[arg(short, long)]
-    pub config: PathBuf,
+    #[arg(short, long, required_unless_present = "preset")]
+    pub config: Option<PathBuf>,
+    /// Generate a bundled example dataset instead of the one of the config
+    #[arg(short, long, value_enum, conflicts_with = "config")]
+    pub preset: Option<Preset>,
+    /// Output directory of the preset
+    #[arg(short, long, default_value = "data/showcase")]
+    pub out_dir: PathBuf,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let config: DataGeneratorConfig =
-        serde_json::from_str(&fs::read_to_string(args.config)?)?;
+    if let Some(Preset::Showcase) = args.preset {
+        let config = write_showcase(&args.out_dir, SHOWCASE_SEED)?;
+        println!(
+            "Wrote the showcase dataset to {}, run it with\n  \
+             cargo run --release --bin portfolio_solver -- -c {}",
+            args.out_dir.display(),
+            config.display()
+        );
+        return Ok(());
+    }
+    let config: DataGeneratorConfig = serde_json::from_str(
+        &fs::read_to_string(args.config.expect("required by clap"))?,
+    )?;
     let out_path = config.out_path.clone();
     let dataframe = generate_data(config)?;
     csv_parser::df_to_normalized_csv(dataframe, out_path)?;
@@ -91,13 +118,195 @@ fn generate_data(config: DataGeneratorConfig) -> Result<LazyFrame> {
     Ok(concat(algorithm_dataframes, false, false)?)
 }
 
+const SHOWCASE_SEED: u64 = 42;
+const SHOWCASE_FAMILIES: [&str; 3] = ["mesh", "social", "circuit"];
+const SHOWCASE_INSTANCES_PER_FAMILY: usize = 12;
+const SHOWCASE_RUNS_PER_INSTANCE: usize = 10;
+
+/// An algorithm of the showcase dataset, its running times and qualities are
+/// relative to the base running time and base quality of an instance
+struct ShowcaseAlgorithm {
+    name: &'static str,
+    threads: &'static [u32],
+    /// Running time on one thread
+    time: f64,
+    /// The running time on `t` threads is `time / t^speedup`
+    speedup: f64,
+    /// Quality per instance family
+    quality: [f64; 3],
+    /// Relative noise of the quality of a run
+    noise: f64,
+    /// Probability of an invalid run (timeout, imbalance) per instance family
+    failure: [f64; 3],
+}
+
+const SHOWCASE_ALGORITHMS: [ShowcaseAlgorithm; 4] = [
+    // fast and noisy, repetitions pay off
+    ShowcaseAlgorithm {
+        name: "greedy",
+        threads: &[1],
+        time: 0.3,
+        speedup: 0.0,
+        quality: [1.10, 1.20, 1.15],
+        noise: 0.08,
+        failure: [0.0, 0.0, 0.0],
+    },
+    // specialist for meshes
+    ShowcaseAlgorithm {
+        name: "refine",
+        threads: &[1],
+        time: 1.0,
+        speedup: 0.0,
+        quality: [0.97, 1.10, 1.02],
+        noise: 0.02,
+        failure: [0.0, 0.05, 0.0],
+    },
+    // parallel, slightly worse with more threads and unstable on social
+    // networks
+    ShowcaseAlgorithm {
+        name: "multilevel",
+        threads: &[1, 2, 4, 8],
+        time: 1.0,
+        speedup: 0.8,
+        quality: [1.02, 0.98, 1.00],
+        noise: 0.04,
+        failure: [0.0, 0.1, 0.0],
+    },
+    // best quality, but too slow for the slowdown ratio of the config
+    ShowcaseAlgorithm {
+        name: "exhaustive",
+        threads: &[1],
+        time: 10.0,
+        speedup: 0.0,
+        quality: [0.92, 0.92, 0.92],
+        noise: 0.01,
+        failure: [0.2, 0.3, 0.2],
+    },
+];
+
+/// Normalized data of the showcase dataset, one data frame per file name
+///
+/// The instances of every family have log-uniform base qualities and running
+/// times, the runs of the algorithms scale them by the factors of
+/// [`SHOWCASE_ALGORITHMS`] with log-normal noise.
+fn generate_showcase(seed: u64) -> Result<Vec<(String, DataFrame)>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let instances = SHOWCASE_FAMILIES
+        .iter()
+        .enumerate()
+        .flat_map(|(family, name)| {
+            (0..SHOWCASE_INSTANCES_PER_FAMILY)
+                .map(move |i| (family, format!("{name}_{i:02}")))
+        })
+        .map(|(family, name)| {
+            let quality = rng.gen_range(100f64.ln()..10000f64.ln()).exp();
+            let time = rng.gen_range(10f64.ln()..1000f64.ln()).exp();
+            (family, name, quality, time)
+        })
+        .collect::<Vec<_>>();
+    let time_noise = LogNormal::new(0.0, 0.1)?;
+    let mut files = Vec::new();
+    for (algo_idx, algo) in SHOWCASE_ALGORITHMS.iter().enumerate() {
+        let quality_noise = LogNormal::new(0.0, algo.noise)?;
+        for &threads in algo.threads {
+            // every algorithm has its own stream to keep the others stable
+            let mut rng = ChaCha8Rng::seed_from_u64(
+                seed + 1 + (algo_idx * 64) as u64 + threads as u64,
+            );
+            let runs = instances.len() * SHOWCASE_RUNS_PER_INSTANCE;
+            let (mut names, mut qualities, mut times, mut valid) = (
+                Vec::with_capacity(runs),
+                Vec::with_capacity(runs),
+                Vec::with_capacity(runs),
+                Vec::with_capacity(runs),
+            );
+            for (family, name, quality, time) in &instances {
+                let thread_penalty = 1.0 + 0.01 * (threads as f64).log2();
+                for _ in 0..SHOWCASE_RUNS_PER_INSTANCE {
+                    names.push(name.clone());
+                    qualities.push(
+                        (quality
+                            * algo.quality[*family]
+                            * thread_penalty
+                            * quality_noise.sample(&mut rng))
+                        .round()
+                        .max(1.0),
+                    );
+                    times.push(
+                        time * algo.time / (threads as f64).powf(algo.speedup)
+                            * time_noise.sample(&mut rng),
+                    );
+                    valid.push(!rng.gen_bool(algo.failure[*family]));
+                }
+            }
+            let file_name = match algo.threads.len() {
+                1 => format!("{}.csv", algo.name),
+                _ => format!("{}_{threads}.csv", algo.name),
+            };
+            files.push((
+                file_name,
+                df! {
+                    "algorithm" => vec![algo.name; runs],
+                    "num_threads" => vec![threads as i64; runs],
+                    "instance" => names,
+                    "quality" => qualities,
+                    "time" => times,
+                    "valid" => valid,
+                }?,
+            ));
+        }
+    }
+    Ok(files)
+}
+
+/// Write the showcase dataset, its instance list and a portfolio_solver
+/// config to `out_dir`, returns the path of the config
+fn write_showcase(out_dir: &Path, seed: u64) -> Result<PathBuf> {
+    fs::create_dir_all(out_dir)?;
+    let mut paths = Vec::new();
+    let mut instances = Vec::new();
+    for (file_name, df) in generate_showcase(seed)? {
+        if instances.is_empty() {
+            instances = df["instance"]
+                .unique_stable()?
+                .utf8()?
+                .into_no_null_iter()
+                .map(String::from)
+                .collect();
+        }
+        let path = out_dir.join(file_name);
+        csv_parser::df_to_normalized_csv(df.lazy(), path.clone())?;
+        paths.push(path);
+    }
+    let graphs = out_dir.join("instances.csv");
+    csv_parser::df_to_normalized_csv(
+        df! { "instance" => instances }?.lazy(),
+        graphs.clone(),
+    )?;
+    let config = serde_json::json!({
+        "files": paths,
+        "graphs": graphs,
+        "num_cores": 8,
+        "slowdown_ratio": 0.8,
+        "num_seeds": SHOWCASE_RUNS_PER_INSTANCE,
+        "out_dir": "results/showcase",
+        "timeout": 60.0,
+    });
+    let config_path = out_dir.join("config.json");
+    fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+    Ok(config_path)
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
+    use portfolio_solver::csv_parser;
+    use portfolio_solver::datastructures::Algorithm;
+
     use crate::{
-        generate_data, AlgorithmConfig, DataGeneratorConfig,
-        InstanceRangeConfig,
+        generate_data, generate_showcase, write_showcase, AlgorithmConfig,
+        DataGeneratorConfig, InstanceRangeConfig,
     };
 
     #[test]
@@ -141,4 +350,49 @@ mod tests {
         let data = generate_data(config).unwrap().collect().unwrap();
         assert_eq!(data.height(), 20);
     }
+
+    #[test]
+    fn test_showcase() {
+        let files = generate_showcase(42).unwrap();
+        assert_eq!(files.len(), 7);
+        assert_eq!(files[2].0, "multilevel_1.csv");
+        assert!(files.iter().all(|(_, df)| df.height() == 360));
+        // the generated data is reproducible
+        assert!(files[0].1.frame_equal(&generate_showcase(42).unwrap()[0].1));
+
+        let out_dir = std::env::temp_dir().join("portfolio_solver_showcase");
+        let config = write_showcase(&out_dir, 42).unwrap();
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(config).unwrap())
+                .unwrap();
+        let paths = config["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|path| PathBuf::from(path.as_str().unwrap()))
+            .collect::<Vec<_>>();
+        let df = csv_parser::parse_normalized_csvs(
+            &paths,
+            Some(PathBuf::from(config["graphs"].as_str().unwrap())),
+            8,
+        )
+        .unwrap();
+        let data = csv_parser::Data::from_normalized_dataframe(
+            df,
+            8,
+            config["slowdown_ratio"].as_f64().unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(data.num_instances, 36);
+        // the exhaustive search is filtered by the slowdown ratio
+        assert_eq!(data.num_algorithms, 6);
+        assert_eq!(
+            data.algorithm_id(&Algorithm::new("exhaustive".into(), 1)),
+            None
+        );
+        assert!(data
+            .algorithm_id(&Algorithm::new("multilevel".into(), 8))
+            .is_some());
+    }
 }
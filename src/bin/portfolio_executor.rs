@@ -1,7 +1,11 @@
 use anyhow::Result;
 use clap::Parser;
-use portfolio_solver::{csv_parser, portfolio_simulator};
-use std::{fs, path::PathBuf};
+use itertools::Itertools;
+use polars::prelude::*;
+use portfolio_solver::{
+    csv_parser, datastructures::Portfolio, portfolio_simulator, schema,
+};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 #[path = "../mt_kahypar_parser.rs"]
 mod mt_kahypar_parser;
@@ -12,6 +16,13 @@ pub struct ConfigArgs {
     /// Path to the json config
     #[arg(short, long)]
     pub config: PathBuf,
+    /// Write one csv per portfolio (named after the portfolio) next to the
+    /// configured output file instead of a single csv
+    #[arg(short, long)]
+    pub per_portfolio: bool,
+    /// Override the portfolio names (in order of the portfolios in the config)
+    #[arg(short, long, value_delimiter = ' ', num_args = 0..)]
+    pub names: Option<Vec<String>>,
 }
 
 fn main() -> Result<()> {
@@ -19,29 +30,177 @@ fn main() -> Result<()> {
     let config_path = args.config;
     let config_str = fs::read_to_string(config_path)?;
     let mt_kahypar_parser::PortfolioExecutorConfig {
+        schema,
         files,
-        portfolios,
+        mut portfolios,
         num_seeds,
         num_cores,
         out,
+        topology,
+        instance_filters,
+        instance_weights,
     } = serde_json::from_str(&config_str)?;
+    schema::check(schema, schema::Kind::Executor)?;
+    rename_portfolios(&mut portfolios, args.names)?;
 
-    let df =
-        mt_kahypar_parser::parse_hypergraph_dataframe(&files, None, num_cores)
-            .or_else(|_| {
-                csv_parser::parse_normalized_csvs(&files, None, num_cores)
-            })?
-            .collect()?;
+    let df = mt_kahypar_parser::parse_hypergraph_dataframe(
+        &files, None, num_cores, None,
+    )
+    .or_else(|_| csv_parser::parse_normalized_csvs(&files, None, num_cores))?
+    .collect()?;
     let algorithms = csv_parser::extract_algorithm_columns(&df)?;
-    let simulation = portfolio_simulator::simulation_df(
-        &df,
-        &algorithms,
-        &portfolios,
-        num_seeds,
-        &["instance"],
-        &["algorithm", "num_threads"],
-        num_cores,
+    let (simulation, manifest) =
+        portfolio_simulator::simulation_df_with_manifest(
+            &df,
+            &algorithms,
+            &portfolios,
+            &instance_filters,
+            num_seeds,
+            true,
+            &["instance"],
+            &["algorithm", "num_threads"],
+            num_cores,
+        )?;
+    serde_json::to_writer_pretty(
+        fs::File::create(out.with_extension("manifest.json"))?,
+        &manifest,
+    )?;
+    let core_plans = portfolios
+        .iter()
+        .map(|portfolio| {
+            let plan = portfolio
+                .core_plan_with_topology(num_cores, topology.as_ref())?;
+            Ok((portfolio.name.clone(), plan))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    serde_json::to_writer_pretty(
+        fs::File::create(out.with_extension("core_plan.json"))?,
+        &core_plans,
     )?;
-    csv_parser::df_to_normalized_csv(simulation, out)?;
+    let simulation = simulation.collect()?;
+    csv_parser::df_to_normalized_csv(
+        portfolio_simulator::simulation_summary(
+            &df,
+            simulation.clone().lazy(),
+            &["instance"],
+            &portfolio_simulator::SummaryOptions {
+                instance_weights,
+                ..Default::default()
+            },
+        )?
+        .lazy(),
+        out.with_extension("summary.csv"),
+    )?;
+    if args.per_portfolio {
+        let names = simulation
+            .column("algorithm")?
+            .utf8()?
+            .into_no_null_iter()
+            .unique()
+            .map(String::from)
+            .collect_vec();
+        for (name, file_name) in names.iter().zip(file_names(&names)?) {
+            csv_parser::df_to_normalized_csv(
+                simulation
+                    .clone()
+                    .lazy()
+                    .filter(col("algorithm").eq(lit(name.as_str()))),
+                out.with_file_name(file_name),
+            )?;
+        }
+    } else {
+        csv_parser::df_to_normalized_csv(simulation.lazy(), out)?;
+    }
+    Ok(())
+}
+
+/// Override the names of the `portfolios` with the `names` (in order), the names of all
+/// portfolios must be unique afterwards
+fn rename_portfolios(
+    portfolios: &mut [Portfolio],
+    names: Option<Vec<String>>,
+) -> Result<()> {
+    if let Some(names) = names {
+        if names.len() > portfolios.len() {
+            anyhow::bail!(
+                "Got {} names for {} portfolios",
+                names.len(),
+                portfolios.len()
+            );
+        }
+        for (portfolio, name) in portfolios.iter_mut().zip(names) {
+            portfolio.name = name;
+        }
+    }
+    if let Some(name) = portfolios
+        .iter()
+        .map(|portfolio| &portfolio.name)
+        .duplicates()
+        .next()
+    {
+        anyhow::bail!("The portfolio name {name} is not unique");
+    }
     Ok(())
 }
+
+/// Names of the csv files of the portfolios `names`, which must differ after replacing
+/// characters other than alphanumerics, `-`, `+` and `.` (e.g. path separators) with `_`
+fn file_names(names: &[String]) -> Result<Vec<String>> {
+    let file_names = names
+        .iter()
+        .map(|name| {
+            name.chars()
+                .map(|c| match c.is_alphanumeric() || "-+.".contains(c) {
+                    true => c,
+                    false => '_',
+                })
+                .collect::<String>()
+                + ".csv"
+        })
+        .collect_vec();
+    if let Some(file_name) = file_names.iter().duplicates().next() {
+        anyhow::bail!("Multiple portfolios are written to {file_name}");
+    }
+    Ok(file_names)
+}
+
+#[cfg(test)]
+mod tests {
+    use portfolio_solver::datastructures::Portfolio;
+
+    use crate::{file_names, rename_portfolios};
+
+    #[test]
+    fn test_rename_portfolios() {
+        let mut portfolios =
+            ["final_portfolio", "random_portfolio"].map(|name| Portfolio {
+                name: name.into(),
+                resource_assignments: Vec::new(),
+            });
+        rename_portfolios(&mut portfolios, Some(vec!["a".into()])).unwrap();
+        assert_eq!(portfolios[0].name, "a");
+        assert_eq!(portfolios[1].name, "random_portfolio");
+        assert!(rename_portfolios(
+            &mut portfolios,
+            Some(vec!["b".into(), "b".into()])
+        )
+        .is_err());
+        assert!(rename_portfolios(
+            &mut portfolios,
+            Some(vec!["c".into(), "d".into(), "e".into()])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_names() {
+        let names = ["final portfolio", "../k8/s0.5", "MT-KaHyPar+D"]
+            .map(String::from);
+        assert_eq!(
+            file_names(&names).unwrap(),
+            ["final_portfolio.csv", ".._k8_s0.5.csv", "MT-KaHyPar+D.csv"]
+        );
+        let names = ["final portfolio", "final/portfolio"].map(String::from);
+        assert!(file_names(&names).is_err());
+    }
+}
@@ -1,16 +1,28 @@
 use core::fmt;
 use itertools::Itertools;
 use polars::{prelude::*, series::IsSorted};
-use std::{f64::EPSILON, path::PathBuf};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    f64::EPSILON,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
 
 use anyhow::Result;
+use log::{info, warn};
 
 use crate::datastructures::*;
 
-pub use utils::extract_algorithm_columns;
+pub use utils::{extract_algorithm_columns, gmean_runtimes};
 
 mod utils;
 
+/// Index of an instance in the [`Data`], i.e. in the first dimension of
+/// [`expected_best_quality`](Data::expected_best_quality)
+pub type InstanceId = usize;
+
 /// Input data structure for the solver, parser for nomalized data frame is available.
 pub struct Data {
     /// A list of algorithms to consider for the portfolio
@@ -29,10 +41,19 @@ pub struct Data {
     ///
     /// Dimension 3: Repetitions
     pub expected_best_quality: ndarray::Array3<f64>,
+    /// Marks the cells of [`expected_best_quality`](Data::expected_best_quality) that were not
+    /// measured but imputed with the worst quality (`None` if unknown, e.g. for [`Data::new`])
+    pub imputed: Option<ndarray::Array3<bool>>,
+    /// Names of the instances in the order of their [ids](InstanceId), sorted by name (`None` if
+    /// unknown, e.g. for [`Data::new`])
+    pub instances: Option<Vec<String>>,
     /// number of instances
     pub num_instances: usize,
     /// number of algorithms
     pub num_algorithms: usize,
+    /// Base seed of the sampling of the expected best qualities, see [`DataOptions`] (`None` if
+    /// the data was not sampled)
+    pub sampling_seed: Option<u64>,
 }
 
 impl fmt::Display for Data {
@@ -67,21 +88,90 @@ impl Data {
                 shape,
                 stats.to_vec(),
             )?,
+            imputed: None,
+            instances: None,
             num_instances,
             num_algorithms,
+            sampling_seed: None,
         })
     }
 
     /// Create a new set of input data for [`crate::solver::solve`] from a normalized data frame
+    ///
+    /// The expected best quality of `s` repetitions of an algorithm is the best quality of `s`
+    /// runs sampled with replacement, using the seed `sampling_seed + s`. Different seeds allow to
+    /// study the noise of this preprocessing, the seed is recorded in the data. See
+    /// [`Data::from_normalized_dataframe_with_options`] for less noisy estimates.
     pub fn from_normalized_dataframe(
         df: LazyFrame,
         k: u32,
         slowdown_ratio: f64,
+        sampling_seed: u64,
+    ) -> Result<Self> {
+        Self::from_normalized_dataframe_with_options(
+            df,
+            k,
+            slowdown_ratio,
+            &DataOptions {
+                sampling_seed,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Create a new set of input data for [`crate::solver::solve`] from a normalized data frame,
+    /// see [`DataOptions`] for the interpretation of the qualities and the estimation of the
+    /// expected best qualities
+    pub fn from_normalized_dataframe_with_options(
+        df: LazyFrame,
+        k: u32,
+        slowdown_ratio: f64,
+        options: &DataOptions,
     ) -> Result<Self> {
         let sort_exprs: [Expr; 3] =
             [col("instance"), col("algorithm"), col("num_threads")];
         let sort_options = vec![false; sort_exprs.len()];
 
+        let split_options;
+        let (df, options) = match options.core_classes.is_empty() {
+            true => (df, options),
+            false => {
+                let classes = &options.core_classes;
+                CoreClass::total_cores(classes)?;
+                split_options = DataOptions {
+                    requirements: classes
+                        .iter()
+                        .flat_map(|class| {
+                            options.requirements.iter().map(|algo| Algorithm {
+                                algorithm: class
+                                    .algorithm_name(&algo.algorithm),
+                                startup: algo
+                                    .startup
+                                    .map(|startup| startup / class.speed),
+                                ..algo.clone()
+                            })
+                        })
+                        .collect(),
+                    ..options.clone()
+                };
+                (utils::split_core_classes(df, classes)?, &split_options)
+            }
+        };
+        let df = match (options.quality_direction, options.quality_semantics) {
+            (QualityDirection::Minimize, _) => df,
+            (QualityDirection::Maximize, QualitySemantics::Ratio) => {
+                df.with_column((lit(1.0) / col("quality")).alias("quality"))
+            }
+            (QualityDirection::Maximize, QualitySemantics::Difference) => {
+                df.with_column((lit(0.0) - col("quality")).alias("quality"))
+            }
+        };
+        let df = match options.time_to_target {
+            Some(target) => {
+                utils::time_to_target(df, target, options.quality_semantics)
+            }
+            None => df,
+        };
         let valid_instance_df = utils::filter_algorithms_by_slowdown(
             df.filter(col("valid")),
             slowdown_ratio,
@@ -94,9 +184,45 @@ impl Data {
             std::process::exit(exitcode::DATAERR);
         }
 
+        let (valid_instance_df, dropped) =
+            utils::filter_algorithms_by_coverage(
+                valid_instance_df,
+                options.min_coverage,
+            )?;
+        if !dropped.is_empty() {
+            warn!(
+                "Dropped {} algorithms with runs on fewer than {}% of the instances:\n{}",
+                dropped.len(),
+                options.min_coverage,
+                dropped
+                    .iter()
+                    .map(|(algo, coverage)| format!("{algo}: {coverage:.1}%"))
+                    .join("\n")
+            );
+        }
+        anyhow::ensure!(
+            valid_instance_df.height() > 0,
+            "No algorithm has runs on at least {}% of the instances",
+            options.min_coverage
+        );
+        if let Some(path) = &options.dump_normalized {
+            df_to_normalized_csv(
+                valid_instance_df.clone().lazy(),
+                path.clone(),
+            )?;
+            info!("Wrote the normalized input data to {}", path.display());
+        }
+
         let algorithms = utils::extract_algorithm_columns(&valid_instance_df)?;
         assert!(algorithms.iter().tuple_windows().all(|(a, b)| a <= b));
-        let num_instances = valid_instance_df["instance"].n_unique()?;
+        let instances = valid_instance_df["instance"]
+            .unique()?
+            .sort(false)
+            .utf8()?
+            .into_no_null_iter()
+            .map(String::from)
+            .collect_vec();
+        let num_instances = instances.len();
         let num_algorithms = algorithms.len();
         let best_per_instance_df = utils::best_per_instance(
             valid_instance_df.clone().lazy(),
@@ -107,9 +233,11 @@ impl Data {
             best_per_instance_df["instance"].is_sorted(),
             IsSorted::Ascending
         );
-        let best_per_instance =
+        let mut best_per_instance =
             utils::column_to_f64_array(&best_per_instance_df, "best_quality")?;
-        assert!(best_per_instance.iter().all(|val| val.abs() >= EPSILON));
+        if options.quality_semantics == QualitySemantics::Ratio {
+            assert!(best_per_instance.iter().all(|val| val.abs() >= EPSILON));
+        }
         let best_per_instance_time_df =
             utils::best_per_instance_time(valid_instance_df.clone().lazy())
                 .collect()?;
@@ -122,13 +250,48 @@ impl Data {
             &utils::best_per_instance_count(valid_instance_df.clone())?,
             "count",
         )?;
+        let max_repetitions = options
+            .time_budget
+            .map(|time_budget| {
+                utils::max_repetitions_by_time(
+                    add_startup_times(
+                        valid_instance_df.clone().lazy(),
+                        &options.requirements,
+                    ),
+                    &algorithms,
+                    k,
+                    time_budget,
+                )
+            })
+            .transpose()?;
+        let max_cores = match valid_instance_df
+            .get_column_names()
+            .contains(&"max_cores")
+        {
+            true => Some(utils::max_cores_per_instance(
+                valid_instance_df.clone().lazy(),
+            )?),
+            false => None,
+        };
 
         assert_eq!(
             valid_instance_df["instance"].is_sorted(),
             IsSorted::Ascending
         );
-        let stats_df = utils::stats_by_sampling(valid_instance_df.lazy(), k)?
-            .collect()?;
+        let stats_df = match options.estimator {
+            QualityEstimator::Sampling(rounds) => {
+                utils::stats_by_repeated_sampling(
+                    valid_instance_df.lazy(),
+                    k,
+                    options.sampling_seed,
+                    rounds,
+                )?
+            }
+            QualityEstimator::Exact => {
+                utils::stats_exact(valid_instance_df.lazy(), k)?
+            }
+        }
+        .collect()?;
 
         let clean_df = utils::cleanup_missing_rows(stats_df, k)?
             .lazy()
@@ -141,7 +304,7 @@ impl Data {
             num_instances * num_algorithms * k as usize,
             clean_df.height()
         );
-        let stats: ndarray::Array3<f64> =
+        let mut stats: ndarray::Array3<f64> =
             ndarray::Array3::<f64>::from_shape_vec(
                 shape,
                 clean_df
@@ -150,15 +313,395 @@ impl Data {
                     .into_no_null_iter()
                     .collect::<Vec<f64>>(),
             )?;
-        Ok(Self {
+        let imputed = ndarray::Array3::<bool>::from_shape_vec(
+            shape,
+            clean_df
+                .column("imputed")?
+                .bool()?
+                .into_no_null_iter()
+                .collect::<Vec<bool>>(),
+        )?;
+        if options.quality_semantics == QualitySemantics::Difference {
+            for (mut instance, best) in
+                stats.outer_iter_mut().zip(best_per_instance.iter_mut())
+            {
+                let shift = std::mem::replace(best, 1.0);
+                instance.mapv_inplace(|e_min| 1.0 + e_min - shift);
+            }
+        }
+        let mut data = Self {
             algorithms,
             best_per_instance,
             best_per_instance_count: Some(best_per_instance_count),
             expected_best_quality: stats,
+            imputed: Some(imputed),
+            instances: Some(instances),
             num_instances,
             num_algorithms,
+            sampling_seed: match options.estimator {
+                QualityEstimator::Sampling(_) => Some(options.sampling_seed),
+                QualityEstimator::Exact => None,
+            },
+        };
+        data.set_requirements(&options.requirements);
+        if let Some(max_cores) = max_cores {
+            data.limit_cores(&max_cores)?;
+        }
+        if let Some(max_repetitions) = max_repetitions {
+            data.limit_repetitions(&max_repetitions)?;
+        }
+        Ok(data)
+    }
+
+    /// Limit the cores available on single instances, e.g. for huge instances that only fit into
+    /// memory a few times
+    ///
+    /// `max_cores[i]` is the number of cores available on instance `i`, `None` means no limit.
+    /// Only the copies of an algorithm that fit into these cores contribute to the expected best
+    /// quality on the instance, algorithms with more threads than available cores get the worst
+    /// possible quality and are marked as [imputed](Data::imputed).
+    pub fn limit_cores(&mut self, max_cores: &[Option<u32>]) -> Result<()> {
+        anyhow::ensure!(
+            max_cores.len() == self.num_instances,
+            "Got core limits for {} instances, but the data has {}",
+            max_cores.len(),
+            self.num_instances
+        );
+        for (i, max_cores) in max_cores.iter().enumerate() {
+            let Some(max_cores) = max_cores else {
+                continue;
+            };
+            for j in 0..self.num_algorithms {
+                let copies = max_cores / self.algorithms[j].num_threads;
+                self.cap_repetitions(i, j, copies as usize);
+            }
+        }
+        Ok(())
+    }
+
+    /// Limit the repetitions of every algorithm on every instance, e.g. to the runs that are
+    /// expected to finish within a time budget (see [`DataOptions::time_budget`])
+    ///
+    /// `max_repetitions[(i, j)]` is the number of repetitions of algorithm `j` that can be run on
+    /// instance `i`, further repetitions don't improve the expected best quality. Algorithms that
+    /// can't be run at all get the worst possible quality and are marked as
+    /// [imputed](Data::imputed).
+    pub fn limit_repetitions(
+        &mut self,
+        max_repetitions: &ndarray::Array2<usize>,
+    ) -> Result<()> {
+        anyhow::ensure!(
+            max_repetitions.dim() == (self.num_instances, self.num_algorithms),
+            "Got repetition limits for {:?} instances and algorithms, but the data has {:?}",
+            max_repetitions.dim(),
+            (self.num_instances, self.num_algorithms)
+        );
+        for ((i, j), &copies) in max_repetitions.indexed_iter() {
+            self.cap_repetitions(i, j, copies);
+        }
+        Ok(())
+    }
+
+    /// Use the expected best quality of `copies` repetitions of algorithm `j` on instance `i` for
+    /// all larger numbers of repetitions, the worst possible quality if `copies` is zero
+    fn cap_repetitions(&mut self, i: usize, j: usize, copies: usize) {
+        let num_repetitions = self.expected_best_quality.dim().2;
+        for k in copies..num_repetitions {
+            self.expected_best_quality[(i, j, k)] = match copies {
+                0 => f64::MAX,
+                _ => self.expected_best_quality[(i, j, copies - 1)],
+            };
+            if let Some(imputed) = self.imputed.as_mut() {
+                imputed[(i, j, k)] =
+                    copies == 0 || imputed[(i, j, copies - 1)];
+            }
+        }
+    }
+
+    /// Set the [`memory`](Algorithm::memory), [`cost`](Algorithm::cost) and
+    /// [`startup`](Algorithm::startup) time of every algorithm of the data that is part of
+    /// `requirements`, keeping the values missing in `requirements`
+    pub fn set_requirements(&mut self, requirements: &[Algorithm]) {
+        for algo in self.algorithms.iter_mut() {
+            if let Some(required) = requirements.iter().find(|a| *a == algo) {
+                algo.memory = required.memory.or(algo.memory);
+                algo.cost = required.cost.or(algo.cost);
+                algo.startup = required.startup.or(algo.startup);
+            }
+        }
+    }
+
+    /// Whether the expected best quality of instance `i` and `k + 1` repetitions of algorithm `j`
+    /// was imputed
+    pub fn is_imputed(&self, i: usize, j: usize, k: usize) -> bool {
+        self.imputed
+            .as_ref()
+            .map_or(false, |imputed| imputed[(i, j, k)])
+    }
+
+    /// Every cell of [`expected_best_quality`](Data::expected_best_quality) as instance, algorithm,
+    /// number of repetitions (starting at one) and expected best quality, ordered by instance,
+    /// algorithm and repetitions
+    pub fn iter_cells(
+        &self,
+    ) -> impl Iterator<Item = (InstanceId, &Algorithm, usize, f64)> + '_ {
+        self.expected_best_quality.indexed_iter().map(
+            |((i, j, k), &quality)| (i, &self.algorithms[j], k + 1, quality),
+        )
+    }
+
+    /// Every instance with its name (if [known](Data::instances)) and best quality, ordered by
+    /// [id](InstanceId)
+    pub fn iter_instances(
+        &self,
+    ) -> impl Iterator<Item = (InstanceId, Option<&str>, f64)> + '_ {
+        self.best_per_instance
+            .iter()
+            .enumerate()
+            .map(|(i, &best)| (i, self.instance_name(i), best))
+    }
+
+    /// Name of instance `i`, `None` if the names are unknown or there is no such instance
+    pub fn instance_name(&self, i: InstanceId) -> Option<&str> {
+        self.instances.as_ref()?.get(i).map(String::as_str)
+    }
+
+    /// Id of the instance called `name`, `None` if the names are unknown or there is no such
+    /// instance
+    pub fn instance_id(&self, name: &str) -> Option<InstanceId> {
+        let instances = self.instances.as_ref()?;
+        instances
+            .binary_search_by(|instance| instance.as_str().cmp(name))
+            .ok()
+    }
+
+    /// Ids of all instances by name, empty if the names are unknown
+    pub fn instance_ids(&self) -> BTreeMap<&str, InstanceId> {
+        self.instances
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect()
+    }
+
+    /// Index of `algorithm` in [`algorithms`](Data::algorithms), matched by name and number of
+    /// threads
+    pub fn algorithm_id(&self, algorithm: &Algorithm) -> Option<usize> {
+        self.algorithms.iter().position(|algo| algo == algorithm)
+    }
+
+    /// Objective value of the virtual best solver, which picks the best algorithm for every
+    /// instance
+    ///
+    /// This is the number of instances by construction, since the objective is the sum of the
+    /// quality ratios to the best quality per instance (which are one plus the difference to the
+    /// best quality with [`QualitySemantics::Difference`]).
+    pub fn vbs_objective(&self) -> f64 {
+        self.num_instances as f64
+    }
+
+    /// Objective value of the single best solver, i.e. the best portfolio consisting of as many
+    /// copies of one algorithm as fit on the machine
+    ///
+    /// Infinite if no algorithm fits on the machine.
+    pub fn sbs_objective(&self) -> f64 {
+        let num_cores = self.expected_best_quality.dim().2 as u32;
+        self.algorithms
+            .iter()
+            .enumerate()
+            .filter(|(_, algo)| algo.num_threads <= num_cores)
+            .map(|(j, algo)| {
+                let mut copies = vec![0.0; self.num_algorithms];
+                copies[j] = (num_cores / algo.num_threads) as f64;
+                crate::solver::objective(self, &copies)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Options for [`Data::from_normalized_dataframe_with_options`]
+pub struct DataOptions {
+    /// How qualities are compared to the best quality per instance
+    ///
+    /// With [`QualitySemantics::Difference`], the expected best qualities are shifted to
+    /// `1 + e_min - best_i` and the best quality of every instance is one, so the quality ratios
+    /// of the model are the additive differences to the best quality (plus one) and all
+    /// objectives, quality guarantees and the [virtual best](Data::vbs_objective) work unchanged.
+    pub quality_semantics: QualitySemantics,
+    /// How the expected best quality of repeated runs is estimated, averaging several sampling
+    /// rounds or using the exact expectation reduces the noise in the objective of the model
+    pub estimator: QualityEstimator,
+    /// Base seed of the sampling, samples of size `s` in round `r` use the seed
+    /// `sampling_seed + r * k + s`
+    pub sampling_seed: u64,
+    /// Memory requirements, costs and startup times of the algorithms, see
+    /// [`Data::set_requirements`]
+    ///
+    /// The startup times are added to the running times for the [`time_budget`](Self::time_budget).
+    pub requirements: Vec<Algorithm>,
+    /// Whether smaller or larger qualities are better
+    ///
+    /// With [`QualityDirection::Maximize`], the data holds the reciprocal qualities (with
+    /// [`QualitySemantics::Ratio`]) or the negated qualities (with
+    /// [`QualitySemantics::Difference`]), so the model still minimizes and the quality ratio of
+    /// an instance is `best_i / q_i` or `1 + best_i - q_i`, respectively.
+    pub quality_direction: QualityDirection,
+    /// Drop algorithms with valid runs on fewer than this percentage of the instances instead of
+    /// imputing their missing runs with the worst quality
+    pub min_coverage: f64,
+    /// Write the normalized data frame the expected best qualities are computed from (valid runs
+    /// of the algorithms remaining after all filters) to this csv file
+    pub dump_normalized: Option<PathBuf>,
+    /// Wall-clock budget per instance in the unit of the `time` column, the repetitions of an
+    /// algorithm are limited to those whose expected maximum running time fits the budget, see
+    /// [`Data::limit_repetitions`]
+    pub time_budget: Option<f64>,
+    /// Optimize the expected time to reach a target quality per instance instead of the expected
+    /// best quality, see [`utils::time_to_target`]
+    ///
+    /// The qualities of the valid runs are replaced by their times to target before all filters,
+    /// so the expected best quality of `k` repetitions becomes the expected time until the first
+    /// of them reaches the target (if they run in parallel) and the quality ratios are relative
+    /// to the fastest run reaching the target.
+    pub time_to_target: Option<TimeToTarget>,
+    /// Classes of cores of a heterogeneous machine, every algorithm is split into one algorithm
+    /// per class with the running times divided by the speed of the class, see
+    /// [`utils::split_core_classes`]
+    ///
+    /// The [`requirements`](Self::requirements) refer to the algorithms without class and apply
+    /// to all of their classes, with the startup times divided by the speeds as well. Empty for
+    /// homogeneous cores.
+    pub core_classes: Vec<CoreClass>,
+}
+
+#[derive(Debug, Clone)]
+/// Options for [`parse_normalized_csvs_with_report`]
+pub struct ParseOptions {
+    /// Lines starting with this character are ignored, a leading block of `key: value` comment
+    /// lines is parsed into the [`FileMetadata`] (defaults to `#`)
+    pub comment_char: Option<u8>,
+    /// Trim whitespace and NFC-normalize instance and algorithm names, so that e.g. trailing
+    /// spaces in hand-edited csvs don't create duplicate instances
+    pub normalize_keys: bool,
+    /// Additionally convert instance and algorithm names to lower case (requires
+    /// `normalize_keys`)
+    pub lowercase_keys: bool,
+    /// Tag every row with the path of the file it was read from (column `file`)
+    pub source_column: bool,
+    /// Extract additional columns from the file name of every input file, each named capture
+    /// group becomes a column with the captured value, e.g. `(?P<machine>[^_]+)_.*\.csv` adds a
+    /// column `machine` (null for files that don't match)
+    pub filename_pattern: Option<Regex>,
+    /// How qualities are interpreted, qualities of zero are only replaced by one with
+    /// [`QualitySemantics::Ratio`]
+    pub quality_semantics: QualitySemantics,
+    /// Derive the number of threads from the algorithm name, see [`infer_num_threads`]. Files
+    /// without a `num_threads` column are read with one thread per algorithm before.
+    pub thread_pattern: Option<Regex>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            comment_char: Some(b'#'),
+            normalize_keys: false,
+            lowercase_keys: false,
+            source_column: false,
+            filename_pattern: None,
+            quality_semantics: QualitySemantics::Ratio,
+            thread_pattern: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Metadata from the header of a csv file
+///
+/// The header is the block of comment lines at the beginning of a file, every line of the form
+/// `key: value` is an entry, e.g.
+/// ```csv
+/// # machine: node42
+/// # date: 2023-03-01
+/// # version: 1.2.0
+/// algorithm,num_threads,instance,quality,time,valid
+/// ```
+pub struct FileMetadata {
+    /// Path to the file
+    pub path: PathBuf,
+    /// All `key: value` entries of the header
+    pub entries: BTreeMap<String, String>,
+}
+
+impl FileMetadata {
+    /// Read the metadata header of the file at `path`
+    pub fn from_path(path: &PathBuf, comment_char: u8) -> Result<Self> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let mut entries = BTreeMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            let Some(comment) = line.strip_prefix(comment_char as char) else {
+                break;
+            };
+            if let Some((key, value)) = comment.split_once(':') {
+                entries
+                    .insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        Ok(Self {
+            path: path.clone(),
+            entries,
         })
     }
+
+    /// Machine the results were produced on (entry `machine`)
+    pub fn machine(&self) -> Option<&str> {
+        self.entries.get("machine").map(String::as_str)
+    }
+
+    /// Date of the experiment (entry `date`)
+    pub fn date(&self) -> Option<&str> {
+        self.entries.get("date").map(String::as_str)
+    }
+
+    /// Version of the tool that produced the results (entry `version`)
+    pub fn tool_version(&self) -> Option<&str> {
+        self.entries.get("version").map(String::as_str)
+    }
+}
+
+/// Rows of the input files that were excluded from parsing and keys that were normalized
+pub struct ParseReport {
+    /// Rows with a `quality` or `time` that is not a finite number, with their original values
+    /// and the file they are from (column `file`)
+    pub quarantined: DataFrame,
+    /// Number of quarantined rows per file
+    pub counts: Vec<(PathBuf, usize)>,
+    /// Files that could not be read at all
+    pub failed_files: Vec<PathBuf>,
+    /// Original and normalized instance and algorithm names that were changed by key
+    /// normalization, keys that are equal after normalization are merged
+    pub normalized_keys: Vec<(String, String)>,
+    /// Metadata header of every file that was read
+    pub metadata: Vec<FileMetadata>,
+    /// Sorted instances left after filtering for the desired instances (`None` without a filter)
+    pub instances: Option<Vec<String>>,
+}
+
+impl fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, count) in self.counts.iter().filter(|(_, c)| *c > 0) {
+            writeln!(f, "{}: {count} quarantined rows", path.display())?;
+        }
+        for path in &self.failed_files {
+            writeln!(f, "{}: failed to parse", path.display())?;
+        }
+        for (original, normalized) in &self.normalized_keys {
+            writeln!(f, "{original:?} normalized to {normalized:?}")?;
+        }
+        Ok(())
+    }
 }
 
 /// Read normalized data from multiple input files.
@@ -177,51 +720,210 @@ impl Data {
 /// algo2,2,instance2,41.0,10.3,true
 /// algo3,1,instance1,42.0,10.0,true
 /// ```
+///
+/// An optional column `max_cores`(int) limits the cores available on an instance, e.g. for huge
+/// instances that only fit into memory a few times. The smallest value of an instance counts and
+/// empty values mean no limit, see [`Data::limit_cores`].
+///
+/// Rows with a `quality` or `time` that is not a finite number are skipped with a warning, see
+/// [`parse_normalized_csvs_with_report`].
 pub fn parse_normalized_csvs(
     paths: &[PathBuf],
     desired_instances: Option<PathBuf>,
     num_cores: u32,
 ) -> Result<LazyFrame> {
-    let read_df =
-        |path: &PathBuf, in_fields: &[&'static str]| -> Result<LazyFrame> {
-            let mut dataframe = CsvReader::from_path(path)?
-                .with_comment_char(Some(b'#'))
+    let (df, report) = parse_normalized_csvs_with_report(
+        paths,
+        desired_instances,
+        num_cores,
+        &ParseOptions::default(),
+    )?;
+    if report.quarantined.height() > 0 || !report.failed_files.is_empty() {
+        warn!("Some input rows were skipped:\n{report}");
+    }
+    Ok(df)
+}
+
+/// Read normalized data from multiple input files and report rows that could not be parsed.
+///
+/// See [`parse_normalized_csvs`] for the input format. Instead of failing the whole file, rows
+/// with a `quality` or `time` that is not a finite number (e.g. `inf` or `n/a`) are quarantined
+/// into the [`ParseReport`].
+pub fn parse_normalized_csvs_with_report(
+    paths: &[PathBuf],
+    desired_instances: Option<PathBuf>,
+    num_cores: u32,
+    options: &ParseOptions,
+) -> Result<(LazyFrame, ParseReport)> {
+    let normalized_keys = std::cell::RefCell::new(BTreeSet::new());
+    // the instance filter is read once and shared by all files
+    let instance_filter = match desired_instances
+        .as_ref()
+        .and_then(|filter| utils::get_desired_instances(filter).ok())
+    {
+        Some(instance_filter) if options.normalize_keys => {
+            let lowercase = options.lowercase_keys;
+            Some(
+                instance_filter
+                    .with_column(col("instance").apply(
+                        move |s: Series| {
+                            Ok(s.utf8()?
+                                .into_iter()
+                                .map(|key| {
+                                    key.map(|key| {
+                                        utils::normalize_key(key, lowercase)
+                                    })
+                                })
+                                .collect::<Utf8Chunked>()
+                                .into_series())
+                        },
+                        GetOutput::from_type(DataType::Utf8),
+                    ))
+                    .collect()?,
+            )
+        }
+        Some(instance_filter) => Some(instance_filter.collect()?),
+        None => None,
+    };
+    let max_cores_files = paths
+        .iter()
+        .map(|path| has_column(path, options.comment_char, "max_cores"))
+        .collect_vec();
+    let read_raw_df =
+        |path: &PathBuf, in_fields: &[&'static str]| -> Result<DataFrame> {
+            Ok(CsvReader::from_path(path)?
+                .with_comment_char(options.comment_char)
                 .has_header(true)
                 .with_columns(Some(
                     in_fields.iter().map(|s| s.to_string()).collect_vec(),
                 ))
                 .with_dtypes(Some(&Schema::from(
-                    [Field::new("quality", DataType::Float64)].into_iter(),
+                    [
+                        Field::new("quality", DataType::Utf8),
+                        Field::new("time", DataType::Utf8),
+                    ]
+                    .into_iter(),
                 )))
-                .finish()?
-                .lazy()
-                .filter(col("num_threads").lt_eq(lit(num_cores)))
-                .with_columns([col("quality").apply(
-                    |s: Series| {
-                        Ok(s.f64()?
-                            .into_no_null_iter()
-                            .map(|i| if i.abs() <= EPSILON { 1.0 } else { i })
-                            .collect())
-                    },
-                    GetOutput::from_type(DataType::Float64),
-                )]);
-            match &desired_instances {
-                Some(filter) => {
-                    if let Ok(instance_filter) =
-                        utils::get_desired_instances(filter)
-                    {
-                        dataframe = dataframe.join(
-                            instance_filter,
-                            &[col("instance")],
-                            &[col("instance")],
-                            JoinType::Inner,
-                        );
-                    }
+                .finish()?)
+        };
+    let read_df = |path: &PathBuf,
+                   in_fields: &[&'static str]|
+     -> Result<(LazyFrame, DataFrame)> {
+        let raw_df =
+            match (read_raw_df(path, in_fields), &options.thread_pattern) {
+                (Ok(raw_df), _) => raw_df,
+                (Err(_), Some(_)) => {
+                    let fields = in_fields
+                        .iter()
+                        .copied()
+                        .filter(|field| *field != "num_threads")
+                        .collect_vec();
+                    let mut raw_df = read_raw_df(path, &fields)?;
+                    let threads = Series::new(
+                        "num_threads",
+                        vec![1_i64; raw_df.height()],
+                    );
+                    raw_df.with_column(threads)?;
+                    raw_df
                 }
-                None => (),
+                (Err(err), None) => return Err(err),
+            };
+        let mut raw_df = match &options.thread_pattern {
+            Some(pattern) => {
+                infer_num_threads(raw_df.lazy(), pattern).collect()?
+            }
+            None => raw_df,
+        };
+        // files without core limits get an empty column to stack with the others
+        if max_cores_files.contains(&true) {
+            let max_cores = match raw_df.drop_in_place("max_cores") {
+                Ok(max_cores) => max_cores.cast(&DataType::Int64)?,
+                Err(_) => Series::full_null(
+                    "max_cores",
+                    raw_df.height(),
+                    &DataType::Int64,
+                ),
             };
-            Ok(dataframe)
+            raw_df.with_column(max_cores)?;
+        }
+        let raw_df = match options.normalize_keys {
+            true => {
+                let (raw_df, changed) = utils::normalize_keys(
+                    raw_df,
+                    &["instance", "algorithm"],
+                    options.lowercase_keys,
+                )?;
+                normalized_keys.borrow_mut().extend(changed);
+                raw_df
+            }
+            false => raw_df,
+        };
+        let is_number = |column: &str| {
+            col(column)
+                .cast(DataType::Float64)
+                .is_not_null()
+                .and(col(column).cast(DataType::Float64).is_finite())
         };
+        let parsable = is_number("quality").and(is_number("time"));
+        let quarantined = raw_df
+            .clone()
+            .lazy()
+            .filter(parsable.clone().not())
+            .with_column(lit(path.display().to_string()).alias("file"))
+            .collect()?;
+        let mut dataframe = raw_df
+            .lazy()
+            .filter(parsable)
+            .with_columns([
+                col("quality").cast(DataType::Float64),
+                col("time").cast(DataType::Float64),
+            ])
+            .filter(col("num_threads").lt_eq(lit(num_cores)));
+        if options.quality_semantics == QualitySemantics::Ratio {
+            dataframe = dataframe.with_columns([col("quality").apply(
+                |s: Series| {
+                    Ok(s.f64()?
+                        .into_no_null_iter()
+                        .map(|i| if i.abs() <= EPSILON { 1.0 } else { i })
+                        .collect())
+                },
+                GetOutput::from_type(DataType::Float64),
+            )]);
+        }
+        if options.source_column {
+            dataframe = dataframe
+                .with_column(lit(path.display().to_string()).alias("file"));
+        }
+        if let Some(pattern) = &options.filename_pattern {
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let captures = pattern.captures(&file_name);
+            if captures.is_none() {
+                warn!("File name {file_name} does not match {pattern}");
+            }
+            for name in pattern.capture_names().flatten() {
+                let value = match captures
+                    .as_ref()
+                    .and_then(|captures| captures.name(name))
+                {
+                    Some(value) => lit(value.as_str()),
+                    None => lit(Null {}).cast(DataType::Utf8),
+                };
+                dataframe = dataframe.with_column(value.alias(name));
+            }
+        }
+        if let Some(instance_filter) = &instance_filter {
+            dataframe = dataframe.join(
+                instance_filter.clone().lazy(),
+                &[col("instance")],
+                &[col("instance")],
+                JoinType::Inner,
+            );
+        }
+        Ok((dataframe, quarantined))
+    };
 
     let columns: [&str; 6] = [
         "algorithm",
@@ -231,12 +933,431 @@ pub fn parse_normalized_csvs(
         "time",
         "valid",
     ];
-    let dataframes: Vec<LazyFrame> = paths
+    let mut dataframes = Vec::new();
+    let mut quarantined = Vec::new();
+    let mut counts = Vec::new();
+    let mut failed_files = Vec::new();
+    let mut metadata = Vec::new();
+    for (path, &has_max_cores) in paths.iter().zip(&max_cores_files) {
+        if let Some(comment_char) = options.comment_char {
+            if let Ok(file_metadata) =
+                FileMetadata::from_path(path, comment_char)
+            {
+                metadata.push(file_metadata);
+            }
+        }
+        let mut file_columns = columns.to_vec();
+        if has_max_cores {
+            file_columns.push("max_cores");
+        }
+        match read_df(path, &file_columns) {
+            Ok((dataframe, quarantined_df)) => {
+                counts.push((path.clone(), quarantined_df.height()));
+                dataframes.push(dataframe);
+                quarantined.push(quarantined_df.lazy());
+            }
+            Err(_) => failed_files.push(path.clone()),
+        }
+    }
+    let quarantined = match quarantined.is_empty() {
+        true => DataFrame::default(),
+        false => concat(quarantined, false, false)?.collect()?,
+    };
+    let df = concat(dataframes, true, true)?;
+    let instances = match instance_filter {
+        Some(_) => Some(
+            df.clone()
+                .select([col("instance")])
+                .unique_stable(None, UniqueKeepStrategy::First)
+                .sort("instance", Default::default())
+                .collect()?
+                .column("instance")?
+                .utf8()?
+                .into_no_null_iter()
+                .map(String::from)
+                .collect(),
+        ),
+        None => None,
+    };
+    Ok((
+        df,
+        ParseReport {
+            quarantined,
+            counts,
+            failed_files,
+            normalized_keys: normalized_keys
+                .into_inner()
+                .into_iter()
+                .collect(),
+            metadata,
+            instances,
+        },
+    ))
+}
+
+/// Whether the csv file at `path` has a column `name` in its header
+fn has_column(path: &PathBuf, comment_char: Option<u8>, name: &str) -> bool {
+    CsvReader::from_path(path)
+        .and_then(|reader| {
+            reader
+                .with_comment_char(comment_char)
+                .has_header(true)
+                .with_n_rows(Some(1))
+                .finish()
+        })
+        .map_or(false, |df| df.get_column_names().contains(&name))
+}
+
+/// Derive the number of threads of every run from its algorithm name with a regex, e.g.
+/// `.*-t(\d+)$` for names like `kahypar-t4`
+///
+/// The number of threads is the capture group `threads` or, if the pattern has no such group, the
+/// first capture group. Runs whose algorithm name does not match keep their `num_threads`.
+pub fn infer_num_threads(df: LazyFrame, pattern: &Regex) -> LazyFrame {
+    let pattern = pattern.clone();
+    let inferred = col("algorithm")
+        .apply(
+            move |s: Series| {
+                Ok(s.utf8()?
+                    .into_iter()
+                    .map(|name| {
+                        let captures = pattern.captures(name?)?;
+                        captures
+                            .name("threads")
+                            .or_else(|| captures.get(1))?
+                            .as_str()
+                            .parse::<i64>()
+                            .ok()
+                    })
+                    .collect::<Int64Chunked>()
+                    .into_series())
+            },
+            GetOutput::from_type(DataType::Int64),
+        )
+        .alias("inferred_threads");
+    df.with_column(inferred)
+        .with_column(
+            when(col("inferred_threads").is_not_null())
+                .then(col("inferred_threads"))
+                .otherwise(col("num_threads").cast(DataType::Int64))
+                .alias("num_threads"),
+        )
+        .drop_columns(["inferred_threads"])
+}
+
+/// Add the column `group` with the group of every run, derived from its instance name with a
+/// regex, e.g. `^[^_]+` for the instance family or `k(\d+)` for the value of k, see
+/// [`split_groups`]
+///
+/// The group is the capture group `group` or, if the pattern has no such group, the first capture
+/// group or the whole match. Runs whose instance name does not match have no group.
+pub fn group_instances(df: LazyFrame, pattern: &Regex) -> LazyFrame {
+    let pattern = pattern.clone();
+    df.with_column(
+        col("instance")
+            .apply(
+                move |s: Series| {
+                    Ok(s.utf8()?
+                        .into_iter()
+                        .map(|name| {
+                            let captures = pattern.captures(name?)?;
+                            let group = captures
+                                .name("group")
+                                .or_else(|| captures.get(1))
+                                .or_else(|| captures.get(0))?;
+                            Some(group.as_str().to_string())
+                        })
+                        .collect::<Utf8Chunked>()
+                        .into_series())
+                },
+                GetOutput::from_type(DataType::Utf8),
+            )
+            .alias("group"),
+    )
+}
+
+/// Split the normalized data frame `df` by the values of `column`, e.g. the `group` of
+/// [`group_instances`], to create one [`Data`] per group for
+/// [`crate::solver::solve_grouped`]
+///
+/// Every instance has to belong to exactly one group.
+pub fn split_groups(
+    df: LazyFrame,
+    column: &str,
+) -> Result<BTreeMap<String, LazyFrame>> {
+    let group = col(column).cast(DataType::Utf8);
+    let pairs = df
+        .clone()
+        .select([col("instance"), group.clone().alias("group")])
+        .unique(None, UniqueKeepStrategy::First)
+        .collect()?;
+    let mut groups = BTreeMap::new();
+    for (instance, name) in pairs["instance"]
+        .utf8()?
+        .into_iter()
+        .zip(pairs["group"].utf8()?)
+    {
+        let instance = instance.unwrap_or_default();
+        let Some(name) = name else {
+            anyhow::bail!("The instance {instance} has no {column}");
+        };
+        if let Some(other) = groups.insert(instance, name) {
+            anyhow::ensure!(
+                other == name,
+                "The instance {instance} is in the groups {other} and {name}"
+            );
+        }
+    }
+    Ok(groups
+        .into_values()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|name| {
+            let rows = df.clone().filter(group.clone().eq(lit(name)));
+            (name.to_string(), rows)
+        })
+        .collect())
+}
+
+/// Add the [startup time](Algorithm::startup) of the `algorithms` to the `time` of their runs in
+/// the normalized data frame `df`
+pub fn add_startup_times(
+    df: LazyFrame,
+    algorithms: &[Algorithm],
+) -> LazyFrame {
+    match utils::startup_times(algorithms) {
+        Some(startup) => df.with_column(
+            (col("time").cast(DataType::Float64) + startup).alias("time"),
+        ),
+        None => df,
+    }
+}
+
+/// Expected maximum running time of `k + 1` valid runs of every algorithm of the `data` on every
+/// instance, in the layout of [`Data::expected_best_quality`], e.g. for
+/// [`crate::solver::solve_pareto`]
+///
+/// `df` is the normalized data frame the data was created from, the
+/// [startup times](Algorithm::startup) of the algorithms of the data are added. Without a valid run
+/// of an algorithm on an instance, the largest expected running time of the instance is used.
+pub fn expected_times(
+    df: LazyFrame,
+    data: &Data,
+) -> Result<ndarray::Array3<f64>> {
+    let algorithms = data.algorithms.to_vec();
+    let runs = add_startup_times(df.filter(col("valid")), &algorithms)
+        .groupby([col("instance"), col("algorithm"), col("num_threads")])
+        .agg([col("time").cast(DataType::Float64)])
+        .with_column(col("num_threads").cast(DataType::UInt32))
+        .collect()?;
+    let instances = runs["instance"].unique()?.sort(false);
+    anyhow::ensure!(
+        instances.len() == data.num_instances,
+        "The data has {} instances, but the data frame has {}",
+        data.num_instances,
+        instances.len()
+    );
+    let instance_indices: BTreeMap<&str, usize> = instances
+        .utf8()?
+        .into_no_null_iter()
+        .enumerate()
+        .map(|(i, instance)| (instance, i))
+        .collect();
+    let shape = data.expected_best_quality.dim();
+    let mut times = ndarray::Array3::from_elem(shape, f64::NAN);
+    for (((instance, algorithm), num_threads), runs) in runs["instance"]
+        .utf8()?
+        .into_no_null_iter()
+        .zip(runs["algorithm"].utf8()?.into_no_null_iter())
+        .zip(runs["num_threads"].u32()?.into_no_null_iter())
+        .zip(runs["time"].list()?)
+    {
+        let Some(j) = algorithms.iter().position(|a| {
+            a.algorithm == algorithm && a.num_threads == num_threads
+        }) else {
+            continue;
+        };
+        let mut runs = match runs {
+            Some(runs) => runs.f64()?.into_no_null_iter().collect_vec(),
+            None => continue,
+        };
+        runs.sort_by(f64::total_cmp);
+        for k in 0..shape.2 {
+            times[(instance_indices[instance], j, k)] =
+                utils::expected_max(&runs, k as u32 + 1);
+        }
+    }
+    for mut instance in times.outer_iter_mut() {
+        let slowest = instance
+            .iter()
+            .copied()
+            .filter(|time| !time.is_nan())
+            .fold(0.0, f64::max);
+        instance.mapv_inplace(|time| match time.is_nan() {
+            true => slowest,
+            false => time,
+        });
+    }
+    Ok(times)
+}
+
+/// Fraction of the runs of every algorithm of the `data` on every instance with a valid quality of
+/// at most `factor` times the best quality of the instance, e.g. for a
+/// [`ChanceConstraint`](crate::datastructures::ChanceConstraint)
+///
+/// `df` is the normalized data frame the data was created from, with smaller qualities being
+/// better. Invalid runs never reach the threshold, algorithms without runs on an instance have a
+/// success rate of zero on it.
+pub fn success_rates(
+    df: LazyFrame,
+    data: &Data,
+    factor: f64,
+) -> Result<ndarray::Array2<f64>> {
+    let instance_ids = data.instance_ids();
+    anyhow::ensure!(
+        instance_ids.len() == data.num_instances,
+        "The instance names of the data are unknown"
+    );
+    let runs = df
+        .select([
+            col("instance"),
+            col("algorithm"),
+            col("num_threads").cast(DataType::UInt32),
+            col("quality").cast(DataType::Float64),
+            col("valid"),
+        ])
+        .collect()?;
+    let shape = (data.num_instances, data.num_algorithms);
+    let mut successes = ndarray::Array2::<f64>::zeros(shape);
+    let mut counts = ndarray::Array2::<f64>::zeros(shape);
+    for ((((instance, algorithm), num_threads), quality), valid) in runs
+        ["instance"]
+        .utf8()?
+        .into_no_null_iter()
+        .zip(runs["algorithm"].utf8()?.into_no_null_iter())
+        .zip(runs["num_threads"].u32()?.into_no_null_iter())
+        .zip(runs["quality"].f64()?)
+        .zip(runs["valid"].bool()?)
+    {
+        let (Some(&i), Some(j)) = (
+            instance_ids.get(instance),
+            data.algorithms.iter().position(|a| {
+                a.algorithm == algorithm && a.num_threads == num_threads
+            }),
+        ) else {
+            continue;
+        };
+        counts[(i, j)] += 1.0;
+        if valid == Some(true)
+            && quality
+                .map_or(false, |q| q <= factor * data.best_per_instance[i])
+        {
+            successes[(i, j)] += 1.0;
+        }
+    }
+    Ok(successes / counts.mapv(|count| count.max(1.0)))
+}
+
+/// Read normalized data with multiple quality checkpoints per run from multiple input files.
+///
+/// The quality of every run is reduced to a single value according to `metric`, e.g. the best
+/// quality reached within a time horizon or the area under the anytime curve. The checkpoints are
+/// either in wide format
+/// ```csv
+/// algorithm,num_threads,instance,quality_at_60s,quality_at_300s,time,valid
+/// algo1,1,instance1,45.0,42.0,310.0,true
+/// ```
+/// or in long format with one row per checkpoint (time in seconds), rows that agree on all other
+/// columns belong to the same run
+/// ```csv
+/// algorithm,num_threads,instance,seed,checkpoint,quality,time,valid
+/// algo1,1,instance1,0,60,45.0,310.0,true
+/// algo1,1,instance1,0,300,42.0,310.0,true
+/// ```
+/// Runs without a checkpoint within the horizon are invalid, the best quality so far follows the
+/// `direction`. See [`parse_normalized_csvs`] for the other columns.
+pub fn parse_anytime_csvs(
+    paths: &[PathBuf],
+    desired_instances: Option<PathBuf>,
+    num_cores: u32,
+    metric: QualityMetric,
+    direction: QualityDirection,
+) -> Result<LazyFrame> {
+    parse_anytime_csvs_with_startup(
+        paths,
+        desired_instances,
+        num_cores,
+        metric,
+        direction,
+        &[],
+    )
+}
+
+/// Read normalized data with multiple quality checkpoints per run like [`parse_anytime_csvs`],
+/// delaying the checkpoints of the `algorithms` by their [startup time](Algorithm::startup)
+pub fn parse_anytime_csvs_with_startup(
+    paths: &[PathBuf],
+    desired_instances: Option<PathBuf>,
+    num_cores: u32,
+    metric: QualityMetric,
+    direction: QualityDirection,
+    algorithms: &[Algorithm],
+) -> Result<LazyFrame> {
+    let dataframes = paths
+        .iter()
+        .map(|path| -> Result<LazyFrame> {
+            let df = CsvReader::from_path(path)?
+                .with_comment_char(Some(b'#'))
+                .has_header(true)
+                .finish()?;
+            Ok(utils::anytime_quality(df, metric, direction, algorithms)?
+                .lazy()
+                .filter(col("num_threads").lt_eq(lit(num_cores))))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let df = concat(dataframes, true, true)?;
+    Ok(
+        match desired_instances
+            .as_ref()
+            .and_then(|filter| utils::get_desired_instances(filter).ok())
+        {
+            Some(instance_filter) => df.join(
+                instance_filter,
+                &[col("instance")],
+                &[col("instance")],
+                JoinType::Inner,
+            ),
+            None => df,
+        },
+    )
+}
+
+/// Create a data frame from the intermediate solutions of the solver
+///
+/// Each row contains the runtime, objective, bound, simulated objective (if
+/// [validated](SolverOptions::validation)) and the non-empty resource assignments of an
+/// intermediate solution, e.g. for plotting the convergence of the solver.
+pub fn history_to_df(history: &[Incumbent]) -> Result<LazyFrame> {
+    let portfolios = history
         .iter()
-        .map(|path| read_df(path, &columns))
-        .filter_map(Result::ok)
+        .map(|incumbent| {
+            incumbent
+                .portfolio
+                .resource_assignments
+                .iter()
+                .filter(|(_, cores)| *cores > 0.0)
+                .map(|(algo, cores)| format!("{algo}: {cores}"))
+                .join("; ")
+        })
         .collect_vec();
-    concat(dataframes, true, true).map_err(anyhow::Error::from)
+    Ok(df! {
+        "time" => history.iter().map(|i| i.time).collect_vec(),
+        "objective" => history.iter().map(|i| i.objective).collect_vec(),
+        "bound" => history.iter().map(|i| i.bound).collect_vec(),
+        "simulated" => history.iter().map(|i| i.simulated).collect_vec(),
+        "portfolio" => portfolios,
+    }?
+    .lazy())
 }
 
 /// Helper to write a data frame to a file
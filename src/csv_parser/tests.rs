@@ -1,6 +1,15 @@
 use super::utils::{
-    best_per_instance_count, filter_algorithms_by_slowdown, stats_by_sampling,
+    anytime_quality, best_per_instance_count, expected_max, expected_min,
+    filter_algorithms_by_coverage, filter_algorithms_by_slowdown,
+    gmean_runtimes, split_core_classes, stats_by_repeated_sampling,
+    stats_by_sampling, stats_exact,
 };
+use super::{
+    expected_times, group_instances, history_to_df, infer_num_threads,
+    parse_normalized_csvs_with_report, split_groups, success_rates, Data,
+    DataOptions, ParseOptions,
+};
+use crate::datastructures::*;
 use polars::prelude::*;
 
 #[test]
@@ -27,7 +36,10 @@ fn test_stats_by_sampling() {
             "num_threads" => vec![1; 8],
             "quality" => [10.0, 8.0, 9.0, 7.0, 20.0, 18.0, 22.0, 19.0],
         }.unwrap();
-    let stats_df = stats_by_sampling(df.lazy(), 4).unwrap().collect().unwrap();
+    let stats_df = stats_by_sampling(df.clone().lazy(), 4, 0)
+        .unwrap()
+        .collect()
+        .unwrap();
     dbg!(&stats_df["e_min"]);
     assert_eq!(
         stats_df["e_min"],
@@ -36,6 +48,63 @@ fn test_stats_by_sampling() {
             vec![9.0, 7.0, 7.0, 7.0, 22.0, 19.0, 18.0, 18.0]
         )
     );
+    // sampling is reproducible for a fixed base seed
+    let sample = |seed: u64| {
+        stats_by_sampling(df.clone().lazy(), 4, seed)
+            .unwrap()
+            .collect()
+            .unwrap()
+    };
+    assert_eq!(sample(0)["e_min"], stats_df["e_min"]);
+    assert_eq!(sample(7)["e_min"], sample(7)["e_min"]);
+}
+
+#[test]
+fn test_stats_exact() {
+    let df = df! {
+            "instance" => ["graph1", "graph1", "graph1", "graph1", "graph2", "graph2", "graph2", "graph2"],
+            "algorithm" => ["algo1", "algo1", "algo1", "algo1", "algo1", "algo1", "algo1", "algo1"],
+            "num_threads" => vec![1; 8],
+            "quality" => [10.0, 8.0, 9.0, 7.0, 20.0, 18.0, 22.0, 19.0],
+        }.unwrap();
+    assert_eq!(expected_min(&[7.0, 8.0, 9.0, 10.0], 1), 8.5);
+    // 7 * 7/16 + 8 * 5/16 + 9 * 3/16 + 10 * 1/16
+    assert_eq!(expected_min(&[7.0, 8.0, 9.0, 10.0], 2), 7.875);
+    let exact = stats_exact(df.clone().lazy(), 4)
+        .unwrap()
+        .collect()
+        .unwrap();
+    assert_eq!(exact.height(), 8);
+    assert_eq!(exact["e_min"].f64().unwrap().get(1), Some(7.875));
+    assert_eq!(exact["e_min"].f64().unwrap().get(4), Some(19.75));
+
+    // a single round is plain sampling, many rounds approach the exact expectation
+    let sampled = |rounds: u32| {
+        stats_by_repeated_sampling(df.clone().lazy(), 4, 0, rounds)
+            .unwrap()
+            .collect()
+            .unwrap()
+    };
+    assert_eq!(
+        sampled(1)["e_min"],
+        stats_by_sampling(df.clone().lazy(), 4, 0)
+            .unwrap()
+            .collect()
+            .unwrap()["e_min"]
+    );
+    let averaged = sampled(200);
+    for (a, e) in averaged["e_min"]
+        .f64()
+        .unwrap()
+        .into_no_null_iter()
+        .zip(exact["e_min"].f64().unwrap().into_no_null_iter())
+    {
+        assert!((a - e).abs() < 0.5, "{a} vs {e}");
+    }
+    assert_eq!(
+        "sampling:10".parse::<QualityEstimator>().unwrap(),
+        QualityEstimator::Sampling(10)
+    );
 }
 
 #[test]
@@ -56,3 +125,874 @@ fn test_algorithm_slowdown_filtering() {
         Series::new("algorithm", &["algo3".to_string(), "algo3".into()])
     );
 }
+
+#[test]
+fn test_gmean_runtimes() {
+    let df = df! {
+            "instance" => ["graph1", "graph1", "graph1", "graph2", "graph2", "graph2"],
+            "algorithm" => ["algo1", "algo2", "algo3", "algo1", "algo2", "algo3"],
+            "num_threads" => vec![1; 6],
+            "quality" => [1.0, 2.0, 2.0, 2.0, 1.0, 2.0],
+            "time" => [2.0, 4.0, 1.0, 2.0, 4.0, 1.0],
+        }.unwrap();
+    let table = gmean_runtimes(df.lazy(), 0.5).unwrap();
+    assert_eq!(
+        table["algorithm"],
+        Series::new("algorithm", &["algo3", "algo1", "algo2"])
+    );
+    assert_eq!(
+        table["gmean"],
+        Series::new("gmean", &[0.0, 2f64.ln(), 4f64.ln()])
+    );
+    assert_eq!(table["kept"], Series::new("kept", &[true, false, false]));
+}
+
+#[test]
+fn test_algorithm_coverage_filtering() {
+    let df = df! {
+        "instance" => ["graph1", "graph2", "graph3", "graph4", "graph1", "graph2", "graph1"],
+        "algorithm" => ["algo1", "algo1", "algo1", "algo1", "algo2", "algo2", "algo3"],
+        "num_threads" => vec![1i64; 7],
+        "quality" => [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+        "time" => [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+        "valid" => vec![true; 7],
+    }
+    .unwrap();
+    let (filtered_df, dropped) =
+        filter_algorithms_by_coverage(df.clone(), 50.0).unwrap();
+    assert_eq!(filtered_df.height(), 6);
+    assert_eq!(dropped, vec![(Algorithm::new("algo3".into(), 1), 25.0)]);
+    let (filtered_df, dropped) =
+        filter_algorithms_by_coverage(df.clone(), 0.0).unwrap();
+    assert_eq!(filtered_df.height(), 7);
+    assert!(dropped.is_empty());
+
+    let options = DataOptions {
+        min_coverage: 75.0,
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.lazy(),
+        1,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        data.algorithms.to_vec(),
+        [Algorithm::new("algo1".into(), 1)]
+    );
+}
+
+#[test]
+fn test_imputed_mask() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph2"],
+        "algorithm" => ["algo1", "algo2", "algo1"],
+        "num_threads" => [1i64, 1, 1],
+        "quality" => [1.0, 2.0, 3.0],
+        "time" => [1.0, 1.0, 1.0],
+        "valid" => [true, true, true],
+    }
+    .unwrap();
+    let data =
+        Data::from_normalized_dataframe(df.lazy(), 2, f64::MAX, 0).unwrap();
+    let imputed = data.imputed.as_ref().unwrap();
+    assert_eq!(imputed.shape(), &[2, 2, 2]);
+    assert_eq!(imputed.iter().filter(|&&imputed| imputed).count(), 2);
+    assert!(data.is_imputed(1, 1, 0) && data.is_imputed(1, 1, 1));
+    assert!(!data.is_imputed(0, 1, 1));
+}
+
+#[test]
+fn test_max_cores_per_instance() {
+    let limited = std::env::temp_dir().join("portfolio_solver_max_cores.csv");
+    std::fs::write(
+        &limited,
+        "algorithm,num_threads,instance,quality,time,valid,max_cores\n\
+         algo1,1,graph1,2.0,10.0,true,1\n\
+         algo1,1,graph1,1.0,10.0,true,1\n\
+         algo2,2,graph1,1.0,10.0,true,1\n",
+    )
+    .unwrap();
+    let unlimited =
+        std::env::temp_dir().join("portfolio_solver_no_max_cores.csv");
+    std::fs::write(
+        &unlimited,
+        "algorithm,num_threads,instance,quality,time,valid\n\
+         algo1,1,graph2,2.0,10.0,true\n\
+         algo1,1,graph2,1.0,10.0,true\n\
+         algo2,2,graph2,1.0,10.0,true\n",
+    )
+    .unwrap();
+    let (df, _) = parse_normalized_csvs_with_report(
+        &[limited.clone(), unlimited.clone()],
+        None,
+        2,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+    std::fs::remove_file(&limited).unwrap();
+    std::fs::remove_file(&unlimited).unwrap();
+    let data = Data::from_normalized_dataframe_with_options(
+        df,
+        2,
+        f64::MAX,
+        &DataOptions {
+            estimator: QualityEstimator::Exact,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let e_min = &data.expected_best_quality;
+    // only one copy of algo1 fits on graph1, the second one doesn't help
+    assert_eq!(e_min[(0, 0, 1)], e_min[(0, 0, 0)]);
+    assert!(e_min[(1, 0, 1)] < e_min[(1, 0, 0)]);
+    // algo2 needs two cores
+    assert_eq!(e_min[(0, 1, 0)], f64::MAX);
+    assert!(data.is_imputed(0, 1, 0) && !data.is_imputed(1, 1, 0));
+}
+
+#[test]
+fn test_time_budget() {
+    assert_eq!(expected_max(&[1.0, 10.0], 1), 5.5);
+    assert_eq!(expected_max(&[1.0, 10.0], 2), 7.75);
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1, 1, 1],
+        "quality" => [2.0, 1.0, 1.5, 3.0, 1.0, 2.0],
+        "time" => [1.0, 10.0, 1.0, 1.0, 20.0, 1.0],
+        "valid" => [true; 6],
+    }
+    .unwrap();
+    let options = DataOptions {
+        estimator: QualityEstimator::Exact,
+        time_budget: Some(6.0),
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.clone().lazy(),
+        3,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    let e_min = &data.expected_best_quality;
+    // two runs of algo1 on graph1 are expected to take 7.75 seconds
+    assert_eq!(e_min[(0, 0, 1)], e_min[(0, 0, 0)]);
+    assert_eq!(e_min[(0, 0, 2)], e_min[(0, 0, 0)]);
+    assert!(e_min[(0, 1, 1)] < e_min[(0, 1, 0)]);
+    // no run of algo1 on graph2 finishes within the budget
+    assert_eq!(e_min[(1, 0, 0)], f64::MAX);
+    assert!(data.is_imputed(1, 0, 0) && !data.is_imputed(1, 1, 2));
+    // with its startup time, no run of algo2 finishes within the budget
+    let options = DataOptions {
+        requirements: vec![Algorithm::new("algo2".into(), 1).with_startup(5.5)],
+        ..options
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.lazy(),
+        3,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(data.algorithms[1].startup, Some(5.5));
+    assert!(data.is_imputed(0, 1, 0) && data.is_imputed(1, 1, 0));
+}
+
+#[test]
+fn test_expected_times() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo1", "algo2", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1, 1],
+        "quality" => [1.0, 2.0, 1.0, 1.0, 2.0],
+        "time" => [1.0, 3.0, 2.0, 4.0, 5.0],
+        "valid" => [true, true, true, true, false],
+    }
+    .unwrap();
+    let options = DataOptions {
+        estimator: QualityEstimator::Exact,
+        requirements: vec![Algorithm::new("algo2".into(), 1).with_startup(1.0)],
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.clone().lazy(),
+        2,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    let times = expected_times(df.lazy(), &data).unwrap();
+    assert_eq!(times.dim(), (2, 2, 2));
+    assert_eq!(times[(0, 0, 0)], 2.0);
+    assert_eq!(times[(0, 0, 1)], expected_max(&[1.0, 3.0], 2));
+    assert_eq!(times[(0, 1, 0)], 3.0);
+    // algo2 has no valid run on graph2
+    assert_eq!(times[(1, 1, 0)], 4.0);
+}
+
+#[test]
+fn test_success_rates() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1, 1, 1],
+        "quality" => [10.0, 12.0, 10.5, 10.0, 5.0, 5.0],
+        "time" => [1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+        "valid" => [true, true, true, false, true, true],
+    }
+    .unwrap();
+    let data =
+        Data::from_normalized_dataframe(df.clone().lazy(), 2, f64::MAX, 0)
+            .unwrap();
+    let success = success_rates(df.lazy(), &data, 1.05).unwrap();
+    assert_eq!(success.dim(), (2, 2));
+    assert_eq!(success[(0, 0)], 0.5);
+    // the invalid run of the best quality does not count
+    assert_eq!(success[(0, 1)], 0.5);
+    assert_eq!(success[(1, 0)], 1.0);
+    assert_eq!(success[(1, 1)], 1.0);
+}
+
+#[test]
+fn test_iter_cells() {
+    let df = df! {
+        "instance" => ["graph2", "graph1", "graph2", "graph1"],
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1],
+        "quality" => [1.0, 2.0, 3.0, 4.0],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => [true, true, true, true],
+    }
+    .unwrap();
+    let options = DataOptions {
+        estimator: QualityEstimator::Exact,
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.lazy(),
+        2,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    let algo2 = Algorithm::new("algo2".into(), 1);
+    let cells = data.iter_cells().collect::<Vec<_>>();
+    assert_eq!(cells.len(), 8);
+    assert_eq!(cells[0], (0, &data.algorithms[0], 1, 2.0));
+    assert_eq!(cells[3], (0, &algo2, 2, 4.0));
+    assert_eq!(cells[6].0, 1);
+    assert_eq!(data.instance_name(1), Some("graph2"));
+    assert_eq!(data.instance_id("graph1"), Some(0));
+    assert_eq!(data.instance_id("graph3"), None);
+    assert_eq!(data.instance_ids().get("graph2"), Some(&1));
+    assert_eq!(data.algorithm_id(&algo2), Some(1));
+    assert_eq!(
+        data.iter_instances().collect::<Vec<_>>(),
+        vec![(0, Some("graph1"), 2.0), (1, Some("graph2"), 1.0)]
+    );
+    let data = Data::new(&[algo2], &[1.0], None, &[1.0, 1.0], 2).unwrap();
+    assert_eq!(data.instance_name(0), None);
+    assert!(data.instance_ids().is_empty());
+}
+
+#[test]
+fn test_dump_normalized() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo2", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1],
+        "quality" => [1.0, 2.0, 3.0, 4.0],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => [true, true, false, true],
+    }
+    .unwrap();
+    let path = std::env::temp_dir().join("portfolio_solver_dump.csv");
+    let options = DataOptions {
+        dump_normalized: Some(path.clone()),
+        ..Default::default()
+    };
+    Data::from_normalized_dataframe_with_options(
+        df.lazy(),
+        1,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    let dumped = CsvReader::from_path(&path).unwrap().finish().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // the invalid run is not part of the data the solver sees
+    assert_eq!(dumped.height(), 3);
+    assert!(dumped["valid"].bool().unwrap().all());
+}
+
+#[test]
+fn test_history_to_df() {
+    let history = vec![Incumbent {
+        portfolio: Portfolio {
+            name: "intermediate_portfolio".into(),
+            resource_assignments: vec![
+                (Algorithm::new("algo1".into(), 1), 2.0),
+                (Algorithm::new("algo2".into(), 1), 0.0),
+                (Algorithm::new("algo3".into(), 2), 1.0),
+            ],
+        },
+        objective: 4.2,
+        bound: 4.0,
+        time: 1.5,
+        simulated: Some(4.5),
+    }];
+    let df = history_to_df(&history).unwrap().collect().unwrap();
+    assert_eq!(df.height(), 1);
+    assert_eq!(df["simulated"].f64().unwrap().get(0), Some(4.5));
+    assert_eq!(
+        df["portfolio"],
+        Series::new("portfolio", &["algo1 1: 2; algo3 2: 1"])
+    );
+}
+
+#[test]
+fn test_quarantine_unparsable_rows() {
+    let path = std::env::temp_dir().join("portfolio_solver_quarantine.csv");
+    std::fs::write(
+        &path,
+        "algorithm,num_threads,instance,quality,time,valid\n\
+         algo1,1,graph1,42.0,10.0,true\n\
+         algo1,1,graph2,inf,10.0,true\n\
+         algo1,1,graph3,40.0,n/a,true\n\
+         algo2,1,graph1,41.0,9.0,true\n",
+    )
+    .unwrap();
+    let (df, report) = parse_normalized_csvs_with_report(
+        std::slice::from_ref(&path),
+        None,
+        1,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(df.collect().unwrap().height(), 2);
+    assert_eq!(report.counts, vec![(path, 2)]);
+    assert_eq!(
+        report.quarantined["instance"],
+        Series::new("instance", &["graph2", "graph3"])
+    );
+    assert!(report.failed_files.is_empty());
+}
+
+#[test]
+fn test_infer_num_threads() {
+    let pattern = regex::Regex::new(r"-t(?P<threads>\d+)$").unwrap();
+    let df = df! {
+        "algorithm" => ["algo-t4", "algo-t16", "other"],
+        "num_threads" => [1i64, 1, 2],
+    }
+    .unwrap();
+    let df = infer_num_threads(df.lazy(), &pattern).collect().unwrap();
+    assert_eq!(
+        df["num_threads"],
+        Series::new("num_threads", &[4i64, 16, 2])
+    );
+
+    let path = std::env::temp_dir().join("portfolio_solver_threads.csv");
+    std::fs::write(
+        &path,
+        "algorithm,instance,quality,time,valid\n\
+         algo-t4,graph1,42.0,10.0,true\n\
+         algo-t64,graph1,40.0,10.0,true\n\
+         algo,graph1,41.0,9.0,true\n",
+    )
+    .unwrap();
+    let options = ParseOptions {
+        thread_pattern: Some(pattern),
+        ..Default::default()
+    };
+    let (df, _) = parse_normalized_csvs_with_report(
+        std::slice::from_ref(&path),
+        None,
+        8,
+        &options,
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let df = df.collect().unwrap().sort(["algorithm"], false).unwrap();
+    assert_eq!(
+        df["algorithm"],
+        Series::new("algorithm", &["algo", "algo-t4"])
+    );
+    assert_eq!(
+        df["num_threads"].cast(&DataType::Int64).unwrap(),
+        Series::new("num_threads", &[1i64, 4])
+    );
+}
+
+#[test]
+fn test_split_groups() {
+    let df = df! {
+        "instance" => ["web_1", "web_1", "web_2", "road_1", "other"],
+        "algorithm" => ["algo1", "algo2", "algo1", "algo1", "algo1"],
+        "quality" => [1.0, 2.0, 3.0, 4.0, 5.0],
+    }
+    .unwrap();
+    let pattern = regex::Regex::new(r"^(?P<group>[a-z]+)_").unwrap();
+    let grouped = group_instances(df.lazy(), &pattern);
+    // the instance without a group is rejected
+    assert!(split_groups(grouped.clone(), "group").is_err());
+    let groups = split_groups(
+        grouped.filter(col("instance").neq(lit("other"))),
+        "group",
+    )
+    .unwrap();
+    assert_eq!(groups.keys().collect::<Vec<_>>(), ["road", "web"]);
+    let web = groups["web"].clone().collect().unwrap();
+    assert_eq!(web["quality"], Series::new("quality", &[1.0, 2.0, 3.0]));
+
+    // an instance in two groups is rejected
+    let df = df! {
+        "instance" => ["graph1", "graph1"],
+        "k" => [2i64, 4],
+    }
+    .unwrap();
+    assert!(split_groups(df.lazy(), "k").is_err());
+}
+
+#[test]
+fn test_instance_universe() {
+    let dir = std::env::temp_dir();
+    let files = [
+        dir.join("portfolio_solver_universe1.csv"),
+        dir.join("portfolio_solver_universe2.csv"),
+    ];
+    for (path, instance) in files.iter().zip(["graph1", "graph3"]) {
+        std::fs::write(
+            path,
+            format!(
+                "algorithm,num_threads,instance,quality,time,valid\n\
+                 algo1,1,{instance},42.0,10.0,true\n\
+                 algo1,1,graph2,40.0,10.0,true\n"
+            ),
+        )
+        .unwrap();
+    }
+    let filter = dir.join("portfolio_solver_universe_filter.csv");
+    std::fs::write(&filter, "instance\ngraph1\ngraph2\ngraph4\n").unwrap();
+    let (df, report) = parse_normalized_csvs_with_report(
+        &files,
+        Some(filter.clone()),
+        1,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+    let (_, unfiltered) = parse_normalized_csvs_with_report(
+        &files,
+        None,
+        1,
+        &ParseOptions::default(),
+    )
+    .unwrap();
+    for path in files.iter().chain([&filter]) {
+        std::fs::remove_file(path).unwrap();
+    }
+    assert_eq!(df.collect().unwrap().height(), 3);
+    assert_eq!(
+        report.instances,
+        Some(vec!["graph1".to_string(), "graph2".into()])
+    );
+    assert_eq!(unfiltered.instances, None);
+}
+
+#[test]
+fn test_normalize_keys() {
+    let path = std::env::temp_dir().join("portfolio_solver_normalize.csv");
+    std::fs::write(
+        &path,
+        "algorithm,num_threads,instance,quality,time,valid\n\
+         algo1,1,graph1,42.0,10.0,true\n\
+         algo1 ,1,graph1 ,40.0,10.0,true\n\
+         Algo1,1,Graph1,41.0,9.0,true\n",
+    )
+    .unwrap();
+    let options = ParseOptions {
+        normalize_keys: true,
+        lowercase_keys: true,
+        ..Default::default()
+    };
+    let (df, report) = parse_normalized_csvs_with_report(
+        std::slice::from_ref(&path),
+        None,
+        1,
+        &options,
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let df = df.collect().unwrap();
+    assert_eq!(df["instance"].n_unique().unwrap(), 1);
+    assert_eq!(df["algorithm"].n_unique().unwrap(), 1);
+    assert_eq!(
+        report.normalized_keys,
+        vec![
+            ("Algo1".to_string(), "algo1".to_string()),
+            ("Graph1".into(), "graph1".into()),
+            ("algo1 ".into(), "algo1".into()),
+            ("graph1 ".into(), "graph1".into()),
+        ]
+    );
+}
+
+#[test]
+fn test_file_metadata() {
+    let path = std::env::temp_dir().join("portfolio_solver_metadata.csv");
+    std::fs::write(
+        &path,
+        "% machine: node42\n\
+         % version: 1.2.0\n\
+         %no entry\n\
+         algorithm,num_threads,instance,quality,time,valid\n\
+         algo1,1,graph1,42.0,10.0,true\n\
+         % date: ignored\n",
+    )
+    .unwrap();
+    let options = ParseOptions {
+        comment_char: Some(b'%'),
+        ..Default::default()
+    };
+    let (df, report) = parse_normalized_csvs_with_report(
+        std::slice::from_ref(&path),
+        None,
+        1,
+        &options,
+    )
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(df.collect().unwrap().height(), 1);
+    assert_eq!(report.metadata.len(), 1);
+    assert_eq!(report.metadata[0].machine(), Some("node42"));
+    assert_eq!(report.metadata[0].tool_version(), Some("1.2.0"));
+    assert_eq!(report.metadata[0].date(), None);
+}
+
+#[test]
+fn test_provenance_columns() {
+    let dir = std::env::temp_dir();
+    let paths = ["node1_algo1.csv", "results.csv"].map(|name| {
+        let path = dir.join(format!("portfolio_solver_{name}"));
+        std::fs::write(
+            &path,
+            "algorithm,num_threads,instance,quality,time,valid\n\
+             algo1,1,graph1,42.0,10.0,true\n",
+        )
+        .unwrap();
+        path
+    });
+    let options = ParseOptions {
+        source_column: true,
+        filename_pattern: Some(
+            regex::Regex::new(r"portfolio_solver_(?P<machine>node\d+)_")
+                .unwrap(),
+        ),
+        ..Default::default()
+    };
+    let (df, _) =
+        parse_normalized_csvs_with_report(&paths, None, 1, &options).unwrap();
+    let df = df.collect().unwrap();
+    for path in &paths {
+        std::fs::remove_file(path).unwrap();
+    }
+    let files = df.column("file").unwrap().utf8().unwrap();
+    assert_eq!(files.get(0), Some(paths[0].display().to_string().as_str()));
+    assert_eq!(files.get(1), Some(paths[1].display().to_string().as_str()));
+    let machines = df.column("machine").unwrap().utf8().unwrap();
+    assert_eq!(machines.get(0), Some("node1"));
+    assert_eq!(machines.get(1), None);
+}
+
+#[test]
+fn test_anytime_quality() {
+    let wide = df! {
+        "algorithm" => ["algo1", "algo2"],
+        "num_threads" => [1, 1],
+        "instance" => ["graph1", "graph1"],
+        "quality_at_10s" => [Some(4.0), None],
+        "quality_at_20s" => [Some(2.0), Some(3.0)],
+        "quality_at_40s" => [Some(1.0), Some(1.0)],
+        "time" => [40.0, 40.0],
+        "valid" => [true, true],
+    }
+    .unwrap();
+    let long = df! {
+        "algorithm" => ["algo1", "algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => vec![1; 5],
+        "instance" => vec!["graph1"; 5],
+        "checkpoint" => [40, 10, 20, 20, 40],
+        "quality" => [1.0, 4.0, 2.0, 3.0, 1.0],
+        "time" => vec![40.0; 5],
+        "valid" => vec![true; 5],
+    }
+    .unwrap();
+    let delayed =
+        |df: &DataFrame, metric, direction, startup: &[Algorithm]| {
+            let df = anytime_quality(df.clone(), metric, direction, startup)
+                .unwrap()
+                .sort(["algorithm"], false)
+                .unwrap();
+            let valid = df.column("valid").unwrap().bool().unwrap();
+            df.column("quality")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_no_null_iter()
+                .zip(valid.into_no_null_iter())
+                .filter_map(|(quality, valid)| valid.then_some(quality))
+                .collect::<Vec<_>>()
+        };
+    let directed = |df: &DataFrame, metric, direction| {
+        delayed(df, metric, direction, &[])
+    };
+    let quality = |df: &DataFrame, metric| {
+        directed(df, metric, QualityDirection::Minimize)
+    };
+    let startup = [Algorithm::new("algo1".into(), 1).with_startup(10.0)];
+    for df in [&wide, &long] {
+        assert_eq!(quality(df, QualityMetric::AtTime(5.0)), Vec::<f64>::new());
+        assert_eq!(quality(df, QualityMetric::AtTime(15.0)), vec![4.0]);
+        assert_eq!(quality(df, QualityMetric::AtTime(30.0)), vec![2.0, 3.0]);
+        assert_eq!(
+            quality(df, QualityMetric::AreaUnderCurve(30.0)),
+            vec![(4.0 * 10.0 + 2.0 * 10.0) / 20.0, 3.0]
+        );
+        // with larger qualities being better, the first checkpoint stays the best of algo1
+        assert_eq!(
+            directed(
+                df,
+                QualityMetric::AtTime(30.0),
+                QualityDirection::Maximize
+            ),
+            vec![4.0, 3.0]
+        );
+        // the startup time delays the first checkpoint of algo1 to 20s
+        assert_eq!(
+            delayed(
+                df,
+                QualityMetric::AtTime(15.0),
+                QualityDirection::Minimize,
+                &startup
+            ),
+            Vec::<f64>::new()
+        );
+        assert_eq!(
+            delayed(
+                df,
+                QualityMetric::AtTime(25.0),
+                QualityDirection::Minimize,
+                &startup
+            ),
+            vec![4.0, 3.0]
+        );
+    }
+}
+
+#[test]
+fn test_quality_direction_maximize() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo2", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1],
+        "quality" => [4.0, 2.0, 1.0, 5.0],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => [true, true, true, true],
+    }
+    .unwrap();
+    let data = |quality_semantics| {
+        let options = DataOptions {
+            quality_semantics,
+            quality_direction: QualityDirection::Maximize,
+            ..Default::default()
+        };
+        Data::from_normalized_dataframe_with_options(
+            df.clone().lazy(),
+            1,
+            f64::MAX,
+            &options,
+        )
+        .unwrap()
+    };
+    // quality ratios are best / q
+    let ratio = data(QualitySemantics::Ratio);
+    assert_eq!(ratio.best_per_instance.to_vec(), [0.25, 0.2]);
+    let ratios = ratio
+        .expected_best_quality
+        .indexed_iter()
+        .map(|((i, _, _), e_min)| e_min / ratio.best_per_instance[i])
+        .collect::<Vec<_>>();
+    assert_eq!(ratios, [1.0, 2.0, 5.0, 1.0]);
+    // quality ratios are 1 + best - q
+    let difference = data(QualitySemantics::Difference);
+    assert_eq!(
+        difference
+            .expected_best_quality
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        [1.0, 3.0, 5.0, 1.0]
+    );
+}
+
+#[test]
+fn test_vbs_sbs_objective() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 2),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        1.0, 1.0, 4.0, 2.0, //
+        4.0, 3.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 2.0], None, &stats, 2).unwrap();
+    assert_eq!(data.vbs_objective(), 2.0);
+    // algo1 with 2 copies: 1 + 3 / 2, algo2 with 1 copy: 4 + 2 / 2
+    assert_eq!(data.sbs_objective(), 2.5);
+}
+
+#[test]
+fn test_quality_semantics_difference() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo2", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1],
+        "quality" => [-3.0, 0.0, 0.0, 2.5],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => [true, true, true, true],
+    }
+    .unwrap();
+    let options = DataOptions {
+        quality_semantics: QualitySemantics::Difference,
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.lazy(),
+        1,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(data.best_per_instance.to_vec(), vec![1.0, 1.0]);
+    assert_eq!(
+        data.expected_best_quality
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![1.0, 4.0, 1.0, 3.5]
+    );
+    assert_eq!(data.vbs_objective(), 2.0);
+    assert_eq!(data.sampling_seed, Some(0));
+    assert_eq!(
+        "difference".parse::<QualitySemantics>().unwrap(),
+        QualitySemantics::Difference
+    );
+}
+
+#[test]
+fn test_core_classes() {
+    let df = df! {
+        "instance" => ["graph1", "graph2"],
+        "algorithm" => ["algo1", "algo1"],
+        "num_threads" => [1i64, 1],
+        "quality" => [1.0, 2.0],
+        "time" => [2.0, 4.0],
+        "valid" => [true, true],
+    }
+    .unwrap();
+    let class = |name: &str, speed: f64| CoreClass {
+        name: name.into(),
+        cores: 2,
+        speed,
+    };
+    let options = DataOptions {
+        core_classes: vec![class("p", 1.0), class("e", 0.5)],
+        requirements: vec![Algorithm {
+            startup: Some(1.0),
+            ..Algorithm::new("algo1".into(), 1)
+        }],
+        time_budget: Some(10.0),
+        estimator: QualityEstimator::Exact,
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.clone().lazy(),
+        4,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        data.algorithms.to_vec(),
+        vec![
+            Algorithm::new("algo1@e".into(), 1),
+            Algorithm::new("algo1@p".into(), 1)
+        ]
+    );
+    assert_eq!(data.algorithms[0].startup, Some(2.0));
+    assert_eq!(data.algorithms[1].startup, Some(1.0));
+    // the running times on the efficiency cores are twice as long
+    let times = split_core_classes(df.lazy(), &options.core_classes)
+        .unwrap()
+        .filter(col("algorithm").eq(lit("algo1@e")))
+        .collect()
+        .unwrap();
+    assert_eq!(
+        times["time"]
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect::<Vec<_>>(),
+        vec![4.0, 8.0]
+    );
+}
+
+#[test]
+fn test_time_to_target() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph2", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo1", "algo2", "algo1", "algo1", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1, 1, 1],
+        "quality" => [100.0, 104.0, 120.0, 10.0, 10.0, 10.0],
+        "time" => [4.0, 2.0, 1.0, 3.0, 1.0, 2.0],
+        "valid" => [true, true, true, true, true, true],
+    }
+    .unwrap();
+    let target: TimeToTarget = "1.05:2".parse().unwrap();
+    assert_eq!(
+        target,
+        TimeToTarget {
+            factor: 1.05,
+            penalty: 2.0
+        }
+    );
+    assert_eq!("1.1".parse::<TimeToTarget>().unwrap().penalty, 10.0);
+    assert!("1.1:0.5".parse::<TimeToTarget>().is_err());
+    let options = DataOptions {
+        time_to_target: Some(target),
+        estimator: QualityEstimator::Exact,
+        ..Default::default()
+    };
+    let data = Data::from_normalized_dataframe_with_options(
+        df.lazy(),
+        1,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    // algo2 misses the target on graph1, so it takes twice the longest run
+    assert_eq!(data.best_per_instance.to_vec(), vec![2.0, 1.0]);
+    assert_eq!(
+        data.expected_best_quality
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![3.0, 8.0, 2.0, 2.0]
+    );
+}
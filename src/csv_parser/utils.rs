@@ -1,7 +1,10 @@
 use std::path::PathBuf;
 
+use itertools::Itertools;
 use log::warn;
 use polars::prelude::*;
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
 
 use anyhow::{Context, Result};
 
@@ -55,9 +58,12 @@ pub fn column_to_f64_array(
     Ok(df.column(column_name)?.f64()?.to_ndarray()?.to_owned())
 }
 
+/// Best quality of `1..=sample_size` runs per instance and algorithm sampled with replacement,
+/// samples of size `s` are drawn with the seed `seed + s`
 pub fn stats_by_sampling(
     df: LazyFrame,
     sample_size: u32,
+    seed: u64,
 ) -> Result<LazyFrame> {
     let columns = vec![col("instance"), col("algorithm"), col("num_threads")];
 
@@ -68,7 +74,7 @@ pub fn stats_by_sampling(
             df.clone()
                 .groupby(&columns)
                 .agg([col("quality")
-                    .sample_n(s as usize, true, true, Some(s))
+                    .sample_n(s as usize, true, true, Some(seed + s))
                     .min()
                     .alias("e_min")])
                 .with_column(lit(s as u32).alias("sample_size"))
@@ -81,6 +87,182 @@ pub fn stats_by_sampling(
     ))
 }
 
+/// [`stats_by_sampling`] averaged over `rounds` independent rounds, round `r` uses the seed
+/// `seed + r * sample_size`
+pub fn stats_by_repeated_sampling(
+    df: LazyFrame,
+    sample_size: u32,
+    seed: u64,
+    rounds: u32,
+) -> Result<LazyFrame> {
+    if rounds <= 1 {
+        return stats_by_sampling(df, sample_size, seed);
+    }
+    let columns = vec![
+        col("instance"),
+        col("algorithm"),
+        col("num_threads"),
+        col("sample_size"),
+    ];
+    let sort_options = vec![false; columns.len()];
+    let samples = (0..rounds as u64)
+        .map(|r| {
+            stats_by_sampling(
+                df.clone(),
+                sample_size,
+                seed + r * sample_size as u64,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(concat(samples, false, false)?
+        .groupby(&columns)
+        .agg([col("e_min").mean()])
+        .sort_by_exprs(&columns, sort_options, false))
+}
+
+/// Exact expectation of the best quality of `1..=sample_size` runs per instance and algorithm
+/// drawn uniformly with replacement
+pub fn stats_exact(df: LazyFrame, sample_size: u32) -> Result<LazyFrame> {
+    let columns = vec![col("instance"), col("algorithm"), col("num_threads")];
+    let sort_exprs = [columns.clone(), vec![col("sample_size")]].concat();
+    let sort_options = vec![false; sort_exprs.len()];
+    let expectations: Vec<LazyFrame> = (1..=sample_size)
+        .map(|s| {
+            let expected_min = move |qualities: Series| {
+                let mut qualities =
+                    qualities.f64()?.into_no_null_iter().collect_vec();
+                qualities.sort_by(f64::total_cmp);
+                Ok(Series::new("e_min", &[expected_min(&qualities, s)]))
+            };
+            df.clone()
+                .groupby(&columns)
+                .agg([col("quality")
+                    .apply(
+                        expected_min,
+                        GetOutput::from_type(DataType::Float64),
+                    )
+                    .first()
+                    .alias("e_min")])
+                .with_column(lit(s).alias("sample_size"))
+        })
+        .collect();
+    Ok(concat(expectations, false, false)?.sort_by_exprs(
+        &sort_exprs,
+        sort_options,
+        false,
+    ))
+}
+
+/// Expected minimum of `s` values drawn uniformly with replacement from the ascending `sorted`
+/// values
+///
+/// The `i`-th smallest of `n` values is the minimum with probability
+/// `((n - i) / n)^s - ((n - i - 1) / n)^s` (zero-based `i`).
+pub fn expected_min(sorted: &[f64], s: u32) -> f64 {
+    let n = sorted.len() as f64;
+    sorted
+        .iter()
+        .enumerate()
+        .map(|(i, quality)| {
+            let at_least = ((n - i as f64) / n).powi(s as i32);
+            let above = ((n - i as f64 - 1.0) / n).powi(s as i32);
+            quality * (at_least - above)
+        })
+        .sum()
+}
+
+/// Expected maximum of `s` values drawn uniformly with replacement from the ascending `sorted`
+/// values, see [`expected_min`]
+pub fn expected_max(sorted: &[f64], s: u32) -> f64 {
+    let negated = sorted.iter().rev().map(|value| -value).collect_vec();
+    -expected_min(&negated, s)
+}
+
+/// The smallest `max_cores` of every instance, sorted by instance (`None` if no run of the instance
+/// has a limit)
+pub fn max_cores_per_instance(df: LazyFrame) -> Result<Vec<Option<u32>>> {
+    let max_cores_df = df
+        .groupby([col("instance")])
+        .agg([col("max_cores").min()])
+        .sort("instance", Default::default())
+        .collect()?;
+    Ok(max_cores_df
+        .column("max_cores")?
+        .cast(&DataType::UInt32)?
+        .u32()?
+        .into_iter()
+        .collect())
+}
+
+/// Largest number of runs (at most `sample_size`) of every instance (in order) and algorithm of
+/// `algorithms` whose expected maximum running time fits the `time_budget`, i.e. all copies are
+/// expected to finish within the budget (`sample_size` without any runs)
+pub fn max_repetitions_by_time(
+    df: LazyFrame,
+    algorithms: &ndarray::Array1<Algorithm>,
+    sample_size: u32,
+    time_budget: f64,
+) -> Result<ndarray::Array2<usize>> {
+    let max_repetitions = move |times: Series| {
+        let mut times = times
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_no_null_iter()
+            .collect_vec();
+        times.sort_by(f64::total_cmp);
+        let fitting = (1..=sample_size)
+            .take_while(|&s| expected_max(&times, s) <= time_budget)
+            .count() as u32;
+        Ok(Series::new("max_repetitions", &[fitting]))
+    };
+    let limits_df = df
+        .groupby([col("instance"), col("algorithm"), col("num_threads")])
+        .agg([col("time")
+            .apply(max_repetitions, GetOutput::from_type(DataType::UInt32))
+            .first()
+            .alias("max_repetitions")])
+        .with_column(col("num_threads").cast(DataType::UInt32))
+        .sort("instance", Default::default())
+        .collect()?;
+    let instances = limits_df.column("instance")?.utf8()?;
+    let instance_indices = instances
+        .into_no_null_iter()
+        .dedup()
+        .enumerate()
+        .map(|(i, instance)| (instance, i))
+        .collect::<std::collections::HashMap<_, _>>();
+    let mut limits = ndarray::Array2::from_elem(
+        (instance_indices.len(), algorithms.len()),
+        sample_size as usize,
+    );
+    for (((instance, algorithm), num_threads), max_repetitions) in instances
+        .into_no_null_iter()
+        .zip(limits_df.column("algorithm")?.utf8()?.into_no_null_iter())
+        .zip(limits_df.column("num_threads")?.u32()?.into_no_null_iter())
+        .zip(
+            limits_df
+                .column("max_repetitions")?
+                .u32()?
+                .into_no_null_iter(),
+        )
+    {
+        let j = algorithms
+            .iter()
+            .position(|a| {
+                a.algorithm == algorithm && a.num_threads == num_threads
+            })
+            .with_context(|| {
+                format!(
+                    "Unknown algorithm {algorithm} with {num_threads} threads"
+                )
+            })?;
+        limits[(instance_indices[instance], j)] = max_repetitions as usize;
+    }
+    Ok(limits)
+}
+
+/// Add the missing rows of every combination of instance, algorithm and sample size with the worst
+/// possible quality, marked in the boolean column `imputed`
 pub fn cleanup_missing_rows(df: DataFrame, k: u32) -> Result<DataFrame> {
     let algorithm_fields = [col("algorithm"), col("num_threads")];
     let algorithm_series = df
@@ -110,14 +292,139 @@ pub fn cleanup_missing_rows(df: DataFrame, k: u32) -> Result<DataFrame> {
     Ok(df
         .lazy()
         .join(full_df.lazy(), &columns, &columns, JoinType::Outer)
+        .with_column(col("e_min").is_null().alias("imputed"))
         .collect()?
         .fill_null(FillNullStrategy::MaxBound)?)
 }
 
+/// Drop the algorithms that have valid runs on fewer than `min_coverage` percent of the instances
+/// of the data frame
+///
+/// Missing rows of the remaining algorithms are imputed by [`cleanup_missing_rows`]. Returns the
+/// filtered data frame and the dropped algorithms with their coverage in percent.
+pub fn filter_algorithms_by_coverage(
+    df: DataFrame,
+    min_coverage: f64,
+) -> Result<(DataFrame, Vec<(Algorithm, f64)>)> {
+    let algorithm_fields = [col("algorithm"), col("num_threads")];
+    let num_instances = df["instance"].n_unique()? as f64;
+    let coverage_df = df
+        .clone()
+        .lazy()
+        .groupby_stable(&algorithm_fields)
+        .agg([(col("instance").n_unique().cast(DataType::Float64)
+            * lit(100.0 / num_instances))
+        .alias("coverage")])
+        .collect()?;
+    let dropped_df = coverage_df
+        .clone()
+        .lazy()
+        .filter(col("coverage").lt(lit(min_coverage)))
+        .collect()?;
+    let dropped = extract_algorithm_columns(&dropped_df)?
+        .into_iter()
+        .zip(dropped_df["coverage"].f64()?.into_no_null_iter())
+        .collect_vec();
+    let kept_df = coverage_df
+        .lazy()
+        .filter(col("coverage").gt_eq(lit(min_coverage)))
+        .select(&algorithm_fields);
+    let filtered_df = df
+        .lazy()
+        .join(
+            kept_df,
+            &algorithm_fields,
+            &algorithm_fields,
+            JoinType::Inner,
+        )
+        .collect()?;
+    Ok((filtered_df, dropped))
+}
+
+/// One copy of every run of `df` per core class, with the algorithm renamed to
+/// `<algorithm>@<class>` and the running time divided by the speed of the class
+pub fn split_core_classes(
+    df: LazyFrame,
+    classes: &[CoreClass],
+) -> Result<LazyFrame> {
+    let runs = classes
+        .iter()
+        .map(|class| {
+            df.clone().with_columns([
+                concat_str(
+                    [col("algorithm"), lit(format!("@{}", class.name))],
+                    "",
+                )
+                .alias("algorithm"),
+                (col("time").cast(DataType::Float64) / lit(class.speed))
+                    .alias("time"),
+            ])
+        })
+        .collect_vec();
+    Ok(concat(runs, false, false)?)
+}
+
+/// Replace the qualities of the valid runs in `df` by their time to reach the `target` quality,
+/// i.e. the running time of runs that reach the target and `penalty` times the longest running
+/// time on the instance for all other runs
+///
+/// The target of an instance is `factor * best` with [`QualitySemantics::Ratio`] and
+/// `best + factor - 1` with [`QualitySemantics::Difference`], where `best` is the best quality of
+/// any valid run on the instance.
+pub fn time_to_target(
+    df: LazyFrame,
+    target: TimeToTarget,
+    quality_semantics: QualitySemantics,
+) -> LazyFrame {
+    let best = col("quality").min().over([col("instance")]);
+    let reached = match quality_semantics {
+        QualitySemantics::Ratio => {
+            col("quality").lt_eq(best * lit(target.factor))
+        }
+        QualitySemantics::Difference => {
+            col("quality").lt_eq(best + lit(target.factor - 1.0))
+        }
+    };
+    let time = col("time").cast(DataType::Float64);
+    df.filter(col("valid")).with_column(
+        when(reached)
+            .then(time.clone())
+            .otherwise(
+                time.max().over([col("instance")]) * lit(target.penalty),
+            )
+            .alias("quality"),
+    )
+}
+
+/// Keep only the algorithms whose [`gmean_runtimes`] are within `slowdown_ratio` times the
+/// virtual best
 pub fn filter_algorithms_by_slowdown(
     df: LazyFrame,
     slowdown_ratio: f64,
 ) -> Result<LazyFrame> {
+    let algorithm_fields = [col("algorithm"), col("num_threads")];
+    let filtered_df = gmean_runtimes(df.clone(), slowdown_ratio)?
+        .lazy()
+        .filter(col("kept"))
+        .select([col("algorithm"), col("num_threads"), col("gmean")]);
+    Ok(df.join(
+        filtered_df,
+        &algorithm_fields,
+        &algorithm_fields,
+        JoinType::Inner,
+    ))
+}
+
+/// Per-algorithm table of the running times used for the slowdown filtering, ranked from the
+/// fastest to the slowest algorithm
+///
+/// The column `gmean` is the mean of the logarithmic running times of an algorithm over all of its
+/// runs, `kept` marks the algorithms with a `gmean` below `slowdown_ratio` times the one of the
+/// best running times per instance, which pass [`filter_algorithms_by_slowdown`].
+pub fn gmean_runtimes(
+    df: LazyFrame,
+    slowdown_ratio: f64,
+) -> Result<DataFrame> {
     let algorithm_fields = [col("algorithm"), col("num_threads")];
     let gmean = |s: Series| -> Result<Series, PolarsError> {
         let gmean = s.f64()?.into_no_null_iter().map(|v| v.ln()).sum::<f64>()
@@ -142,22 +449,23 @@ pub fn filter_algorithms_by_slowdown(
         }
         gmean_best_per_instance
     };
-    let filtered_df = df
-        .clone()
+    Ok(df
         .groupby(&algorithm_fields)
         .agg([col("time")
             .apply(gmean, GetOutput::from_type(DataType::Float64))
             .first()
             .alias("gmean")])
-        .filter(
-            col("gmean").lt(lit(slowdown_ratio * gmean_best_per_instance)),
-        );
-    Ok(df.join(
-        filtered_df,
-        &algorithm_fields,
-        &algorithm_fields,
-        JoinType::Inner,
-    ))
+        .with_column(
+            col("gmean")
+                .lt(lit(slowdown_ratio * gmean_best_per_instance))
+                .alias("kept"),
+        )
+        .sort_by_exprs(
+            [col("gmean"), col("algorithm"), col("num_threads")],
+            [false, false, false],
+            false,
+        )
+        .collect()?)
 }
 
 pub fn best_per_instance_count(df: DataFrame) -> Result<DataFrame> {
@@ -184,6 +492,46 @@ pub fn best_per_instance_count(df: DataFrame) -> Result<DataFrame> {
         .fill_null(FillNullStrategy::Zero)?)
 }
 
+/// Trim whitespace, NFC-normalize and optionally lowercase a key
+pub fn normalize_key(key: &str, lowercase: bool) -> String {
+    let key = key.trim().nfc().collect::<String>();
+    match lowercase {
+        true => key.to_lowercase(),
+        false => key,
+    }
+}
+
+/// Normalize the string `columns` of `df` with [`normalize_key`]
+///
+/// Also returns all pairs of original and normalized keys that differ.
+pub fn normalize_keys(
+    mut df: DataFrame,
+    columns: &[&str],
+    lowercase: bool,
+) -> Result<(DataFrame, Vec<(String, String)>)> {
+    let mut changed = Vec::new();
+    for &column in columns {
+        let mut normalized = df
+            .column(column)?
+            .utf8()?
+            .into_iter()
+            .map(|key| {
+                key.map(|key| {
+                    let normalized = normalize_key(key, lowercase);
+                    if normalized != key {
+                        changed.push((key.to_string(), normalized.clone()));
+                    }
+                    normalized
+                })
+            })
+            .collect::<Utf8Chunked>()
+            .into_series();
+        normalized.rename(column);
+        df.replace(column, normalized)?;
+    }
+    Ok((df, changed))
+}
+
 pub fn get_desired_instances(path: &PathBuf) -> Result<LazyFrame> {
     if let Ok(reader) = CsvReader::from_path(path) {
         Ok(reader.has_header(true).finish()?.lazy())
@@ -195,3 +543,146 @@ pub fn get_desired_instances(path: &PathBuf) -> Result<LazyFrame> {
         Err(anyhow::Error::msg("No instances file"))
     }
 }
+
+/// Reduce runs with multiple quality checkpoints to a single `quality` per run
+///
+/// The checkpoints are either in long format, with a `checkpoint` column (time in seconds) and
+/// one row per checkpoint, where rows that agree on all other columns belong to the same run, or
+/// in wide format with one `quality_at_<seconds>s` column per checkpoint. Runs without a
+/// checkpoint within the horizon of the `metric` are marked as invalid.
+///
+/// The checkpoints of the `algorithms` with a [startup time](Algorithm::startup) are delayed by
+/// it.
+pub fn anytime_quality(
+    df: DataFrame,
+    metric: QualityMetric,
+    direction: QualityDirection,
+    algorithms: &[Algorithm],
+) -> Result<DataFrame> {
+    let df = match df.get_column_names().contains(&"checkpoint") {
+        true => df,
+        false => wide_to_long_checkpoints(df)?,
+    };
+    let df = match startup_times(algorithms) {
+        Some(startup) => df
+            .lazy()
+            .with_column(
+                (col("checkpoint").cast(DataType::Float64) + startup)
+                    .alias("checkpoint"),
+            )
+            .collect()?,
+        None => df,
+    };
+    let keys = df
+        .get_column_names()
+        .into_iter()
+        .filter(|column| !["checkpoint", "quality"].contains(column))
+        .map(col)
+        .collect_vec();
+    let runs = df
+        .lazy()
+        .with_columns([
+            col("checkpoint").cast(DataType::Float64),
+            col("quality").cast(DataType::Float64),
+        ])
+        .sort("checkpoint", Default::default())
+        .groupby_stable(keys)
+        .agg([col("checkpoint"), col("quality")])
+        .collect()?;
+    let mut quality = runs
+        .column("checkpoint")?
+        .list()?
+        .into_iter()
+        .zip(runs.column("quality")?.list()?)
+        .map(|(checkpoints, qualities)| -> Result<Option<f64>> {
+            let (Some(checkpoints), Some(qualities)) =
+                (checkpoints, qualities)
+            else {
+                return Ok(None);
+            };
+            let curve = checkpoints
+                .f64()?
+                .into_iter()
+                .zip(qualities.f64()?)
+                .filter_map(|(time, quality)| Some((time?, quality?)))
+                .collect_vec();
+            Ok(metric.evaluate_with_direction(&curve, direction))
+        })
+        .collect::<Result<Float64Chunked>>()?
+        .into_series();
+    let mut runs = runs.drop("checkpoint")?.drop("quality")?;
+    quality.rename("quality");
+    runs.with_column(quality)?;
+    let runs = runs
+        .lazy()
+        .with_columns([
+            when(col("quality").is_null())
+                .then(lit(false))
+                .otherwise(col("valid"))
+                .alias("valid"),
+            col("quality").fill_null(lit(f64::MAX)),
+        ])
+        .collect()?;
+    Ok(match runs.get_column_names().contains(&"run") {
+        true => runs.drop("run")?,
+        false => runs,
+    })
+}
+
+/// Startup time of the algorithm of every row, see [`Algorithm::startup`]
+///
+/// `None` if no algorithm of `algorithms` has a startup time.
+pub fn startup_times(algorithms: &[Algorithm]) -> Option<Expr> {
+    algorithms
+        .iter()
+        .filter_map(|algo| Some((algo, algo.startup?)))
+        .fold(None, |startup, (algo, time)| {
+            let matches = col("algorithm")
+                .eq(lit(algo.algorithm.clone()))
+                .and(col("num_threads").eq(lit(algo.num_threads)));
+            Some(
+                when(matches)
+                    .then(lit(time))
+                    .otherwise(startup.unwrap_or_else(|| lit(0.0))),
+            )
+        })
+}
+
+fn wide_to_long_checkpoints(df: DataFrame) -> Result<DataFrame> {
+    let pattern = Regex::new(r"^quality_at_(\d+(?:\.\d+)?)s$")?;
+    let checkpoints = df
+        .get_column_names()
+        .into_iter()
+        .filter(|column| pattern.is_match(column))
+        .map(String::from)
+        .collect_vec();
+    anyhow::ensure!(
+        !checkpoints.is_empty(),
+        "Found neither a checkpoint column nor quality_at_<seconds>s columns"
+    );
+    let df = df.with_row_count("run", None)?;
+    let id_vars = df
+        .get_column_names()
+        .into_iter()
+        .filter(|column| !checkpoints.iter().any(|c| c == column))
+        .map(String::from)
+        .collect_vec();
+    let mut df = df.melt(id_vars, checkpoints)?;
+    let mut checkpoint = df
+        .column("variable")?
+        .utf8()?
+        .into_iter()
+        .map(|variable| {
+            pattern
+                .captures(variable?)
+                .and_then(|captures| captures[1].parse::<f64>().ok())
+        })
+        .collect::<Float64Chunked>()
+        .into_series();
+    checkpoint.rename("checkpoint");
+    let mut quality = df.column("value")?.clone();
+    quality.rename("quality");
+    df.with_column(checkpoint)?;
+    df.with_column(quality)?;
+    Ok(df.drop("variable")?.drop("value")?)
+}
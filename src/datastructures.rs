@@ -1,20 +1,43 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use core::fmt;
 use itertools::Itertools;
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 
-#[derive(
-    Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize, PartialOrd,
-)]
+use crate::schema::{self, Schema};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 /// A data structure representing a portfolio algorithm
+///
+/// Algorithms are identified by their name and number of threads, the memory requirement, cost and
+/// startup time are not compared.
 pub struct Algorithm {
     /// Algorithm name
     pub algorithm: String,
     /// Number of threads the algorithm was executed with
     pub num_threads: u32,
+    /// Peak memory of a single run, in the unit of [`SolverOptions::memory_budget`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<f64>,
+    /// Cost of using the algorithm in the portfolio at all (e.g. license fee or maintenance
+    /// burden), in the unit of [`SolverOptions::cost_budget`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// Fixed startup time of every run (e.g. JIT warm-up or loading a model), in the unit of the
+    /// `time` column, that is not part of the measured running times
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup: Option<f64>,
 }
 
 impl Algorithm {
@@ -23,8 +46,58 @@ impl Algorithm {
         Self {
             algorithm,
             num_threads,
+            memory: None,
+            cost: None,
+            startup: None,
         }
     }
+
+    /// Set the peak memory of a single run
+    pub fn with_memory(mut self, memory: f64) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Set the cost of using the algorithm
+    pub fn with_cost(mut self, cost: f64) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Set the startup time of every run
+    pub fn with_startup(mut self, startup: f64) -> Self {
+        self.startup = Some(startup);
+        self
+    }
+}
+
+impl PartialEq for Algorithm {
+    fn eq(&self, other: &Self) -> bool {
+        self.algorithm == other.algorithm
+            && self.num_threads == other.num_threads
+    }
+}
+
+impl Eq for Algorithm {}
+
+impl std::hash::Hash for Algorithm {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.algorithm.hash(state);
+        self.num_threads.hash(state);
+    }
+}
+
+impl PartialOrd for Algorithm {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Algorithm {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.algorithm, self.num_threads)
+            .cmp(&(&other.algorithm, other.num_threads))
+    }
 }
 
 impl fmt::Display for Algorithm {
@@ -49,8 +122,1002 @@ impl FromStr for Timeout {
     }
 }
 
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// MIP solver used by the [solver](crate::solver::solve)
+pub enum Backend {
+    /// Gurobi, requires a license, falls back to HiGHS if none is found and the `highs` feature
+    /// is enabled
+    #[default]
+    Gurobi,
+    /// HiGHS, requires the `highs` feature
+    Highs,
+    /// COIN-OR CBC, requires the `cbc` feature
+    Cbc,
+    /// OR-Tools CP-SAT, requires the `cpsat` feature, see [`crate::solver::cpsat`]
+    #[serde(rename = "cpsat")]
+    CpSat,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "gurobi" => Ok(Self::Gurobi),
+            "highs" => Ok(Self::Highs),
+            "cbc" => Ok(Self::Cbc),
+            "cpsat" => Ok(Self::CpSat),
+            _ => anyhow::bail!("Unknown backend {s}"),
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// Formulation of the model of the Gurobi backend of the [solver](crate::solver::solve)
+pub enum Decomposition {
+    /// Benders decomposition for data with more than [`crate::solver::BENDERS_CELLS`] cells
+    /// (instances times algorithms times repetitions), the monolithic model otherwise
+    #[default]
+    Auto,
+    /// A single model with an assignment variable per cell
+    Monolithic,
+    /// Benders decomposition into a master model selecting the algorithms and per-instance
+    /// subproblems, see [`crate::solver::solve`]
+    Benders,
+}
+
+impl FromStr for Decomposition {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "monolithic" => Ok(Self::Monolithic),
+            "benders" => Ok(Self::Benders),
+            _ => anyhow::bail!("Unknown decomposition {s}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+/// Objective of the [solver](crate::solver::solve) in terms of the quality ratio `q_i / best_i` of
+/// every instance, i.e. the expected best quality of the portfolio relative to the best quality
+pub enum Objective {
+    /// Sum of the quality ratios
+    #[default]
+    QualityRatio,
+    /// Geometric mean of the quality ratios, the solver minimizes the sum of their logarithms
+    GeometricMean,
+    /// Largest quality ratio of any instance (worst-case regret)
+    WorstCase,
+    /// Sum of the quality ratios, but ratios above `cutoff` count as `factor * cutoff` (like PAR10
+    /// for running times)
+    Penalized {
+        /// Largest quality ratio that is not penalized
+        cutoff: f64,
+        /// Multiple of the `cutoff` a penalized instance counts as
+        factor: f64,
+    },
+    /// Conditional value at risk: mean of the quality ratios of the worst `alpha`-fraction of
+    /// instances, between the mean (`alpha = 1`) and the worst case (`alpha` close to 0)
+    Cvar {
+        /// Fraction of the instances in the tail, in `(0, 1]`
+        alpha: f64,
+    },
+    /// Mean plus `lambda` times the (population) variance of the quality ratios, to prefer
+    /// portfolios that are consistently good over ones that are great on average but bad on a few
+    /// instances, requires the Gurobi backend (quadratic objective)
+    MeanVariance {
+        /// Weight of the variance, at least 0
+        lambda: f64,
+    },
+    /// Objective registered under this name with [`crate::solver::objectives::register`], its
+    /// [`score`](Objective::score) and [`value`](Objective::value) are the ones of
+    /// [`Objective::QualityRatio`], see [`crate::solver::objectives::value`] instead
+    Custom(String),
+}
+
+impl Objective {
+    /// Contribution of an instance with quality ratio `ratio` to the objective, the objective is
+    /// the sum (or the maximum for [`Objective::WorstCase`], the tail mean for
+    /// [`Objective::Cvar`] and the mean plus the weighted variance for
+    /// [`Objective::MeanVariance`]) of all contributions
+    pub fn score(&self, ratio: f64) -> f64 {
+        match self {
+            Self::QualityRatio
+            | Self::WorstCase
+            | Self::Cvar { .. }
+            | Self::MeanVariance { .. }
+            | Self::Custom(_) => ratio,
+            Self::GeometricMean => ratio.ln(),
+            Self::Penalized { cutoff, factor } => match ratio > *cutoff {
+                true => factor * cutoff,
+                false => ratio,
+            },
+        }
+    }
+
+    /// Objective value of the quality ratios of all instances
+    pub fn value(&self, ratios: impl IntoIterator<Item = f64>) -> f64 {
+        let scores = ratios.into_iter().map(|ratio| self.score(ratio));
+        match self {
+            Self::WorstCase => scores.fold(f64::NEG_INFINITY, f64::max),
+            Self::Cvar { alpha } => {
+                let scores =
+                    scores.sorted_by(|a, b| b.total_cmp(a)).collect_vec();
+                // the instance at the boundary of the tail counts fractionally
+                let tail = alpha * scores.len() as f64;
+                let mut remaining = tail;
+                let mut sum = 0.0;
+                for score in scores {
+                    let weight = remaining.min(1.0);
+                    if weight <= 0.0 {
+                        break;
+                    }
+                    sum += weight * score;
+                    remaining -= weight;
+                }
+                sum / tail
+            }
+            Self::MeanVariance { lambda } => {
+                let scores = scores.collect_vec();
+                let m = scores.len() as f64;
+                let mean = scores.iter().sum::<f64>() / m;
+                let variance =
+                    scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / m;
+                mean + lambda * variance
+            }
+            _ => scores.sum(),
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = anyhow::Error;
+    /// `ratio`, `gmean`, `worst-case`, `par<factor>:<cutoff>` (e.g. `par10:2.0`),
+    /// `cvar<alpha>` (e.g. `cvar0.1`), `mv<lambda>` (e.g. `mv0.5`) or the name of a
+    /// [registered](crate::solver::objectives::register) objective
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "ratio" => Ok(Self::QualityRatio),
+            "gmean" => Ok(Self::GeometricMean),
+            "worst-case" => Ok(Self::WorstCase),
+            par if par.starts_with("par") => {
+                let (factor, cutoff) = par[3..]
+                    .split_once(':')
+                    .context("Expected par<factor>:<cutoff>")?;
+                Ok(Self::Penalized {
+                    cutoff: cutoff.parse()?,
+                    factor: factor.parse()?,
+                })
+            }
+            cvar if cvar.starts_with("cvar") => {
+                let alpha: f64 = cvar[4..].parse()?;
+                anyhow::ensure!(
+                    alpha > 0.0 && alpha <= 1.0,
+                    "The CVaR fraction {alpha} is not in (0, 1]"
+                );
+                Ok(Self::Cvar { alpha })
+            }
+            mv if mv.starts_with("mv") => {
+                let lambda: f64 = mv[2..].parse()?;
+                anyhow::ensure!(
+                    lambda >= 0.0,
+                    "The variance weight {lambda} is negative"
+                );
+                Ok(Self::MeanVariance { lambda })
+            }
+            name if crate::solver::objectives::get(name).is_some() => {
+                Ok(Self::Custom(name.to_string()))
+            }
+            _ => anyhow::bail!("Unknown objective {s}"),
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// How the qualities of the input data are compared to the best quality per instance, see
+/// [`crate::csv_parser::DataOptions`]
+pub enum QualitySemantics {
+    /// Qualities are positive absolute values (e.g. a cut), the quality ratio of an instance is
+    /// `q_i / best_i`. Qualities of zero are replaced by one.
+    #[default]
+    Ratio,
+    /// Qualities are relative to some reference (e.g. the improvement over an input solution) and
+    /// may be zero or negative, the quality ratio of an instance is the additive difference
+    /// `1 + q_i - best_i`
+    Difference,
+}
+
+impl FromStr for QualitySemantics {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "ratio" => Ok(Self::Ratio),
+            "difference" => Ok(Self::Difference),
+            _ => anyhow::bail!("Unknown quality semantics {s}"),
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// Whether smaller or larger qualities of the input data are better, see
+/// [`crate::csv_parser::DataOptions`]
+pub enum QualityDirection {
+    /// Smaller qualities are better (e.g. a cut)
+    #[default]
+    Minimize,
+    /// Larger qualities are better (e.g. a score or a gain)
+    Maximize,
+}
+
+impl QualityDirection {
+    /// The better of two qualities
+    pub fn better(self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Minimize => a.min(b),
+            Self::Maximize => a.max(b),
+        }
+    }
+}
+
+impl FromStr for QualityDirection {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "minimize" | "min" => Ok(Self::Minimize),
+            "maximize" | "max" => Ok(Self::Maximize),
+            _ => anyhow::bail!("Unknown quality direction {s}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// How the expected best quality of repeated runs of an algorithm is estimated from its runs, see
+/// [`crate::csv_parser::DataOptions`]
+pub enum QualityEstimator {
+    /// Best quality of runs sampled with replacement, averaged over the given number of
+    /// independent sampling rounds
+    Sampling(u32),
+    /// Exact expectation of the best quality of runs drawn uniformly with replacement
+    Exact,
+}
+
+impl Default for QualityEstimator {
+    fn default() -> Self {
+        Self::Sampling(1)
+    }
+}
+
+impl FromStr for QualityEstimator {
+    type Err = anyhow::Error;
+    /// `exact`, `sampling` or `sampling:<rounds>` (e.g. `sampling:10`)
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(Self::Exact),
+            "sampling" => Ok(Self::Sampling(1)),
+            sampling if sampling.starts_with("sampling:") => {
+                let rounds = sampling["sampling:".len()..].parse()?;
+                anyhow::ensure!(
+                    rounds > 0,
+                    "At least one sampling round is required"
+                );
+                Ok(Self::Sampling(rounds))
+            }
+            _ => anyhow::bail!("Unknown quality estimator {s}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// Method that found the portfolio of [`crate::solver::solve_racing`]
+pub enum SolveMethod {
+    /// The MIP, see [`crate::solver::solve`]
+    Mip,
+    /// The genetic algorithm, see [`crate::solver::genetic`]
+    Genetic,
+    /// The greedy heuristic, see [`crate::solver::greedy_solve`]
+    Greedy,
+}
+
+impl fmt::Display for SolveMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mip => write!(f, "MIP"),
+            Self::Genetic => write!(f, "genetic"),
+            Self::Greedy => write!(f, "greedy"),
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// How portfolios with the same name are handled, see [`disambiguate_names`]
+pub enum NameCollision {
+    /// Append a counter to the name
+    #[default]
+    Suffix,
+    /// Fail
+    Error,
+    /// Keep the name, e.g. the file of the last portfolio with the name is kept
+    Overwrite,
+}
+
+impl FromStr for NameCollision {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "suffix" => Ok(Self::Suffix),
+            "error" => Ok(Self::Error),
+            "overwrite" => Ok(Self::Overwrite),
+            _ => anyhow::bail!("Unknown name collision mode {s}"),
+        }
+    }
+}
+
+/// Unique names for the `names` of the portfolios of a run, e.g. named with
+/// [`Portfolio::with_parameters`]
+///
+/// A name that occurs multiple times is handled according to `mode`, with
+/// [`NameCollision::Suffix`] it becomes `<name>_2`, `<name>_3`, ... from its second occurrence on.
+pub fn disambiguate_names(
+    names: &[String],
+    mode: NameCollision,
+) -> Result<Vec<String>> {
+    let mut used = HashSet::new();
+    names
+        .iter()
+        .map(|name| {
+            let unique = match (used.contains(name), mode) {
+                (false, _) | (true, NameCollision::Overwrite) => name.clone(),
+                (true, NameCollision::Error) => {
+                    anyhow::bail!("The portfolio name {name} is not unique")
+                }
+                (true, NameCollision::Suffix) => (2..)
+                    .map(|i| format!("{name}_{i}"))
+                    .find(|candidate| {
+                        !used.contains(candidate) && !names.contains(candidate)
+                    })
+                    .unwrap(),
+            };
+            used.insert(unique.clone());
+            Ok(unique)
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+/// How the quality checkpoints of a run are reduced to a single quality, see
+/// [`crate::csv_parser::parse_anytime_csvs`]
+pub enum QualityMetric {
+    /// Best quality reached within the given number of seconds
+    AtTime(f64),
+    /// Area under the curve of the best quality so far up to the given number of seconds,
+    /// divided by the time from the first checkpoint to the horizon
+    AreaUnderCurve(f64),
+}
+
+impl QualityMetric {
+    /// Time horizon in seconds
+    pub fn horizon(&self) -> f64 {
+        match *self {
+            QualityMetric::AtTime(horizon) => horizon,
+            QualityMetric::AreaUnderCurve(horizon) => horizon,
+        }
+    }
+
+    /// Reduce the `(time, quality)` checkpoints of a run (sorted by time) to a single quality
+    ///
+    /// `None` if the run has no checkpoint within the horizon.
+    pub fn evaluate(&self, checkpoints: &[(f64, f64)]) -> Option<f64> {
+        self.evaluate_with_direction(checkpoints, QualityDirection::Minimize)
+    }
+
+    /// Reduce the `(time, quality)` checkpoints of a run (sorted by time) to a single quality,
+    /// where the best quality so far follows the `direction`
+    pub fn evaluate_with_direction(
+        &self,
+        checkpoints: &[(f64, f64)],
+        direction: QualityDirection,
+    ) -> Option<f64> {
+        let horizon = self.horizon();
+        let checkpoints = checkpoints
+            .iter()
+            .take_while(|(time, _)| *time <= horizon)
+            .collect_vec();
+        let &(start, _) = *checkpoints.first()?;
+        match self {
+            QualityMetric::AtTime(_) => checkpoints
+                .iter()
+                .map(|(_, quality)| *quality)
+                .reduce(|a, b| direction.better(a, b)),
+            QualityMetric::AreaUnderCurve(_) => {
+                if horizon <= start {
+                    return Some(checkpoints[0].1);
+                }
+                let mut best = checkpoints[0].1;
+                let mut area = 0.0;
+                for (i, (time, quality)) in checkpoints.iter().enumerate() {
+                    best = direction.better(best, *quality);
+                    let end = checkpoints
+                        .get(i + 1)
+                        .map_or(horizon, |(end, _)| *end);
+                    area += best * (end - time);
+                }
+                Some(area / (horizon - start))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Hard upper bound on the expected quality per instance relative to the best quality
+pub struct QualityGuarantee {
+    /// The expected quality of an instance may be at most `factor * best_per_instance`
+    pub factor: f64,
+    /// Indices of the instances the guarantee applies to, all instances if `None`
+    pub instances: Option<Vec<usize>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// Hard quality threshold per instance of a [`ChanceConstraint`], like in competitions that only
+/// score solutions within a factor of the best known solution
+pub struct QualityThreshold {
+    /// A run reaches the threshold with a quality of at most `factor * best_per_instance`
+    pub factor: f64,
+    /// Minimum probability that a run of the portfolio reaches the threshold on every instance,
+    /// between zero and one (exclusive)
+    pub probability: f64,
+}
+
+impl FromStr for QualityThreshold {
+    type Err = anyhow::Error;
+    /// `<factor>:<probability>`, e.g. `1.05:0.9`
+    fn from_str(s: &str) -> Result<Self> {
+        let (factor, probability) = s
+            .split_once(':')
+            .context("Expected <factor>:<probability>")?;
+        Ok(Self {
+            factor: factor.parse()?,
+            probability: probability.parse()?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// Target quality per instance of the time-to-target mode, the model then minimizes the expected
+/// time until a run of the portfolio reaches the target instead of the expected best quality, see
+/// [`crate::csv_parser::DataOptions::time_to_target`]
+pub struct TimeToTarget {
+    /// A run reaches the target with a quality of at most `factor * best_per_instance`
+    pub factor: f64,
+    /// Runs that miss the target count as `penalty` times the longest running time of a valid
+    /// run on the instance, like the PAR10 score of algorithm competitions
+    #[serde(default = "TimeToTarget::default_penalty")]
+    pub penalty: f64,
+}
+
+impl TimeToTarget {
+    fn default_penalty() -> f64 {
+        10.0
+    }
+}
+
+impl FromStr for TimeToTarget {
+    type Err = anyhow::Error;
+    /// `<factor>[:<penalty>]`, e.g. `1.05` or `1.05:2`
+    fn from_str(s: &str) -> Result<Self> {
+        let (factor, penalty) = match s.split_once(':') {
+            Some((factor, penalty)) => (factor, penalty.parse()?),
+            None => (s, Self::default_penalty()),
+        };
+        let factor = factor.parse()?;
+        anyhow::ensure!(
+            penalty >= 1.0,
+            "The penalty of missed targets must be at least 1, got {penalty}"
+        );
+        Ok(Self { factor, penalty })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Per-instance chance constraint of the [solver](crate::solver::solve): with the probability
+/// of the [threshold](QualityThreshold), at least one run of the portfolio on an instance reaches
+/// the quality threshold
+///
+/// The runs of all algorithm copies are assumed to be independent, so no copy reaches the
+/// threshold with probability `prod_j (1 - success[(i, j)])^copies_j`.
+pub struct ChanceConstraint {
+    /// The threshold and the required probability
+    pub threshold: QualityThreshold,
+    /// `success[(i, j)]` is the probability that a single run of algorithm `j` reaches the
+    /// threshold on instance `i`, see [`crate::csv_parser::success_rates`]
+    pub success: ndarray::Array2<f64>,
+}
+
+impl ChanceConstraint {
+    /// Probability per instance that at least one run of the `copies` (per algorithm) reaches the
+    /// threshold
+    pub fn probabilities(&self, copies: &[f64]) -> Vec<f64> {
+        self.success
+            .outer_iter()
+            .map(|success| {
+                1.0 - success
+                    .iter()
+                    .zip(copies)
+                    .map(|(s, c)| (1.0 - s).powf(c.round()))
+                    .product::<f64>()
+            })
+            .collect()
+    }
+
+    /// Whether the `copies` reach the threshold with the required probability on every instance
+    pub fn satisfied(&self, copies: &[f64]) -> bool {
+        self.probabilities(copies)
+            .into_iter()
+            .all(|p| p >= self.threshold.probability - 1e-9)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Penalty for deviating from a previous portfolio, e.g. when re-optimizing with new data
+pub struct StabilityPenalty {
+    /// The portfolio to stay close to
+    pub previous: Portfolio,
+    /// Penalty per core that is assigned differently than in the previous portfolio
+    pub penalty: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// Algorithms that are forced into or out of the portfolio
+pub struct AlgorithmConstraints {
+    /// Algorithms that get at least one copy in the portfolio, e.g. a trusted default solver
+    #[serde(default)]
+    pub include: Vec<Algorithm>,
+    /// Algorithms that get no cores in the portfolio
+    #[serde(default)]
+    pub exclude: Vec<Algorithm>,
+    /// Bounds on the cores of single algorithms
+    #[serde(default)]
+    pub core_bounds: Vec<CoreBounds>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Bounds on the cores an algorithm receives in the portfolio, e.g. for algorithms that don't
+/// scale to many copies
+pub struct CoreBounds {
+    /// The bounded algorithm
+    pub algorithm: Algorithm,
+    /// The algorithm gets at least this many cores, i.e. it is forced into the portfolio if this
+    /// is nonzero
+    #[serde(default)]
+    pub min_cores: u32,
+    /// The algorithm gets at most this many cores
+    #[serde(default)]
+    pub max_cores: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// Options for the [solver](crate::solver::solve)
+pub struct SolverOptions {
+    /// Timeout for the solver
+    pub timeout: Timeout,
+    /// Optional per-instance quality guarantees added as constraints to the model
+    pub quality_guarantee: Option<QualityGuarantee>,
+    /// Optional per-instance chance constraint on reaching a quality threshold, only supported by
+    /// the Gurobi backend without [checkpoints](SolverOptions::checkpoint)
+    #[serde(skip)]
+    pub chance_constraint: Option<ChanceConstraint>,
+    /// Optional penalty per unassigned core added to the objective
+    ///
+    /// Only has an effect if all algorithms are multi-threaded, otherwise all cores are used
+    /// anyway. The penalty is in the unit of the [`objective`](SolverOptions::objective), e.g.
+    /// quality ratios by default.
+    pub idle_core_penalty: Option<f64>,
+    /// Optional penalty per algorithm in the portfolio added to the objective, to prefer fewer
+    /// algorithms when more of them improve the objective only marginally
+    ///
+    /// The penalty is in the unit of the [`objective`](SolverOptions::objective), e.g. a penalty
+    /// of 0.01 only keeps an algorithm if it improves the sum of the quality ratios by more than
+    /// 0.01.
+    #[serde(default)]
+    pub complexity_penalty: Option<f64>,
+    /// Optional penalty on the L1 distance of the core assignment to a previous portfolio
+    pub stability_penalty: Option<StabilityPenalty>,
+    /// MIP solver to use
+    pub backend: Backend,
+    /// Formulation of the model of the Gurobi backend
+    #[serde(default)]
+    pub decomposition: Decomposition,
+    /// Add the constraints linking the assignment of the instances to the selected algorithms and
+    /// their qualities (constraints 1 and 5) of the monolithic Gurobi model lazily when a solution
+    /// violates them, instead of all `m n k` of them upfront
+    #[serde(default)]
+    pub lazy_linking: bool,
+    /// Drop the selections of algorithms and numbers of repetitions that are dominated by another
+    /// selection from the model of the Gurobi backend, see [`crate::solver::dominated_cells`]
+    #[serde(default)]
+    pub prune_dominated: bool,
+    /// Objective to minimize, all objectives but [`Objective::QualityRatio`] are only supported
+    /// by the Gurobi backend
+    pub objective: Objective,
+    /// Algorithms that must or must not be part of the portfolio
+    pub algorithm_constraints: AlgorithmConstraints,
+    /// Optional bound on the total [`memory`](Algorithm::memory) of all algorithm copies, e.g.
+    /// the RAM of the node (algorithms without a memory requirement need no memory)
+    pub memory_budget: Option<f64>,
+    /// Optional bound on the total [`cost`](Algorithm::cost) of the algorithms in the portfolio,
+    /// every algorithm with at least one copy counts once
+    pub cost_budget: Option<f64>,
+    /// Classes of cores of a heterogeneous machine, the algorithms of every class (see
+    /// [`CoreClass::of`]) get at most the cores of the class, empty for homogeneous cores
+    #[serde(default)]
+    pub core_classes: Vec<CoreClass>,
+    /// Optional topology of the machine, every copy of an algorithm has to fit on the cores of a
+    /// single NUMA node (or socket), e.g. to keep multi-threaded algorithms from spanning sockets
+    #[serde(default)]
+    pub topology: Option<Topology>,
+    /// Forbid assigning instances to algorithms whose expected best quality on the instance was
+    /// [imputed](crate::csv_parser::Data::imputed) instead of measured
+    pub forbid_imputed: bool,
+    /// Optional relative MIP gap at which the solver stops early, independent of the timeout, see
+    /// [`Termination::GapReached`]
+    pub mip_gap: Option<f64>,
+    /// Optional limit on the number of branch-and-bound nodes explored by the solver, ignored by
+    /// the CP-SAT backend
+    pub node_limit: Option<u64>,
+    /// Optional number of threads used by the solver, the solver decides if not set
+    pub threads: Option<u32>,
+    /// Optional random seed of the solver
+    pub seed: Option<u32>,
+    /// Optional `NumericFocus` between 0 and 3 of the Gurobi backend, 1 if not set
+    pub numeric_focus: Option<u8>,
+    /// Whether the solver logs its progress, by default only if the log level is at least `Info`
+    pub solver_output: Option<bool>,
+    /// Optional token to stop the solver early, e.g. from another thread of a service
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+    /// Optional number of best distinct portfolios returned in the
+    /// [`pool`](OptimizationResult::pool) of the Gurobi backend, e.g. to compare near-optimal
+    /// alternatives in the simulation
+    pub pool_size: Option<usize>,
+    /// Optional relative gap of the objective of the portfolios in the pool to the best portfolio,
+    /// worse portfolios are discarded
+    pub pool_gap: Option<f64>,
+    /// Optional checkpoints of the Gurobi backend to [resume](crate::solver::resume) an
+    /// interrupted solve, e.g. of a cluster job running into its wall-time limit
+    pub checkpoint: Option<CheckpointOptions>,
+    /// Optional simulation of every incumbent of the Gurobi backend, see
+    /// [`SimulationValidation`]
+    #[serde(skip)]
+    pub validation: Option<SimulationValidation>,
+    /// Optional relative degradation of the objective up to which algorithms are removed from the
+    /// final portfolio, see [`crate::solver::prune`]
+    #[serde(default)]
+    pub prune_tolerance: Option<f64>,
+    /// Optional file the Gurobi backend writes its model to before solving it, e.g. to tune the
+    /// parameters with external tools (not the master of the [`Decomposition::Benders`])
+    #[serde(default)]
+    pub model_export: Option<ModelExport>,
+    /// Optional `.sol` or `.mst` file the Gurobi backend reads a start solution from, e.g. a
+    /// solution of the [exported model](SolverOptions::model_export) found by another tool, it
+    /// replaces the initial portfolio for the variables it contains
+    #[serde(default)]
+    pub start_solution: Option<PathBuf>,
+    /// Optional license of the Gurobi environments the solver creates, the license file found by
+    /// Gurobi if not set (not written to checkpoints)
+    #[serde(skip)]
+    pub license: Option<GurobiLicense>,
+    /// Add the expected quality of the final portfolio on every instance to the
+    /// [`instance_qualities`](OptimizationResult::instance_qualities) of the result
+    #[serde(default)]
+    pub instance_qualities: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+/// License of a Gurobi environment, see [`crate::solver::Solver::with_license`]
+pub enum GurobiLicense {
+    /// Floating license checked out from a token server
+    TokenServer {
+        /// Host name or address of the token server
+        server: String,
+        /// Optional password of the token server
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// Web License Service license, e.g. for containers and cloud machines
+    Wls {
+        /// WLS access id
+        access_id: String,
+        /// WLS secret
+        secret: String,
+        /// WLS license id
+        license_id: i32,
+    },
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// File format of an exported model, see [`ModelExport`]
+pub enum ModelFormat {
+    /// The human-readable LP format
+    #[default]
+    Lp,
+    /// The MPS format, which most MIP solvers and tuning tools read
+    Mps,
+}
+
+impl ModelFormat {
+    /// Extension of the files of this format, Gurobi chooses the format by the extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Lp => "lp",
+            Self::Mps => "mps",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Where and in which format the Gurobi backend writes its model, see
+/// [`SolverOptions::model_export`]
+pub struct ModelExport {
+    /// Path of the model file, its extension is replaced by the one of the format
+    pub path: PathBuf,
+    /// Format of the model file
+    #[serde(default)]
+    pub format: ModelFormat,
+}
+
+impl ModelExport {
+    /// The path with the extension of the format
+    pub fn file(&self) -> PathBuf {
+        self.path.with_extension(self.format.extension())
+    }
+}
+
+impl FromStr for ModelExport {
+    type Err = anyhow::Error;
+    /// Path of the model file, whose extension `.lp` or `.mps` determines the format
+    fn from_str(s: &str) -> Result<Self> {
+        let path = PathBuf::from(s);
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("lp") => ModelFormat::Lp,
+            Some("mps") => ModelFormat::Mps,
+            _ => anyhow::bail!(
+                "Expected a model file ending in .lp or .mps, got {s}"
+            ),
+        };
+        Ok(Self { path, format })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Where and how often the [solver](crate::solver::solve) writes checkpoints, see
+/// [`crate::solver::resume`]
+pub struct CheckpointOptions {
+    /// Directory of the checkpoint, created if missing
+    pub dir: PathBuf,
+    /// Seconds between two checkpoints without a new incumbent, which only update the elapsed
+    /// runtime (every new incumbent is written immediately)
+    pub interval: f64,
+}
+
+#[derive(Debug, Clone)]
+/// Simulate every incumbent of the [solver](crate::solver::solve) on the measured runs and record
+/// the empirical objective in [`Incumbent::simulated`], to catch mismatches between the expected
+/// qualities of the model and the simulated execution while the solver is still running
+///
+/// Like for [`crate::portfolio_simulator::consistency_check`], the data has to be built from `df`
+/// without filters removing instances and with [`QualitySemantics::Ratio`].
+pub struct SimulationValidation {
+    /// Normalized data frame the data was built from, see
+    /// [`crate::csv_parser::parse_normalized_csvs`]
+    pub df: Arc<polars::prelude::DataFrame>,
+    /// Number of simulated seeds per incumbent, every seed slows down the solver
+    pub num_seeds: u32,
+    /// Relative deviation of the simulated from the expected objective above which a warning is
+    /// logged
+    pub tolerance: f64,
+}
+
+impl SolverOptions {
+    /// Construct default solver options with a timeout
+    pub fn new(timeout: Timeout) -> Self {
+        Self {
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Set the relative MIP gap at which the solver stops early
+    pub fn with_mip_gap(mut self, mip_gap: f64) -> Self {
+        self.mip_gap = Some(mip_gap);
+        self
+    }
+
+    /// Set the limit on the number of branch-and-bound nodes
+    pub fn with_node_limit(mut self, node_limit: u64) -> Self {
+        self.node_limit = Some(node_limit);
+        self
+    }
+
+    /// Set the number of threads used by the solver
+    pub fn with_threads(mut self, threads: u32) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Set the random seed of the solver
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the Gurobi `NumericFocus`
+    pub fn with_numeric_focus(mut self, numeric_focus: u8) -> Self {
+        self.numeric_focus = Some(numeric_focus);
+        self
+    }
+
+    /// Enable or disable the log output of the solver
+    pub fn with_solver_output(mut self, solver_output: bool) -> Self {
+        self.solver_output = Some(solver_output);
+        self
+    }
+
+    /// Stop the solver when the `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Return the `pool_size` best distinct portfolios within the relative objective gap
+    /// `pool_gap` (if any) of the best portfolio, see [`OptimizationResult::pool`]
+    pub fn with_pool(
+        mut self,
+        pool_size: usize,
+        pool_gap: Option<f64>,
+    ) -> Self {
+        self.pool_size = Some(pool_size);
+        self.pool_gap = pool_gap;
+        self
+    }
+
+    /// Write checkpoints to `dir`, at least every `interval` seconds
+    pub fn with_checkpoint(
+        mut self,
+        dir: impl Into<PathBuf>,
+        interval: f64,
+    ) -> Self {
+        self.checkpoint = Some(CheckpointOptions {
+            dir: dir.into(),
+            interval,
+        });
+        self
+    }
+
+    /// Simulate every incumbent on `df` with `num_seeds` seeds and warn about relative deviations
+    /// from the expected objective above `tolerance`, see [`SimulationValidation`]
+    pub fn with_validation(
+        mut self,
+        df: Arc<polars::prelude::DataFrame>,
+        num_seeds: u32,
+        tolerance: f64,
+    ) -> Self {
+        self.validation = Some(SimulationValidation {
+            df,
+            num_seeds,
+            tolerance,
+        });
+        self
+    }
+
+    /// Remove algorithms from the final portfolio as long as the objective degrades by at most
+    /// the relative `tolerance`, see [`crate::solver::prune`]
+    pub fn with_prune_tolerance(mut self, tolerance: f64) -> Self {
+        self.prune_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Write the model to `path` in the `format` before solving it, see
+    /// [`SolverOptions::model_export`]
+    pub fn with_model_export(
+        mut self,
+        path: impl Into<PathBuf>,
+        format: ModelFormat,
+    ) -> Self {
+        self.model_export = Some(ModelExport {
+            path: path.into(),
+            format,
+        });
+        self
+    }
+
+    /// Start from the solution in the `.sol` or `.mst` file at `path`, see
+    /// [`SolverOptions::start_solution`]
+    pub fn with_start_solution(mut self, path: impl Into<PathBuf>) -> Self {
+        self.start_solution = Some(path.into());
+        self
+    }
+
+    /// Create the Gurobi environments of the solver with the `license`, see
+    /// [`SolverOptions::license`]
+    pub fn with_license(mut self, license: GurobiLicense) -> Self {
+        self.license = Some(license);
+        self
+    }
+
+    /// Add the expected quality of the final portfolio on every instance to the result
+    pub fn with_instance_qualities(mut self) -> Self {
+        self.instance_qualities = true;
+        self
+    }
+
+    /// Whether the [`cancellation`](SolverOptions::cancellation) token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .map_or(false, CancellationToken::is_cancelled)
+    }
+
+    /// Whether the solver logs its progress
+    pub fn solver_output(&self) -> bool {
+        self.solver_output
+            .unwrap_or_else(|| log::log_enabled!(log::Level::Info))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Handle to stop a running [solver](crate::solver::solve) gracefully
+///
+/// All clones share the same state, keep a clone and pass another one to the solver in the
+/// [`SolverOptions`]. After [`cancel`](CancellationToken::cancel), the solver stops as soon as
+/// possible and returns the best portfolio found so far.
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Construct a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signal the solver to stop
+    pub fn cancel(&self) {
+        self.0.store(true, atomic::Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](CancellationToken::cancel) was called on any clone of the token
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(atomic::Ordering::Relaxed)
+    }
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(into = "PortfolioJson", try_from = "PortfolioJson")]
 /// A algorithm portfolio with resource assignment
+///
+/// Serialized with the [`Kind::Portfolio`](crate::schema::Kind::Portfolio) schema.
 pub struct Portfolio {
     /// Name of the portfolio
     pub name: String,
@@ -58,6 +1125,37 @@ pub struct Portfolio {
     pub resource_assignments: Vec<(Algorithm, f64)>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// JSON format of a [`Portfolio`]
+struct PortfolioJson {
+    #[serde(default)]
+    schema: Option<Schema>,
+    name: String,
+    resource_assignments: Vec<(Algorithm, f64)>,
+}
+
+impl From<Portfolio> for PortfolioJson {
+    fn from(portfolio: Portfolio) -> Self {
+        Self {
+            schema: Some(Schema::current(schema::Kind::Portfolio)),
+            name: portfolio.name,
+            resource_assignments: portfolio.resource_assignments,
+        }
+    }
+}
+
+impl TryFrom<PortfolioJson> for Portfolio {
+    type Error = anyhow::Error;
+    fn try_from(json: PortfolioJson) -> Result<Self> {
+        schema::check(json.schema, schema::Kind::Portfolio)?;
+        Ok(Self {
+            name: json.name,
+            resource_assignments: json.resource_assignments,
+        })
+    }
+}
+
 impl fmt::Display for Portfolio {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (algo, cores) in &self.resource_assignments {
@@ -116,13 +1214,334 @@ impl Portfolio {
                 .collect_vec(),
         }
     }
+
+    /// The portfolio named `<name>_<parameters>`, e.g. `final_portfolio_k8_s0.5`, the `_opt`
+    /// suffix of an optimal portfolio stays at the end
+    pub fn with_parameters(mut self, parameters: &str) -> Self {
+        self.name = match self.name.strip_suffix("_opt") {
+            Some(name) => format!("{name}_{parameters}_opt"),
+            None => format!("{}_{parameters}", self.name),
+        };
+        self
+    }
+
+    /// Map every algorithm copy to a contiguous range of cores, in order of the resource
+    /// assignments
+    ///
+    /// Fails if the portfolio uses more than `num_cores` cores.
+    pub fn core_plan(&self, num_cores: u32) -> Result<Vec<CoreRange>> {
+        self.core_plan_with_topology(num_cores, None)
+    }
+
+    /// Like [`Portfolio::core_plan`], but with a NUMA `topology`, every copy is placed on a single
+    /// NUMA node if possible
+    ///
+    /// Copies with more threads are placed first, each on the first node with enough contiguous
+    /// free cores or, if there is none, on the first contiguous free cores spanning multiple
+    /// nodes. The plan is sorted by the first core of the ranges.
+    pub fn core_plan_with_topology(
+        &self,
+        num_cores: u32,
+        topology: Option<&Topology>,
+    ) -> Result<Vec<CoreRange>> {
+        let copies = self
+            .resource_assignments
+            .iter()
+            .flat_map(|(algorithm, copies)| {
+                (0..*copies as u32).map(move |_| algorithm)
+            })
+            .collect_vec();
+        let used = copies.iter().map(|a| a.num_threads).sum::<u32>();
+        anyhow::ensure!(
+            used <= num_cores,
+            "Portfolio {} uses {used} cores, only {num_cores} are available",
+            self.name
+        );
+        let Some(topology) = topology else {
+            let mut first_core = 0;
+            return Ok(copies
+                .into_iter()
+                .map(|algorithm| {
+                    let range = CoreRange {
+                        algorithm: algorithm.clone(),
+                        first_core,
+                        numa_node: None,
+                    };
+                    first_core += algorithm.num_threads;
+                    range
+                })
+                .collect_vec());
+        };
+        anyhow::ensure!(
+            topology.numa_nodes.iter().sum::<u32>() == num_cores,
+            "The NUMA nodes have {} cores, expected {num_cores}",
+            topology.numa_nodes.iter().sum::<u32>()
+        );
+        let nodes = topology
+            .numa_nodes
+            .iter()
+            .scan(0, |first_core, &size| {
+                *first_core += size;
+                Some(*first_core - size..*first_core)
+            })
+            .collect_vec();
+        let node_of = |core: u32| nodes.iter().position(|n| n.contains(&core));
+        let mut free = vec![true; num_cores as usize];
+        let mut first_fit = |range: std::ops::Range<u32>, threads: u32| {
+            let mut run = 0;
+            for core in range {
+                run = if free[core as usize] { run + 1 } else { 0 };
+                if run == threads {
+                    let first_core = core + 1 - threads;
+                    free[first_core as usize..=core as usize].fill(false);
+                    return Some(first_core);
+                }
+            }
+            None
+        };
+        let mut plan = copies
+            .into_iter()
+            .sorted_by_key(|algorithm| {
+                std::cmp::Reverse(algorithm.num_threads)
+            })
+            .map(|algorithm| {
+                let threads = algorithm.num_threads;
+                let first_core = nodes
+                    .iter()
+                    .find_map(|node| first_fit(node.clone(), threads))
+                    .or_else(|| first_fit(0..num_cores, threads))
+                    .with_context(|| {
+                        format!("No contiguous free cores for {algorithm}")
+                    })?;
+                let last_core = first_core + threads - 1;
+                Ok(CoreRange {
+                    algorithm: algorithm.clone(),
+                    first_core,
+                    numa_node: node_of(first_core)
+                        .filter(|&node| node_of(last_core) == Some(node)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        plan.sort_by_key(|range| range.first_core);
+        Ok(plan)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+/// NUMA topology of the machine a portfolio is executed on
+pub struct Topology {
+    /// Number of cores of every NUMA node, the cores of a node are numbered contiguously
+    pub numa_nodes: Vec<u32>,
+}
+
+impl FromStr for Topology {
+    type Err = anyhow::Error;
+    /// `<sockets>x<cores per socket>`, e.g. `2x8`, or the cores of every node, e.g. `8,4`
+    fn from_str(s: &str) -> Result<Self> {
+        let numa_nodes = match s.split_once('x') {
+            Some((sockets, cores)) => {
+                vec![cores.trim().parse()?; sockets.trim().parse()?]
+            }
+            None => s
+                .split(',')
+                .map(|cores| cores.trim().parse())
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        anyhow::ensure!(
+            !numa_nodes.is_empty() && numa_nodes.iter().all(|&c| c > 0),
+            "Expected <sockets>x<cores> or a list of cores per node, got {s}"
+        );
+        Ok(Self { numa_nodes })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+/// A class of identical cores of a heterogeneous machine, e.g. the performance or the efficiency
+/// cores of a hybrid CPU
+///
+/// With core classes, every algorithm of the data exists once per class as
+/// `<algorithm>@<class>`, see [`crate::csv_parser::DataOptions::core_classes`] and
+/// [`SolverOptions::core_classes`].
+pub struct CoreClass {
+    /// Name of the class
+    pub name: String,
+    /// Number of cores of the class
+    pub cores: u32,
+    /// Speed of the cores relative to the cores the running times were measured on, the running
+    /// times on the class are divided by it
+    #[serde(default = "CoreClass::default_speed")]
+    pub speed: f64,
+}
+
+impl CoreClass {
+    fn default_speed() -> f64 {
+        1.0
+    }
+
+    /// Name of `algorithm` running on the cores of the class
+    pub fn algorithm_name(&self, algorithm: &str) -> String {
+        format!("{algorithm}@{}", self.name)
+    }
+
+    /// Index of the class of `classes` that `algorithm` runs on, by the suffix of its name
+    pub fn of(algorithm: &Algorithm, classes: &[CoreClass]) -> Option<usize> {
+        let (_, class) = algorithm.algorithm.rsplit_once('@')?;
+        classes.iter().position(|c| c.name == class)
+    }
+
+    /// Check that the `classes` have distinct names and positive speeds and return their total
+    /// number of cores
+    pub fn total_cores(classes: &[CoreClass]) -> Result<u32> {
+        for (k, class) in classes.iter().enumerate() {
+            anyhow::ensure!(
+                class.speed > 0.0,
+                "Core class {} has a non-positive speed {}",
+                class.name,
+                class.speed
+            );
+            anyhow::ensure!(
+                classes[..k].iter().all(|other| other.name != class.name),
+                "Core class {} is defined twice",
+                class.name
+            );
+        }
+        Ok(classes.iter().map(|class| class.cores).sum())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+/// Contiguous range of cores a copy of an algorithm is pinned to, see [`Portfolio::core_plan`]
+pub struct CoreRange {
+    /// Algorithm of the copy, uses `num_threads` cores
+    pub algorithm: Algorithm,
+    /// First core of the range
+    pub first_core: u32,
+    /// NUMA node of all cores of the range, `None` without topology or if the range spans
+    /// multiple nodes
+    pub numa_node: Option<usize>,
+}
+
+impl CoreRange {
+    /// Cores of the range in cpuset notation, e.g. `4-7`
+    pub fn cpuset(&self) -> String {
+        match self.algorithm.num_threads {
+            1 => self.first_core.to_string(),
+            threads => {
+                format!(
+                    "{}-{}",
+                    self.first_core,
+                    self.first_core + threads - 1
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+/// An intermediate solution found by the [solver](crate::solver::solve)
+pub struct Incumbent {
+    /// Portfolio of the intermediate solution
+    pub portfolio: Portfolio,
+    /// Objective value of the intermediate solution
+    pub objective: f64,
+    /// Best lower bound at the time the solution was found
+    pub bound: f64,
+    /// Elapsed solver runtime in seconds when the solution was found
+    pub time: f64,
+    /// Objective of the portfolio in a short simulation, if the solver
+    /// [validates](SolverOptions::validation) its incumbents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulated: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// Progress reported by [`crate::solver::solve_with_progress`]
+pub enum ProgressEvent {
+    /// The solver found a new intermediate solution
+    Incumbent(Incumbent),
+    /// The final portfolio was improved by the local search after a timeout
+    Refined(Incumbent),
+    /// Algorithms were removed from the final portfolio, see [`SolverOptions::prune_tolerance`]
+    Pruned(Incumbent),
+}
+
+#[derive(Debug, Clone)]
+/// Explanation of an infeasible model based on an irreducible inconsistent subsystem (IIS)
+///
+/// Returned as error by the [solver](crate::solver::solve), use
+/// [`anyhow::Error::downcast_ref`] to access it.
+pub struct Infeasibility {
+    /// Human-readable descriptions of the conflicting constraints
+    pub conflicts: Vec<String>,
+    /// Options of the config that introduced the conflicting constraints, relaxing one of them
+    /// resolves this conflict
+    pub options: Vec<String>,
 }
 
+impl fmt::Display for Infeasibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "The model is infeasible, conflicting constraints:")?;
+        for conflict in &self.conflicts {
+            writeln!(f, "  - {conflict}")?;
+        }
+        if !self.options.is_empty() {
+            writeln!(f, "Relax one of: {}", self.options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Infeasibility {}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Kind of failure of the Gurobi backend of the [solver](crate::solver::solve), e.g. to decide
+/// whether to retry with another [`Backend`]
+///
+/// Returned as error like [`Infeasibility`], use [`anyhow::Error::downcast_ref`] to access it.
+/// Invalid data or options are reported as other errors.
+pub enum SolverError {
+    /// The Gurobi environment could not be started or the license does not cover the model, e.g.
+    /// because no license was found or the model exceeds a size-limited license
+    Environment(String),
+    /// Gurobi failed to build the model, e.g. because it ran out of memory
+    ModelBuild(String),
+    /// Gurobi stopped because of numerical trouble, even with the rescaled qualities and the
+    /// highest `NumericFocus`
+    Numerical,
+    /// The timeout in seconds was reached before the solver found a portfolio
+    Timeout(f64),
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Environment(err) => {
+                write!(f, "The Gurobi environment is not available: {err}")
+            }
+            Self::ModelBuild(err) => {
+                write!(f, "Failed to build the model: {err}")
+            }
+            Self::Numerical => write!(
+                f,
+                "The solver stopped because of numerical trouble"
+            ),
+            Self::Timeout(timeout) => write!(
+                f,
+                "The solver found no portfolio within the timeout of {timeout}s"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
 /// Result of the [solver](crate::solver::solve)
 pub struct OptimizationResult {
     /// Optional initial portfolio
     ///
-    /// Only present if a initial solution was provided or the [input data](crate::csv_parser::Data) contains `best_per_instance_count` values for the heuristic
+    /// The provided initial solution or the heuristic one (see [`crate::solver::solve`]), only
+    /// missing for solvers without initial solutions
     pub initial_portfolio: Option<Portfolio>,
     /// Final portfolio after the solver is finished or ran into a
     /// [`crate::datastructures::Timeout`]
@@ -130,13 +1549,148 @@ pub struct OptimizationResult {
     /// Remaining gap between the current objective value and the lower bound after the solver ran
     /// into the timelimit. Will be 0 if the solution is optimal.
     pub gap: f64,
+    /// All intermediate solutions found by the solver in order of discovery
+    pub history: Vec<Incumbent>,
+    /// Fraction of the gap between the single and virtual best solver closed by the final
+    /// portfolio, see [`crate::solver::gap_closed`]
+    pub gap_closed: f64,
+    /// Fraction of the gap closed by the initial portfolio, if any
+    pub initial_gap_closed: Option<f64>,
+    /// Whether the final portfolio of the solver was improved by the local search after a timeout,
+    /// the improved portfolio is the last entry of the history
+    pub refined: bool,
+    /// Best distinct portfolios of the solution pool in order of their objective, starting with
+    /// the final portfolio of the solver (before any local search), only filled by the Gurobi
+    /// backend if [`SolverOptions::pool_size`] is set
+    pub pool: Vec<Portfolio>,
+    /// Statistics of the solve
+    pub stats: SolveStats,
+    /// Expected quality of the final portfolio on every instance, only filled if
+    /// [`SolverOptions::instance_qualities`] is set, see [`crate::solver::instance_qualities`]
+    pub instance_qualities: Option<Vec<InstanceQuality>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Expected quality of a portfolio on an instance, see [`crate::solver::instance_qualities`]
+pub struct InstanceQuality {
+    /// Name of the instance, missing if the [names](crate::csv_parser::Data::instances) are
+    /// unknown
+    pub instance: Option<String>,
+    /// Expected best quality of the selected algorithms on the instance
+    pub expected_quality: f64,
+    /// Ratio of the expected quality to the best quality of the instance
+    pub ratio: f64,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default,
+)]
+#[serde(rename_all = "snake_case")]
+/// Why the [solver](crate::solver::solve) stopped, see [`SolveStats::termination`]
+pub enum Termination {
+    /// The final portfolio is optimal (within the tolerance of the solver)
+    Optimal,
+    /// The relative gap of the final portfolio dropped to the
+    /// [`mip_gap`](SolverOptions::mip_gap) before it was proven optimal
+    GapReached,
+    /// The solver ran into the [`Timeout`]
+    Timeout,
+    /// The solver was [cancelled](SolverOptions::cancellation)
+    Interrupted,
+    /// The solver ran into another limit, e.g. the [`node_limit`](SolverOptions::node_limit) or
+    /// the number of generations of the genetic algorithm
+    Limit,
+    /// The solver finished without proving the final portfolio optimal, e.g. a heuristic
+    #[default]
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+/// Statistics of a run of the [solver](crate::solver::solve)
+pub struct SolveStats {
+    /// Wall-clock time of the solve in seconds, including building the model
+    pub wall_time: f64,
+    /// Number of explored branch-and-bound nodes (search branches for CP-SAT), missing for solvers
+    /// without branch and bound
+    pub node_count: Option<u64>,
+    /// Objective of the final portfolio (including the penalties of the options)
+    pub objective: f64,
+    /// Best lower bound on the objective, missing for solvers without a bound
+    pub bound: Option<f64>,
+    /// Number of incumbents in the [`history`](OptimizationResult::history)
+    pub num_incumbents: usize,
+    /// Why the solver stopped
+    pub termination: Termination,
+    /// `NumericFocus` of the Gurobi solve that found the final portfolio after the solves with
+    /// lower values ran into numerical trouble, missing if the first solve succeeded
+    ///
+    /// The retries solve the data with the qualities of every instance divided by its best
+    /// quality, see [`solve`](crate::solver::solve).
+    #[serde(default)]
+    pub numeric_focus: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Summary of a run of the solver, written to `report.json` next to the portfolios
+///
+/// Gaps that are not a number (e.g. for solvers without a lower bound) are missing.
+pub struct Report {
+    /// The [`Kind::Report`](schema::Kind::Report) schema of the report
+    pub schema: Schema,
+    /// Number of cores of the portfolios
+    pub num_cores: u32,
+    /// Slowdown ratio of the algorithms that were considered
+    pub slowdown_ratio: f64,
+    /// Objective of the solver
+    pub objective: Objective,
+    /// Names of the written portfolios, starting with the final portfolio
+    pub portfolios: Vec<String>,
+    /// See [`OptimizationResult::gap`]
+    pub gap: Option<f64>,
+    /// See [`OptimizationResult::gap_closed`]
+    pub gap_closed: Option<f64>,
+    /// See [`OptimizationResult::initial_gap_closed`]
+    pub initial_gap_closed: Option<f64>,
+    /// See [`OptimizationResult::refined`]
+    pub refined: bool,
+    /// Number of incumbents in the [history](OptimizationResult::history)
+    pub incumbents: usize,
+}
+
+impl Report {
+    /// Summarize the `result` of the solver, without any [portfolios](Report::portfolios)
+    pub fn new(
+        result: &OptimizationResult,
+        num_cores: u32,
+        slowdown_ratio: f64,
+        objective: Objective,
+    ) -> Self {
+        let finite = |x: f64| Some(x).filter(|x| !x.is_nan());
+        Self {
+            schema: Schema::current(schema::Kind::Report),
+            num_cores,
+            slowdown_ratio,
+            objective,
+            portfolios: Vec::new(),
+            gap: finite(result.gap),
+            gap_closed: finite(result.gap_closed),
+            initial_gap_closed: result.initial_gap_closed.and_then(finite),
+            refined: result.refined,
+            incumbents: result.history.len(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Algorithm;
 
-    use super::Portfolio;
+    use super::{
+        disambiguate_names, CancellationToken, ChanceConstraint,
+        GurobiLicense, ModelExport, ModelFormat, NameCollision, Objective,
+        Portfolio, QualityThreshold, SolverOptions, Timeout, Topology,
+    };
 
     #[test]
     fn test_random_portfolio() {
@@ -159,4 +1713,231 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_core_plan() {
+        let portfolio = Portfolio {
+            name: String::from("portfolio"),
+            resource_assignments: vec![
+                (Algorithm::new("algo1".into(), 1), 2.0),
+                (Algorithm::new("algo2".into(), 3), 1.0),
+                (Algorithm::new("algo3".into(), 2), 1.0),
+            ],
+        };
+        let cpusets = |plan: Vec<super::CoreRange>| {
+            plan.iter()
+                .map(|range| {
+                    (range.algorithm.algorithm.clone(), range.cpuset())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            cpusets(portfolio.core_plan(8).unwrap()),
+            vec![
+                ("algo1".into(), "0".into()),
+                ("algo1".into(), "1".into()),
+                ("algo2".into(), "2-4".into()),
+                ("algo3".into(), "5-6".into()),
+            ]
+        );
+        assert!(portfolio.core_plan(6).is_err());
+        // without topology, algo2 would span both nodes
+        let topology = Topology {
+            numa_nodes: vec![4, 4],
+        };
+        let plan = portfolio
+            .core_plan_with_topology(8, Some(&topology))
+            .unwrap();
+        assert!(plan.iter().all(|range| range.numa_node.is_some()));
+        assert_eq!(
+            cpusets(plan),
+            vec![
+                ("algo2".into(), "0-2".into()),
+                ("algo1".into(), "3".into()),
+                ("algo3".into(), "4-5".into()),
+                ("algo1".into(), "6".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_objective() {
+        let ratios = [1.0, 1.5, 4.0];
+        assert_eq!(Objective::QualityRatio.value(ratios), 6.5);
+        assert_eq!(Objective::WorstCase.value(ratios), 4.0);
+        assert!(
+            (Objective::GeometricMean.value(ratios) - 6.0f64.ln()).abs()
+                < 1e-12
+        );
+        let par10 = "par10:2".parse::<Objective>().unwrap();
+        assert_eq!(
+            par10,
+            Objective::Penalized {
+                cutoff: 2.0,
+                factor: 10.0
+            }
+        );
+        assert_eq!(par10.value(ratios), 22.5);
+        assert!("par10".parse::<Objective>().is_err());
+        let cvar = "cvar0.5".parse::<Objective>().unwrap();
+        assert_eq!(cvar, Objective::Cvar { alpha: 0.5 });
+        // worst instance and half of the second worst
+        assert_eq!(cvar.value(ratios), (4.0 + 0.5 * 1.5) / 1.5);
+        assert_eq!(Objective::Cvar { alpha: 1.0 }.value(ratios), 6.5 / 3.0);
+        assert!("cvar0".parse::<Objective>().is_err());
+        let mv = "mv0.5".parse::<Objective>().unwrap();
+        assert_eq!(mv, Objective::MeanVariance { lambda: 0.5 });
+        // mean 2, variance (1 + 0 + 1) / 3
+        assert_eq!(mv.value([1.0, 2.0, 3.0]), 2.0 + 0.5 * 2.0 / 3.0);
+        assert!("mv-1".parse::<Objective>().is_err());
+    }
+
+    #[test]
+    fn test_solver_options_builder() {
+        let options = SolverOptions::new(Timeout(60.0))
+            .with_mip_gap(0.01)
+            .with_node_limit(1000)
+            .with_threads(4)
+            .with_seed(42)
+            .with_numeric_focus(2)
+            .with_solver_output(false)
+            .with_pool(3, Some(0.1));
+        assert_eq!(options.timeout.0, 60.0);
+        assert_eq!(options.mip_gap, Some(0.01));
+        assert_eq!(options.node_limit, Some(1000));
+        assert_eq!(options.threads, Some(4));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.numeric_focus, Some(2));
+        assert!(!options.solver_output());
+        assert_eq!(
+            (options.pool_size, options.pool_gap),
+            (Some(3), Some(0.1))
+        );
+        assert_eq!(SolverOptions::new(Timeout(60.0)).mip_gap, None);
+    }
+
+    #[test]
+    fn test_solver_options_defaults() {
+        // options of checkpoints written before these fields existed
+        let mut json = serde_json::to_value(SolverOptions::default()).unwrap();
+        for field in [
+            "complexity_penalty",
+            "decomposition",
+            "lazy_linking",
+            "prune_dominated",
+            "core_classes",
+            "topology",
+            "prune_tolerance",
+            "model_export",
+            "start_solution",
+            "instance_qualities",
+        ] {
+            json.as_object_mut().unwrap().remove(field).unwrap();
+        }
+        let options: SolverOptions = serde_json::from_value(json).unwrap();
+        assert!(!options.lazy_linking && !options.prune_dominated);
+        assert!(options.core_classes.is_empty());
+    }
+
+    #[test]
+    fn test_model_export() {
+        let export = "out/model.mps".parse::<ModelExport>().unwrap();
+        assert_eq!(export.format, ModelFormat::Mps);
+        assert_eq!(export.file(), std::path::Path::new("out/model.mps"));
+        assert!("model.txt".parse::<ModelExport>().is_err());
+        let options = SolverOptions::default()
+            .with_model_export("model", ModelFormat::Lp);
+        assert_eq!(
+            options.model_export.unwrap().file(),
+            std::path::Path::new("model.lp")
+        );
+    }
+
+    #[test]
+    fn test_gurobi_license() {
+        let license: GurobiLicense = serde_json::from_str(
+            r#"{"token_server": {"server": "license.example.com"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            license,
+            GurobiLicense::TokenServer {
+                server: "license.example.com".into(),
+                password: None
+            }
+        );
+        let license: GurobiLicense = serde_json::from_str(
+            r#"{"wls": {"access_id": "id", "secret": "secret", "license_id": 42}}"#,
+        )
+        .unwrap();
+        assert!(matches!(license, GurobiLicense::Wls { license_id: 42, .. }));
+        // the license is not written to checkpoints
+        let options = SolverOptions::default().with_license(license);
+        let json = serde_json::to_string(&options).unwrap();
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let options =
+            SolverOptions::default().with_cancellation(token.clone());
+        assert!(!options.is_cancelled());
+        token.cancel();
+        assert!(options.is_cancelled() && options.clone().is_cancelled());
+        assert!(!SolverOptions::default().is_cancelled());
+    }
+
+    #[test]
+    fn test_disambiguate_names() {
+        let portfolio = |name: &str| {
+            Portfolio {
+                name: name.into(),
+                resource_assignments: Vec::new(),
+            }
+            .with_parameters("k8_s0.5")
+            .name
+        };
+        assert_eq!(
+            portfolio("final_portfolio_opt"),
+            "final_portfolio_k8_s0.5_opt"
+        );
+        let names = [
+            portfolio("final_portfolio"),
+            portfolio("final_portfolio"),
+            portfolio("random_portfolio"),
+            String::from("final_portfolio_k8_s0.5_2"),
+        ];
+        assert_eq!(
+            disambiguate_names(&names, NameCollision::Suffix).unwrap(),
+            [
+                "final_portfolio_k8_s0.5",
+                "final_portfolio_k8_s0.5_3",
+                "random_portfolio_k8_s0.5",
+                "final_portfolio_k8_s0.5_2",
+            ]
+        );
+        assert!(disambiguate_names(&names, NameCollision::Error).is_err());
+        assert_eq!(
+            disambiguate_names(&names, NameCollision::Overwrite).unwrap(),
+            names
+        );
+    }
+
+    #[test]
+    fn test_chance_constraint() {
+        let threshold: QualityThreshold = "1.05:0.9".parse().unwrap();
+        assert_eq!(threshold.factor, 1.05);
+        assert_eq!(threshold.probability, 0.9);
+        assert!("1.05".parse::<QualityThreshold>().is_err());
+        let chance = ChanceConstraint {
+            threshold,
+            success: ndarray::arr2(&[[0.5, 1.0], [0.5, 0.0]]),
+        };
+        let probabilities = chance.probabilities(&[3.0, 0.0]);
+        assert_eq!(probabilities, vec![0.875, 0.875]);
+        assert!(!chance.satisfied(&[3.0, 0.0]));
+        assert_eq!(chance.probabilities(&[4.0, 1.0]), vec![1.0, 0.9375]);
+        assert!(chance.satisfied(&[4.0, 1.0]));
+    }
 }
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use anyhow::Result;
+use itertools::Itertools;
+use polars::prelude::*;
+
+use crate::csv_parser::extract_algorithm_columns;
+use crate::datastructures::Algorithm;
+
+#[derive(Debug, Clone)]
+/// Thresholds of [`compare`] above which a change of the data warrants re-optimizing the portfolio
+pub struct DriftOptions {
+    /// Relative change of the quality or running time of an algorithm, or absolute change of its
+    /// fraction of valid runs
+    pub threshold: f64,
+    /// Fraction of the instances of the previous snapshot that were added or removed
+    pub instance_threshold: f64,
+}
+
+impl Default for DriftOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.05,
+            instance_threshold: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Change of the runs of an algorithm between two snapshots on their common instances
+pub struct AlgorithmDrift {
+    /// The algorithm
+    pub algorithm: Algorithm,
+    /// Number of common instances with valid runs of the algorithm in both snapshots
+    pub num_instances: usize,
+    /// Geometric mean of the ratios of the mean valid quality per instance (current / previous),
+    /// above one if the qualities got larger
+    pub quality_ratio: f64,
+    /// Geometric mean of the ratios of the mean valid running time per instance (current /
+    /// previous)
+    pub time_ratio: f64,
+    /// Fraction of valid runs on the common instances in the previous and the current snapshot
+    pub valid_rate: (f64, f64),
+}
+
+impl AlgorithmDrift {
+    /// Whether the quality, running time or fraction of valid runs changed by more than
+    /// `threshold`
+    pub fn drifted(&self, threshold: f64) -> bool {
+        (self.quality_ratio - 1.0).abs() > threshold
+            || (self.time_ratio - 1.0).abs() > threshold
+            || (self.valid_rate.1 - self.valid_rate.0).abs() > threshold
+    }
+}
+
+impl fmt::Display for AlgorithmDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: quality x{:.3}, time x{:.3}, valid {:.1}% -> {:.1}% ({} instances)",
+            self.algorithm,
+            self.quality_ratio,
+            self.time_ratio,
+            self.valid_rate.0 * 100.0,
+            self.valid_rate.1 * 100.0,
+            self.num_instances
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Differences between two snapshots of the normalized input data, see [`compare`]
+pub struct DriftReport {
+    /// Instances that are only part of the current snapshot
+    pub added_instances: Vec<String>,
+    /// Instances that are only part of the previous snapshot
+    pub removed_instances: Vec<String>,
+    /// Algorithms that are only part of the current snapshot
+    pub added_algorithms: Vec<Algorithm>,
+    /// Algorithms that are only part of the previous snapshot
+    pub removed_algorithms: Vec<Algorithm>,
+    /// Drift of the algorithms that are part of both snapshots, sorted by algorithm
+    pub algorithms: Vec<AlgorithmDrift>,
+    /// Why the portfolio should be optimized again, empty if the data did not change
+    /// significantly
+    pub reasons: Vec<String>,
+}
+
+impl DriftReport {
+    /// Whether the portfolio should be optimized again for the current snapshot
+    pub fn reoptimize(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} instances added, {} instances removed",
+            self.added_instances.len(),
+            self.removed_instances.len()
+        )?;
+        for algo in &self.added_algorithms {
+            writeln!(f, "{algo}: added")?;
+        }
+        for algo in &self.removed_algorithms {
+            writeln!(f, "{algo}: removed")?;
+        }
+        for drift in &self.algorithms {
+            writeln!(f, "{drift}")?;
+        }
+        match self.reoptimize() {
+            true => {
+                writeln!(f, "Re-optimization recommended:")?;
+                for reason in &self.reasons {
+                    writeln!(f, "  - {reason}")?;
+                }
+            }
+            false => writeln!(f, "No re-optimization needed")?,
+        }
+        Ok(())
+    }
+}
+
+/// Compare two snapshots of normalized data, e.g. the data frames of
+/// [`parse_normalized_csvs`](crate::csv_parser::parse_normalized_csvs) of the runs of two dates,
+/// and recommend whether the portfolio of the `previous` snapshot should be optimized again.
+///
+/// The qualities and running times of every algorithm are compared on the instances both
+/// snapshots have valid runs of it on, using the mean of the valid runs per instance. Instances
+/// with non-positive mean qualities or running times are skipped, since they have no ratio.
+/// Re-optimization is recommended if algorithms were added or removed, the instances changed by
+/// more than the `instance_threshold` or an algorithm [drifted](AlgorithmDrift::drifted).
+pub fn compare(
+    previous: LazyFrame,
+    current: LazyFrame,
+    options: &DriftOptions,
+) -> Result<DriftReport> {
+    let previous = previous.collect()?;
+    let current = current.collect()?;
+    let previous_instances = instances(&previous)?;
+    let current_instances = instances(&current)?;
+    let previous_algorithms = algorithms(&previous)?;
+    let current_algorithms = algorithms(&current)?;
+    let added_instances = current_instances
+        .difference(&previous_instances)
+        .cloned()
+        .collect_vec();
+    let removed_instances = previous_instances
+        .difference(&current_instances)
+        .cloned()
+        .collect_vec();
+    let added_algorithms = current_algorithms
+        .difference(&previous_algorithms)
+        .cloned()
+        .collect_vec();
+    let removed_algorithms = previous_algorithms
+        .difference(&current_algorithms)
+        .cloned()
+        .collect_vec();
+
+    let previous_runs = instance_means(previous)?;
+    let current_runs = instance_means(current)?;
+    let algorithms = previous_algorithms
+        .intersection(&current_algorithms)
+        .map(|algo| {
+            let cells = previous_runs
+                .range((algo.clone(), String::new())..)
+                .take_while(|((a, _), _)| a == algo)
+                .filter_map(|(key, previous)| {
+                    Some((previous, current_runs.get(key)?))
+                })
+                .collect_vec();
+            let ratios = cells
+                .iter()
+                .filter_map(|(previous, current)| {
+                    let positive =
+                        |value: Option<f64>| value.filter(|v| *v > 0.0);
+                    Some((
+                        positive(current.quality)?
+                            / positive(previous.quality)?,
+                        positive(current.time)? / positive(previous.time)?,
+                    ))
+                })
+                .collect_vec();
+            AlgorithmDrift {
+                algorithm: algo.clone(),
+                num_instances: ratios.len(),
+                quality_ratio: mean(ratios.iter().map(|r| r.0.ln())).exp(),
+                time_ratio: mean(ratios.iter().map(|r| r.1.ln())).exp(),
+                valid_rate: (
+                    mean(cells.iter().map(|c| c.0.valid_rate)),
+                    mean(cells.iter().map(|c| c.1.valid_rate)),
+                ),
+            }
+        })
+        .collect_vec();
+
+    let mut reasons = Vec::new();
+    let changed_instances = added_instances.len() + removed_instances.len();
+    let instance_change =
+        changed_instances as f64 / previous_instances.len().max(1) as f64;
+    if instance_change > options.instance_threshold {
+        reasons.push(format!(
+            "{changed_instances} of {} instances changed",
+            previous_instances.len()
+        ));
+    }
+    if !added_algorithms.is_empty() || !removed_algorithms.is_empty() {
+        reasons.push(format!(
+            "{} algorithms added, {} algorithms removed",
+            added_algorithms.len(),
+            removed_algorithms.len()
+        ));
+    }
+    reasons.extend(
+        algorithms
+            .iter()
+            .filter(|drift| drift.drifted(options.threshold))
+            .map(|drift| format!("{drift}")),
+    );
+    Ok(DriftReport {
+        added_instances,
+        removed_instances,
+        added_algorithms,
+        removed_algorithms,
+        algorithms,
+        reasons,
+    })
+}
+
+/// Mean valid quality and running time and fraction of valid runs of an algorithm on an instance
+struct InstanceMeans {
+    quality: Option<f64>,
+    time: Option<f64>,
+    valid_rate: f64,
+}
+
+/// Mean of the `values`, zero without values
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) =
+        values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    sum / count.max(1) as f64
+}
+
+fn instances(df: &DataFrame) -> Result<BTreeSet<String>> {
+    Ok(df["instance"]
+        .utf8()?
+        .into_no_null_iter()
+        .map(String::from)
+        .collect())
+}
+
+fn algorithms(df: &DataFrame) -> Result<BTreeSet<Algorithm>> {
+    Ok(extract_algorithm_columns(df)?.into_iter().collect())
+}
+
+fn instance_means(
+    df: DataFrame,
+) -> Result<BTreeMap<(Algorithm, String), InstanceMeans>> {
+    let means = df
+        .lazy()
+        .groupby([col("algorithm"), col("num_threads"), col("instance")])
+        .agg([
+            col("quality")
+                .filter(col("valid"))
+                .cast(DataType::Float64)
+                .mean(),
+            col("time")
+                .filter(col("valid"))
+                .cast(DataType::Float64)
+                .mean(),
+            col("valid")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("valid_rate"),
+        ])
+        .with_column(col("num_threads").cast(DataType::UInt32))
+        .collect()?;
+    Ok(means["algorithm"]
+        .utf8()?
+        .into_no_null_iter()
+        .zip(means["num_threads"].u32()?.into_no_null_iter())
+        .zip(means["instance"].utf8()?.into_no_null_iter())
+        .zip(means["quality"].f64()?)
+        .zip(means["time"].f64()?)
+        .zip(means["valid_rate"].f64()?.into_no_null_iter())
+        .map(
+            |(
+                ((((algorithm, threads), instance), quality), time),
+                valid_rate,
+            )| {
+                (
+                    (
+                        Algorithm::new(algorithm.into(), threads),
+                        instance.to_string(),
+                    ),
+                    InstanceMeans {
+                        quality,
+                        time,
+                        valid_rate,
+                    },
+                )
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let previous = df! {
+            "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+            "num_threads" => [1i64, 1, 1, 1],
+            "instance" => ["graph1", "graph2", "graph1", "graph2"],
+            "quality" => [1.0, 2.0, 2.0, 4.0],
+            "time" => [1.0, 1.0, 1.0, 1.0],
+            "valid" => [true, true, true, true],
+        }
+        .unwrap();
+        let report = compare(
+            previous.clone().lazy(),
+            previous.clone().lazy(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert!(!report.reoptimize());
+        assert_eq!(report.algorithms.len(), 2);
+        assert_eq!(report.algorithms[0].quality_ratio, 1.0);
+        assert_eq!(report.algorithms[0].valid_rate, (1.0, 1.0));
+
+        // algo2 got twice as slow and a new instance was added
+        let current = df! {
+            "algorithm" => ["algo1", "algo1", "algo1", "algo2", "algo2"],
+            "num_threads" => [1i64, 1, 1, 1, 1],
+            "instance" => ["graph1", "graph2", "graph3", "graph1", "graph2"],
+            "quality" => [1.0, 2.0, 1.0, 2.0, 4.0],
+            "time" => [1.0, 1.0, 1.0, 2.0, 2.0],
+            "valid" => [true, true, true, true, true],
+        }
+        .unwrap();
+        let report =
+            compare(previous.lazy(), current.lazy(), &Default::default())
+                .unwrap();
+        assert_eq!(report.added_instances, vec![String::from("graph3")]);
+        assert!(report.removed_instances.is_empty());
+        let algo2 = &report.algorithms[1];
+        assert_eq!(algo2.algorithm, Algorithm::new("algo2".into(), 1));
+        assert_eq!(algo2.num_instances, 2);
+        assert_eq!(algo2.time_ratio, 2.0);
+        assert!(algo2.drifted(0.05) && !report.algorithms[0].drifted(0.05));
+        // the instance change and algo2
+        assert_eq!(report.reasons.len(), 2);
+        assert!(report.reoptimize());
+    }
+}
@@ -0,0 +1,213 @@
+use std::{fmt::Write, str::FromStr};
+
+use anyhow::Result;
+use itertools::Itertools;
+
+use crate::datastructures::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Format of an exported portfolio
+pub enum ExportFormat {
+    /// SLURM job-array script, see [`to_slurm_array`]
+    Slurm,
+    /// YAML task list for the execution wrapper, see [`to_yaml`]
+    Yaml,
+}
+
+impl ExportFormat {
+    /// File extension of the exported file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Slurm => "sbatch",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    /// Export `portfolio` in this format, see [`Portfolio::core_plan_with_topology`] for the
+    /// cores every algorithm copy is pinned to
+    pub fn export(
+        &self,
+        portfolio: &Portfolio,
+        num_cores: u32,
+        topology: Option<&Topology>,
+    ) -> Result<String> {
+        let plan = portfolio.core_plan_with_topology(num_cores, topology)?;
+        Ok(match self {
+            Self::Slurm => to_slurm_array(portfolio, &plan),
+            Self::Yaml => to_yaml(portfolio, &plan),
+        })
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "slurm" => Ok(Self::Slurm),
+            "yaml" => Ok(Self::Yaml),
+            _ => anyhow::bail!("Unknown export format {s}"),
+        }
+    }
+}
+
+/// Quote a string for bash and YAML (double quotes with JSON escapes are valid in both for the
+/// algorithm names we expect)
+fn quote(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+/// SLURM job-array script with one array task per algorithm copy of the `portfolio`, in order of
+/// the core `plan`
+///
+/// The script is submitted with `sbatch <script> <command> [args...]` and every array task runs
+/// `<command> [args...] <algorithm> <threads>` with the cpuset of the copy in the environment
+/// variable `CPUSET` (for pinning on exclusively allocated nodes). All array tasks request as many
+/// cpus as the algorithm with the most threads.
+pub fn to_slurm_array(portfolio: &Portfolio, plan: &[CoreRange]) -> String {
+    let max_threads = plan
+        .iter()
+        .map(|range| range.algorithm.num_threads)
+        .max()
+        .unwrap_or(1);
+    let mut script = String::from("#!/bin/bash\n");
+    writeln!(script, "#SBATCH --job-name={}", portfolio.name).unwrap();
+    writeln!(script, "#SBATCH --array=0-{}", plan.len().max(1) - 1).unwrap();
+    writeln!(script, "#SBATCH --cpus-per-task={max_threads}").unwrap();
+    writeln!(
+        script,
+        "# Usage: sbatch <this script> <command> [args...], runs <command> [args...] <algorithm> <threads>"
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "ALGORITHMS=({})",
+        plan.iter()
+            .map(|range| quote(&range.algorithm.algorithm))
+            .join(" ")
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "THREADS=({})",
+        plan.iter()
+            .map(|range| range.algorithm.num_threads)
+            .join(" ")
+    )
+    .unwrap();
+    writeln!(
+        script,
+        "CPUSETS=({})",
+        plan.iter().map(|range| quote(&range.cpuset())).join(" ")
+    )
+    .unwrap();
+    script.push_str("i=$SLURM_ARRAY_TASK_ID\n");
+    script.push_str("export CPUSET=\"${CPUSETS[$i]}\"\n");
+    script.push_str("exec \"$@\" \"${ALGORITHMS[$i]}\" \"${THREADS[$i]}\"\n");
+    script
+}
+
+/// YAML task list of the `portfolio` for the execution wrapper
+///
+/// Every algorithm copy of the core `plan` is a task with the algorithm name, number of threads
+/// and the cpuset (e.g. `4-7`) it is pinned to.
+pub fn to_yaml(portfolio: &Portfolio, plan: &[CoreRange]) -> String {
+    let mut yaml = format!("name: {}\ntasks:\n", quote(&portfolio.name));
+    for range in plan {
+        writeln!(yaml, "  - algorithm: {}", quote(&range.algorithm.algorithm))
+            .unwrap();
+        writeln!(yaml, "    threads: {}", range.algorithm.num_threads)
+            .unwrap();
+        writeln!(yaml, "    cpuset: {}", quote(&range.cpuset())).unwrap();
+    }
+    yaml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_slurm_array, to_yaml, ExportFormat};
+    use crate::datastructures::*;
+
+    fn portfolio() -> Portfolio {
+        Portfolio {
+            name: String::from("final_portfolio"),
+            resource_assignments: vec![
+                (Algorithm::new("algo1".into(), 1), 2.0),
+                (Algorithm::new("algo2".into(), 4), 0.0),
+                (Algorithm::new("algo3".into(), 2), 1.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let portfolio = portfolio();
+        assert_eq!(
+            to_yaml(&portfolio, &portfolio.core_plan(4).unwrap()),
+            "name: \"final_portfolio\"
+tasks:
+  - algorithm: \"algo1\"
+    threads: 1
+    cpuset: \"0\"
+  - algorithm: \"algo1\"
+    threads: 1
+    cpuset: \"1\"
+  - algorithm: \"algo3\"
+    threads: 2
+    cpuset: \"2-3\"
+"
+        );
+    }
+
+    #[test]
+    fn test_to_slurm_array() {
+        let portfolio = portfolio();
+        assert_eq!(
+            to_slurm_array(&portfolio, &portfolio.core_plan(4).unwrap()),
+            "#!/bin/bash
+#SBATCH --job-name=final_portfolio
+#SBATCH --array=0-2
+#SBATCH --cpus-per-task=2
+# Usage: sbatch <this script> <command> [args...], runs <command> [args...] <algorithm> <threads>
+ALGORITHMS=(\"algo1\" \"algo1\" \"algo3\")
+THREADS=(1 1 2)
+CPUSETS=(\"0\" \"1\" \"2-3\")
+i=$SLURM_ARRAY_TASK_ID
+export CPUSET=\"${CPUSETS[$i]}\"
+exec \"$@\" \"${ALGORITHMS[$i]}\" \"${THREADS[$i]}\"
+"
+        );
+    }
+
+    #[test]
+    fn test_export_format() {
+        let portfolio = portfolio();
+        let topology = "2x2".parse::<Topology>().unwrap();
+        let format = "YAML".parse::<ExportFormat>().unwrap();
+        assert_eq!(format.extension(), "yaml");
+        // the copy with two threads is placed first, on the first NUMA node
+        assert_eq!(
+            format.export(&portfolio, 4, Some(&topology)).unwrap(),
+            "name: \"final_portfolio\"
+tasks:
+  - algorithm: \"algo3\"
+    threads: 2
+    cpuset: \"0-1\"
+  - algorithm: \"algo1\"
+    threads: 1
+    cpuset: \"2\"
+  - algorithm: \"algo1\"
+    threads: 1
+    cpuset: \"3\"
+"
+        );
+        let format = "slurm".parse::<ExportFormat>().unwrap();
+        assert_eq!(format.extension(), "sbatch");
+        assert!(format
+            .export(&portfolio, 4, None)
+            .unwrap()
+            .contains("CPUSETS=(\"0\" \"1\" \"2-3\")\n"));
+        // the portfolio needs four cores
+        assert!(format.export(&portfolio, 3, None).is_err());
+        assert!("json".parse::<ExportFormat>().is_err());
+    }
+}
@@ -19,6 +19,21 @@
 //! using it with other input data. Most importantly, parsing of normalized data frames is
 //! supported, so all you need to do is convert your data into this (very simple) format.
 //!
+//! To get a good portfolio without choosing a solver or its options, use [`solver::auto`] with a
+//! time budget, it falls back to heuristics if Gurobi is not available.
+//!
+//! To find the smallest number of cores for a quality target instead, use
+//! [`solver::solve_min_cores`].
+//!
+//! To decide whether a portfolio has to be optimized again for a new snapshot of the runs, compare
+//! the snapshots with [`drift::compare`].
+//!
+//! To run cheap algorithms one after another within a time budget instead of splitting the cores
+//! statically, build a sequential schedule with [`schedule::optimize`].
+//!
+//! To follow the solver while it runs, e.g. for a dashboard, pass a callback to
+//! [`solver::solve_with_progress`] that receives every intermediate portfolio.
+//!
 //! Example
 //! ```rust
 //! use portfolio_solver::csv_parser;
@@ -32,7 +47,8 @@
 //!     let paths = [PathBuf::from("input1.csv"), "input2.csv".into()];
 //!     let num_cores: u32 = 8; // number of cores available to the portfolio
 //!     let slowdown_ratio = 0.5; // find a portfolio that is approximatly 2x faster than the best
-//!     let timeout = datastructures::Timeout::default(); // defaults to 900 seconds
+//!     // defaults to a timeout of 900 seconds without additional constraints
+//!     let options = datastructures::SolverOptions::default();
 //!
 //!     // normalized csvs have the following header (types in parenthesis):
 //!     // algorithm(str),num_threads(int),instance(str),quality(float),time(float),valid(bool)
@@ -46,17 +62,18 @@
 //!         df.clone(),
 //!         num_cores,
 //!         slowdown_ratio,
+//!         0, // seed for sampling the expected best quality of repeated runs
 //!        )?;
 //!
 //!     let datastructures::OptimizationResult {
 //!         initial_portfolio,
 //!         final_portfolio,
-//!         gap: _,
+//!         ..
 //!         } = solver::solve(
 //!                 &data,
 //!                 num_cores as usize,
-//!                 timeout,
-//!                 None, // optionally provide a initial solutions, fallback to a heuristic
+//!                 &options,
+//!                 None, // optionally provide an initial portfolio, fallback to a heuristic
 //!                 )?;
 //!
 //!     // datastructures::Portfolio implements serde::{Serialize, Deserialize}
@@ -70,7 +87,9 @@
 //!         &df.collect()?,
 //!         &algorithms,
 //!         &[initial_portfolio, final_portfolio], // portfolios to simulate
+//!         &[], // optionally restrict portfolios to instances
 //!         10, // number of seeds
+//!         true, // sample the same runs for all portfolios
 //!         &["instance"], // instance columns
 //!         &["algorithm", "num_threads"], // algorithm columns
 //!         num_cores,
@@ -88,8 +107,27 @@ pub mod csv_parser;
 /// Data structures for easier usage of the solver.
 pub mod datastructures;
 
+/// Compare snapshots of the input data to decide whether to re-optimize a portfolio.
+pub mod drift;
+
+/// Export portfolios to scheduler-friendly formats.
+pub mod export;
+
+/// Prometheus metrics of running optimizations.
+#[cfg(feature = "server")]
+pub mod metrics;
+
 /// Helper functions to simulate a portfolio execution from csv data.
 pub mod portfolio_simulator;
 
+/// Versioned schemas of the JSON artifacts.
+pub mod schema;
+
+/// Sequential schedules of algorithm runs in time slices per core.
+pub mod schedule;
+
 /// A solver based on Gurobi for the algorithm portfolio optimization problem.
 pub mod solver;
+
+/// Re-solve while the input csv files are still being written.
+pub mod watch;
@@ -1,11 +1,14 @@
 use anyhow::Result;
 use clap::Parser;
-use log::info;
-use std::fs;
+use log::{info, warn};
+use std::{collections::BTreeMap, fs, path::PathBuf, rc::Rc, time::Duration};
 
 use portfolio_solver::csv_parser;
 use portfolio_solver::datastructures::*;
+use portfolio_solver::schedule;
+use portfolio_solver::schema::{Kind, Schema};
 use portfolio_solver::solver;
+use portfolio_solver::watch;
 
 mod mt_kahypar_parser;
 
@@ -14,6 +17,10 @@ fn main() -> Result<()> {
     env_logger::Builder::new()
         .filter_level(args.verbosity.log_level_filter())
         .init();
+    #[cfg(feature = "server")]
+    if let Some(addr) = &args.metrics {
+        portfolio_solver::metrics::serve(addr)?;
+    }
     let Ok(mt_kahypar_parser::Config {
         files,
         graphs,
@@ -24,33 +31,550 @@ fn main() -> Result<()> {
         num_seeds,
         out_dir,
         timeout,
-    }) = mt_kahypar_parser::Config::from_cli(&args) else { std::process::exit(exitcode::CONFIG); };
-    fs::create_dir(&out_dir).ok();
-    let instance_filter = mt_kahypar_parser::InstanceFilter {
-        instance_path: graphs.clone(),
-        ks,
-        feasibility_thresholds,
+        mip_gap,
+        quality_guarantee,
+        quality_metric,
+        backend,
+        decomposition,
+        lazy_linking,
+        prune_dominated,
+        topology,
+        name_collision,
+        objective,
+        quality_semantics,
+        quality_direction,
+        algorithm_constraints,
+        sampling_seed,
+        quality_estimator,
+        requirements,
+        memory_budget,
+        cost_budget,
+        min_coverage,
+        forbid_imputed,
+        thread_pattern,
+        pool_size,
+        time_budget,
+        prune_tolerance,
+        complexity_penalty,
+        chance_constraint,
+        time_to_target,
+        core_classes,
+        license,
+    }) = mt_kahypar_parser::Config::from_cli(&args)
+    else {
+        std::process::exit(exitcode::CONFIG);
     };
-    let df = mt_kahypar_parser::parse_hypergraph_dataframe(
-        &files,
-        Some(instance_filter),
-        num_cores,
-    )
-    .or_else(|_| {
-        csv_parser::parse_normalized_csvs(&files, Some(graphs), num_cores)
-    })?;
-    let data = csv_parser::Data::from_normalized_dataframe(
-        df,
+    fs::create_dir_all(&out_dir).ok();
+    let options = SolverOptions {
+        timeout,
+        quality_guarantee: quality_guarantee.map(|factor| QualityGuarantee {
+            factor,
+            instances: None,
+        }),
+        backend,
+        decomposition,
+        lazy_linking,
+        prune_dominated,
+        objective,
+        algorithm_constraints,
+        memory_budget,
+        cost_budget,
+        forbid_imputed,
+        pool_size,
+        prune_tolerance,
+        complexity_penalty,
+        core_classes: core_classes.clone(),
+        topology: topology.clone(),
+        checkpoint: args.checkpoint.map(|interval| CheckpointOptions {
+            dir: out_dir.join("checkpoint"),
+            interval,
+        }),
+        model_export: args.write_model.clone(),
+        start_solution: args.start_solution.clone(),
+        license,
+        mip_gap,
+        ..Default::default()
+    };
+    let data_options = csv_parser::DataOptions {
+        quality_semantics,
+        estimator: quality_estimator,
+        sampling_seed,
+        requirements,
+        quality_direction,
+        min_coverage,
+        dump_normalized: args.dump_normalized.clone(),
+        time_budget,
+        time_to_target,
+        core_classes: core_classes.clone(),
+    };
+    let thread_pattern = thread_pattern
+        .map(|pattern| regex::Regex::new(&pattern))
+        .transpose()?;
+    let startup = data_options.requirements.clone();
+    let parse_options = csv_parser::ParseOptions {
+        quality_semantics,
+        thread_pattern: thread_pattern.clone(),
+        ..Default::default()
+    };
+    let comment_char = parse_options.comment_char;
+    let parse_files = move |files: &[PathBuf], num_cores: u32| {
+        if let Some(metric) = quality_metric {
+            return csv_parser::parse_anytime_csvs_with_startup(
+                files,
+                Some(graphs.clone()),
+                num_cores,
+                metric,
+                quality_direction,
+                &startup,
+            );
+        }
+        let instance_filter = mt_kahypar_parser::InstanceFilter {
+            instance_path: graphs.clone(),
+            ks: ks.clone(),
+            feasibility_thresholds: feasibility_thresholds.clone(),
+        };
+        mt_kahypar_parser::parse_hypergraph_dataframe(
+            files,
+            Some(instance_filter),
+            num_cores,
+            thread_pattern.as_ref(),
+        )
+        .or_else(|_| {
+            let (df, report) = csv_parser::parse_normalized_csvs_with_report(
+                files,
+                Some(graphs.clone()),
+                num_cores,
+                &parse_options,
+            )?;
+            if report.quarantined.height() > 0
+                || !report.failed_files.is_empty()
+            {
+                warn!("Some input rows were skipped:\n{report}");
+            }
+            if let Some(instances) = &report.instances {
+                info!("{} instances left after filtering", instances.len());
+            }
+            Ok(df)
+        })
+    };
+    let parse_cores = Rc::new(move |files: &[PathBuf], num_cores: u32| {
+        #[cfg(feature = "server")]
+        let start = std::time::Instant::now();
+        let df = parse_files(files, num_cores);
+        #[cfg(feature = "server")]
+        portfolio_solver::metrics::record_parse(start);
+        df
+    });
+    let parse = {
+        let parse_cores = parse_cores.clone();
+        move |files: &[PathBuf]| parse_cores(files, num_cores)
+    };
+    if let Some(interval) = args.watch {
+        let mut watcher = watch::Watcher::with_parser(
+            files,
+            parse,
+            num_cores,
+            slowdown_ratio,
+        )
+        .with_data_options(data_options.clone());
+        return watcher.run(
+            &solver::Solver::for_options(&options)?,
+            &options,
+            Duration::from_secs_f64(interval),
+            |_, result| {
+                info!("Updated portfolio:\n{}", result.final_portfolio);
+                serde_json::to_writer_pretty(
+                    fs::File::create(out_dir.join("final_portfolio.json"))?,
+                    &result.final_portfolio,
+                )?;
+                Ok(true)
+            },
+        );
+    }
+    let df = parse(&files)?;
+    if let Some(pattern) = &args.group_by {
+        let groups = csv_parser::split_groups(
+            csv_parser::group_instances(df, pattern),
+            "group",
+        )?
+        .into_iter()
+        .map(|(group, df)| {
+            let data =
+                csv_parser::Data::from_normalized_dataframe_with_options(
+                    df,
+                    num_cores,
+                    slowdown_ratio,
+                    &data_options,
+                )?;
+            info!("Group {group}: {data}");
+            Ok((group, data))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+        let portfolios = solver::solve_grouped(
+            &groups,
+            num_cores as usize,
+            args.max_deployed,
+            &options,
+        )?;
+        let mut csv = String::from("group,instances,gap_closed\n");
+        for (group, portfolio) in &portfolios {
+            let data = &groups[group];
+            let gap_closed = solver::gap_closed(data, portfolio);
+            info!(
+                "{}: closed {:.1}% of the gap\n{portfolio}",
+                portfolio.name,
+                gap_closed * 100.0
+            );
+            csv += &format!("{group},{},{gap_closed}\n", data.num_instances);
+            serde_json::to_writer_pretty(
+                fs::File::create(
+                    out_dir.join(format!("{}.json", portfolio.name)),
+                )?,
+                portfolio,
+            )?;
+        }
+        fs::write(out_dir.join("groups.csv"), csv)?;
+        return Ok(());
+    }
+    if let Some(num_points) = args.calibrate_slowdown {
+        let points = solver::calibrate_slowdown_ratio(
+            df,
+            num_cores,
+            &solver::slowdown_ratio_grid(1.0, slowdown_ratio, num_points),
+            &data_options,
+            match args.relaxation {
+                true => solver::CalibrationSolver::Relaxation,
+                false => solver::CalibrationSolver::Greedy,
+            },
+            &options,
+        )?;
+        let mut csv = String::from(
+            "portfolio,slowdown_ratio,algorithms,objective,runtime,frontier\n",
+        );
+        for point in &points {
+            let name = &point.portfolio.name;
+            info!(
+                "{name}: slowdown ratio {}, objective {}, runtime {}\n{}",
+                point.slowdown_ratio,
+                point.objective,
+                point.runtime,
+                point.portfolio
+            );
+            csv += &format!(
+                "{name},{},{},{},{},{}\n",
+                point.slowdown_ratio,
+                point.num_algorithms,
+                point.objective,
+                point.runtime,
+                point.on_frontier
+            );
+            serde_json::to_writer_pretty(
+                fs::File::create(out_dir.join(format!("{name}.json")))?,
+                &point.portfolio,
+            )?;
+        }
+        fs::write(out_dir.join("slowdown.csv"), csv)?;
+        return Ok(());
+    }
+    let data = csv_parser::Data::from_normalized_dataframe_with_options(
+        df.clone(),
         num_cores,
         slowdown_ratio,
+        &data_options,
     )?;
     info!("{data}");
+    if chance_constraint.is_some() && time_to_target.is_some() {
+        anyhow::bail!(
+            "The chance constraint can't be combined with time-to-target"
+        );
+    }
+    if chance_constraint.is_some()
+        && (quality_direction != QualityDirection::Minimize
+            || quality_semantics != QualitySemantics::Ratio)
+    {
+        anyhow::bail!(
+            "The chance constraint needs minimized qualities compared by ratio"
+        );
+    }
+    if quality_direction != QualityDirection::Minimize
+        && (args.racing || args.validate.is_some())
+    {
+        anyhow::bail!("--racing and --validate need minimized qualities");
+    }
+    let schedule_runs = match (args.schedule, time_budget) {
+        (false, _) => None,
+        (true, None) => anyhow::bail!("The schedule needs a time budget"),
+        (true, Some(_)) if quality_direction != QualityDirection::Minimize => {
+            anyhow::bail!("The schedule needs minimized qualities")
+        }
+        (true, Some(time_budget)) => Some((
+            schedule::ScheduleData::from_normalized_dataframe(
+                df.clone(),
+                num_cores,
+            )?,
+            time_budget,
+        )),
+    };
+    let options = match chance_constraint {
+        Some(threshold) => SolverOptions {
+            chance_constraint: Some(ChanceConstraint {
+                success: csv_parser::success_rates(
+                    df.clone(),
+                    &data,
+                    threshold.factor,
+                )?,
+                threshold,
+            }),
+            ..options
+        },
+        None => options,
+    };
+    if let Some(num_points) = args.pareto {
+        let times = csv_parser::expected_times(df, &data)?;
+        let front = solver::solve_pareto(
+            &data,
+            &times,
+            num_cores as usize,
+            num_points,
+            &options,
+        )?;
+        let mut csv = String::from("portfolio,objective,runtime\n");
+        for point in &front {
+            let name = &point.portfolio.name;
+            info!(
+                "{name}: objective {}, runtime {}\n{}",
+                point.objective, point.runtime, point.portfolio
+            );
+            csv += &format!("{name},{},{}\n", point.objective, point.runtime);
+            serde_json::to_writer_pretty(
+                fs::File::create(out_dir.join(format!("{name}.json")))?,
+                &point.portfolio,
+            )?;
+        }
+        fs::write(out_dir.join("pareto.csv"), csv)?;
+        return Ok(());
+    }
+    let options = match args.validate {
+        Some(num_seeds) => options.with_validation(
+            std::sync::Arc::new(df.clone().collect()?),
+            num_seeds,
+            0.1,
+        ),
+        None => options,
+    };
+    // the metadata headers are read independently of the parser that succeeded
+    let metadata = files
+        .iter()
+        .filter_map(|path| {
+            csv_parser::FileMetadata::from_path(path, comment_char?).ok()
+        })
+        .filter(|metadata| !metadata.entries.is_empty())
+        .collect::<Vec<_>>();
+    serde_json::to_writer_pretty(
+        fs::File::create(out_dir.join("metadata.json"))?,
+        &metadata,
+    )?;
+    if options.prune_dominated {
+        let dominated =
+            solver::dominated_cells(&data, num_cores as usize, &options);
+        let mut csv = String::from(
+            "algorithm,num_threads,copies,dominated_by,by_threads,by_copies\n",
+        );
+        for cell in &dominated {
+            info!("Pruned {cell}");
+            let (algo, by) = (&cell.algorithm, &cell.dominated_by);
+            csv += &format!(
+                "{},{},{},{},{},{}\n",
+                algo.algorithm,
+                algo.num_threads,
+                cell.copies,
+                by.algorithm,
+                by.num_threads,
+                cell.dominating_copies
+            );
+        }
+        fs::write(out_dir.join("dominated.csv"), csv)?;
+    }
+    let constraints = solver::constraint_options(&options);
+    if (args.racing || args.genetic) && !constraints.is_empty() {
+        anyhow::bail!(
+            "--racing and --genetic don't support the constraints {}",
+            constraints.join(", ")
+        );
+    }
+    let result = match (
+        args.racing,
+        args.genetic,
+        args.relaxation,
+        args.race_solvers,
+    ) {
+        _ if args.resume => solver::resume(out_dir.join("checkpoint"))?,
+        (true, _, _, _) => {
+            let final_portfolio = solver::racing::solve(
+                &df.collect()?,
+                num_cores,
+                &Default::default(),
+            )?;
+            OptimizationResult {
+                initial_portfolio: None,
+                gap_closed: solver::gap_closed(&data, &final_portfolio),
+                initial_gap_closed: None,
+                final_portfolio,
+                gap: f64::NAN,
+                history: Vec::new(),
+                refined: false,
+                pool: Vec::new(),
+                stats: Default::default(),
+                instance_qualities: None,
+            }
+        }
+        (false, true, _, _) => solver::genetic::solve(
+            &data,
+            num_cores as usize,
+            &solver::genetic::GeneticOptions {
+                timeout: options.timeout.clone(),
+                ..Default::default()
+            },
+        )?,
+        (false, false, true, _) => {
+            let solver::relaxation::RelaxationResult {
+                fractional_portfolio,
+                result,
+                rounding_loss,
+            } = solver::relaxation::solve(
+                &data,
+                num_cores as usize,
+                &options,
+                &Default::default(),
+            )?;
+            info!("Fractional portfolio:\n{fractional_portfolio}");
+            info!("Rounding the fractional portfolio lost {rounding_loss}");
+            serde_json::to_writer_pretty(
+                fs::File::create(out_dir.join("fractional_portfolio.json"))?,
+                &fractional_portfolio,
+            )?;
+            result
+        }
+        (false, false, false, true) => {
+            solver::solve_racing(&data, num_cores as usize, &options, None)?.0
+        }
+        (false, false, false, false) => {
+            solver::solve(&data, num_cores as usize, &options, None)?
+        }
+    };
+    let mut report = Report::new(
+        &result,
+        num_cores,
+        slowdown_ratio,
+        options.objective.clone(),
+    );
     let OptimizationResult {
         initial_portfolio,
         final_portfolio,
-        gap: _,
-    } = solver::solve(&data, num_cores as usize, timeout, None)?;
+        history,
+        gap_closed,
+        pool,
+        stats,
+        ..
+    } = result;
     info!("Final portfolio:\n{final_portfolio}");
+    info!(
+        "Solver stopped ({:?}) after {:.1}s with {} incumbents",
+        stats.termination, stats.wall_time, stats.num_incumbents
+    );
+    if let Some(numeric_focus) = stats.numeric_focus {
+        warn!(
+            "Rescaled the qualities and retried with NumericFocus \
+             {numeric_focus} after numerical trouble"
+        );
+    }
+    info!(
+        "Closed {:.1}% of the gap between the single and virtual best solver",
+        gap_closed * 100.0
+    );
+    if args.instance_qualities {
+        let mut csv = String::from("instance,expected_quality,ratio\n");
+        for (i, quality) in solver::instance_qualities(&data, &final_portfolio)
+            .into_iter()
+            .enumerate()
+        {
+            let instance = quality.instance.unwrap_or_else(|| i.to_string());
+            csv += &format!(
+                "{instance},{},{}\n",
+                quality.expected_quality, quality.ratio
+            );
+        }
+        fs::write(out_dir.join("instance_qualities.csv"), csv)?;
+    }
+    if let Some(additional) = args.marginal_cores {
+        let cores = num_cores + additional as u32;
+        let df = parse_cores(&files, cores)?;
+        let extended =
+            csv_parser::Data::from_normalized_dataframe_with_options(
+                df.clone(),
+                cores,
+                slowdown_ratio,
+                &data_options,
+            )?;
+        let chance_constraint = match &options.chance_constraint {
+            Some(chance) => Some(ChanceConstraint {
+                success: csv_parser::success_rates(
+                    df,
+                    &extended,
+                    chance.threshold.factor,
+                )?,
+                threshold: chance.threshold,
+            }),
+            None => None,
+        };
+        let marginal = solver::marginal_cores(
+            &extended,
+            num_cores as usize,
+            additional,
+            &SolverOptions {
+                chance_constraint,
+                validation: None,
+                ..options.clone()
+            },
+            &final_portfolio,
+        )?;
+        let mut csv = String::from(
+            "additional_cores,objective,improvement,marginal,gap\n",
+        );
+        for point in &marginal {
+            csv += &format!(
+                "{},{},{},{},{}\n",
+                point.additional_cores,
+                point.objective,
+                point.improvement,
+                point.marginal,
+                point.gap
+            );
+        }
+        fs::write(out_dir.join("marginal_cores.csv"), csv)?;
+    }
+    if let Some((runs, time_budget)) = schedule_runs {
+        let schedule = schedule::optimize(&runs, num_cores, time_budget)?;
+        let static_split = schedule::Schedule::from_portfolio(
+            &final_portfolio,
+            num_cores,
+            time_budget,
+        );
+        info!("Schedule:\n{schedule}");
+        info!(
+            "Objective of the schedule {}, of the final portfolio {}",
+            runs.objective(&schedule),
+            runs.objective(&static_split)
+        );
+        serde_json::to_writer_pretty(
+            fs::File::create(out_dir.join("schedule.json"))?,
+            &schedule,
+        )?;
+    }
+    csv_parser::df_to_normalized_csv(
+        csv_parser::history_to_df(&history)?,
+        out_dir.join("history.csv"),
+    )?;
     let random_portfolio = Portfolio::random(&data.algorithms, num_cores, 42);
     let portfolios = {
         let initial_portfolio_valid = match &initial_portfolio {
@@ -60,7 +584,12 @@ fn main() -> Result<()> {
             }
             None => false,
         };
-        let mut portfolios = vec![final_portfolio];
+        let alternatives = pool.into_iter().filter(|portfolio| {
+            portfolio.resource_assignments
+                != final_portfolio.resource_assignments
+        });
+        let mut portfolios = alternatives.collect::<Vec<_>>();
+        portfolios.insert(0, final_portfolio);
         if args.random_portfolio {
             portfolios.push(random_portfolio);
         }
@@ -73,22 +602,58 @@ fn main() -> Result<()> {
         }
         portfolios
     };
+    let parameters = format!("k{num_cores}_s{slowdown_ratio}");
+    let mut portfolios = portfolios
+        .into_iter()
+        .map(|portfolio| portfolio.with_parameters(&parameters))
+        .collect::<Vec<_>>();
+    let names = disambiguate_names(
+        &portfolios
+            .iter()
+            .map(|portfolio| portfolio.name.replace("_opt", ""))
+            .collect::<Vec<_>>(),
+        name_collision,
+    )?;
+    for (portfolio, name) in portfolios.iter_mut().zip(names) {
+        if name != portfolio.name.replace("_opt", "") {
+            info!("Renaming portfolio {} to {name}", portfolio.name);
+            portfolio.name = name;
+        }
+    }
+    report.portfolios = portfolios.iter().map(|p| p.name.clone()).collect();
+    serde_json::to_writer_pretty(
+        fs::File::create(out_dir.join("report.json"))?,
+        &report,
+    )?;
     serde_json::to_writer_pretty(
         fs::File::create(out_dir.join("executor.json"))?,
         &mt_kahypar_parser::PortfolioExecutorConfig {
+            schema: Some(Schema::current(Kind::Executor)),
             files,
             portfolios: portfolios.clone(),
             num_seeds,
             num_cores,
             out: out_dir.join("execution.csv"),
+            topology: topology.clone(),
+            instance_filters: Vec::new(),
+            instance_weights: Default::default(),
         },
     )?;
     for portfolio in portfolios {
         let portfolio_name = portfolio.name.replace("_opt", "");
+        let path = |extension: &str| {
+            out_dir.join(format!("{portfolio_name}.{extension}"))
+        };
         serde_json::to_writer_pretty(
-            fs::File::create(out_dir.join(portfolio_name + ".json"))?,
+            fs::File::create(path("json"))?,
             &portfolio,
         )?;
+        for format in &args.export {
+            fs::write(
+                path(format.extension()),
+                format.export(&portfolio, num_cores, topology.as_ref())?,
+            )?;
+        }
     }
     Ok(())
 }
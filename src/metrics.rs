@@ -0,0 +1,173 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, ToSocketAddrs},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::Instant,
+};
+
+use anyhow::Result;
+use log::warn;
+
+/// A floating point metric that can be updated from multiple threads
+pub struct Metric {
+    name: &'static str,
+    help: &'static str,
+    kind: &'static str,
+    bits: AtomicU64,
+}
+
+impl Metric {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        kind: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            help,
+            kind,
+            bits: AtomicU64::new(0),
+        }
+    }
+
+    /// Current value of the metric
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+
+    /// Set the metric to `value`
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Add `value` to the metric
+    pub fn add(&self, value: f64) {
+        let _ = self.bits.fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |bits| Some((f64::from_bits(bits) + value).to_bits()),
+        );
+    }
+}
+
+/// Number of solves currently running
+pub static JOBS_RUNNING: Metric = Metric::new(
+    "portfolio_solver_jobs_running",
+    "Number of solves currently running",
+    "gauge",
+);
+/// Number of solves started
+pub static JOBS_TOTAL: Metric = Metric::new(
+    "portfolio_solver_jobs_total",
+    "Number of solves started",
+    "counter",
+);
+/// Relative gap of the most recent incumbent or final solution
+pub static SOLVER_GAP: Metric = Metric::new(
+    "portfolio_solver_gap",
+    "Relative gap of the most recent incumbent or final solution",
+    "gauge",
+);
+/// Duration of the most recent parse of input data in seconds
+pub static PARSE_DURATION: Metric = Metric::new(
+    "portfolio_solver_parse_duration_seconds",
+    "Duration of the most recent parse of input data in seconds",
+    "gauge",
+);
+/// Total time spent parsing input data in seconds
+pub static PARSE_DURATION_TOTAL: Metric = Metric::new(
+    "portfolio_solver_parse_duration_seconds_total",
+    "Total time spent parsing input data in seconds",
+    "counter",
+);
+
+static METRICS: [&Metric; 5] = [
+    &JOBS_RUNNING,
+    &JOBS_TOTAL,
+    &SOLVER_GAP,
+    &PARSE_DURATION,
+    &PARSE_DURATION_TOTAL,
+];
+
+/// Marks a solve as running until it is dropped
+pub(crate) struct JobGuard;
+
+impl JobGuard {
+    pub(crate) fn start() -> Self {
+        JOBS_RUNNING.add(1.0);
+        JOBS_TOTAL.add(1.0);
+        Self
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        JOBS_RUNNING.add(-1.0);
+    }
+}
+
+/// Record the duration of a parse of the input files that started at `start`, e.g. around the
+/// parser of the binary
+pub fn record_parse(start: Instant) {
+    let duration = start.elapsed().as_secs_f64();
+    PARSE_DURATION.set(duration);
+    PARSE_DURATION_TOTAL.add(duration);
+}
+
+/// Render all metrics in the Prometheus text format
+pub fn render() -> String {
+    METRICS
+        .iter()
+        .map(|metric| {
+            format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n",
+                name = metric.name,
+                help = metric.help,
+                kind = metric.kind,
+                value = metric.get()
+            )
+        })
+        .collect()
+}
+
+/// Serve the metrics on `addr` (e.g. `"0.0.0.0:9100"`) from a background thread
+///
+/// Every HTTP request is answered with the [rendered](render) metrics, regardless of its path.
+pub fn serve<A: ToSocketAddrs>(addr: A) -> Result<thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|mut stream| {
+                // consume the request header before answering
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line)? > 2 {
+                    line.clear();
+                }
+                let body = render();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+            });
+            if let Err(err) = result {
+                warn!("Failed to serve metrics: {err}");
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        SOLVER_GAP.set(0.5);
+        let rendered = render();
+        assert!(rendered.contains("# TYPE portfolio_solver_gap gauge\n"));
+        assert!(rendered.contains("\nportfolio_solver_gap 0.5\n"));
+    }
+}
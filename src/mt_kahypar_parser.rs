@@ -4,9 +4,18 @@ use clap_verbosity_flag::Verbosity;
 use itertools::Itertools;
 use log::warn;
 use polars::{lazy::dsl::GetOutput, prelude::*};
-use portfolio_solver::datastructures::{Portfolio, Timeout};
+use portfolio_solver::csv_parser;
+use portfolio_solver::datastructures::{
+    Algorithm, AlgorithmConstraints, Backend, CoreClass, Decomposition,
+    GurobiLicense, ModelExport, NameCollision, Objective, Portfolio,
+    QualityDirection, QualityEstimator, QualityMetric, QualitySemantics,
+    QualityThreshold, TimeToTarget, Timeout, Topology,
+};
+use portfolio_solver::export::ExportFormat;
+use portfolio_solver::schema;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{f64::EPSILON, fs, path::PathBuf};
+use std::{collections::HashMap, f64::EPSILON, fs, path::PathBuf};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
@@ -23,15 +32,104 @@ pub struct Config {
     pub out_dir: PathBuf,
     #[serde(default)]
     pub timeout: Timeout,
+    /// Relative MIP gap at which the solver stops, independent of the timeout
+    #[serde(default)]
+    pub mip_gap: Option<f64>,
+    #[serde(default)]
+    pub quality_guarantee: Option<f64>,
+    #[serde(default)]
+    pub quality_metric: Option<QualityMetric>,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub decomposition: Decomposition,
+    #[serde(default)]
+    pub lazy_linking: bool,
+    #[serde(default)]
+    pub prune_dominated: bool,
+    #[serde(default)]
+    pub topology: Option<Topology>,
+    #[serde(default)]
+    pub name_collision: NameCollision,
+    #[serde(default)]
+    pub objective: Objective,
+    #[serde(default)]
+    pub quality_semantics: QualitySemantics,
+    #[serde(default)]
+    pub quality_direction: QualityDirection,
+    #[serde(default)]
+    pub algorithm_constraints: AlgorithmConstraints,
+    #[serde(default)]
+    pub sampling_seed: u64,
+    #[serde(default)]
+    pub quality_estimator: QualityEstimator,
+    /// Algorithms with their memory requirements, costs and startup times
+    #[serde(default, alias = "memory_requirements")]
+    pub requirements: Vec<Algorithm>,
+    #[serde(default)]
+    pub memory_budget: Option<f64>,
+    #[serde(default)]
+    pub cost_budget: Option<f64>,
+    /// Minimum percentage of instances an algorithm needs valid runs on
+    #[serde(default)]
+    pub min_coverage: f64,
+    /// Never assign instances to algorithms without measured runs on them
+    #[serde(default)]
+    pub forbid_imputed: bool,
+    /// Regex deriving the number of threads from the algorithm name
+    #[serde(default)]
+    pub thread_pattern: Option<String>,
+    /// Number of best distinct portfolios of the solution pool to write
+    #[serde(default)]
+    pub pool_size: Option<usize>,
+    /// Wall-clock budget per instance in seconds
+    #[serde(default)]
+    pub time_budget: Option<f64>,
+    /// Relative degradation of the objective up to which algorithms are
+    /// removed from the final portfolio
+    #[serde(default)]
+    pub prune_tolerance: Option<f64>,
+    /// Penalty per algorithm in the portfolio added to the objective
+    #[serde(default)]
+    pub complexity_penalty: Option<f64>,
+    /// Quality threshold per instance a run of the portfolio has to reach
+    /// with a minimum probability
+    #[serde(default)]
+    pub chance_constraint: Option<QualityThreshold>,
+    /// Optimize the expected time to reach a target quality per instance
+    /// instead of the expected best quality
+    #[serde(default)]
+    pub time_to_target: Option<TimeToTarget>,
+    /// Classes of cores with their speeds, all algorithms are split into one
+    /// algorithm per class
+    #[serde(default)]
+    pub core_classes: Vec<CoreClass>,
+    /// Gurobi license (token server or WLS) instead of the license file found
+    /// by Gurobi
+    #[serde(default)]
+    pub license: Option<GurobiLicense>,
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PortfolioExecutorConfig {
+    /// Kind and version of the format, the current version if missing
+    #[serde(default)]
+    pub schema: Option<schema::Schema>,
     pub files: Vec<PathBuf>,
     pub portfolios: Vec<Portfolio>,
     pub num_seeds: u32,
     pub num_cores: u32,
     pub out: PathBuf,
+    #[serde(default)]
+    pub topology: Option<Topology>,
+    /// Instances every portfolio (in order) is simulated on, all instances
+    /// if missing
+    #[serde(default)]
+    pub instance_filters: Vec<Option<Vec<String>>>,
+    /// Weight of every instance in the summary, 1 if missing
+    #[serde(default)]
+    pub instance_weights: HashMap<String, f64>,
 }
 
 impl Config {
@@ -51,6 +149,85 @@ impl Config {
         if let Some(timeout) = &args.timeout {
             config.timeout = timeout.clone();
         }
+        if let Some(mip_gap) = args.mip_gap {
+            config.mip_gap = Some(mip_gap);
+        }
+        if let Some(quality_guarantee) = args.quality_guarantee {
+            config.quality_guarantee = Some(quality_guarantee);
+        }
+        if let Some(threshold) = args.chance_constraint {
+            config.chance_constraint = Some(threshold);
+        }
+        if let Some(target) = args.time_to_target {
+            config.time_to_target = Some(target);
+        }
+        if let Some(horizon) = args.quality_at {
+            config.quality_metric = Some(QualityMetric::AtTime(horizon));
+        }
+        if let Some(horizon) = args.quality_auc {
+            config.quality_metric =
+                Some(QualityMetric::AreaUnderCurve(horizon));
+        }
+        if let Some(name_collision) = args.name_collision {
+            config.name_collision = name_collision;
+        }
+        if let Some(backend) = args.backend {
+            config.backend = backend;
+        }
+        if let Some(decomposition) = args.decomposition {
+            config.decomposition = decomposition;
+        }
+        if args.lazy_linking {
+            config.lazy_linking = true;
+        }
+        if args.prune_dominated {
+            config.prune_dominated = true;
+        }
+        if let Some(objective) = &args.objective {
+            config.objective = objective.clone();
+        }
+        if let Some(quality_semantics) = args.quality_semantics {
+            config.quality_semantics = quality_semantics;
+        }
+        if let Some(quality_direction) = args.quality_direction {
+            config.quality_direction = quality_direction;
+        }
+        if let Some(sampling_seed) = args.sampling_seed {
+            config.sampling_seed = sampling_seed;
+        }
+        if let Some(quality_estimator) = args.quality_estimator {
+            config.quality_estimator = quality_estimator;
+        }
+        if let Some(thread_pattern) = &args.thread_pattern {
+            config.thread_pattern = Some(thread_pattern.clone());
+        }
+        if args.forbid_imputed {
+            config.forbid_imputed = true;
+        }
+        if let Some(min_coverage) = args.min_coverage {
+            config.min_coverage = min_coverage;
+        }
+        if let Some(memory_budget) = args.memory_budget {
+            config.memory_budget = Some(memory_budget);
+        }
+        if let Some(cost_budget) = args.cost_budget {
+            config.cost_budget = Some(cost_budget);
+        }
+        if let Some(pool_size) = args.pool_size {
+            config.pool_size = Some(pool_size);
+        }
+        if let Some(time_budget) = args.time_budget {
+            config.time_budget = Some(time_budget);
+        }
+        if let Some(prune_tolerance) = args.prune {
+            config.prune_tolerance = Some(prune_tolerance);
+        }
+        if let Some(complexity_penalty) = args.complexity_penalty {
+            config.complexity_penalty = Some(complexity_penalty);
+        }
+        if let Some(topology) = &args.topology {
+            config.topology = Some(topology.clone());
+        }
         if let Some(num_cores) = args.num_cores {
             config.num_cores = num_cores;
         }
@@ -91,12 +268,25 @@ pub fn parse_hypergraph_dataframe(
     paths: &[PathBuf],
     desired_instances: Option<InstanceFilter>,
     num_cores: u32,
+    thread_pattern: Option<&Regex>,
 ) -> Result<LazyFrame> {
     let instance_fields: [Expr; 3] = [col("graph"), col("k"), col("epsilon")];
+    // the cross join of the instance filter is computed once for all files
+    let instance_filter = match desired_instances.and_then(|filter| {
+        get_desired_instances(
+            &filter.instance_path,
+            &filter.ks,
+            &filter.feasibility_thresholds,
+        )
+        .ok()
+    }) {
+        Some(instance_filter) => Some(instance_filter.collect()?),
+        None => None,
+    };
     let read_df = |path: &PathBuf,
                    in_fields: &[&'static str]|
      -> Result<LazyFrame> {
-        let mut dataframe = CsvReader::from_path(path)?
+        let mut raw_df = CsvReader::from_path(path)?
             .with_comment_char(Some(b'#'))
             .has_header(true)
             .with_columns(Some(
@@ -105,8 +295,19 @@ pub fn parse_hypergraph_dataframe(
             .with_dtypes(Some(&Schema::from(
                 [Field::new("km1", DataType::Float64)].into_iter(),
             )))
-            .finish()?
-            .lazy()
+            .finish()?;
+        if !in_fields.contains(&"num_threads") {
+            let threads =
+                Series::new("num_threads", vec![1_i64; raw_df.height()]);
+            raw_df.with_column(threads)?;
+        }
+        let raw_df = match thread_pattern {
+            Some(pattern) => {
+                csv_parser::infer_num_threads(raw_df.lazy(), pattern)
+            }
+            None => raw_df.lazy(),
+        };
+        let mut dataframe = raw_df
             .filter(col("num_threads").lt_eq(lit(num_cores)))
             .with_columns([
                 col("graph").apply(
@@ -128,23 +329,14 @@ pub fn parse_hypergraph_dataframe(
                     GetOutput::from_type(DataType::Float64),
                 ),
             ]);
-        match &desired_instances {
-            Some(filter) => {
-                if let Ok(instance_filter) = get_desired_instances(
-                    &filter.instance_path,
-                    &filter.ks,
-                    &filter.feasibility_thresholds,
-                ) {
-                    dataframe = dataframe.join(
-                        instance_filter,
-                        &instance_fields,
-                        &instance_fields,
-                        JoinType::Inner,
-                    );
-                }
-            }
-            None => (),
-        };
+        if let Some(instance_filter) = &instance_filter {
+            dataframe = dataframe.join(
+                instance_filter.clone().lazy(),
+                &instance_fields,
+                &instance_fields,
+                JoinType::Inner,
+            );
+        }
         Ok(dataframe.select([
             concat_str(&instance_fields, "").alias("instance"),
             col("algorithm"),
@@ -175,14 +367,9 @@ pub fn parse_hypergraph_dataframe(
     fixed_in_fields.retain(|s| *s != "num_threads");
     let dataframes: Vec<LazyFrame> = paths
         .iter()
-        .map(|path| match read_df(path, &columns) {
-            Ok(result) => Ok(result),
-            Err(_) => match read_df(path, &fixed_in_fields) {
-                Ok(result) => {
-                    Ok(result.with_column(lit(1_i64).alias("num_threads")))
-                }
-                Err(err) => anyhow::bail!(err),
-            },
+        .map(|path| {
+            read_df(path, &columns)
+                .or_else(|_| read_df(path, &fixed_in_fields))
         })
         .filter_map(Result::ok)
         .collect();
@@ -248,19 +435,237 @@ pub struct Args {
     /// How often a portfolio run is sampled for each instance
     #[arg(short, long)]
     pub num_seeds: Option<u32>,
+    /// Seed for sampling the expected best quality of repeated runs of an
+    /// algorithm (samples of size s use SEED + s)
+    #[arg(long, value_name = "SEED")]
+    pub sampling_seed: Option<u64>,
+    /// Estimate the expected best quality of repeated runs of an algorithm
+    /// by sampling (sampling), averaged over ROUNDS sampling rounds
+    /// (sampling:<ROUNDS>) or exactly (exact)
+    #[arg(long, value_name = "ESTIMATOR", value_parser)]
+    pub quality_estimator: Option<QualityEstimator>,
+    /// Drop algorithms with valid runs on fewer than PERCENT of the
+    /// instances instead of imputing their missing runs
+    #[arg(long, value_name = "PERCENT")]
+    pub min_coverage: Option<f64>,
+    /// Never assign instances to algorithms without measured runs on them,
+    /// instead of relying on the imputed worst quality
+    #[arg(long)]
+    pub forbid_imputed: bool,
+    /// Derive the number of threads from the algorithm name with REGEX, the
+    /// capture group `threads` or the first capture group is the number of
+    /// threads (e.g. ".*-t(\d+)$")
+    #[arg(long, value_name = "REGEX")]
+    pub thread_pattern: Option<String>,
     /// Path to the output directory
     #[arg(short, long, value_name = "DIR")]
     pub out_dir: Option<PathBuf>,
     /// Timeout for the LP solver in seconds
     #[arg(short, long, value_parser)]
     pub timeout: Option<Timeout>,
+    /// Stop as soon as the relative gap of the portfolio to the lower bound
+    /// is at most GAP (e.g. 0.001), independent of the timeout
+    #[arg(long, value_name = "GAP")]
+    pub mip_gap: Option<f64>,
+    /// MIP solver (gurobi, highs, cbc or cpsat), all but gurobi require
+    /// the corresponding feature
+    #[arg(long, value_parser)]
+    pub backend: Option<Backend>,
+    /// Solve the Gurobi model at once (monolithic), with a Benders
+    /// decomposition (benders) or with the decomposition only for large
+    /// data (auto)
+    #[arg(long, value_parser)]
+    pub decomposition: Option<Decomposition>,
+    /// Add the constraints linking the instances to the selected algorithms
+    /// only once a solution violates them, which shrinks the initial model
+    #[arg(long)]
+    pub lazy_linking: bool,
+    /// Drop the numbers of repetitions of algorithms that another selection
+    /// is at least as good as on every instance, written to dominated.csv
+    #[arg(long)]
+    pub prune_dominated: bool,
+    /// Objective on the quality ratios to the best quality: sum (ratio),
+    /// geometric mean (gmean), maximum (worst-case), sum with ratios above
+    /// CUTOFF counted as FACTOR * CUTOFF (par<FACTOR>:<CUTOFF>), mean of
+    /// the worst ALPHA-fraction of instances (cvar<ALPHA>), mean plus
+    /// LAMBDA times the variance (mv<LAMBDA>) or fraction of the single
+    /// algorithms that are better on every instance (rank)
+    #[arg(long, value_parser)]
+    pub objective: Option<Objective>,
+    /// Qualities are positive absolute values compared by ratio to the best
+    /// quality (ratio), or relative to a reference solution and compared by
+    /// difference to the best quality, keeping zeros and negatives
+    /// (difference)
+    #[arg(long, value_name = "SEMANTICS", value_parser)]
+    pub quality_semantics: Option<QualitySemantics>,
+    /// Smaller qualities are better (minimize) or larger qualities are
+    /// better (maximize)
+    #[arg(long, value_name = "DIRECTION", value_parser)]
+    pub quality_direction: Option<QualityDirection>,
     /// Number of cores available to the portfolio
     #[arg(short = 'k', long)]
     pub num_cores: Option<u32>,
+    /// Total memory available to the portfolio, in the unit of the
+    /// memory requirements of the config
+    #[arg(long, value_name = "MEMORY")]
+    pub memory_budget: Option<f64>,
+    /// Total cost of the algorithms in the portfolio, in the unit of the
+    /// costs of the config
+    #[arg(long, value_name = "COST")]
+    pub cost_budget: Option<f64>,
+    /// Require the expected quality of every instance to be within
+    /// FACTOR * best quality
+    #[arg(long, value_name = "FACTOR")]
+    pub quality_guarantee: Option<f64>,
+    /// Require a run of the portfolio to reach a quality within FACTOR *
+    /// best quality with at least PROBABILITY on every instance, estimated
+    /// from the repeated runs
+    #[arg(long, value_name = "FACTOR:PROBABILITY", value_parser)]
+    pub chance_constraint: Option<QualityThreshold>,
+    /// Minimize the expected time until a run reaches a quality within
+    /// FACTOR * best quality, runs missing it count as PENALTY (default 10)
+    /// times the longest run on the instance
+    #[arg(
+        long,
+        value_name = "FACTOR[:PENALTY]",
+        value_parser,
+        conflicts_with = "chance_constraint"
+    )]
+    pub time_to_target: Option<TimeToTarget>,
+    /// Input files contain quality checkpoints per run, use the best quality
+    /// reached within SECONDS
+    #[arg(long, value_name = "SECONDS", conflicts_with = "quality_auc")]
+    pub quality_at: Option<f64>,
+    /// Input files contain quality checkpoints per run, use the area under
+    /// the quality curve up to SECONDS
+    #[arg(long, value_name = "SECONDS")]
+    pub quality_auc: Option<f64>,
+    /// Wall-clock budget per instance, only consider as many repetitions of
+    /// an algorithm as are expected to finish within SECONDS
+    #[arg(long, value_name = "SECONDS")]
+    pub time_budget: Option<f64>,
+    /// Create the portfolio by racing candidate allocations in the
+    /// simulator instead of solving the MIP (faster for many algorithms)
+    #[arg(long)]
+    pub racing: bool,
+    /// Create the portfolio with a genetic algorithm on the expected
+    /// qualities instead of solving the MIP
+    #[arg(long, conflicts_with = "racing")]
+    pub genetic: bool,
+    /// Create the portfolio by randomized rounding of the LP relaxation
+    /// instead of solving the MIP, the fractional solution is written to
+    /// fractional_portfolio.json
+    #[arg(long, conflicts_with_all = ["racing", "genetic"])]
+    pub relaxation: bool,
+    /// Run the MIP, the genetic algorithm and the greedy heuristic in
+    /// parallel and keep the best portfolio found within the timeout
+    #[arg(long, conflicts_with_all = ["racing", "genetic", "relaxation"])]
+    pub race_solvers: bool,
+    /// Also write the N best distinct portfolios of the Gurobi solution
+    /// pool (pool_portfolio_<i>) and add them to the executor config
+    #[arg(long, value_name = "N")]
+    pub pool_size: Option<usize>,
+    /// Remove algorithms from the final portfolio as long as the objective
+    /// degrades by at most TOLERANCE (relative), e.g. 0.01 for 1%
+    #[arg(long, value_name = "TOLERANCE")]
+    pub prune: Option<f64>,
+    /// Add LAMBDA per algorithm in the portfolio to the objective to prefer
+    /// fewer algorithms, e.g. 0.01 to drop algorithms that improve the sum
+    /// of the quality ratios by less
+    #[arg(long, value_name = "LAMBDA")]
+    pub complexity_penalty: Option<f64>,
+    /// Machine topology as SOCKETSxCORES (e.g. 2x8) or cores per NUMA node
+    /// (e.g. 8,8), every algorithm copy has to fit on a single node
+    #[arg(long, value_name = "SOCKETSxCORES", value_parser)]
+    pub topology: Option<Topology>,
+    /// Write checkpoints of the Gurobi solve to <OUT_DIR>/checkpoint, at
+    /// least every SECONDS
+    #[arg(long, value_name = "SECONDS")]
+    pub checkpoint: Option<f64>,
+    /// Write the Gurobi model to FILE before solving it, the extension (.lp
+    /// or .mps) determines the format
+    #[arg(long, value_name = "FILE", value_parser)]
+    pub write_model: Option<ModelExport>,
+    /// Start the Gurobi solve from the solution in FILE (.sol or .mst),
+    /// e.g. found by an external tool on the model of --write-model
+    #[arg(long, value_name = "FILE")]
+    pub start_solution: Option<PathBuf>,
+    /// Resume the interrupted Gurobi solve from the checkpoint in
+    /// <OUT_DIR>/checkpoint instead of solving from scratch
+    #[arg(long, conflicts_with = "race_solvers")]
+    #[arg(conflicts_with_all = ["racing", "genetic", "relaxation"])]
+    pub resume: bool,
+    /// Simulate every incumbent of the Gurobi solve with SEEDS seeds, warn
+    /// if it deviates from the expected objective and add the simulated
+    /// objective to history.csv
+    #[arg(long, value_name = "SEEDS")]
+    pub validate: Option<u32>,
+    /// Enumerate POINTS portfolios on the Pareto front of quality and
+    /// expected running time with the Gurobi solver instead of a single
+    /// portfolio, written to pareto_portfolio_<i>.json and pareto.csv
+    #[arg(long, value_name = "POINTS")]
+    #[arg(conflicts_with_all = ["resume", "race_solvers", "watch"])]
+    #[arg(conflicts_with_all = ["racing", "genetic", "relaxation"])]
+    pub pareto: Option<usize>,
+    /// Create one portfolio per group of instances instead of a single
+    /// portfolio, the group of an instance is the match of PATTERN on its
+    /// name (capture group `group` or the first one), e.g. `^[^_]+` for
+    /// instance families; written to group_<group>_portfolio.json and
+    /// groups.csv
+    #[arg(long, value_name = "PATTERN", value_parser)]
+    #[arg(conflicts_with_all = ["resume", "race_solvers", "watch"])]
+    #[arg(conflicts_with_all = ["racing", "genetic", "relaxation"])]
+    #[arg(conflicts_with_all = ["pareto", "marginal_cores", "schedule"])]
+    pub group_by: Option<regex::Regex>,
+    /// Deploy at most N distinct algorithms over all groups of --group-by
+    #[arg(long, value_name = "N", requires = "group_by")]
+    pub max_deployed: Option<usize>,
+    /// Solve POINTS slowdown ratios between 1 and the slowdown ratio
+    /// greedily (or by the LP relaxation with --relaxation) instead of a
+    /// single portfolio, written to slowdown_portfolio_<i>.json and
+    /// slowdown.csv with the quality and expected running time of each
+    #[arg(long, value_name = "POINTS")]
+    #[arg(conflicts_with_all = ["resume", "race_solvers", "watch"])]
+    #[arg(conflicts_with_all = ["racing", "genetic", "pareto", "group_by"])]
+    #[arg(conflicts_with_all = ["marginal_cores", "schedule"])]
+    pub calibrate_slowdown: Option<usize>,
+    /// After solving, solve again for 1 to N additional cores starting from
+    /// the final portfolio and write how much they improve the objective
+    /// to marginal_cores.csv
+    #[arg(long, value_name = "N", conflicts_with_all = ["pareto", "watch"])]
+    pub marginal_cores: Option<usize>,
+    /// After solving, also build a sequential schedule of time slices per
+    /// core within the time budget and write it to schedule.json
+    #[arg(long, conflicts_with_all = ["pareto", "watch"])]
+    pub schedule: bool,
+    /// Keep watching the input files and solve again whenever they change,
+    /// polling every SECONDS
+    #[arg(short, long, value_name = "SECONDS")]
+    pub watch: Option<f64>,
+    /// Serve Prometheus metrics on ADDR (e.g. 0.0.0.0:9100)
+    #[cfg(feature = "server")]
+    #[arg(long, value_name = "ADDR")]
+    pub metrics: Option<String>,
+    /// Write the normalized input data used for the optimization (after
+    /// all filters) to FILE
+    #[arg(long, value_name = "FILE")]
+    pub dump_normalized: Option<PathBuf>,
+    /// Write the expected quality of the final portfolio on every instance
+    /// and its ratio to the best quality to instance_qualities.csv
+    #[arg(long)]
+    pub instance_qualities: bool,
     /// Write initial portfolio to output
     /// (Only if different from final portfolio)
     #[arg(short, long)]
     pub initial_portfolio: bool,
+    /// Additionally export every written portfolio as SLURM job-array script
+    /// (slurm) or task list with cpusets (yaml)
+    #[arg(long, value_name = "FORMAT", value_delimiter = ' ', num_args = 0..)]
+    pub export: Vec<ExportFormat>,
+    /// How to name portfolios of the same name: append a counter (suffix),
+    /// fail (error) or overwrite the file (overwrite)
+    #[arg(long, value_name = "MODE", value_parser)]
+    pub name_collision: Option<NameCollision>,
     /// Write random portfolio to output
     /// (Only if at least 1 sequential algorithm remains after slowdown filtering)
     #[arg(short, long)]
@@ -279,7 +684,7 @@ mod tests {
     fn test_hypergraph_parser() {
         let k = 4;
         let path = PathBuf::from("data/test/algo4.csv");
-        let df = parse_hypergraph_dataframe(&[path], None, k)
+        let df = parse_hypergraph_dataframe(&[path], None, k, None)
             .unwrap()
             .collect()
             .unwrap();
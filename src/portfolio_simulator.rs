@@ -1,29 +1,127 @@
 use crate::datastructures::*;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use itertools::Itertools;
 use polars::prelude::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// A single simulated execution of a portfolio, see [`rerun_simulation`]
+pub struct SimulationRun {
+    /// The simulated portfolio, for single algorithms with the sampled number of runs
+    pub portfolio: Portfolio,
+    /// Seed used to sample the runs
+    pub seed: u64,
+    /// Hash of the data frame the runs were sampled from (see [`data_hash`])
+    pub data_hash: u64,
+    /// Instances the portfolio was simulated on, all instances if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instances: Option<Vec<String>>,
+}
 
 /// Simulate execution of a portfolio
 ///
 /// For each algorithm `num_seeds` runs will be sampled from the data frame for each instance
+///
+/// `instance_filters` optionally restricts the portfolio at the same position to the given
+/// instances (e.g. a portfolio specialized on a family of instances), portfolios without a filter
+/// are simulated on all instances. Runs are named after their portfolio, so portfolios with the
+/// same name and disjoint filters are reported as a single portfolio on the union of their
+/// instances. Overlapping filters of portfolios with the same name are an error, as every instance
+/// would be counted multiple times.
+///
+/// With `paired_seeds`, all portfolios are simulated with the same seeds, so portfolios sample the
+/// same underlying runs of the algorithms they share (common random numbers), which reduces the
+/// variance of head-to-head comparisons. Otherwise, every portfolio gets its own seeds and the
+/// samples of different portfolios are independent.
+#[allow(clippy::too_many_arguments)]
 pub fn simulation_df(
     df: &DataFrame,
     algorithms: &ndarray::Array1<Algorithm>,
     portfolios: &[Portfolio],
+    instance_filters: &[Option<Vec<String>>],
     num_seeds: u32,
+    paired_seeds: bool,
     instance_fields: &[&str],
     algorithm_fields: &[&str],
     num_cores: u32,
 ) -> Result<LazyFrame> {
+    Ok(simulation_df_with_manifest(
+        df,
+        algorithms,
+        portfolios,
+        instance_filters,
+        num_seeds,
+        paired_seeds,
+        instance_fields,
+        algorithm_fields,
+        num_cores,
+    )?
+    .0)
+}
+
+/// Simulate execution of a portfolio, see [`simulation_df`]
+///
+/// Additionally returns a manifest with every simulated run, which can be used to
+/// [re-run](rerun_simulation) a single run.
+#[allow(clippy::too_many_arguments)]
+pub fn simulation_df_with_manifest(
+    df: &DataFrame,
+    algorithms: &ndarray::Array1<Algorithm>,
+    portfolios: &[Portfolio],
+    instance_filters: &[Option<Vec<String>>],
+    num_seeds: u32,
+    paired_seeds: bool,
+    instance_fields: &[&str],
+    algorithm_fields: &[&str],
+    num_cores: u32,
+) -> Result<(LazyFrame, Vec<SimulationRun>)> {
+    anyhow::ensure!(
+        instance_filters.len() <= portfolios.len(),
+        "Got {} instance filters for {} portfolios",
+        instance_filters.len(),
+        portfolios.len()
+    );
+    check_overlapping_filters(portfolios, instance_filters)?;
+    let data_hash = data_hash(df)?;
+    let (portfolios, filters): (Vec<_>, Vec<_>) = portfolios
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| !p.resource_assignments.is_empty())
+        .map(|(i, p)| (p.clone(), instance_filters.get(i).cloned().flatten()))
+        .chain(
+            algorithms_as_portfolios(algorithms, num_cores)
+                .into_iter()
+                .map(|p| (p, None)),
+        )
+        .unzip();
+    // the runs of an algorithm are sampled as a prefix of the same random sequence for the same
+    // seed, so equal seeds share the runs of common algorithms
+    let seeds = |i: usize| {
+        let offset = match paired_seeds {
+            true => 0,
+            false => i as u64 * num_seeds as u64,
+        };
+        (0..num_seeds as u64).map(move |seed| offset + seed)
+    };
     let portfolio_runs = portfolios
         .iter()
-        .filter(|p| !p.resource_assignments.is_empty())
-        .map(|p| {
+        .zip(&filters)
+        .enumerate()
+        .map(|(i, (p, filter))| {
+            let filtered;
+            let df = match filter.as_ref() {
+                Some(instances) => {
+                    filtered = filter_instances(df, instances)?;
+                    &filtered
+                }
+                None => df,
+            };
             simulate_portfolio_execution(
                 df,
                 p,
-                num_seeds,
+                seeds(i),
                 instance_fields,
                 algorithm_fields,
                 num_cores,
@@ -31,32 +129,334 @@ pub fn simulation_df(
         })
         .filter_map(Result::ok)
         .collect_vec();
-    let algorithm_portfolios = simulate_algorithms_as_portfolio(
-        df,
-        algorithms,
-        num_seeds,
+    let manifest = portfolios
+        .iter()
+        .zip(&filters)
+        .enumerate()
+        .flat_map(|(i, (portfolio, filter))| {
+            seeds(i).map(move |seed| SimulationRun {
+                portfolio: portfolio.clone(),
+                seed,
+                data_hash,
+                instances: filter.clone(),
+            })
+        })
+        .collect_vec();
+    Ok((concat(portfolio_runs, false, false)?, manifest))
+}
+
+/// Re-run a single simulated run from a manifest on a single instance
+///
+/// `df` has to be the data frame the run was originally simulated on.
+pub fn rerun_simulation(
+    df: &DataFrame,
+    run: &SimulationRun,
+    instance: &str,
+    instance_fields: &[&str],
+    algorithm_fields: &[&str],
+    num_cores: u32,
+) -> Result<DataFrame> {
+    anyhow::ensure!(
+        data_hash(df)? == run.data_hash,
+        "The data differs from the data the run of {} was simulated on",
+        run.portfolio.name
+    );
+    if let Some(instances) = &run.instances {
+        anyhow::ensure!(
+            instances.iter().any(|i| i == instance),
+            "Portfolio {} was not simulated on instance {instance}",
+            run.portfolio.name
+        );
+    }
+    let instance_df = df
+        .clone()
+        .lazy()
+        .filter(col("instance").eq(lit(instance)))
+        .collect()?;
+    anyhow::ensure!(instance_df.height() > 0, "Instance {instance} not found");
+    Ok(portfolio_run_from_samples(
+        simulate(&instance_df, &run.portfolio, run.seed)?,
         instance_fields,
         algorithm_fields,
         num_cores,
-    )?;
-    Ok(concat(
-        &[portfolio_runs, vec![algorithm_portfolios]].concat(),
-        false,
-        false,
-    )?)
+        &run.portfolio.name,
+    )
+    .collect()?)
 }
 
-fn simulate_portfolio_execution(
+/// Rows of `df` with one of the `instances`
+fn filter_instances(
     df: &DataFrame,
+    instances: &[String],
+) -> Result<DataFrame> {
+    let instances =
+        instances.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mask = df
+        .column("instance")?
+        .utf8()?
+        .into_iter()
+        .map(|instance| instance.map_or(false, |i| instances.contains(i)))
+        .collect::<BooleanChunked>();
+    Ok(df.filter(&mask)?)
+}
+
+/// Fail if portfolios with the same name are simulated on the same instance
+fn check_overlapping_filters(
+    portfolios: &[Portfolio],
+    instance_filters: &[Option<Vec<String>>],
+) -> Result<()> {
+    let mut seen = HashMap::<&str, Vec<Option<&Vec<String>>>>::new();
+    for (i, portfolio) in portfolios.iter().enumerate() {
+        let filter = instance_filters.get(i).and_then(Option::as_ref);
+        let previous = seen.entry(&portfolio.name).or_default();
+        for other in previous.iter() {
+            let overlapping = match (filter, *other) {
+                (Some(filter), Some(other)) => {
+                    filter.iter().find(|instance| other.contains(instance))
+                }
+                (Some(filter), None) | (None, Some(filter)) => filter.first(),
+                (None, None) => None,
+            };
+            if let Some(instance) = overlapping {
+                anyhow::bail!(
+                    "Portfolio {} is simulated on instance {instance} multiple times",
+                    portfolio.name
+                );
+            }
+        }
+        previous.push(filter);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Deviation of the simulated expected best quality from the expected best quality of the
+/// [`Data`](crate::csv_parser::Data), see [`consistency_check`]
+pub struct Deviation {
+    /// Instance name
+    pub instance: String,
+    /// Algorithm of the portfolio
+    pub algorithm: Algorithm,
+    /// Number of runs of the algorithm
+    pub copies: usize,
+    /// Expected best quality of the data
+    pub expected: f64,
+    /// Mean best quality of the simulated runs
+    pub simulated: f64,
+}
+
+impl Deviation {
+    /// Deviation of the simulated quality relative to the expected quality
+    pub fn relative(&self) -> f64 {
+        (self.simulated - self.expected).abs()
+            / self.expected.abs().max(f64::EPSILON)
+    }
+}
+
+/// Check that the simulator reproduces the expected best qualities of `data` built from `df`
+///
+/// Every algorithm of the `portfolio` is simulated on its own with its number of runs for
+/// `num_seeds` seeds and the mean best quality per instance is compared to the corresponding
+/// expected best quality of the data, matching the instances of the data to the sorted instance
+/// names of the valid runs in `df`. For large seed counts, the deviations converge to zero unless
+/// the data and the simulator disagree on the schema or the order of instances or algorithms, so
+/// this can be used as a canary for the whole pipeline. The data has to be built from `df` without
+/// filters removing instances and with [`QualitySemantics::Ratio`].
+///
+/// Returns the deviations of all measured cells, largest relative deviation first. Cells of
+/// instances without runs of an algorithm (imputed in the data) are skipped.
+pub fn consistency_check(
+    df: &DataFrame,
+    data: &crate::csv_parser::Data,
     portfolio: &Portfolio,
     num_seeds: u32,
+) -> Result<Vec<Deviation>> {
+    let valid_df = df.clone().lazy().filter(col("valid")).collect()?;
+    let instances = valid_df["instance"].unique()?.sort(false);
+    anyhow::ensure!(
+        instances.len() == data.num_instances,
+        "The data has {} instances, but the data frame has {}",
+        data.num_instances,
+        instances.len()
+    );
+    let mut deviations = Vec::new();
+    for (algo, copies) in &portfolio.resource_assignments {
+        if *copies < 1.0 {
+            continue;
+        }
+        let j = data
+            .algorithms
+            .iter()
+            .position(|a| a == algo)
+            .with_context(|| format!("{algo} is not part of the data"))?;
+        let k = *copies as usize;
+        anyhow::ensure!(
+            k <= data.expected_best_quality.shape()[2],
+            "{k} copies of {algo} exceed the repetitions of the data"
+        );
+        let single = Portfolio {
+            name: algo.to_string(),
+            resource_assignments: vec![(algo.clone(), *copies)],
+        };
+        let runs = (0..num_seeds)
+            .map(|seed| {
+                Ok(simulate(&valid_df, &single, seed as u64)?
+                    .groupby([col("instance"), col("seed")])
+                    .agg([min("quality")]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let means = concat(runs, false, false)?
+            .groupby([col("instance")])
+            .agg([col("quality").mean()])
+            .collect()?;
+        let simulated: HashMap<&str, f64> = means["instance"]
+            .utf8()?
+            .into_no_null_iter()
+            .zip(means["quality"].f64()?.into_no_null_iter())
+            .collect();
+        for (i, instance) in instances.utf8()?.into_no_null_iter().enumerate()
+        {
+            let Some(&simulated) = simulated.get(instance) else {
+                continue;
+            };
+            deviations.push(Deviation {
+                instance: instance.to_string(),
+                algorithm: algo.clone(),
+                copies: k,
+                expected: data.expected_best_quality[(i, j, k - 1)],
+                simulated,
+            });
+        }
+    }
+    deviations.sort_by(|a, b| b.relative().total_cmp(&a.relative()));
+    Ok(deviations)
+}
+
+/// A user-defined metric of [`simulation_summary`], computed from the per-instance frame of a
+/// single portfolio
+pub type SummaryMetric = Box<dyn Fn(&DataFrame) -> Result<f64>>;
+
+#[derive(Default)]
+/// Options for [`simulation_summary`]
+pub struct SummaryOptions {
+    /// Weight of every instance (by name) in the weighted geometric mean, instances without a
+    /// weight count once
+    pub instance_weights: HashMap<String, f64>,
+    /// Additional metrics as pairs of column name and metric
+    pub metrics: Vec<(String, SummaryMetric)>,
+}
+
+/// Summary of every simulated portfolio across instances and seeds
+///
+/// The quality ratio of a portfolio run on an instance is its quality relative to the virtual
+/// best solver, i.e. `quality / best_quality` with the best valid quality of the instance in `df`,
+/// and its regret is `ratio - 1`. Returns one row per portfolio of the `simulation` (see
+/// [`simulation_df`]) with the weighted geometric mean of the quality ratios (`gmean_ratio`) and
+/// the mean, median, 90th percentile and maximum regret over all instances and seeds, as the mean
+/// alone hides occasional disasters caused by unlucky sampling.
+///
+/// The metrics of the `options` are evaluated on the per-instance frame of every portfolio, i.e.
+/// its simulated runs with the additional columns `best_quality`, `ratio`, `log_ratio`, `regret`
+/// and `weight`.
+pub fn simulation_summary(
+    df: &DataFrame,
+    simulation: LazyFrame,
+    instance_fields: &[&str],
+    options: &SummaryOptions,
+) -> Result<DataFrame> {
+    let instances = instance_fields.iter().map(|&f| col(f)).collect_vec();
+    let best = df
+        .clone()
+        .lazy()
+        .filter(col("valid"))
+        .groupby(&instances)
+        .agg([min("quality").alias("best_quality")]);
+    let mut frame = simulation
+        .join(best, &instances, &instances, JoinType::Inner)
+        .with_column((col("quality") / col("best_quality")).alias("ratio"))
+        .with_column((col("ratio") - lit(1.0)).alias("regret"))
+        .collect()?;
+    let weights = frame
+        .column("instance")?
+        .utf8()?
+        .into_iter()
+        .map(|instance| {
+            instance
+                .and_then(|i| options.instance_weights.get(i))
+                .copied()
+                .unwrap_or(1.0)
+        })
+        .collect_vec();
+    let mut log_ratios =
+        frame.column("ratio")?.f64()?.apply(f64::ln).into_series();
+    log_ratios.rename("log_ratio");
+    frame.with_column(Series::new("weight", weights))?;
+    frame.with_column(log_ratios)?;
+    let regret = col("regret");
+    let mut summary = frame
+        .clone()
+        .lazy()
+        .groupby_stable([col("algorithm")])
+        .agg([
+            ((col("weight") * col("log_ratio")).sum() / col("weight").sum())
+                .alias("gmean_ratio"),
+            regret.clone().mean().alias("mean_regret"),
+            regret.clone().median().alias("median_regret"),
+            regret
+                .clone()
+                .quantile(lit(0.9), QuantileInterpolOptions::Linear)
+                .alias("p90_regret"),
+            regret.max().alias("max_regret"),
+        ])
+        .collect()?;
+    summary.apply("gmean_ratio", |s| {
+        s.f64().unwrap().apply(f64::exp).into_series()
+    })?;
+    let names = summary
+        .column("algorithm")?
+        .utf8()?
+        .into_no_null_iter()
+        .map(String::from)
+        .collect_vec();
+    for (name, metric) in &options.metrics {
+        let values = names
+            .iter()
+            .map(|portfolio| {
+                metric(
+                    &frame
+                        .clone()
+                        .lazy()
+                        .filter(col("algorithm").eq(lit(portfolio.as_str())))
+                        .collect()?,
+                )
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        summary.with_column(Series::new(name, values))?;
+    }
+    Ok(summary)
+}
+
+/// Hash of the content of a data frame, used to detect changed data in [`rerun_simulation`]
+///
+/// The hash is the XXH3 hash of the csv of the data frame, which is stable across Rust releases,
+/// so manifests stay valid after a toolchain update.
+pub fn data_hash(df: &DataFrame) -> Result<u64> {
+    let mut buffer = Vec::new();
+    CsvWriter::new(&mut buffer).finish(&mut df.clone())?;
+    Ok(xxhash_rust::xxh3::xxh3_64(&buffer))
+}
+
+fn simulate_portfolio_execution(
+    df: &DataFrame,
+    portfolio: &Portfolio,
+    seeds: impl Iterator<Item = u64>,
     instance_fields: &[&str],
     algorithm_fields: &[&str],
     num_cores: u32,
 ) -> Result<LazyFrame> {
-    let runs = (0..num_seeds)
+    let runs = seeds
         .map(|seed| -> Result<LazyFrame> {
-            let simulation_df = simulate(df, portfolio, seed as u64)?;
+            let simulation_df = simulate(df, portfolio, seed)?;
             Ok(portfolio_run_from_samples(
                 simulation_df,
                 instance_fields,
@@ -70,15 +470,11 @@ fn simulate_portfolio_execution(
     Ok(concat(runs, false, false)?)
 }
 
-fn simulate_algorithms_as_portfolio(
-    df: &DataFrame,
+fn algorithms_as_portfolios(
     algorithms: &ndarray::Array1<Algorithm>,
-    num_seeds: u32,
-    instance_fields: &[&str],
-    algorithm_fields: &[&str],
     num_cores: u32,
-) -> Result<LazyFrame> {
-    let algorithm_portfolios = algorithms
+) -> Vec<Portfolio> {
+    algorithms
         .iter()
         .filter(|a| a.num_threads <= num_cores)
         .map(|algo| {
@@ -97,33 +493,31 @@ fn simulate_algorithms_as_portfolio(
                 resource_assignments: vec![(algo.clone(), num_samples)],
             }
         })
-        .map(|portfolio| {
-            simulate_portfolio_execution(
-                df,
-                &portfolio,
-                num_seeds,
-                instance_fields,
-                algorithm_fields,
-                num_cores,
-            )
-        })
-        .filter_map(Result::ok)
-        .collect_vec();
-    Ok(concat(algorithm_portfolios, false, false)?)
+        .collect_vec()
 }
 
-fn simulate(
+/// Sample the runs of every algorithm of the `portfolio` on every instance
+///
+/// With a `max_cores` column, only the samples of an algorithm that fit into the cores available
+/// on the instance are kept. The [startup time](Algorithm::startup) of an algorithm is added to
+/// the time of its samples.
+pub(crate) fn simulate(
     df: &DataFrame,
     portfolio: &Portfolio,
     seed: u64,
 ) -> Result<LazyFrame> {
-    let explode_list =
+    let limit_cores = df.get_column_names().contains(&"max_cores");
+    let mut explode_list =
         vec!["algorithm", "num_threads", "quality", "time", "valid"];
+    if limit_cores {
+        explode_list.push("max_cores");
+    }
     let samples = &portfolio
         .resource_assignments
         .iter()
         .map(|(algo, cores)| {
-            df.clone()
+            let samples = df
+                .clone()
                 .lazy()
                 .filter(col("algorithm").eq(lit(algo.algorithm.clone())))
                 .filter(col("num_threads").eq(lit(algo.num_threads)))
@@ -135,13 +529,34 @@ fn simulate(
                     Some(seed),
                 )])
                 .explode(explode_list.clone())
-                .with_column(lit(seed).alias("seed"))
+                .with_column(lit(seed).alias("seed"));
+            let samples = match algo.startup {
+                Some(startup) => samples
+                    .with_column((col("time") + lit(startup)).alias("time")),
+                None => samples,
+            };
+            match limit_cores {
+                true => {
+                    let used_cores = (col("instance")
+                        .cumcount(false)
+                        .over([col("instance")])
+                        .cast(DataType::Int64)
+                        + lit(1i64))
+                        * lit(algo.num_threads as i64);
+                    samples.filter(
+                        col("max_cores")
+                            .is_null()
+                            .or(used_cores.lt_eq(col("max_cores"))),
+                    )
+                }
+                false => samples,
+            }
         })
         .collect::<Vec<LazyFrame>>();
     Ok(concat(samples, false, false)?)
 }
 
-fn portfolio_run_from_samples(
+pub(crate) fn portfolio_run_from_samples(
     df: LazyFrame,
     instance_fields: &[&str],
     algorithm_fields: &[&str],
@@ -1,8 +1,13 @@
 use polars::prelude::*;
 
 use crate::{
+    csv_parser::{Data, DataOptions},
     datastructures::*,
-    portfolio_simulator::{portfolio_run_from_samples, simulate},
+    portfolio_simulator::{
+        consistency_check, data_hash, portfolio_run_from_samples,
+        rerun_simulation, simulate, simulation_df_with_manifest,
+        simulation_summary, SummaryOptions,
+    },
 };
 
 #[test]
@@ -22,6 +27,9 @@ fn test_simple_model_simulation() {
                 Algorithm {
                     algorithm: "algo1".into(),
                     num_threads: 1,
+                    memory: None,
+                    cost: None,
+                    startup: None,
                 },
                 0.0,
             ),
@@ -29,6 +37,9 @@ fn test_simple_model_simulation() {
                 Algorithm {
                     algorithm: "algo2".into(),
                     num_threads: 1,
+                    memory: None,
+                    cost: None,
+                    startup: None,
                 },
                 2.0,
             ),
@@ -46,6 +57,68 @@ fn test_simple_model_simulation() {
         .any(|s| s == "algo1"));
 }
 
+#[test]
+fn test_simulation_max_cores() {
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => [1i64, 1, 2, 2],
+        "instance" => ["graph1", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 1.0, 1.0, 1.0],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => vec![true; 4],
+        "max_cores" => [Some(1i64), None, Some(1), None],
+    }
+    .unwrap();
+    let portfolio = Portfolio {
+        name: "final_portfolio_opt".to_string(),
+        resource_assignments: vec![
+            (Algorithm::new("algo1".into(), 1), 2.0),
+            (Algorithm::new("algo2".into(), 2), 1.0),
+        ],
+    };
+    let simulation_df = simulate(&df, &portfolio, 42)
+        .unwrap()
+        .collect()
+        .unwrap()
+        .sort(["instance"], false)
+        .unwrap();
+    // a single run of algo1 fits on graph1, all three runs on graph2
+    assert_eq!(
+        simulation_df["instance"],
+        Series::new("instance", &["graph1", "graph2", "graph2", "graph2"])
+    );
+}
+
+#[test]
+fn test_simulation_startup() {
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => vec![1i64; 4],
+        "instance" => ["graph1", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 1.0, 1.0, 1.0],
+        "time" => [1.0, 2.0, 1.0, 2.0],
+        "valid" => vec![true; 4],
+    }
+    .unwrap();
+    let portfolio = Portfolio {
+        name: "final_portfolio".to_string(),
+        resource_assignments: vec![
+            (Algorithm::new("algo1".into(), 1).with_startup(0.5), 1.0),
+            (Algorithm::new("algo2".into(), 1), 1.0),
+        ],
+    };
+    let simulation_df = simulate(&df, &portfolio, 42)
+        .unwrap()
+        .collect()
+        .unwrap()
+        .sort(["algorithm", "instance"], false)
+        .unwrap();
+    assert_eq!(
+        simulation_df["time"],
+        Series::new("time", &[1.5, 2.5, 1.0, 2.0])
+    );
+}
+
 #[test]
 fn test_simple_model_simulation_from_samples() {
     let df = df! {
@@ -81,3 +154,313 @@ fn test_simple_model_simulation_from_samples() {
         ndarray::Array1::from_vec(vec![1.0, 2.0])
     );
 }
+
+#[test]
+fn test_rerun_simulation() {
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => vec![1; 6],
+        "instance" => ["graph1", "graph1", "graph2", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+        "time" => [1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+        "valid" => vec![true; 6],
+    }
+    .unwrap();
+    let algorithm = Algorithm::new("algo1".into(), 1);
+    let portfolio = Portfolio {
+        name: "portfolio".to_string(),
+        resource_assignments: vec![(algorithm.clone(), 1.0)],
+    };
+    let (simulation, manifest) = simulation_df_with_manifest(
+        &df,
+        &ndarray::arr1(&[algorithm]),
+        &[portfolio],
+        &[],
+        3,
+        false,
+        &["instance"],
+        &["algorithm", "num_threads"],
+        1,
+    )
+    .unwrap();
+    assert_eq!(manifest.len(), 6);
+    let simulation = simulation.collect().unwrap();
+    for run in manifest
+        .iter()
+        .filter(|run| run.portfolio.name == "portfolio")
+    {
+        let rerun = rerun_simulation(
+            &df,
+            run,
+            "graph2",
+            &["instance"],
+            &["algorithm", "num_threads"],
+            1,
+        )
+        .unwrap();
+        let expected = simulation
+            .clone()
+            .lazy()
+            .filter(
+                col("algorithm")
+                    .eq(lit("portfolio"))
+                    .and(col("seed").eq(lit(run.seed)))
+                    .and(col("instance").eq(lit("graph2"))),
+            )
+            .collect()
+            .unwrap();
+        assert_eq!(rerun.height(), 1);
+        assert_eq!(
+            rerun.column("quality").unwrap(),
+            expected.column("quality").unwrap()
+        );
+    }
+    let mut changed = df.clone();
+    changed.apply("quality", |s| s * 2.0).unwrap();
+    assert!(rerun_simulation(
+        &changed,
+        &manifest[0],
+        "graph2",
+        &["instance"],
+        &["algorithm", "num_threads"],
+        1,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_instance_filters() {
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => vec![1; 4],
+        "instance" => ["graph1", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 2.0, 3.0, 4.0],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => vec![true; 4],
+    }
+    .unwrap();
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let portfolio = |algorithm: &Algorithm| Portfolio {
+        name: "family".to_string(),
+        resource_assignments: vec![(algorithm.clone(), 1.0)],
+    };
+    let portfolios = [portfolio(&algorithms[0]), portfolio(&algorithms[1])];
+    let filters = |first: &str, second: &str| {
+        vec![
+            Some(vec![first.to_string()]),
+            Some(vec![second.to_string()]),
+        ]
+    };
+    let (simulation, manifest) = simulation_df_with_manifest(
+        &df,
+        &ndarray::arr1(&algorithms),
+        &portfolios,
+        &filters("graph1", "graph2"),
+        2,
+        true,
+        &["instance"],
+        &["algorithm", "num_threads"],
+        1,
+    )
+    .unwrap();
+    // the specialized portfolios are reported as one portfolio on all instances
+    let family = simulation
+        .filter(col("algorithm").eq(lit("family")))
+        .sort("instance", Default::default())
+        .collect()
+        .unwrap();
+    assert_eq!(family.height(), 4);
+    assert_eq!(
+        family
+            .column("quality")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect::<Vec<_>>(),
+        vec![1.0, 1.0, 4.0, 4.0]
+    );
+    assert!(rerun_simulation(
+        &df,
+        &manifest[0],
+        "graph2",
+        &["instance"],
+        &["algorithm", "num_threads"],
+        1,
+    )
+    .is_err());
+    assert!(simulation_df_with_manifest(
+        &df,
+        &ndarray::arr1(&algorithms),
+        &portfolios,
+        &filters("graph1", "graph1"),
+        2,
+        true,
+        &["instance"],
+        &["algorithm", "num_threads"],
+        1,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_paired_seeds() {
+    let df = df! {
+        "algorithm" => vec!["algo1"; 8],
+        "num_threads" => vec![1; 8],
+        "instance" => ["graph1", "graph1", "graph1", "graph1", "graph2", "graph2", "graph2", "graph2"],
+        "quality" => [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0],
+        "time" => vec![1.0; 8],
+        "valid" => vec![true; 8],
+    }
+    .unwrap();
+    let algorithm = Algorithm::new("algo1".into(), 1);
+    let portfolio = |name: &str| Portfolio {
+        name: name.to_string(),
+        resource_assignments: vec![(algorithm.clone(), 1.0)],
+    };
+    let qualities = |paired_seeds: bool| {
+        let (simulation, manifest) = simulation_df_with_manifest(
+            &df,
+            &ndarray::arr1(std::slice::from_ref(&algorithm)),
+            &[portfolio("first"), portfolio("second")],
+            &[],
+            8,
+            paired_seeds,
+            &["instance"],
+            &["algorithm", "num_threads"],
+            1,
+        )
+        .unwrap();
+        let simulation = simulation
+            .sort_by_exprs(
+                [col("instance"), col("seed")],
+                [false, false],
+                false,
+            )
+            .collect()
+            .unwrap();
+        let quality = |name: &str| {
+            simulation
+                .clone()
+                .lazy()
+                .filter(col("algorithm").eq(lit(name)))
+                .collect()
+                .unwrap()["quality"]
+                .clone()
+        };
+        (quality("first"), quality("second"), manifest)
+    };
+    // identical portfolios sample the same runs with paired seeds
+    let (first, second, manifest) = qualities(true);
+    assert_eq!(first, second);
+    assert_eq!(manifest[0].seed, manifest[8].seed);
+    let (first, second, manifest) = qualities(false);
+    assert_ne!(first, second);
+    assert_ne!(manifest[0].seed, manifest[8].seed);
+}
+
+#[test]
+fn test_simulation_summary() {
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => vec![1; 4],
+        "instance" => ["graph1", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 2.0, 2.0, 1.0],
+        "time" => [1.0, 1.0, 1.0, 1.0],
+        "valid" => vec![true; 4],
+    }
+    .unwrap();
+    // one portfolio, 2 instances and 5 seeds, one unlucky run
+    let simulation = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph1", "graph1",
+                       "graph2", "graph2", "graph2", "graph2", "graph2"],
+        "algorithm" => vec!["portfolio"; 10],
+        "seed" => [0, 1, 2, 3, 4, 0, 1, 2, 3, 4],
+        "quality" => [1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 4.0],
+    }
+    .unwrap();
+    let options = SummaryOptions {
+        instance_weights: [(String::from("graph2"), 3.0)].into(),
+        metrics: vec![(
+            String::from("disasters"),
+            Box::new(|frame: &DataFrame| {
+                Ok(frame.column("regret")?.gt(1.0)?.sum().unwrap_or(0) as f64)
+            }),
+        )],
+    };
+    let summary =
+        simulation_summary(&df, simulation.lazy(), &["instance"], &options)
+            .unwrap();
+    assert_eq!(summary.height(), 1);
+    let value = |column: &str| {
+        summary
+            .column(column)
+            .unwrap()
+            .f64()
+            .unwrap()
+            .get(0)
+            .unwrap()
+    };
+    assert!((value("mean_regret") - 0.3).abs() < 1e-9);
+    assert_eq!(value("median_regret"), 0.0);
+    assert!((value("p90_regret") - 0.3).abs() < 1e-9);
+    assert_eq!(value("max_regret"), 3.0);
+    // the unlucky run counts 3 times out of 20 weighted runs
+    assert!((value("gmean_ratio") - 4f64.powf(3.0 / 20.0)).abs() < 1e-9);
+    assert_eq!(value("disasters"), 1.0);
+}
+
+#[test]
+fn test_consistency_check() {
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph2", "graph2", "graph2", "graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo1", "algo1", "algo1", "algo1", "algo1", "algo2", "algo2", "algo2", "algo2"],
+        "num_threads" => vec![1i64; 10],
+        "quality" => [1.0, 2.0, 3.0, 10.0, 20.0, 40.0, 2.0, 4.0, 8.0, 30.0],
+        "time" => vec![1.0; 10],
+        "valid" => vec![true; 10],
+    }
+    .unwrap();
+    let options = DataOptions {
+        estimator: QualityEstimator::Exact,
+        ..Default::default()
+    };
+    let mut data = Data::from_normalized_dataframe_with_options(
+        df.clone().lazy(),
+        2,
+        f64::MAX,
+        &options,
+    )
+    .unwrap();
+    let portfolio = Portfolio {
+        name: String::from("final_portfolio"),
+        resource_assignments: vec![
+            (Algorithm::new("algo1".into(), 2), 0.0),
+            (Algorithm::new("algo1".into(), 1), 2.0),
+            (Algorithm::new("algo2".into(), 1), 1.0),
+        ],
+    };
+    let deviations = consistency_check(&df, &data, &portfolio, 500).unwrap();
+    assert_eq!(deviations.len(), 4);
+    assert!(deviations[0].relative() < 0.1, "{:?}", deviations[0]);
+
+    // swapped instances are flagged
+    data.expected_best_quality.invert_axis(ndarray::Axis(0));
+    let deviations = consistency_check(&df, &data, &portfolio, 500).unwrap();
+    assert!(deviations[0].relative() > 1.0);
+}
+
+#[test]
+fn test_data_hash() {
+    let df = df! {
+        "algorithm" => ["algo1", "algo2"],
+        "quality" => [1.0, 2.0],
+    }
+    .unwrap();
+    // the hash is persisted in manifests, so it must not change between toolchains
+    assert_eq!(data_hash(&df).unwrap(), 14016768216129267646);
+}
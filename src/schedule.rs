@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use anyhow::Result;
+use itertools::Itertools;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::datastructures::{Algorithm, Portfolio};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// A run of an algorithm in a time slice of a [`Schedule`]
+pub struct Slice {
+    /// The algorithm that runs in the slice
+    pub algorithm: Algorithm,
+    /// First core of the run, the run occupies `num_threads` consecutive cores
+    pub core: u32,
+    /// Start of the slice, in the unit of the `time` column
+    pub start: f64,
+    /// Length of the slice, the run is killed if it doesn't finish within it
+    pub duration: f64,
+}
+
+impl Slice {
+    /// End of the slice
+    pub fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+
+    fn cores(&self) -> std::ops::Range<u32> {
+        self.core..self.core + self.algorithm.num_threads
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// Sequential schedule of algorithm runs in time slices per core, every run that finishes within
+/// its slice contributes its quality and the best of them is the result on an instance
+pub struct Schedule {
+    /// Name of the schedule
+    pub name: String,
+    /// Number of cores of the schedule
+    pub num_cores: u32,
+    /// Wall-clock budget per instance all slices end within
+    pub time_budget: f64,
+    /// The slices sorted by core and start
+    pub slices: Vec<Slice>,
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for slice in &self.slices {
+            writeln!(
+                f,
+                "cores {}..{}: {} from {} to {}",
+                slice.core,
+                slice.core + slice.algorithm.num_threads,
+                slice.algorithm,
+                slice.start,
+                slice.end()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Schedule {
+    /// The static split of `portfolio` as a schedule, every copy of an algorithm runs for the whole
+    /// `time_budget` on its own cores
+    pub fn from_portfolio(
+        portfolio: &Portfolio,
+        num_cores: u32,
+        time_budget: f64,
+    ) -> Self {
+        let mut core = 0;
+        let mut slices = Vec::new();
+        for (algorithm, copies) in &portfolio.resource_assignments {
+            for _ in 0..copies.round() as usize {
+                slices.push(Slice {
+                    algorithm: algorithm.clone(),
+                    core,
+                    start: 0.0,
+                    duration: time_budget,
+                });
+                core += algorithm.num_threads;
+            }
+        }
+        Self {
+            name: portfolio.name.clone(),
+            num_cores,
+            time_budget,
+            slices,
+        }
+    }
+
+    /// Time from which all of the `num_threads` cores starting at `core` are idle
+    fn free_at(&self, core: u32, num_threads: u32) -> f64 {
+        self.slices
+            .iter()
+            .filter(|slice| {
+                slice.core < core + num_threads
+                    && core < slice.core + slice.algorithm.num_threads
+            })
+            .map(Slice::end)
+            .fold(0.0, f64::max)
+    }
+
+    /// Earliest slice of `duration` for `algorithm` after the existing slices of its cores, if it
+    /// ends within the time budget
+    fn append(&self, algorithm: &Algorithm, duration: f64) -> Option<Slice> {
+        let threads = algorithm.num_threads;
+        (0..=self.num_cores.checked_sub(threads)?)
+            .map(|core| Slice {
+                algorithm: algorithm.clone(),
+                core,
+                start: self.free_at(core, threads),
+                duration,
+            })
+            .min_by(|a, b| a.start.total_cmp(&b.start))
+            .filter(|slice| slice.end() <= self.time_budget * (1.0 + 1e-9))
+    }
+
+    /// Extend every slice until the next slice on one of its cores starts or the time budget ends,
+    /// a longer slice never makes a run less likely to finish
+    fn fill_idle_time(&mut self) {
+        for s in 0..self.slices.len() {
+            let end = self
+                .slices
+                .iter()
+                .filter(|other| {
+                    other.start >= self.slices[s].end()
+                        && self.slices[s]
+                            .cores()
+                            .any(|core| other.cores().contains(&core))
+                })
+                .map(|other| other.start)
+                .fold(self.time_budget, f64::min);
+            self.slices[s].duration = end - self.slices[s].start;
+        }
+    }
+}
+
+/// Runs of the algorithms on the instances that [`optimize`] schedules
+pub struct ScheduleData {
+    /// The algorithms with at most as many threads as cores
+    pub algorithms: Vec<Algorithm>,
+    /// The instances
+    pub instances: Vec<String>,
+    /// `runs[i][j]` are the running times and qualities of the runs of algorithm `j` on instance
+    /// `i`, invalid runs never finish
+    runs: Vec<Vec<Vec<(f64, f64)>>>,
+    /// Best quality of a valid run per instance
+    best: Vec<f64>,
+    /// Worst quality of a valid run per instance, the result if no run finishes
+    worst: Vec<f64>,
+}
+
+impl ScheduleData {
+    /// Collect the runs of a normalized data frame (see
+    /// [`parse_normalized_csvs`](crate::csv_parser::parse_normalized_csvs)) with minimized
+    /// qualities, instances without valid runs are skipped
+    pub fn from_normalized_dataframe(
+        df: LazyFrame,
+        num_cores: u32,
+    ) -> Result<Self> {
+        let df = df
+            .select([
+                col("instance"),
+                col("algorithm"),
+                col("num_threads").cast(DataType::UInt32),
+                col("quality").cast(DataType::Float64),
+                col("time").cast(DataType::Float64),
+                col("valid"),
+            ])
+            .filter(col("num_threads").lt_eq(lit(num_cores)))
+            .collect()?;
+        let mut cells = BTreeMap::<_, Vec<(f64, f64)>>::new();
+        for (((((instance, algorithm), threads), quality), time), valid) in df
+            ["instance"]
+            .utf8()?
+            .into_no_null_iter()
+            .zip(df["algorithm"].utf8()?.into_no_null_iter())
+            .zip(df["num_threads"].u32()?.into_no_null_iter())
+            .zip(df["quality"].f64()?)
+            .zip(df["time"].f64()?)
+            .zip(df["valid"].bool()?)
+        {
+            let run = match (valid, quality, time) {
+                (Some(true), Some(quality), Some(time)) => (time, quality),
+                _ => (f64::INFINITY, f64::INFINITY),
+            };
+            cells
+                .entry((
+                    instance.to_string(),
+                    Algorithm::new(algorithm.into(), threads),
+                ))
+                .or_default()
+                .push(run);
+        }
+        let algorithms = cells
+            .keys()
+            .map(|(_, algo)| algo.clone())
+            .sorted()
+            .dedup()
+            .collect_vec();
+        let (mut instances, mut runs, mut best, mut worst) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for (instance, group) in
+            &cells.into_iter().group_by(|((i, _), _)| i.clone())
+        {
+            let group = group.collect_vec();
+            let qualities = group
+                .iter()
+                .flat_map(|(_, runs)| runs.iter().map(|run| run.1))
+                .filter(|q| q.is_finite())
+                .collect_vec();
+            if qualities.is_empty() {
+                continue;
+            }
+            instances.push(instance);
+            best.push(qualities.iter().copied().fold(f64::INFINITY, f64::min));
+            worst.push(qualities.iter().copied().fold(f64::MIN, f64::max));
+            runs.push(
+                algorithms
+                    .iter()
+                    .map(|algo| {
+                        group
+                            .iter()
+                            .find(|((_, a), _)| a == algo)
+                            .map_or_else(Vec::new, |(_, runs)| runs.clone())
+                    })
+                    .collect_vec(),
+            );
+        }
+        anyhow::ensure!(!instances.is_empty(), "No instance has a valid run");
+        anyhow::ensure!(
+            best.iter().all(|b| *b > 0.0),
+            "Schedules need positive qualities"
+        );
+        Ok(Self {
+            algorithms,
+            instances,
+            runs,
+            best,
+            worst,
+        })
+    }
+
+    /// Sum of the expected best quality ratios per instance of the `schedule`
+    ///
+    /// The runs of all slices are independent, a slice of algorithm `j` finishes with the quality
+    /// of a run of `j` drawn uniformly from its runs on the instance if its running time fits the
+    /// slice. An instance gets its worst valid quality if no run of the schedule finishes.
+    pub fn objective(&self, schedule: &Schedule) -> f64 {
+        let slices = schedule
+            .slices
+            .iter()
+            .filter_map(|slice| {
+                let j = self
+                    .algorithms
+                    .iter()
+                    .position(|a| *a == slice.algorithm)?;
+                Some((j, slice.duration))
+            })
+            .collect_vec();
+        (0..self.instances.len())
+            .map(|i| self.expected_best(i, &slices) / self.best[i])
+            .sum()
+    }
+
+    /// Expected best quality of the `slices` (algorithm and duration) on instance `i`
+    fn expected_best(&self, i: usize, slices: &[(usize, f64)]) -> f64 {
+        let worst = self.worst[i];
+        // outcomes of every slice with runs on the instance, equally likely
+        let outcomes = slices
+            .iter()
+            .filter(|(j, _)| !self.runs[i][*j].is_empty())
+            .map(|(j, duration)| {
+                self.runs[i][*j]
+                    .iter()
+                    .map(|(time, quality)| match time <= duration {
+                        true => *quality,
+                        false => worst,
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let values = outcomes
+            .iter()
+            .flatten()
+            .copied()
+            .chain([worst])
+            .sorted_by(f64::total_cmp)
+            .dedup()
+            .collect_vec();
+        // P(best >= v) for every value
+        let probabilities = values
+            .iter()
+            .map(|v| {
+                outcomes
+                    .iter()
+                    .map(|outcomes| {
+                        outcomes.iter().filter(|q| *q >= v).count() as f64
+                            / outcomes.len() as f64
+                    })
+                    .product::<f64>()
+            })
+            .collect_vec();
+        values
+            .iter()
+            .enumerate()
+            .map(|(k, v)| {
+                let next = probabilities.get(k + 1).copied().unwrap_or(0.0);
+                v * (probabilities[k] - next)
+            })
+            .sum()
+    }
+
+    /// Candidate slice lengths of algorithm `j`: up to `max_candidates` quantiles of its finite
+    /// running times within the `time_budget` and the time budget itself
+    fn candidate_durations(
+        &self,
+        j: usize,
+        time_budget: f64,
+        max_candidates: usize,
+    ) -> Vec<f64> {
+        let times = self
+            .runs
+            .iter()
+            .flat_map(|runs| runs[j].iter().map(|run| run.0))
+            .filter(|time| *time <= time_budget)
+            .sorted_by(f64::total_cmp)
+            .dedup()
+            .collect_vec();
+        let step = (times.len() as f64 / max_candidates as f64).max(1.0);
+        (1..=max_candidates)
+            .filter_map(|k| times.get((k as f64 * step).ceil() as usize - 1))
+            .copied()
+            .chain([time_budget])
+            .dedup()
+            .collect_vec()
+    }
+}
+
+/// Greedily build a [`Schedule`] of `num_cores` cores within the `time_budget` per instance,
+/// like the sequential schedules of 3S or SUNNY.
+///
+/// Slice lengths are taken from the running times of every algorithm (up to 16 quantiles and the
+/// whole time budget). In every step, the slice with the largest decrease of the
+/// [objective](ScheduleData::objective) per core-second is appended to the cores that are idle
+/// the earliest, until no slice fits the remaining time or improves the objective. Finally,
+/// every slice is extended until the next slice on its cores starts. A cheap algorithm that solves
+/// most instances quickly thus runs first, before a heavyweight algorithm gets the remaining time,
+/// which no static split of the cores can express.
+pub fn optimize(
+    data: &ScheduleData,
+    num_cores: u32,
+    time_budget: f64,
+) -> Result<Schedule> {
+    anyhow::ensure!(
+        time_budget > 0.0,
+        "The time budget must be positive, got {time_budget}"
+    );
+    let candidates = (0..data.algorithms.len())
+        .flat_map(|j| {
+            data.candidate_durations(j, time_budget, 16)
+                .into_iter()
+                .map(move |duration| (j, duration))
+        })
+        .collect_vec();
+    let mut schedule = Schedule {
+        name: format!("k{num_cores}_schedule"),
+        num_cores,
+        time_budget,
+        slices: Vec::new(),
+    };
+    let mut objective = data.objective(&schedule);
+    loop {
+        let best = candidates
+            .iter()
+            .filter_map(|(j, duration)| {
+                let algorithm = &data.algorithms[*j];
+                let slice = schedule.append(algorithm, *duration)?;
+                let mut extended = schedule.clone();
+                extended.slices.push(slice);
+                let value = data.objective(&extended);
+                let gain = (objective - value)
+                    / (duration * algorithm.num_threads as f64);
+                Some((gain, value, extended))
+            })
+            .filter(|(gain, _, _)| *gain > 1e-12)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        match best {
+            Some((_, value, extended)) => {
+                objective = value;
+                schedule = extended;
+            }
+            None => break,
+        }
+    }
+    schedule.fill_idle_time();
+    schedule.slices.sort_by(|a, b| {
+        (a.core, a.start).partial_cmp(&(b.core, b.start)).unwrap()
+    });
+    Ok(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimize() {
+        // fast is best on graph1, slow is best on graph2 but takes much longer
+        let df = df! {
+            "instance" => ["graph1", "graph2", "graph1", "graph2"],
+            "algorithm" => ["fast", "fast", "slow", "slow"],
+            "num_threads" => [1i64, 1, 1, 1],
+            "quality" => [1.0, 3.0, 2.0, 1.0],
+            "time" => [1.0, 1.0, 8.0, 8.0],
+            "valid" => [true, true, true, true],
+        }
+        .unwrap();
+        let data =
+            ScheduleData::from_normalized_dataframe(df.lazy(), 1).unwrap();
+        assert_eq!(data.instances, vec!["graph1", "graph2"]);
+        let fast = Algorithm::new("fast".into(), 1);
+        let slow = Algorithm::new("slow".into(), 1);
+        let static_split = Schedule::from_portfolio(
+            &Portfolio {
+                name: "static".into(),
+                resource_assignments: vec![(fast.clone(), 1.0)],
+            },
+            1,
+            10.0,
+        );
+        assert_eq!(data.objective(&static_split), 4.0);
+
+        let schedule = optimize(&data, 1, 10.0).unwrap();
+        assert_eq!(
+            schedule.slices,
+            vec![
+                Slice {
+                    algorithm: fast,
+                    core: 0,
+                    start: 0.0,
+                    duration: 1.0,
+                },
+                Slice {
+                    algorithm: slow,
+                    core: 0,
+                    start: 1.0,
+                    duration: 9.0,
+                },
+            ]
+        );
+        assert_eq!(data.objective(&schedule), 2.0);
+
+        // slow doesn't fit after fast anymore
+        let schedule = optimize(&data, 1, 8.5).unwrap();
+        assert_eq!(schedule.slices.len(), 1);
+        assert_eq!(schedule.slices[0].duration, 8.5);
+    }
+}
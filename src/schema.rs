@@ -0,0 +1,167 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON artifacts written by this crate, increased on every incompatible change of
+/// any of their formats
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Kind of a JSON artifact, see [`Schema`]
+pub enum Kind {
+    /// A single [portfolio](crate::datastructures::Portfolio), e.g. `final_portfolio.json`
+    Portfolio,
+    /// Configuration of the `portfolio_executor`, i.e. `executor.json`
+    Executor,
+    /// Summary of a run of the solver, i.e. `report.json`
+    Report,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Portfolio => "portfolio",
+            Self::Executor => "executor",
+            Self::Report => "report",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+/// Value of the `schema` field of every JSON artifact, `portfolio_solver/<kind>/v<version>`
+/// (e.g. `portfolio_solver/portfolio/v1`)
+///
+/// Artifacts reject unknown fields, so tooling can rely on a format not changing without a new
+/// version. Artifacts without a `schema` field are read as the current version.
+pub struct Schema {
+    /// Kind of the artifact
+    pub kind: Kind,
+    /// Version of the format of the artifact
+    pub version: u32,
+}
+
+impl Schema {
+    /// The current version of the `kind` of artifact
+    pub fn current(kind: Kind) -> Self {
+        Self {
+            kind,
+            version: VERSION,
+        }
+    }
+
+    /// Check that an artifact read as `kind` has this schema and that its version is supported
+    pub fn check(&self, kind: Kind) -> Result<()> {
+        anyhow::ensure!(
+            self.kind == kind,
+            "Expected a {} artifact, got {self}",
+            kind.name()
+        );
+        anyhow::ensure!(
+            self.version <= VERSION,
+            "{self} is newer than the supported version {VERSION}"
+        );
+        Ok(())
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "portfolio_solver/{}/v{}", self.kind.name(), self.version)
+    }
+}
+
+impl FromStr for Schema {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split('/').collect::<Vec<_>>();
+        let [crate_name, kind, version] = parts[..] else {
+            anyhow::bail!("Malformed schema {s}");
+        };
+        anyhow::ensure!(
+            crate_name == "portfolio_solver",
+            "Unknown schema {s}"
+        );
+        let kind = [Kind::Portfolio, Kind::Executor, Kind::Report]
+            .into_iter()
+            .find(|k| k.name() == kind)
+            .with_context(|| format!("Unknown artifact kind in schema {s}"))?;
+        let version =
+            version
+                .strip_prefix('v')
+                .and_then(|v| v.parse().ok())
+                .with_context(|| format!("Malformed version in schema {s}"))?;
+        Ok(Self { kind, version })
+    }
+}
+
+impl From<Schema> for String {
+    fn from(schema: Schema) -> Self {
+        schema.to_string()
+    }
+}
+
+impl TryFrom<String> for Schema {
+    type Error = anyhow::Error;
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Check the optional `schema` of an artifact read as `kind`, see [`Schema::check`]
+pub fn check(schema: Option<Schema>, kind: Kind) -> Result<()> {
+    schema.map_or(Ok(()), |schema| schema.check(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kind, Schema};
+    use crate::datastructures::{Algorithm, Portfolio};
+
+    #[test]
+    fn test_schema() {
+        let schema = Schema::current(Kind::Portfolio);
+        assert_eq!(schema.to_string(), "portfolio_solver/portfolio/v1");
+        assert_eq!(schema.to_string().parse::<Schema>().unwrap(), schema);
+        assert!("portfolio_solver/portfolio".parse::<Schema>().is_err());
+        assert!("other/portfolio/v1".parse::<Schema>().is_err());
+        assert!("portfolio_solver/plan/v1".parse::<Schema>().is_err());
+        assert!(schema.check(Kind::Portfolio).is_ok());
+        assert!(schema.check(Kind::Report).is_err());
+        let newer = Schema {
+            version: super::VERSION + 1,
+            ..schema
+        };
+        assert!(newer.check(Kind::Portfolio).is_err());
+    }
+
+    #[test]
+    fn test_portfolio_schema() {
+        let portfolio = Portfolio {
+            name: String::from("portfolio"),
+            resource_assignments: vec![(
+                Algorithm::new("algo".into(), 2),
+                1.0,
+            )],
+        };
+        let json = serde_json::to_value(&portfolio).unwrap();
+        assert_eq!(json["schema"], "portfolio_solver/portfolio/v1");
+        assert_eq!(
+            serde_json::from_value::<Portfolio>(json.clone()).unwrap(),
+            portfolio
+        );
+        let mut legacy = json.clone();
+        legacy.as_object_mut().unwrap().remove("schema");
+        assert_eq!(
+            serde_json::from_value::<Portfolio>(legacy).unwrap(),
+            portfolio
+        );
+        let mut unknown = json.clone();
+        unknown["cores"] = 2.into();
+        assert!(serde_json::from_value::<Portfolio>(unknown).is_err());
+        let mut wrong_kind = json;
+        wrong_kind["schema"] = "portfolio_solver/report/v1".into();
+        assert!(serde_json::from_value::<Portfolio>(wrong_kind).is_err());
+    }
+}
@@ -2,129 +2,864 @@ use std::cmp::Ordering;
 
 use crate::datastructures::*;
 use itertools::Itertools;
-use log::{debug, info, log_enabled};
+use log::{debug, info, log_enabled, warn};
 
 use crate::csv_parser::Data;
 use anyhow::{Context, Result};
 use grb::prelude::*;
-use ndarray::{Array1, Array2, Array3};
+use ndarray::{Array1, Array2, Array3, Axis};
+use objectives::ObjectiveFunction;
+
+#[cfg(feature = "cbc")]
+pub use self::cbc::solve_cbc;
+#[cfg(feature = "highs")]
+pub use self::highs::solve_highs;
+pub use anytime::{solve_anytime, spawn_solve, AnytimeSolve, SolveHandle};
+pub use benders::BENDERS_CELLS;
+pub use calibration::{
+    calibrate_slowdown_ratio, slowdown_ratio_grid, CalibrationSolver,
+    SlowdownPoint,
+};
+pub use checkpoint::resume;
+pub use colgen::solve_colgen;
+pub use cross_validation::{
+    cross_validate, CrossValidation, CrossValidationOptions, Fold,
+};
+pub use dominance::{dominated_cells, DominatedCell};
+pub use grouped::solve_grouped;
+pub use incremental::Incremental;
+pub use local_search::prune;
+pub use multi_period::{recency_weights, solve_multi_period};
+pub use pareto::{expected_runtime, solve_pareto, ParetoPoint};
+pub use sensitivity::{marginal_cores, MarginalCores};
+
+mod anytime;
+mod benders;
+/// Gurobi-free solver using Bayesian optimization over the core allocations
+#[cfg(feature = "bo")]
+pub mod bo;
+mod calibration;
+#[cfg(feature = "cbc")]
+mod cbc;
+mod checkpoint;
+mod colgen;
+/// Solver using the OR-Tools CP-SAT solver with the quality ratios scaled to integers
+#[cfg(feature = "cpsat")]
+pub mod cpsat;
+mod cross_validation;
+mod dominance;
+/// Gurobi-free solver evolving a population of core allocations
+pub mod genetic;
+mod grouped;
+#[cfg(feature = "highs")]
+mod highs;
+mod incremental;
+mod lazy;
+#[cfg(any(feature = "highs", feature = "cbc"))]
+mod linear;
+mod local_search;
+mod multi_period;
+/// Objectives implemented outside of the model, selected by name with [`Objective::Custom`]
+pub mod objectives;
+mod pareto;
+/// Heuristic solver racing candidate allocations in the simulator, without any MIP
+pub mod racing;
+/// Approximate solver rounding the LP relaxation of the portfolio model
+pub mod relaxation;
+mod sensitivity;
+mod validation;
 
 /// Create a portfolio from the input data using the Gurobi Optimizer.
 ///
-/// If no initial solution is provided, the solver will fall back to using a heuristic based on the
-/// `best_per_instance_count`s of the data. If this is not available, the solver is run without any
-/// initial solutions **(this may lead to significantly longer runtimes)**.
+/// The `initial` portfolio is used as initial solution, its algorithms are matched to the
+/// algorithms of the data by name and number of threads. It is an error if the portfolio contains
+/// algorithms that are not part of the data, fractional copies or more than `num_cores` cores.
+/// If no initial portfolio is provided, the solver will fall back to using a heuristic based on
+/// the `best_per_instance_count`s of the data. If this is not available, the [`greedy_solve`]
+/// portfolio is used as initial solution.
+///
+/// A new Gurobi environment is created for every call (with the [`license`](SolverOptions::license)
+/// of the `options`, if any), use [`Solver`] to solve multiple models with the same environment,
+/// e.g. an environment configured by the caller. With the `highs` feature, the model is solved with
+/// [`solve_highs`] instead if no Gurobi environment can be created (e.g. without a license).
+/// Failures of the Gurobi backend, e.g. a license that does not cover the model or the timeout
+/// without a portfolio, are returned as [`SolverError`]. If Gurobi runs into numerical trouble, the
+/// monolithic model is solved again with the qualities of every instance divided by its best
+/// quality and an increasing `NumericFocus` (see [`SolveStats::numeric_focus`]).
+///
+/// Other backends can be selected with [`SolverOptions::backend`] if the corresponding feature is
+/// enabled.
+///
+/// If the solver runs into the timeout with a nonzero gap, the final portfolio is improved by a
+/// local search over the resource assignments (see [`OptimizationResult::refined`]). With a
+/// [`prune_tolerance`](SolverOptions::prune_tolerance), algorithms that hardly improve the
+/// objective are removed from the final portfolio afterwards (see [`prune`]).
+///
+/// The Gurobi backend stops early with the best portfolio found so far if the
+/// [`cancellation`](SolverOptions::cancellation) token is cancelled, other backends can't be
+/// cancelled. Depending on the [`decomposition`](SolverOptions::decomposition), it solves large
+/// models with a Benders decomposition instead of the monolithic model.
 pub fn solve(
     data: &Data,
     num_cores: usize,
-    timeout: Timeout,
-    initial_resource_assignment: Option<Vec<f64>>,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
 ) -> Result<OptimizationResult> {
-    let env = {
-        let log_level = match log_enabled!(log::Level::Info) {
-            true => 1,
-            false => 0,
-        };
-        let mut env = grb::Env::empty()?;
-        env.set(param::OutputFlag, log_level)?;
-        env.start()?
+    solve_with_progress(data, num_cores, options, initial, |_| ())
+}
+
+/// Create a portfolio like [`solve`] and report the progress of the solver to `progress`.
+///
+/// The Gurobi backend reports every incumbent as soon as it is found, e.g. to update a dashboard
+/// or to persist intermediate portfolios. Backends without callbacks report the
+/// [`history`](OptimizationResult::history) once they are finished. A portfolio improved by the
+/// local search is reported as [`ProgressEvent::Refined`] at the end.
+pub fn solve_with_progress<F>(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+    mut progress: F,
+) -> Result<OptimizationResult>
+where
+    F: FnMut(ProgressEvent),
+{
+    let result = match options.backend {
+        Backend::Gurobi => {
+            #[cfg(feature = "highs")]
+            let env = match Solver::for_options(options) {
+                Ok(solver) => solver.env,
+                Err(err) => {
+                    warn!("Gurobi is not available, falling back to HiGHS: {err}");
+                    let result =
+                        solve_highs(data, num_cores, options, initial)?;
+                    report_history(&result, &mut progress);
+                    return Ok(refine(
+                        data,
+                        num_cores,
+                        options,
+                        result,
+                        &mut progress,
+                    ));
+                }
+            };
+            #[cfg(not(feature = "highs"))]
+            let env = Solver::for_options(options)?.env;
+            solve_with_env(
+                &env,
+                data,
+                num_cores,
+                options,
+                initial,
+                &mut progress,
+                None,
+            )?
+        }
+        #[cfg(feature = "highs")]
+        Backend::Highs => solve_highs(data, num_cores, options, initial)?,
+        #[cfg(feature = "cbc")]
+        Backend::Cbc => solve_cbc(data, num_cores, options, initial)?,
+        #[cfg(feature = "cpsat")]
+        Backend::CpSat => cpsat::solve(data, num_cores, options, initial)?,
+        #[allow(unreachable_patterns)]
+        backend => anyhow::bail!(
+            "The {backend:?} backend is not available, enable its feature"
+        ),
     };
-    let mut model = Model::with_env("portfolio_model", &env)?;
-    model.set_param(param::NumericFocus, 1)?;
-    model.set_param(param::TimeLimit, timeout.0)?;
-    let (n, m) = (data.num_algorithms, data.num_instances);
+    if options.backend != Backend::Gurobi {
+        report_history(&result, &mut progress);
+    }
+    Ok(refine(data, num_cores, options, result, &mut progress))
+}
 
-    let a =
-        Array3::<grb::Var>::from_shape_fn((m, n, num_cores), |(i, j, k)| {
-            add_binvar!(model, name: format!("a_{i}_{j}_{k}").as_str())
-                .unwrap()
-        });
-    let b = Array2::<grb::Var>::from_shape_fn((n, num_cores), |(j, k)| {
-        add_binvar!(model, name: format!("b_{j}_{k}").as_str()).unwrap()
-    });
-    let q = Array1::<grb::Var>::from_shape_fn(m, |i| {
-        add_ctsvar!(model, name: format!("q_{i}").as_str(), bounds: 0..)
-            .unwrap()
+/// Report all incumbents of a finished solver to `progress`
+fn report_history(
+    result: &OptimizationResult,
+    progress: &mut dyn FnMut(ProgressEvent),
+) {
+    for incumbent in &result.history {
+        progress(ProgressEvent::Incumbent(incumbent.clone()));
+    }
+}
+
+/// Improve the final portfolio by the local search, see [`local_search::refine`], prune it with
+/// the [`prune_tolerance`](SolverOptions::prune_tolerance) of the `options` (if any) and report
+/// the changed portfolios to `progress`
+///
+/// The [`instance_qualities`](OptimizationResult::instance_qualities) of the final portfolio are
+/// added if the `options` ask for them.
+fn refine(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    result: OptimizationResult,
+    progress: &mut dyn FnMut(ProgressEvent),
+) -> OptimizationResult {
+    let result = local_search::refine(data, num_cores, options, result);
+    if result.refined {
+        if let Some(incumbent) = result.history.last() {
+            progress(ProgressEvent::Refined(incumbent.clone()));
+        }
+    }
+    let mut result = prune_final(data, num_cores, options, result, progress);
+    if options.instance_qualities {
+        result.instance_qualities =
+            Some(instance_qualities(data, &result.final_portfolio));
+    }
+    result
+}
+
+/// Prune the final portfolio with the [`prune_tolerance`](SolverOptions::prune_tolerance) of the
+/// `options` (if any), see [`refine`]
+fn prune_final(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    mut result: OptimizationResult,
+    progress: &mut dyn FnMut(ProgressEvent),
+) -> OptimizationResult {
+    let Some(tolerance) = options.prune_tolerance else {
+        return result;
+    };
+    let selected = |portfolio: &Portfolio| {
+        portfolio_copies(portfolio, data)
+            .into_iter()
+            .filter(|copies| copies.round() >= 1.0)
+            .count()
+    };
+    let pruned = Portfolio {
+        name: String::from("final_portfolio"),
+        ..prune(data, num_cores, options, &result.final_portfolio, tolerance)
+    };
+    if selected(&pruned) == selected(&result.final_portfolio) {
+        return result;
+    }
+    let objective = local_search::penalized_objective(
+        data,
+        num_cores,
+        options,
+        &portfolio_copies(&pruned, data),
+    );
+    let (bound, time) = result
+        .history
+        .last()
+        .map_or((f64::NEG_INFINITY, 0.0), |last| (last.bound, last.time));
+    let incumbent = Incumbent {
+        portfolio: pruned.clone(),
+        objective,
+        bound,
+        time,
+        simulated: None,
+    };
+    progress(ProgressEvent::Pruned(incumbent.clone()));
+    result.history.push(incumbent);
+    result.stats.objective = objective;
+    result.stats.num_incumbents = result.history.len();
+    if bound.is_finite() {
+        result.gap = (objective - bound).abs() / objective.abs();
+    }
+    result.gap_closed = gap_closed(data, &pruned);
+    result.final_portfolio = pruned;
+    result
+}
+
+/// Create a portfolio by racing the heuristics against the MIP.
+///
+/// The MIP ([`solve`]) and the [`genetic`] algorithm run concurrently on separate threads with the
+/// timeout of the `options`, the [`greedy_solve`] portfolio is created meanwhile. The portfolio
+/// with the best objective (including the penalties of the `options`) that satisfies the quality
+/// guarantees is returned along with the method that found it, so there is a portfolio even if the
+/// MIP has no incumbent at the timeout. Ties are won by the MIP, failing methods are only logged
+/// as long as any method finds a portfolio.
+pub fn solve_racing(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+) -> Result<(OptimizationResult, SolveMethod)> {
+    let genetic_options = genetic::GeneticOptions {
+        timeout: options.timeout.clone(),
+        ..Default::default()
+    };
+    let (mip, genetic, greedy) = std::thread::scope(|scope| {
+        let mip = scope.spawn(|| solve(data, num_cores, options, initial));
+        let genetic =
+            scope.spawn(|| genetic::solve(data, num_cores, &genetic_options));
+        let greedy = greedy_solve(data, num_cores);
+        let join = |handle: std::thread::ScopedJoinHandle<'_, _>| {
+            handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        };
+        (join(mip), join(genetic), greedy)
     });
-    let best_per_instance = &data.best_per_instance;
+    best_result(
+        data,
+        num_cores,
+        options,
+        [
+            (SolveMethod::Mip, mip),
+            (SolveMethod::Genetic, genetic),
+            (SolveMethod::Greedy, Ok(heuristic_result(data, greedy))),
+        ],
+    )
+}
 
-    let e_min = &data.expected_best_quality;
+/// Create a good portfolio within `budget` seconds without any configuration.
+///
+/// The [`greedy_solve`] portfolio is created first and the [`genetic`] algorithm runs for half of
+/// the budget. If a Gurobi environment can be created and at least a second of the budget remains,
+/// the MIP ([`Solver::solve`]) runs for the rest of the budget, starting from the better heuristic
+/// portfolio. Like [`solve_racing`], the portfolio with the best objective is returned along with
+/// the method that found it, ties are won by the MIP.
+pub fn auto(
+    data: &Data,
+    num_cores: usize,
+    budget: Timeout,
+) -> Result<(OptimizationResult, SolveMethod)> {
+    let start = std::time::Instant::now();
+    let greedy = heuristic_result(data, greedy_solve(data, num_cores));
+    let genetic = genetic::solve(
+        data,
+        num_cores,
+        &genetic::GeneticOptions {
+            timeout: Timeout(budget.0 / 2.0),
+            ..Default::default()
+        },
+    );
+    let (heuristic, method) = best_result(
+        data,
+        num_cores,
+        &SolverOptions::default(),
+        [
+            (SolveMethod::Genetic, genetic),
+            (SolveMethod::Greedy, Ok(greedy)),
+        ],
+    )?;
+    let remaining = budget.0 - start.elapsed().as_secs_f64();
+    if remaining < 1.0 {
+        info!("No time left for the MIP, keeping the {method} portfolio");
+        return Ok((heuristic, method));
+    }
+    let solver = match Solver::new() {
+        Ok(solver) => solver,
+        Err(err) => {
+            info!("Gurobi is not available, keeping the {method} portfolio: {err}");
+            return Ok((heuristic, method));
+        }
+    };
+    let options = SolverOptions {
+        timeout: Timeout(remaining),
+        ..Default::default()
+    };
+    let initial = Some(&heuristic.final_portfolio);
+    let mip = solver.solve(data, num_cores, &options, initial);
+    best_result(
+        data,
+        num_cores,
+        &options,
+        [(SolveMethod::Mip, mip), (method, Ok(heuristic))],
+    )
+}
 
-    // constraint 1
-    let _c_1 = a
-        .indexed_iter()
-        .map(|((i, j, k), &val_a)| {
-            model.add_constr(
-                format!("c1_{i}_{j}_{k}").as_str(),
-                c!(val_a * e_min[(i, j, k)] <= q[i]),
-            )
-        })
+/// Find the portfolio with the fewest cores whose expected quality is within `quality_target`
+/// times the [virtual best](Data::vbs_objective), i.e. the smallest machine that is good enough.
+///
+/// The sum of the quality ratios of the portfolio may be at most `quality_target *
+/// data.vbs_objective()`, at most as many cores as repetitions in the data are used. Among the
+/// portfolios with the fewest cores, the one with the best quality is preferred. The number of
+/// cores is the sum of the [`resource_assignments`](Portfolio::resource_assignments) times the
+/// number of threads of the final portfolio.
+pub fn solve_min_cores(
+    data: &Data,
+    quality_target: f64,
+    timeout: Timeout,
+) -> Result<OptimizationResult> {
+    let start = std::time::Instant::now();
+    anyhow::ensure!(
+        quality_target >= 1.0,
+        "The quality target {quality_target} is better than the virtual best"
+    );
+    let max_cores = data.expected_best_quality.shape()[2];
+    let env = Solver::new()?.env;
+    let mut model = Model::with_env("min_cores_model", &env)?;
+    set_parameters(&mut model, &SolverOptions::new(timeout))?;
+    let n = data.num_algorithms;
+
+    let vars = add_portfolio_model(&mut model, data, max_cores, "")?;
+    // the portfolio may leave cores unused
+    model.update()?;
+    let core_sum = model
+        .get_constr_by_name("c3")?
+        .context("missing core constraint")?;
+    model.set_obj_attr(attr::Sense, &core_sum, ConstrSense::Less)?;
+    model.set_obj_attr(attr::ConstrName, &core_sum, "cores")?;
+    let bound = quality_target * data.vbs_objective();
+    let quality = quality_objective(
+        &mut model,
+        &vars,
+        data,
+        &Objective::QualityRatio,
+        "",
+    )?;
+    model.add_constr("quality_target", c!(quality.clone() <= bound))?;
+    // the quality is at most `bound`, so the tie-break never outweighs a core
+    let cores = vars.cores.iter().cloned().grb_sum();
+    model.set_objective(
+        cores + quality * (1.0 / (bound + 1.0)),
+        ModelSense::Minimize,
+    )?;
+    model.optimize()?;
+    if model.status()? == Status::Infeasible {
+        let infeasibility = compute_infeasibility(
+            &mut model,
+            &[data],
+            max_cores,
+            &SolverOptions::default(),
+        )?;
+        return Err(anyhow::Error::new(infeasibility).context(format!(
+            "The quality target {quality_target} can't be reached with {max_cores} cores"
+        )));
+    }
+    anyhow::ensure!(
+        model.get_attr(attr::SolCount)? > 0,
+        "No portfolio reaching the quality target found within the timeout"
+    );
+    let solution = model.get_obj_attr_batch(attr::X, vars.b)?;
+    let gap = model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+    let final_portfolio = postprocess_solution(
+        solution,
+        n,
+        max_cores,
+        &data.algorithms,
+        "final_portfolio",
+        gap.abs() < f64::EPSILON,
+    );
+    Ok(OptimizationResult {
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: None,
+        initial_portfolio: None,
+        final_portfolio,
+        gap,
+        history: Vec::new(),
+        refined: false,
+        pool: Vec::new(),
+        stats: solve_stats(&model, start, 0, &Default::default())?,
+        instance_qualities: None,
+    })
+}
+
+/// Create a portfolio for every number of cores in `budgets` from the same data, e.g. for machines
+/// of different sizes.
+///
+/// The data has to cover the largest budget, i.e. have at least as many repetitions. The budgets
+/// are solved in increasing order with the timeout of the `options` each, and every budget starts
+/// from the final portfolio of the next smaller one, whose free cores are filled greedily (see
+/// [`solve`] for the initial portfolio of the smallest budget). The Gurobi environment is created
+/// once for all budgets. The results are returned in the order of the `budgets`.
+pub fn solve_for_budgets(
+    data: &Data,
+    budgets: &[usize],
+    options: &SolverOptions,
+) -> Result<Vec<OptimizationResult>> {
+    let max_cores = data.expected_best_quality.shape()[2];
+    let order = (0..budgets.len())
+        .sorted_by_key(|&b| budgets[b])
         .collect_vec();
+    for (&b, &c) in order.iter().tuple_windows() {
+        anyhow::ensure!(
+            budgets[b] != budgets[c],
+            "The budget of {} cores is given twice",
+            budgets[b]
+        );
+    }
+    if let Some(&b) = order.last() {
+        anyhow::ensure!(
+            budgets[b] <= max_cores,
+            "The budget of {} cores exceeds the {max_cores} repetitions of the data",
+            budgets[b]
+        );
+    }
+    solve_budgets(data, budgets, order, options, None)
+}
 
-    // constraint 2
-    let _c_2 = b
-        .rows()
-        .into_iter()
-        .enumerate()
-        .map(|(i, row)| {
-            model.add_constr(
-                format!("c2_{i}").as_str(),
-                c!(row.into_iter().grb_sum() <= 1),
-            )
-        })
+/// Solve the `budgets` in the given `order`, starting from the filled `start` copies (if any) or
+/// the final portfolio of the previous budget, see [`solve_for_budgets`]
+fn solve_budgets(
+    data: &Data,
+    budgets: &[usize],
+    order: Vec<usize>,
+    options: &SolverOptions,
+    start: Option<Vec<f64>>,
+) -> Result<Vec<OptimizationResult>> {
+    // other backends don't need an environment, and `solve` falls back to HiGHS without Gurobi
+    let solver = match options.backend {
+        Backend::Gurobi => Solver::for_options(options).ok(),
+        _ => None,
+    };
+    let mut results = budgets.iter().map(|_| None).collect_vec();
+    let mut previous = start;
+    for b in order {
+        let num_cores = budgets[b];
+        info!("Solving for {num_cores} cores");
+        let initial = previous.as_ref().map(|copies| {
+            let copies = local_search::fill(data, num_cores, options, copies);
+            portfolio_from_copies(data, &copies, "initial_portfolio")
+        });
+        let result = match &solver {
+            Some(solver) => {
+                solver.solve(data, num_cores, options, initial.as_ref())
+            }
+            None => solve(data, num_cores, options, initial.as_ref()),
+        }
+        .with_context(|| format!("Solving for {num_cores} cores failed"))?;
+        previous = Some(portfolio_copies(&result.final_portfolio, data));
+        results[b] = Some(result);
+    }
+    Ok(results.into_iter().flatten().collect_vec())
+}
+
+/// Result of a heuristic portfolio without a bound
+fn heuristic_result(data: &Data, portfolio: Portfolio) -> OptimizationResult {
+    let stats = SolveStats {
+        objective: objective(data, &portfolio_copies(&portfolio, data)),
+        ..Default::default()
+    };
+    OptimizationResult {
+        initial_portfolio: None,
+        gap_closed: gap_closed(data, &portfolio),
+        initial_gap_closed: None,
+        final_portfolio: Portfolio {
+            name: String::from("final_portfolio"),
+            ..portfolio
+        },
+        gap: f64::NAN,
+        history: Vec::new(),
+        refined: false,
+        pool: Vec::new(),
+        stats,
+        instance_qualities: None,
+    }
+}
+
+/// The result with the best objective (including the penalties of the `options`) that satisfies
+/// all constraints of the `options`, earlier results win ties
+fn best_result(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    results: impl IntoIterator<Item = (SolveMethod, Result<OptimizationResult>)>,
+) -> Result<(OptimizationResult, SolveMethod)> {
+    let threads = data
+        .algorithms
+        .iter()
+        .map(|a| a.num_threads as usize)
         .collect_vec();
+    let mut best: Option<(OptimizationResult, SolveMethod, f64)> = None;
+    for (method, result) in results {
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                warn!("The {method} solver failed: {err:#}");
+                continue;
+            }
+        };
+        let copies = portfolio_copies(&result.final_portfolio, data);
+        if !local_search::feasible(data, num_cores, options, &threads, &copies)
+        {
+            info!("The {method} portfolio violates the constraints");
+            continue;
+        }
+        let value = local_search::penalized_objective(
+            data, num_cores, options, &copies,
+        );
+        info!("The {method} solver found a portfolio with objective {value}");
+        if best.as_ref().map_or(true, |(_, _, best)| value < *best) {
+            best = Some((result, method, value));
+        }
+    }
+    let (result, method, _) = best.context("No solver found a portfolio")?;
+    info!("The {method} solver won");
+    Ok((result, method))
+}
 
-    // constraint 3
-    let sums = b
-        .rows()
-        .into_iter()
-        .zip(&data.algorithms)
-        .map(|(row, algo)| {
-            row.into_iter()
-                .zip(1..=num_cores)
-                .map(|(var, k)| *var * k * algo.num_threads)
-                .grb_sum()
+/// A solver holding a Gurobi environment that is reused for every model it solves.
+///
+/// Creating an environment checks out a license, which can be slow (e.g. on a token server), so
+/// prefer this over [`solve`] for sweeps or cross-validation. The environment can be shared with
+/// other code of the caller ([`Solver::with_env`]), configured before it is started
+/// ([`Solver::with_config`]) or use a token server or WLS license ([`Solver::with_license`]).
+pub struct Solver {
+    env: grb::Env,
+}
+
+impl Solver {
+    /// Create a solver with a new Gurobi environment
+    ///
+    /// Gurobi output is enabled if the log level is at least `Info`.
+    pub fn new() -> Result<Self> {
+        Self::with_config(|_| Ok(()))
+    }
+
+    /// Create a solver with a new Gurobi environment that is configured by `configure` before it
+    /// is started, e.g. to set license or Compute Server parameters
+    ///
+    /// Gurobi output is enabled if the log level is at least `Info`, `configure` may override
+    /// this.
+    pub fn with_config(
+        configure: impl FnOnce(&mut grb::EmptyEnv) -> grb::Result<()>,
+    ) -> Result<Self> {
+        let log_level = match log_enabled!(log::Level::Info) {
+            true => 1,
+            false => 0,
+        };
+        let start = || {
+            let mut env = grb::Env::empty()?;
+            env.set(param::OutputFlag, log_level)?;
+            configure(&mut env)?;
+            env.start()
+        };
+        let env = start()
+            .map_err(|err| SolverError::Environment(err.to_string()))?;
+        Ok(Self { env })
+    }
+
+    /// Create a solver with a new Gurobi environment that uses the `license` instead of the
+    /// license file found by Gurobi
+    pub fn with_license(license: &GurobiLicense) -> Result<Self> {
+        Self::with_config(|env| {
+            match license {
+                GurobiLicense::TokenServer { server, password } => {
+                    env.set(param::TokenServer, server.clone())?;
+                    if let Some(password) = password {
+                        env.set(param::ServerPassword, password.clone())?;
+                    }
+                }
+                GurobiLicense::Wls {
+                    access_id,
+                    secret,
+                    license_id,
+                } => {
+                    env.set(param::WLSAccessID, access_id.clone())?;
+                    env.set(param::WLSSecret, secret.clone())?;
+                    env.set(param::LicenseID, *license_id)?;
+                }
+            }
+            Ok(())
         })
-        .grb_sum();
-    let sum_constraint = if data.algorithms.iter().any(|a| a.num_threads == 1)
+    }
+
+    /// Create a solver with a new Gurobi environment for the `options`, which uses their
+    /// [`license`](SolverOptions::license) if any
+    pub fn for_options(options: &SolverOptions) -> Result<Self> {
+        match &options.license {
+            Some(license) => Self::with_license(license),
+            None => Self::new(),
+        }
+    }
+
+    /// Create a solver from an existing Gurobi environment
+    pub fn with_env(env: grb::Env) -> Self {
+        Self { env }
+    }
+
+    /// The Gurobi environment used for all models of this solver
+    pub fn env(&self) -> &grb::Env {
+        &self.env
+    }
+
+    /// Create a portfolio from the input data, see [`solve`]
+    ///
+    /// The environment is only used with the Gurobi [backend](SolverOptions::backend).
+    pub fn solve(
+        &self,
+        data: &Data,
+        num_cores: usize,
+        options: &SolverOptions,
+        initial: Option<&Portfolio>,
+    ) -> Result<OptimizationResult> {
+        self.solve_with_progress(data, num_cores, options, initial, |_| ())
+    }
+
+    /// Create a portfolio from the input data and report the progress, see
+    /// [`solve_with_progress`]
+    pub fn solve_with_progress<F>(
+        &self,
+        data: &Data,
+        num_cores: usize,
+        options: &SolverOptions,
+        initial: Option<&Portfolio>,
+        mut progress: F,
+    ) -> Result<OptimizationResult>
+    where
+        F: FnMut(ProgressEvent),
     {
-        c!(sums == num_cores)
-    } else {
-        c!(sums <= num_cores)
+        if options.backend != Backend::Gurobi {
+            return solve_with_progress(
+                data, num_cores, options, initial, progress,
+            );
+        }
+        let result = solve_with_env(
+            &self.env,
+            data,
+            num_cores,
+            options,
+            initial,
+            &mut progress,
+            None,
+        )?;
+        Ok(refine(data, num_cores, options, result, &mut progress))
+    }
+}
+
+/// Solve the MIP with the Gurobi environment `env`, continuing the previous runs of a `resumed`
+/// solve if any
+///
+/// If the solve runs into numerical trouble, it is retried with the [`rescaled`] data and an
+/// increasing `NumericFocus` up to 3.
+fn solve_with_env(
+    env: &grb::Env,
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+    progress: &mut dyn FnMut(ProgressEvent),
+    resumed: Option<checkpoint::Resumed>,
+) -> Result<OptimizationResult> {
+    if resumed.is_none() && benders::applies(data, num_cores, options) {
+        return benders::solve(
+            env, data, num_cores, options, initial, progress,
+        );
+    }
+    let mut error = match solve_mip(
+        env,
+        data,
+        num_cores,
+        options,
+        initial,
+        progress,
+        resumed.clone(),
+    ) {
+        Err(error) if is_numerical(&error) => error,
+        result => return result,
     };
-    let _c_3 = model.add_constr("c3", sum_constraint);
-    // constraint 4
-    let _c_4 = a
-        .outer_iter()
-        .into_iter()
-        .enumerate()
-        .map(|(i, row)| {
-            model.add_constr(
-                format!("c4_{i}").as_str(),
-                c!(row.iter().grb_sum() == 1),
-            )
-        })
-        .collect_vec();
+    let scaled = rescaled(data);
+    let focus = options.numeric_focus.unwrap_or(1);
+    for numeric_focus in (focus + 1).min(3)..=3 {
+        warn!(
+            "{error}, retrying with rescaled qualities and NumericFocus {numeric_focus}"
+        );
+        let options = options.clone().with_numeric_focus(numeric_focus);
+        match solve_mip(
+            env,
+            &scaled,
+            num_cores,
+            &options,
+            initial,
+            progress,
+            resumed.clone(),
+        ) {
+            Ok(result) => {
+                info!("Solved the rescaled model with NumericFocus {numeric_focus}");
+                return Ok(OptimizationResult {
+                    stats: SolveStats {
+                        numeric_focus: Some(numeric_focus),
+                        ..result.stats
+                    },
+                    ..result
+                });
+            }
+            Err(next) if is_numerical(&next) => error = next,
+            Err(next) => return Err(next),
+        }
+    }
+    Err(error)
+}
 
-    // constraint 5
-    let _c_5 = a
-        .indexed_iter()
-        .map(|((i, j, k), &val_a)| {
-            model.add_constr(
-                format!("c5_{i}_{j}_{k}").as_str(),
-                c!(val_a <= b[(j, k)]),
-            )
-        })
-        .collect_vec();
+/// Whether the `error` of a solve is [`SolverError::Numerical`]
+fn is_numerical(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref(), Some(SolverError::Numerical))
+}
 
-    let objective_function = q
-        .iter()
-        .zip(best_per_instance.iter())
-        .map(|(&var, &best)| var * (1.0 / best))
-        .grb_sum();
+/// The `data` with the qualities of every instance divided by its best quality
+///
+/// The quality ratios and thus the objectives are the same, but the objective coefficients
+/// `1 / best` of the instances, which may span many orders of magnitude, are all 1. Instances
+/// without a positive best quality are not scaled.
+fn rescaled(data: &Data) -> Data {
+    let scales = data.best_per_instance.mapv(|best| match best > 0.0 {
+        true => best,
+        false => 1.0,
+    });
+    let mut expected_best_quality = data.expected_best_quality.clone();
+    for (mut qualities, scale) in
+        expected_best_quality.outer_iter_mut().zip(&scales)
+    {
+        qualities /= *scale;
+    }
+    Data {
+        algorithms: data.algorithms.clone(),
+        best_per_instance: &data.best_per_instance / &scales,
+        best_per_instance_count: data.best_per_instance_count.clone(),
+        expected_best_quality,
+        imputed: data.imputed.clone(),
+        instances: data.instances.clone(),
+        num_instances: data.num_instances,
+        num_algorithms: data.num_algorithms,
+        sampling_seed: data.sampling_seed,
+    }
+}
+
+/// Solve the monolithic MIP of [`solve_with_env`] once
+fn solve_mip(
+    env: &grb::Env,
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+    progress: &mut dyn FnMut(ProgressEvent),
+    resumed: Option<checkpoint::Resumed>,
+) -> Result<OptimizationResult> {
+    let start = std::time::Instant::now();
+    check_quality_guarantee(data, num_cores, options)?;
+    check_chance_constraint(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    #[cfg(feature = "server")]
+    let _job = crate::metrics::JobGuard::start();
+    let n = data.num_algorithms;
+    let pruned = pruned_cells(data, num_cores, options);
+    let (mut model, vars) =
+        build_model(env, data, num_cores, options, pruned.as_ref())
+            .map_err(model_build_error)?;
+    let b = &vars.b;
 
+    let mut checkpointer = checkpoint::Checkpointer::start(
+        data,
+        num_cores,
+        options,
+        resumed.as_ref(),
+    )?;
+    let elapsed = resumed.as_ref().map_or(0.0, |resumed| resumed.elapsed);
+    let validator = validation::Validator::new(data, options)?;
+    let mut history = Vec::new();
+    let mut num_links = 0;
     let mut callback = |w: Where| {
+        if options.is_cancelled() {
+            terminate(&w);
+        }
+        if let (Where::MIP(ctx), Some(checkpointer)) = (&w, &mut checkpointer)
+        {
+            checkpointer.update(&history, ctx.runtime()?)?;
+        }
         if let Where::MIPSol(ctx) = w {
+            if options.lazy_linking {
+                let links = lazy::separate(&ctx, &vars, data)?;
+                num_links += links;
+                // the solution violated the linking constraints
+                if links > 0 {
+                    return Ok(());
+                }
+            }
             let sol = ctx.get_solution(b.iter())?;
             let obj = ctx.obj()?;
             let obj_bnd = ctx.obj_bnd()?;
@@ -139,26 +874,44 @@ pub fn solve(
             );
             debug!("{res}");
             debug!("Lower bound: {obj_bnd}\nCurrent objective value: {obj}");
+            #[cfg(feature = "server")]
+            crate::metrics::SOLVER_GAP.set((obj - obj_bnd).abs() / obj.abs());
+            let incumbent = Incumbent {
+                portfolio: res,
+                objective: obj,
+                bound: obj_bnd,
+                time: elapsed + ctx.runtime()?,
+                simulated: None,
+            };
+            let incumbent = Incumbent {
+                simulated: validator
+                    .as_ref()
+                    .map(|validator| validator.validate(&incumbent.portfolio))
+                    .transpose()?,
+                ..incumbent
+            };
+            progress(ProgressEvent::Incumbent(incumbent.clone()));
+            history.push(incumbent);
+            if let Some(checkpointer) = &mut checkpointer {
+                checkpointer.write(&history, ctx.runtime()?)?;
+            }
         }
         Ok(())
     };
 
     let initial_portfolio = if let Some(initial_assignment) =
-        match (initial_resource_assignment, &data.best_per_instance_count) {
-            (Some(assignment), _) => Some(assignment),
-            (None, Some(counts)) => {
-                get_b_start(counts, &data.algorithms, m, num_cores).ok()
-            }
-            (None, None) => None,
-        } {
-        let mut initial_solution = vec![0.0; n * num_cores];
-        for (i, v) in initial_assignment.iter().enumerate() {
-            if v.abs() <= std::f64::EPSILON {
-                continue;
-            }
-            model.set_obj_attr(attr::Start, &b[(i, *v as usize - 1)], 1.0)?;
-            initial_solution[i * num_cores + *v as usize - 1] = 1.0;
-        }
+        initial_assignment(initial, data, num_cores)?
+    {
+        let initial_solution = selection(&initial_assignment, num_cores);
+        // an equivalent start that satisfies the symmetry breaking constraints
+        let start = symmetry_canonical_copies(
+            &initial_assignment,
+            &interchangeable_algorithms(data, options),
+        );
+        model.set_obj_attr_batch(
+            attr::Start,
+            b.iter().copied().zip(selection(&start, num_cores)),
+        )?;
 
         let initial_portfolio = postprocess_solution(
             initial_solution,
@@ -174,11 +927,45 @@ pub fn solve(
         info!("No initial portfolio provided");
         None
     };
-    model.set_objective(objective_function, ModelSense::Minimize)?;
-    model.write("portfolio_model.lp")?;
-    model.optimize_with_callback(&mut callback)?;
-    let solution = model.get_obj_attr_batch(attr::X, b)?;
+    export_and_import(&mut model, options)?;
+    model
+        .optimize_with_callback(&mut callback)
+        .map_err(solve_error)?;
+    if let Some(checkpointer) = &mut checkpointer {
+        checkpointer.write(&history, model.get_attr(attr::Runtime)?)?;
+    }
+    if options.lazy_linking {
+        info!("Added {num_links} linking constraints lazily");
+    }
+    check_solve_status(&model, options)?;
+    if model.status()? == Status::Infeasible {
+        if options.lazy_linking {
+            // the conflict may involve linking constraints that were not added yet
+            add_linking_constraints(
+                &mut model,
+                &vars,
+                data,
+                pruned.as_ref(),
+                "",
+            )?;
+        }
+        return Err(compute_infeasibility(
+            &mut model,
+            &[data],
+            num_cores,
+            options,
+        )?
+        .into());
+    }
+    if model.status()? == Status::Interrupted
+        && model.get_attr(attr::SolCount)? == 0
+    {
+        anyhow::bail!("The solver was cancelled before it found a portfolio");
+    }
+    let solution = model.get_obj_attr_batch(attr::X, b.iter().copied())?;
     let gap = model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+    #[cfg(feature = "server")]
+    crate::metrics::SOLVER_GAP.set(gap);
     let final_portfolio = postprocess_solution(
         solution,
         n,
@@ -187,37 +974,1803 @@ pub fn solve(
         "final_portfolio",
         gap.abs() < f64::EPSILON,
     );
-    debug!(
-        "Final objective value: {}",
-        model.get_attr(attr::ObjVal).unwrap()
-    );
+    debug!("Final objective value: {}", model.get_attr(attr::ObjVal)?);
+    if let Some(resumed) = resumed {
+        history = resumed.history.into_iter().chain(history).collect();
+    }
+    let stats = solve_stats(&model, start, history.len(), options)?;
+    let pool = match options.pool_size {
+        Some(pool_size) => {
+            solution_pool(&mut model, b, data, num_cores, pool_size)?
+        }
+        None => Vec::new(),
+    };
     Ok(OptimizationResult {
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: initial_portfolio
+            .as_ref()
+            .map(|portfolio| gap_closed(data, portfolio)),
         initial_portfolio,
         final_portfolio,
         gap,
+        history,
+        refined: false,
+        pool,
+        stats,
+        instance_qualities: None,
     })
 }
 
-fn postprocess_solution(
-    solution: Vec<f64>,
-    n: usize,
+/// Build the MIP of the `options` with the Gurobi environment `env`, except for the `pruned`
+/// cells, see [`solve`]
+fn build_model(
+    env: &grb::Env,
+    data: &Data,
     num_cores: usize,
-    algorithms: &ndarray::Array1<Algorithm>,
-    portfolio_name: &str,
-    opt: bool,
-) -> Portfolio {
-    let mut portfolio_selection = vec![0.0; n];
-    for j in 0..n {
-        for k in 0..num_cores {
-            portfolio_selection[j] +=
-                solution[j * num_cores + k] * (k + 1) as f64;
-        }
+    options: &SolverOptions,
+    pruned: Option<&Array2<bool>>,
+) -> Result<(Model, PortfolioVars)> {
+    let mut model = Model::with_env("portfolio_model", env)?;
+    set_parameters(&mut model, options)?;
+    if let Some(pool_size) = options.pool_size {
+        model.set_param(param::PoolSolutions, i32::try_from(pool_size)?)?;
+        model.set_param(param::PoolSearchMode, 2)?;
     }
-    let resource_assignments = algorithms
-        .iter()
-        .zip(portfolio_selection)
-        .map(|(algo, cores)| (algo.clone(), cores))
-        .collect_vec();
+    if let Some(pool_gap) = options.pool_gap {
+        model.set_param(param::PoolGap, pool_gap)?;
+    }
+    let vars = add_portfolio_vars(&mut model, data, num_cores, pruned, "")?;
+    match options.lazy_linking {
+        true => model.set_param(param::LazyConstraints, 1)?,
+        false => add_linking_constraints(&mut model, &vars, data, pruned, "")?,
+    }
+    add_quality_guarantees(&mut model, &vars.q, data, options, "")?;
+    add_chance_constraints(&mut model, &vars.b, options, "")?;
+    add_algorithm_constraints(&mut model, &vars, data, options, "")?;
+    add_budgets(&mut model, &vars.b, data, options, "")?;
+    add_symmetry_breaking(&mut model, &vars.b, data, options, "")?;
+    let objective_function =
+        quality_objective(&mut model, &vars, data, &options.objective, "")?
+            + option_penalties(
+                &mut model,
+                &vars.b,
+                &vars.cores,
+                data,
+                num_cores,
+                options,
+                "",
+            )?;
+    model.set_objective(objective_function, ModelSense::Minimize)?;
+    Ok((model, vars))
+}
+
+/// Gurobi error codes of a missing license and of a model exceeding a size-limited license
+const LICENSE_ERRORS: [i32; 2] = [10009, 10010];
+
+/// The [`SolverError`] of a Gurobi `error`, [`SolverError::Environment`] for license errors and
+/// `kind` otherwise
+fn gurobi_error(
+    error: grb::Error,
+    kind: fn(String) -> SolverError,
+) -> SolverError {
+    match error {
+        grb::Error::FromAPI(_, code) if LICENSE_ERRORS.contains(&code) => {
+            SolverError::Environment(error.to_string())
+        }
+        _ => kind(error.to_string()),
+    }
+}
+
+/// Classify the Gurobi errors of building a model as [`SolverError::ModelBuild`] (or
+/// [`SolverError::Environment`]), other errors are invalid data or options
+fn model_build_error(error: anyhow::Error) -> anyhow::Error {
+    match error.downcast::<grb::Error>() {
+        Ok(error) => gurobi_error(error, SolverError::ModelBuild).into(),
+        Err(error) => error,
+    }
+}
+
+/// Classify the license errors of solving a model as [`SolverError::Environment`]
+fn solve_error(error: grb::Error) -> anyhow::Error {
+    match error {
+        grb::Error::FromAPI(_, code) if LICENSE_ERRORS.contains(&code) => {
+            SolverError::Environment(error.to_string()).into()
+        }
+        _ => error.into(),
+    }
+}
+
+/// Fail with [`SolverError::Numerical`] if the solve of `model` stopped because of numerical
+/// trouble, or with [`SolverError::Timeout`] if it stopped at the timeout without a portfolio
+///
+/// The portfolio of a solve that is suboptimal or unbounded is not trustworthy, the objective is
+/// bounded from below, so both are caused by numerical trouble.
+fn check_solve_status(model: &Model, options: &SolverOptions) -> Result<()> {
+    match model.status()? {
+        Status::Numeric | Status::SubOptimal | Status::Unbounded => {
+            Err(SolverError::Numerical.into())
+        }
+        Status::TimeLimit if model.get_attr(attr::SolCount)? == 0 => {
+            Err(SolverError::Timeout(options.timeout.0).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Statistics of a solved Gurobi `model` whose solve with the `options` started at `start`
+fn solve_stats(
+    model: &Model,
+    start: std::time::Instant,
+    num_incumbents: usize,
+    options: &SolverOptions,
+) -> Result<SolveStats> {
+    let termination = match model.status()? {
+        Status::Optimal => gap_termination(
+            Termination::Optimal,
+            model.get_attr(attr::MIPGap).unwrap_or(0.0),
+            options,
+        ),
+        Status::TimeLimit => Termination::Timeout,
+        Status::Interrupted => Termination::Interrupted,
+        _ => Termination::Limit,
+    };
+    Ok(SolveStats {
+        wall_time: start.elapsed().as_secs_f64(),
+        node_count: model
+            .get_attr(attr::NodeCount)
+            .ok()
+            .map(|nodes| nodes as u64),
+        objective: model.get_attr(attr::ObjVal)?,
+        bound: model.get_attr(attr::ObjBound).ok(),
+        num_incumbents,
+        termination,
+        numeric_focus: None,
+    })
+}
+
+/// [`Termination::GapReached`] instead of [`Termination::Optimal`] if the solver stopped at the
+/// [`mip_gap`](SolverOptions::mip_gap) of the `options` with a remaining relative `gap`
+fn gap_termination(
+    termination: Termination,
+    gap: f64,
+    options: &SolverOptions,
+) -> Termination {
+    match termination {
+        Termination::Optimal
+            if options.mip_gap.is_some() && gap > f64::EPSILON =>
+        {
+            Termination::GapReached
+        }
+        _ => termination,
+    }
+}
+
+/// The `pool_size` best distinct portfolios in the solution pool of a solved model, in order of
+/// their objective
+///
+/// Solutions that only differ in the assignment of the instances are the same portfolio, so there
+/// may be fewer portfolios than solutions in the pool.
+fn solution_pool(
+    model: &mut Model,
+    b: &Array2<grb::Var>,
+    data: &Data,
+    num_cores: usize,
+    pool_size: usize,
+) -> Result<Vec<Portfolio>> {
+    let mut pool: Vec<Portfolio> = Vec::new();
+    for i in 0..model.get_attr(attr::SolCount)? {
+        if pool.len() == pool_size {
+            break;
+        }
+        model.set_param(param::SolutionNumber, i)?;
+        let portfolio = postprocess_solution(
+            model.get_obj_attr_batch(attr::Xn, b.iter().copied())?,
+            data.num_algorithms,
+            num_cores,
+            &data.algorithms,
+            &format!("pool_portfolio_{}", pool.len()),
+            false,
+        );
+        debug!(
+            "Pool portfolio with objective value {}:\n{portfolio}",
+            model.get_attr(attr::PoolObjVal)?
+        );
+        if pool
+            .iter()
+            .all(|p| p.resource_assignments != portfolio.resource_assignments)
+        {
+            pool.push(portfolio);
+        }
+    }
+    Ok(pool)
+}
+
+/// Signal Gurobi to stop the optimization from any callback
+fn terminate(w: &Where) {
+    match w {
+        Where::Polling(ctx) => ctx.terminate(),
+        Where::PreSolve(ctx) => ctx.terminate(),
+        Where::Simplex(ctx) => ctx.terminate(),
+        Where::MIP(ctx) => ctx.terminate(),
+        Where::MIPSol(ctx) => ctx.terminate(),
+        Where::MIPNode(ctx) => ctx.terminate(),
+        Where::Message(ctx) => ctx.terminate(),
+        Where::Barrier(ctx) => ctx.terminate(),
+    }
+}
+
+/// Variables of the portfolio model for one set of input data
+pub struct PortfolioVars {
+    /// Instance `i` is assigned to algorithm `j` with `k + 1` repetitions
+    pub a: Array3<grb::Var>,
+    /// Algorithm `j` is selected with `k + 1` repetitions
+    pub b: Array2<grb::Var>,
+    /// Expected best quality of instance `i`
+    pub q: Array1<grb::Var>,
+    /// Number of cores assigned to each algorithm
+    pub cores: Vec<grb::Expr>,
+}
+
+/// Write the Gurobi `model` to the [`model_export`](SolverOptions::model_export) and read the
+/// [`start_solution`](SolverOptions::start_solution) into it, if set
+fn export_and_import(
+    model: &mut Model,
+    options: &SolverOptions,
+) -> Result<()> {
+    if let Some(export) = &options.model_export {
+        let file = export.file();
+        model.write(&file.to_string_lossy()).with_context(|| {
+            format!("Failed to write the model to {}", file.display())
+        })?;
+        info!("Wrote the model to {}", file.display());
+    }
+    if let Some(path) = &options.start_solution {
+        anyhow::ensure!(
+            matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("sol" | "mst")
+            ),
+            "Expected a start solution ending in .sol or .mst, got {}",
+            path.display()
+        );
+        // the variables of the start solution are matched by name
+        model.update()?;
+        model.read(&path.to_string_lossy()).with_context(|| {
+            format!("Failed to read the start solution {}", path.display())
+        })?;
+        info!("Read the start solution {}", path.display());
+    }
+    Ok(())
+}
+
+/// Set the solver parameters of the `options` on a Gurobi model
+fn set_parameters(model: &mut Model, options: &SolverOptions) -> Result<()> {
+    model.set_param(param::OutputFlag, options.solver_output() as i32)?;
+    model.set_param(
+        param::NumericFocus,
+        options.numeric_focus.unwrap_or(1) as i32,
+    )?;
+    model.set_param(param::TimeLimit, options.timeout.0)?;
+    if let Some(mip_gap) = options.mip_gap {
+        model.set_param(param::MIPGap, mip_gap)?;
+    }
+    if let Some(node_limit) = options.node_limit {
+        model.set_param(param::NodeLimit, node_limit as f64)?;
+    }
+    if let Some(threads) = options.threads {
+        model.set_param(param::Threads, i32::try_from(threads)?)?;
+    }
+    if let Some(seed) = options.seed {
+        model.set_param(param::Seed, i32::try_from(seed)?)?;
+    }
+    Ok(())
+}
+
+/// Add the variables and constraints of the portfolio model for `data` to `model`
+///
+/// All names are prefixed with `prefix` to allow multiple portfolios in one model.
+fn add_portfolio_model(
+    model: &mut Model,
+    data: &Data,
+    num_cores: usize,
+    prefix: &str,
+) -> Result<PortfolioVars> {
+    let vars = add_portfolio_vars(model, data, num_cores, None, prefix)?;
+    add_linking_constraints(model, &vars, data, None, prefix)?;
+    Ok(vars)
+}
+
+/// Add the portfolio model for `data` to `model` without the linking constraints (1 and 5), see
+/// [`add_portfolio_model`]
+///
+/// The variables of the `pruned` cells `(j, k)` (see [`pruned_cells`]) are fixed to zero.
+fn add_portfolio_vars(
+    model: &mut Model,
+    data: &Data,
+    num_cores: usize,
+    pruned: Option<&Array2<bool>>,
+    prefix: &str,
+) -> Result<PortfolioVars> {
+    let (n, m) = (data.num_algorithms, data.num_instances);
+
+    let a = add_vars(
+        (m, n, num_cores),
+        |(i, j, k)| add_binvar!(model, name: format!("{prefix}a_{i}_{j}_{k}").as_str()),
+    )?;
+    let (b, cores) =
+        add_selection_model(model, data, num_cores, pruned, prefix)?;
+    if let Some(pruned) = pruned {
+        for ((_, j, k), var) in a.indexed_iter() {
+            if pruned[(j, k)] {
+                model.set_obj_attr(attr::UB, var, 0.0)?;
+            }
+        }
+    }
+    let q = add_vars(
+        m,
+        |i| add_ctsvar!(model, name: format!("{prefix}q_{i}").as_str(), bounds: 0..),
+    )?;
+
+    // constraint 4
+    for (i, row) in a.outer_iter().enumerate() {
+        model.add_constr(
+            format!("{prefix}c4_{i}").as_str(),
+            c!(row.iter().grb_sum() == 1),
+        )?;
+    }
+
+    Ok(PortfolioVars { a, b, q, cores })
+}
+
+/// Array of the variables created by `add` for every index of `shape` in row-major order
+///
+/// Unlike [`ndarray::Array::from_shape_fn`], this stops at the first variable Gurobi fails to add.
+fn add_vars<D: ndarray::Dimension>(
+    shape: impl ndarray::IntoDimension<Dim = D>,
+    add: impl FnMut(D::Pattern) -> grb::Result<grb::Var>,
+) -> Result<ndarray::Array<grb::Var, D>> {
+    let shape = shape.into_dimension();
+    let vars = ndarray::indices(shape.clone())
+        .into_iter()
+        .map(add)
+        .collect::<grb::Result<Vec<_>>>()?;
+    Ok(ndarray::Array::from_shape_vec(shape, vars)?)
+}
+
+/// Add the constraints linking the assignment `a` of the instances to their quality `q`
+/// (constraint 1) and to the selection `b` (constraint 5), except for the `pruned` cells
+fn add_linking_constraints(
+    model: &mut Model,
+    vars: &PortfolioVars,
+    data: &Data,
+    pruned: Option<&Array2<bool>>,
+    prefix: &str,
+) -> Result<()> {
+    let PortfolioVars { a, b, q, .. } = vars;
+    let e_min = &data.expected_best_quality;
+    let kept =
+        |j: usize, k: usize| pruned.map_or(true, |pruned| !pruned[(j, k)]);
+
+    // constraint 1
+    for ((i, j, k), &val_a) in
+        a.indexed_iter().filter(|((_, j, k), _)| kept(*j, *k))
+    {
+        model.add_constr(
+            format!("{prefix}c1_{i}_{j}_{k}").as_str(),
+            c!(val_a * e_min[(i, j, k)] <= q[i]),
+        )?;
+    }
+
+    // constraint 5
+    for ((i, j, k), &val_a) in
+        a.indexed_iter().filter(|((_, j, k), _)| kept(*j, *k))
+    {
+        model.add_constr(
+            format!("{prefix}c5_{i}_{j}_{k}").as_str(),
+            c!(val_a <= b[(j, k)]),
+        )?;
+    }
+
+    // aggregation of constraint 1, as every instance is assigned exactly once (constraint 4), it
+    // is valid and tighter in the LP relaxation, where a fractional assignment otherwise only
+    // bounds the quality by its largest term
+    for (i, row) in a.outer_iter().enumerate() {
+        let assigned = row
+            .indexed_iter()
+            .filter(|((j, k), _)| kept(*j, *k))
+            .map(|((j, k), &val_a)| val_a * e_min[(i, j, k)])
+            .grb_sum();
+        model.add_constr(
+            format!("{prefix}tight_{i}").as_str(),
+            c!(assigned <= q[i]),
+        )?;
+    }
+    Ok(())
+}
+
+/// Mask of the cells `(j, k)` of the selection that are dropped from the model, if
+/// [`prune_dominated`](SolverOptions::prune_dominated) is set, see [`dominated_cells`]
+fn pruned_cells(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Option<Array2<bool>> {
+    if !options.prune_dominated {
+        return None;
+    }
+    let dominated = dominance::dominated_mask(data, num_cores, options);
+    let pruned = dominated.map(Option::is_some);
+    info!(
+        "Pruned {} of {} dominated selections of algorithms and repetitions",
+        pruned.iter().filter(|&&pruned| pruned).count(),
+        pruned.len()
+    );
+    Some(pruned)
+}
+
+/// Order the copies of interchangeable algorithms (see [`interchangeable_algorithms`]) by
+/// their ids, which removes the symmetric portfolios that only swap the copies of such algorithms
+/// from the search
+fn add_symmetry_breaking(
+    model: &mut Model,
+    b: &Array2<grb::Var>,
+    data: &Data,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<()> {
+    let copies = |j: usize| {
+        b.row(j)
+            .iter()
+            .enumerate()
+            .map(|(k, &var)| (k + 1) as f64 * var)
+            .grb_sum()
+    };
+    for class in interchangeable_algorithms(data, options) {
+        for (&j, &l) in class.iter().tuple_windows() {
+            model.add_constr(
+                format!("{prefix}symmetry_{j}_{l}").as_str(),
+                c!(copies(j) >= copies(l)),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Classes of at least two interchangeable algorithms of the data, in the order of their ids
+///
+/// Algorithms are interchangeable if they only differ in their names, i.e. they have the same
+/// threads, [core class](SolverOptions::core_classes), requirements, expected best qualities (and
+/// imputed cells, success rates of the chance constraint and previous cores of the stability
+/// penalty) and are not bounded by the algorithm constraints. Swapping the copies of two
+/// interchangeable algorithms does not change whether a portfolio is feasible or its objective.
+/// Custom objectives may score the algorithms in any way, so their algorithms are never
+/// interchangeable.
+fn interchangeable_algorithms(
+    data: &Data,
+    options: &SolverOptions,
+) -> Vec<Vec<usize>> {
+    if let Objective::Custom(_) = options.objective {
+        return Vec::new();
+    }
+    let constrained = forced_algorithms(data, options)
+        .into_iter()
+        .map(|(j, _)| j)
+        .chain(core_bounds(data, 1, options).into_iter().map(|(j, _, _)| j))
+        .collect_vec();
+    let previous = options
+        .stability_penalty
+        .as_ref()
+        .map(|stability| previous_cores(&stability.previous, data));
+    let column = |j: usize| data.expected_best_quality.index_axis(Axis(1), j);
+    let interchangeable = |j: usize, l: usize| {
+        let (first, second) = (&data.algorithms[j], &data.algorithms[l]);
+        first.num_threads == second.num_threads
+            && core_class(data, j, options) == core_class(data, l, options)
+            && first.memory == second.memory
+            && first.cost == second.cost
+            && first.startup == second.startup
+            && column(j) == column(l)
+            && data.imputed.as_ref().map_or(true, |imputed| {
+                imputed.index_axis(Axis(1), j)
+                    == imputed.index_axis(Axis(1), l)
+            })
+            && options.chance_constraint.as_ref().map_or(true, |chance| {
+                chance.success.column(j) == chance.success.column(l)
+            })
+            && previous
+                .as_ref()
+                .map_or(true, |previous| previous[j] == previous[l])
+    };
+    let mut classes: Vec<Vec<usize>> = Vec::new();
+    for j in (0..data.num_algorithms).filter(|j| !constrained.contains(j)) {
+        match classes
+            .iter_mut()
+            .find(|class| interchangeable(class[0], j))
+        {
+            Some(class) => class.push(j),
+            None => classes.push(vec![j]),
+        }
+    }
+    classes.retain(|class| class.len() > 1);
+    classes
+}
+
+/// Values of the selection `b` (in the order of its variables) for the `copies` of every
+/// algorithm
+fn selection(copies: &[f64], num_cores: usize) -> Vec<f64> {
+    let mut selection = vec![0.0; copies.len() * num_cores];
+    for (j, copies) in copies.iter().enumerate() {
+        if copies.abs() > std::f64::EPSILON {
+            selection[j * num_cores + *copies as usize - 1] = 1.0;
+        }
+    }
+    selection
+}
+
+/// The `copies` of every algorithm with the copies of the interchangeable algorithms `classes`
+/// sorted in decreasing order, which is an equivalent portfolio that satisfies the symmetry
+/// breaking constraints of [`add_symmetry_breaking`]
+fn symmetry_canonical_copies(
+    copies: &[f64],
+    classes: &[Vec<usize>],
+) -> Vec<f64> {
+    let mut canonical = copies.to_vec();
+    for class in classes {
+        let sorted = class
+            .iter()
+            .map(|&j| copies[j])
+            .sorted_by(|a, b| b.total_cmp(a));
+        for (&j, copies) in class.iter().zip(sorted) {
+            canonical[j] = copies;
+        }
+    }
+    canonical
+}
+
+/// Add the selection `b` of the algorithms and their numbers of repetitions (constraints 2 and 3)
+/// and return it along with the number of cores of every algorithm
+///
+/// The `pruned` cells `(j, k)` are fixed to zero.
+fn add_selection_model(
+    model: &mut Model,
+    data: &Data,
+    num_cores: usize,
+    pruned: Option<&Array2<bool>>,
+    prefix: &str,
+) -> Result<(Array2<grb::Var>, Vec<grb::Expr>)> {
+    let b = add_vars(
+        (data.num_algorithms, num_cores),
+        |(j, k)| add_binvar!(model, name: format!("{prefix}b_{j}_{k}").as_str()),
+    )?;
+    if let Some(pruned) = pruned {
+        for (var, _) in b.iter().zip(pruned).filter(|(_, &pruned)| pruned) {
+            model.set_obj_attr(attr::UB, var, 0.0)?;
+        }
+    }
+
+    // constraint 2
+    for (j, row) in b.rows().into_iter().enumerate() {
+        model.add_constr(
+            format!("{prefix}c2_{j}").as_str(),
+            c!(row.into_iter().grb_sum() <= 1),
+        )?;
+    }
+
+    // constraint 3
+    let cores = b
+        .rows()
+        .into_iter()
+        .zip(&data.algorithms)
+        .map(|(row, algo)| {
+            row.into_iter()
+                .zip(1..=num_cores)
+                .map(|(var, k)| *var * k * algo.num_threads)
+                .grb_sum()
+        })
+        .collect_vec();
+    let sums = cores.iter().cloned().grb_sum();
+    let sum_constraint = if data.algorithms.iter().any(|a| a.num_threads == 1)
+    {
+        c!(sums == num_cores)
+    } else {
+        c!(sums <= num_cores)
+    };
+    model.add_constr(format!("{prefix}c3").as_str(), sum_constraint)?;
+    Ok((b, cores))
+}
+
+/// The `objective` of the expected quality ratios to the best quality over all instances
+///
+/// The sum of the quality ratios uses `q` directly. Other summing objectives score every
+/// assignment `a` of an instance, which selects the best algorithm for the instance, as exactly
+/// one assignment per instance is chosen and the scores are monotone in the quality ratio. The
+/// worst case is an additional variable bounding all quality ratios. The conditional value at risk
+/// uses the formulation of Rockafellar and Uryasev, `t + sum_i max(0, ratio_i - t) / (alpha * m)`
+/// with a free threshold `t`, which is minimal for the mean of the worst `alpha`-fraction. The
+/// mean-variance objective is the quadratic `mean + lambda / m * sum_i d_i^2` with the deviations
+/// `d_i = ratio_i - mean`, which is convex, and needs the exact quality ratios, see
+/// [`add_exact_qualities`]. Custom objectives are built by the
+/// [registered](objectives::register) objective.
+fn quality_objective(
+    model: &mut Model,
+    vars: &PortfolioVars,
+    data: &Data,
+    objective: &Objective,
+    prefix: &str,
+) -> Result<grb::Expr> {
+    let ratios = vars
+        .q
+        .iter()
+        .zip(data.best_per_instance.iter())
+        .map(|(&var, &best)| var * (1.0 / best));
+    Ok(match objective {
+        Objective::QualityRatio => {
+            objectives::RatioSum.build(model, vars, data, prefix)?
+        }
+        Objective::Penalized { cutoff, factor } => objectives::Penalized {
+            cutoff: *cutoff,
+            factor: *factor,
+        }
+        .build(model, vars, data, prefix)?,
+        Objective::Custom(name) => objectives::get(name)
+            .with_context(|| format!("No objective registered as {name}"))?
+            .build(model, vars, data, prefix)?,
+        Objective::WorstCase => {
+            let worst = add_ctsvar!(model, name: format!("{prefix}worst_case").as_str(), bounds: 0..)?;
+            for (i, ratio) in ratios.enumerate() {
+                model.add_constr(
+                    format!("{prefix}worst_case_{i}").as_str(),
+                    c!(worst >= ratio),
+                )?;
+            }
+            worst.into()
+        }
+        Objective::Cvar { alpha } => {
+            anyhow::ensure!(
+                *alpha > 0.0 && *alpha <= 1.0,
+                "The CVaR fraction {alpha} is not in (0, 1]"
+            );
+            let threshold = add_ctsvar!(model, name: format!("{prefix}cvar_threshold").as_str(), bounds: ..)?;
+            let excess = ratios
+                .enumerate()
+                .map(|(i, ratio)| -> Result<grb::Var> {
+                    let excess = add_ctsvar!(model, name: format!("{prefix}cvar_excess_{i}").as_str(), bounds: 0..)?;
+                    model.add_constr(
+                        format!("{prefix}cvar_{i}").as_str(),
+                        c!(excess >= ratio - threshold),
+                    )?;
+                    Ok(excess)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let tail = alpha * data.num_instances as f64;
+            threshold + excess.into_iter().grb_sum() * (1.0 / tail)
+        }
+        Objective::MeanVariance { lambda } => {
+            anyhow::ensure!(
+                *lambda >= 0.0,
+                "The variance weight {lambda} is negative"
+            );
+            add_exact_qualities(model, vars, data, prefix)?;
+            let m = data.num_instances as f64;
+            let mean = add_ctsvar!(model, name: format!("{prefix}mean").as_str(), bounds: ..)?;
+            model.add_constr(
+                format!("{prefix}mean").as_str(),
+                c!(mean == ratios.clone().grb_sum() * (1.0 / m)),
+            )?;
+            let squares = ratios
+                .enumerate()
+                .map(|(i, ratio)| -> Result<grb::Expr> {
+                    let deviation = add_ctsvar!(model, name: format!("{prefix}deviation_{i}").as_str(), bounds: ..)?;
+                    model.add_constr(
+                        format!("{prefix}deviation_{i}").as_str(),
+                        c!(deviation == ratio - mean),
+                    )?;
+                    Ok(deviation * deviation)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            mean + squares.into_iter().grb_sum() * (lambda / m)
+        }
+        Objective::GeometricMean => {
+            objectives::assignment_objective(vars, data, |_, ratio| {
+                objective.score(ratio)
+            })
+        }
+    })
+}
+
+/// Force the quality `q` of every instance to the expected best quality of the best selected
+/// algorithm, which the other constraints only bound from below
+///
+/// Objectives that are not monotone in the quality ratios, like the variance, could otherwise
+/// worsen the quality of some instances or assign them to worse selected algorithms. Every
+/// selected cell bounds the quality from above with a big-M of the worst expected best quality
+/// of the instance.
+fn add_exact_qualities(
+    model: &mut Model,
+    vars: &PortfolioVars,
+    data: &Data,
+    prefix: &str,
+) -> Result<()> {
+    let PortfolioVars { a, b, q, .. } = vars;
+    let e_min = &data.expected_best_quality;
+    for (i, row) in a.outer_iter().enumerate() {
+        let assigned = row
+            .indexed_iter()
+            .map(|((j, k), &val_a)| val_a * e_min[(i, j, k)])
+            .grb_sum();
+        model.add_constr(
+            format!("{prefix}exact_{i}").as_str(),
+            c!(q[i] <= assigned),
+        )?;
+        let worst = e_min
+            .index_axis(Axis(0), i)
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        for ((j, k), &e) in e_min.index_axis(Axis(0), i).indexed_iter() {
+            model.add_constr(
+                format!("{prefix}best_{i}_{j}_{k}").as_str(),
+                c!(q[i] <= e + (worst - e) * (1.0 - b[(j, k)])),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Select every algorithm forced into the portfolio with some number of repetitions, no
+/// algorithm forced out of it, keep the cores of every algorithm within its core bounds and assign
+/// no instance to imputed qualities if they are forbidden
+fn add_algorithm_constraints(
+    model: &mut Model,
+    vars: &PortfolioVars,
+    data: &Data,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<()> {
+    let PortfolioVars { a, b, cores, .. } = vars;
+    add_selection_constraints(model, b, cores, data, options, prefix)?;
+    for (i, j, k) in forbidden_cells(data, options) {
+        model.add_constr(
+            format!("{prefix}imputed_{i}_{j}_{k}").as_str(),
+            c!(a[(i, j, k)] == 0),
+        )?;
+    }
+    Ok(())
+}
+
+/// Select every algorithm forced into the portfolio with some number of repetitions, no
+/// algorithm forced out of it and keep the cores of every algorithm within its core bounds
+fn add_selection_constraints(
+    model: &mut Model,
+    b: &Array2<grb::Var>,
+    cores: &[grb::Expr],
+    data: &Data,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<()> {
+    for (j, include) in forced_algorithms(data, options) {
+        let selected = b.row(j).iter().grb_sum();
+        match include {
+            true => model.add_constr(
+                format!("{prefix}include_{j}").as_str(),
+                c!(selected == 1),
+            )?,
+            false => model.add_constr(
+                format!("{prefix}exclude_{j}").as_str(),
+                c!(selected == 0),
+            )?,
+        };
+    }
+    for (j, min, max) in core_bounds(data, b.ncols(), options) {
+        model.add_constr(
+            format!("{prefix}mincores_{j}").as_str(),
+            c!(cores[j].clone() >= min),
+        )?;
+        model.add_constr(
+            format!("{prefix}maxcores_{j}").as_str(),
+            c!(cores[j].clone() <= max),
+        )?;
+    }
+    Ok(())
+}
+
+/// Keep the total memory of all algorithm copies and the total cost of the selected algorithms
+/// within the budgets of the `options`, the cores of the algorithms of every core class within
+/// the cores of the class and every algorithm copy on a single node of the topology
+///
+/// With a topology, the integer variable `x_j_s` is the number of copies of algorithm `j` on node
+/// `s`, the copies of every algorithm are distributed over the nodes (`placed_j`) and the copies
+/// on a node use at most its cores (`node_s`).
+fn add_budgets(
+    model: &mut Model,
+    b: &Array2<grb::Var>,
+    data: &Data,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<()> {
+    if let Some(budget) = options.memory_budget {
+        let used = b
+            .indexed_iter()
+            .map(|((j, k), &var)| var * ((k + 1) as f64 * memory(data, j)))
+            .grb_sum();
+        model.add_constr(
+            format!("{prefix}memory").as_str(),
+            c!(used <= budget),
+        )?;
+    }
+    if let Some(budget) = options.cost_budget {
+        let spent = b
+            .indexed_iter()
+            .map(|((j, _), &var)| var * cost(data, j))
+            .grb_sum();
+        model.add_constr(
+            format!("{prefix}cost").as_str(),
+            c!(spent <= budget),
+        )?;
+    }
+    for (c, class) in options.core_classes.iter().enumerate() {
+        let used = b
+            .indexed_iter()
+            .filter(|((j, _), _)| core_class(data, *j, options) == Some(c))
+            .map(|((j, k), &var)| {
+                var * ((k + 1) * data.algorithms[j].num_threads as usize)
+            })
+            .grb_sum();
+        model.add_constr(
+            format!("{prefix}class_{c}").as_str(),
+            c!(used <= class.cores),
+        )?;
+    }
+    if let Some(topology) = &options.topology {
+        let nodes = &topology.numa_nodes;
+        let placed = add_vars(
+            (data.num_algorithms, nodes.len()),
+            |(j, s)| add_intvar!(model, name: format!("{prefix}x_{j}_{s}").as_str(), bounds: 0..),
+        )?;
+        for (j, row) in placed.outer_iter().enumerate() {
+            let copies = b
+                .row(j)
+                .iter()
+                .enumerate()
+                .map(|(k, &var)| var * (k + 1) as f64)
+                .grb_sum();
+            model.add_constr(
+                format!("{prefix}placed_{j}").as_str(),
+                c!(row.iter().grb_sum() == copies),
+            )?;
+        }
+        for (s, &cores) in nodes.iter().enumerate() {
+            let used = placed
+                .column(s)
+                .iter()
+                .zip(&data.algorithms)
+                .map(|(&var, algo)| var * algo.num_threads as f64)
+                .grb_sum();
+            model.add_constr(
+                format!("{prefix}node_{s}").as_str(),
+                c!(used <= cores),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn add_quality_guarantees(
+    model: &mut Model,
+    q: &Array1<grb::Var>,
+    data: &Data,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<()> {
+    if let Some(guarantee) = &options.quality_guarantee {
+        for i in guarantee_instances(guarantee, data.num_instances) {
+            model.add_constr(
+                format!("{prefix}guarantee_{i}").as_str(),
+                c!(q[i] <= guarantee.factor * data.best_per_instance[i]),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Chance constraints of the `options` on the selected copies `b`
+///
+/// No copy reaches the threshold on instance `i` with probability `prod_j (1 - s_ij)^c_j` for
+/// `c_j` copies of algorithm `j` with the success rate `s_ij`, which is linear in the copies after
+/// taking the logarithm: `sum_jk (k + 1) ln(1 - s_ij) b_jk <= ln(1 - p)`. Copies that always reach
+/// the threshold have a coefficient of minus infinity, it is replaced by the big-M `ln(1 - p)`,
+/// since a single such copy satisfies the constraint on its own.
+fn add_chance_constraints(
+    model: &mut Model,
+    b: &Array2<grb::Var>,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<()> {
+    let Some(chance) = &options.chance_constraint else {
+        return Ok(());
+    };
+    let bound = (1.0 - chance.threshold.probability).ln();
+    for (i, success) in chance.success.outer_iter().enumerate() {
+        let failure = b
+            .indexed_iter()
+            .filter(|((j, _), _)| success[*j] > 0.0)
+            .map(|((j, k), &var)| {
+                var * ((k + 1) as f64 * (1.0 - success[j]).ln()).max(bound)
+            })
+            .grb_sum();
+        model.add_constr(
+            format!("{prefix}chance_{i}").as_str(),
+            c!(failure <= bound),
+        )?;
+    }
+    Ok(())
+}
+
+/// Objective terms of the optional penalties in `options`
+fn option_penalties(
+    model: &mut Model,
+    b: &Array2<grb::Var>,
+    cores: &[grb::Expr],
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    prefix: &str,
+) -> Result<grb::Expr> {
+    let mut penalties = grb::Expr::default();
+    if let Some(penalty) = options.idle_core_penalty {
+        penalties = penalties
+            + penalty * (num_cores as f64 - cores.iter().cloned().grb_sum());
+    }
+    // at most one number of repetitions is selected per algorithm
+    if let Some(penalty) = options.complexity_penalty {
+        penalties = penalties + penalty * b.iter().grb_sum();
+    }
+    if let Some(stability) = &options.stability_penalty {
+        let previous_cores = previous_cores(&stability.previous, data);
+        let deviation = add_l1_distance(
+            model,
+            cores,
+            &previous_cores
+                .into_iter()
+                .map(grb::Expr::from)
+                .collect_vec(),
+            &format!("{prefix}stability"),
+        )?;
+        penalties = penalties + stability.penalty * deviation;
+    }
+    Ok(penalties)
+}
+
+/// Add variables for the element-wise absolute difference of `lhs` and `rhs` and return their
+/// sum
+fn add_l1_distance(
+    model: &mut Model,
+    lhs: &[grb::Expr],
+    rhs: &[grb::Expr],
+    name: &str,
+) -> Result<grb::Expr> {
+    Ok(lhs
+        .iter()
+        .zip(rhs)
+        .enumerate()
+        .map(|(j, (l, r))| {
+            let d = add_ctsvar!(model, name: format!("{name}_d_{j}").as_str(), bounds: 0..)?;
+            model.add_constr(
+                format!("{name}_pos_{j}").as_str(),
+                c!(d >= l.clone() - r.clone()),
+            )?;
+            model.add_constr(
+                format!("{name}_neg_{j}").as_str(),
+                c!(d >= r.clone() - l.clone()),
+            )?;
+            Ok(d)
+        })
+        .collect::<Result<Vec<grb::Var>>>()?
+        .grb_sum())
+}
+
+/// Compute an IIS of the infeasible model and translate its constraints back
+fn compute_infeasibility(
+    model: &mut Model,
+    periods: &[&Data],
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<Infeasibility> {
+    model.compute_iis()?;
+    let constrs = model.get_constrs()?.to_vec();
+    let in_iis = model.get_obj_attr_batch(attr::IISConstr, constrs.clone())?;
+    let names = constrs
+        .iter()
+        .zip(in_iis)
+        .filter(|(_, in_iis)| *in_iis > 0)
+        .map(|(constr, _)| model.get_obj_attr(attr::ConstrName, constr))
+        .collect::<grb::Result<Vec<String>>>()?;
+    Ok(Infeasibility {
+        conflicts: names
+            .iter()
+            .map(|name| describe_conflict(name, periods, num_cores, options))
+            .collect(),
+        options: names
+            .iter()
+            .filter_map(|name| constraint_option(name))
+            .unique()
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// Split the period of a constraint of the [multi-period model](multi_period) off its name
+fn split_period(name: &str) -> Option<(usize, &str)> {
+    let (period, rest) = name.strip_prefix('p')?.split_once('_')?;
+    Some((period.parse().ok()?, rest))
+}
+
+/// Option of the config that introduced the constraint `name`, if any
+fn constraint_option(name: &str) -> Option<&'static str> {
+    let name = split_period(name).map_or(name, |(_, rest)| rest);
+    match name.split('_').next().unwrap_or_default() {
+        "c3" => Some("num_cores"),
+        "include" => Some("algorithm_constraints.include"),
+        "exclude" => Some("algorithm_constraints.exclude"),
+        "mincores" | "maxcores" => Some("algorithm_constraints.core_bounds"),
+        "imputed" => Some("forbid_imputed"),
+        "memory" => Some("memory_budget"),
+        "cost" => Some("cost_budget"),
+        "class" => Some("core_classes"),
+        "placed" | "node" => Some("topology"),
+        "guarantee" => Some("quality_guarantee"),
+        "chance" => Some("chance_constraint"),
+        "quality" => Some("quality_target"),
+        _ => None,
+    }
+}
+
+/// Description of the constraint `name` of a conflict against the data of its period, see
+/// [`describe_constraint`] (the data of a single-period model is the only period)
+fn describe_conflict(
+    name: &str,
+    periods: &[&Data],
+    num_cores: usize,
+    options: &SolverOptions,
+) -> String {
+    match split_period(name) {
+        Some((t, rest)) if t < periods.len() => format!(
+            "in period {t}, {}",
+            describe_constraint(rest, periods[t], num_cores, options)
+        ),
+        _ => describe_constraint(name, periods[0], num_cores, options),
+    }
+}
+
+fn describe_constraint(
+    name: &str,
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> String {
+    let mut parts = name.split('_');
+    let kind = parts.next().unwrap_or_default();
+    let idx = parts.filter_map(|p| p.parse::<usize>().ok()).collect_vec();
+    match (kind, idx.as_slice()) {
+        ("c1", &[i, j, k]) => format!(
+            "instance {i} has at least the expected quality of {} with {} repetitions if assigned to it",
+            data.algorithms[j],
+            k + 1
+        ),
+        ("c2", &[j]) => format!(
+            "{} is selected with at most one number of repetitions",
+            data.algorithms[j]
+        ),
+        ("c3", _) => {
+            if data.algorithms.iter().any(|a| a.num_threads == 1) {
+                format!("the portfolio uses exactly {num_cores} cores")
+            } else {
+                format!("the portfolio uses at most {num_cores} cores")
+            }
+        }
+        ("cores", _) => {
+            format!("the portfolio uses at most {num_cores} cores")
+        }
+        ("c4", &[i]) => {
+            format!("instance {i} is assigned to exactly one algorithm")
+        }
+        ("c5", &[i, j, k]) => format!(
+            "instance {i} can only be assigned to {} with {} repetitions if it is selected",
+            data.algorithms[j],
+            k + 1
+        ),
+        ("include", &[j]) => {
+            format!("{} is forced into the portfolio", data.algorithms[j])
+        }
+        ("exclude", &[j]) => {
+            format!("{} is forced out of the portfolio", data.algorithms[j])
+        }
+        ("mincores", &[j]) => format!(
+            "{} gets at least {} cores",
+            data.algorithms[j],
+            core_bounds(data, num_cores, options)
+                .into_iter()
+                .find(|(bounded, _, _)| *bounded == j)
+                .map_or(f64::NAN, |(_, min, _)| min)
+        ),
+        ("maxcores", &[j]) => format!(
+            "{} gets at most {} cores",
+            data.algorithms[j],
+            core_bounds(data, num_cores, options)
+                .into_iter()
+                .find(|(bounded, _, _)| *bounded == j)
+                .map_or(f64::NAN, |(_, _, max)| max)
+        ),
+        ("imputed", &[i, j, k]) => format!(
+            "instance {i} is not assigned to {} copies of {}, whose quality was imputed",
+            k + 1,
+            data.algorithms[j]
+        ),
+        ("imputed", &[i]) => format!(
+            "instance {i} is assigned to an algorithm whose quality was not imputed"
+        ),
+        ("selected", _) => {
+            "the portfolio contains at least one algorithm".to_string()
+        }
+        ("memory", _) => format!(
+            "the portfolio uses at most {} memory",
+            options.memory_budget.unwrap_or(f64::NAN)
+        ),
+        ("cost", _) => format!(
+            "the algorithms of the portfolio cost at most {}",
+            options.cost_budget.unwrap_or(f64::NAN)
+        ),
+        ("placed", &[j]) => format!(
+            "every copy of {} runs on a single NUMA node",
+            data.algorithms[j]
+        ),
+        ("node", &[s]) => format!(
+            "the copies on NUMA node {s} use at most {} cores",
+            options
+                .topology
+                .as_ref()
+                .and_then(|topology| topology.numa_nodes.get(s))
+                .map_or(f64::NAN, |&cores| cores as f64)
+        ),
+        ("class", &[c]) => {
+            let class = &options.core_classes[c];
+            format!(
+                "the algorithms on the {} cores use at most {} cores",
+                class.name, class.cores
+            )
+        }
+        ("guarantee", &[i]) => format!(
+            "the expected quality of instance {i} is at most {} * {}",
+            options
+                .quality_guarantee
+                .as_ref()
+                .map_or(f64::NAN, |g| g.factor),
+            data.best_per_instance[i]
+        ),
+        ("chance", &[i]) => {
+            let threshold = options
+                .chance_constraint
+                .as_ref()
+                .map(|chance| chance.threshold);
+            format!(
+                "a run of the portfolio reaches a quality of at most {} * {} on instance {i} with probability {}",
+                threshold.map_or(f64::NAN, |t| t.factor),
+                data.best_per_instance[i],
+                threshold.map_or(f64::NAN, |t| t.probability)
+            )
+        }
+        ("tight", &[i]) => format!(
+            "instance {i} has at least the expected quality of the algorithm it is assigned to"
+        ),
+        ("symmetry", &[j, l]) => format!(
+            "{} gets at least as many copies as the interchangeable {}",
+            data.algorithms[j], data.algorithms[l]
+        ),
+        ("quality", _) => format!(
+            "the sum of the quality ratios is at most the quality target times the virtual best ({})",
+            data.vbs_objective()
+        ),
+        _ => name.to_string(),
+    }
+}
+
+/// Cores assigned to each algorithm of the data in a (previous) portfolio
+fn previous_cores(portfolio: &Portfolio, data: &Data) -> Vec<f64> {
+    for (algo, _) in &portfolio.resource_assignments {
+        if !data.algorithms.iter().any(|a| a == algo) {
+            warn!("{algo} of portfolio {} is not part of the data and will be ignored", portfolio.name);
+        }
+    }
+    data.algorithms
+        .iter()
+        .map(|algo| {
+            portfolio
+                .resource_assignments
+                .iter()
+                .filter(|(a, _)| a == algo)
+                .map(|(a, copies)| copies * a.num_threads as f64)
+                .sum()
+        })
+        .collect_vec()
+}
+
+/// Check that the objective and constraints of the `options` are supported by the backends other
+/// than Gurobi
+#[cfg(any(feature = "highs", feature = "cbc", feature = "cpsat"))]
+fn check_objective(options: &SolverOptions) -> Result<()> {
+    anyhow::ensure!(
+        options.objective == Objective::QualityRatio,
+        "The {:?} objective is only supported by the Gurobi backend",
+        options.objective
+    );
+    anyhow::ensure!(
+        options.chance_constraint.is_none(),
+        "The chance constraint is only supported by the Gurobi backend"
+    );
+    anyhow::ensure!(
+        options.core_classes.is_empty() && options.topology.is_none(),
+        "Core classes and the topology are only supported by the Gurobi backend"
+    );
+    Ok(())
+}
+
+/// Options of the config that constrain the portfolio and are set in the `options`, e.g. to reject
+/// them in heuristics that ignore them
+pub fn constraint_options(options: &SolverOptions) -> Vec<&'static str> {
+    let constraints = &options.algorithm_constraints;
+    [
+        (
+            !constraints.include.is_empty(),
+            "algorithm_constraints.include",
+        ),
+        (
+            !constraints.exclude.is_empty(),
+            "algorithm_constraints.exclude",
+        ),
+        (
+            !constraints.core_bounds.is_empty(),
+            "algorithm_constraints.core_bounds",
+        ),
+        (options.forbid_imputed, "forbid_imputed"),
+        (options.memory_budget.is_some(), "memory_budget"),
+        (options.cost_budget.is_some(), "cost_budget"),
+        (!options.core_classes.is_empty(), "core_classes"),
+        (options.topology.is_some(), "topology"),
+        (options.quality_guarantee.is_some(), "quality_guarantee"),
+        (options.chance_constraint.is_some(), "chance_constraint"),
+    ]
+    .into_iter()
+    .filter_map(|(set, option)| set.then_some(option))
+    .collect()
+}
+
+/// Error of an open-source backend for an infeasible model, which only Gurobi explains
+#[cfg(any(feature = "highs", feature = "cbc", feature = "cpsat"))]
+fn unexplained_infeasibility() -> anyhow::Error {
+    anyhow::anyhow!(
+        "The model is infeasible, use the Gurobi backend for the conflicting constraints"
+    )
+}
+
+/// Memory of a single copy of algorithm `j` of the data
+fn memory(data: &Data, j: usize) -> f64 {
+    data.algorithms[j].memory.unwrap_or(0.0)
+}
+
+/// Cost of using algorithm `j` of the data
+fn cost(data: &Data, j: usize) -> f64 {
+    data.algorithms[j].cost.unwrap_or(0.0)
+}
+
+/// Whether the `copies` of the algorithms of the data fit on the nodes of the `topology` with every
+/// copy on a single node, by first-fit decreasing placement
+///
+/// This is conservative, first-fit decreasing may fail for some placements that exist.
+fn fits_topology(data: &Data, topology: &Topology, copies: &[f64]) -> bool {
+    let mut free = topology.numa_nodes.clone();
+    copies
+        .iter()
+        .zip(&data.algorithms)
+        .flat_map(|(&c, algo)| {
+            std::iter::repeat(algo.num_threads).take(c.round() as usize)
+        })
+        .sorted_by_key(|&threads| std::cmp::Reverse(threads))
+        .all(|threads| match free.iter_mut().find(|f| **f >= threads) {
+            Some(node) => {
+                *node -= threads;
+                true
+            }
+            None => false,
+        })
+}
+
+/// Index of the [core class](SolverOptions::core_classes) algorithm `j` of the data runs on
+fn core_class(
+    data: &Data,
+    j: usize,
+    options: &SolverOptions,
+) -> Option<usize> {
+    CoreClass::of(&data.algorithms[j], &options.core_classes)
+}
+
+/// Indices of the algorithms of the data that are forced into (`true`) or out of (`false`) the
+/// portfolio by the algorithm constraints of the `options`
+///
+/// Excluded algorithms that are not part of the data are ignored.
+fn forced_algorithms(
+    data: &Data,
+    options: &SolverOptions,
+) -> Vec<(usize, bool)> {
+    let constraints = &options.algorithm_constraints;
+    data.algorithms
+        .iter()
+        .enumerate()
+        .filter_map(|(j, algo)| {
+            if constraints.include.contains(algo) {
+                Some((j, true))
+            } else if constraints.exclude.contains(algo) {
+                Some((j, false))
+            } else {
+                None
+            }
+        })
+        .collect_vec()
+}
+
+/// Indices of the algorithms of the data with core bounds in the algorithm constraints of the
+/// `options` along with their minimum and maximum number of cores
+///
+/// The maximum defaults to `num_cores`, bounds of algorithms that are not part of the data are
+/// ignored.
+fn core_bounds(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Vec<(usize, f64, f64)> {
+    options
+        .algorithm_constraints
+        .core_bounds
+        .iter()
+        .filter_map(|bounds| {
+            let j = data
+                .algorithms
+                .iter()
+                .position(|a| *a == bounds.algorithm)?;
+            let max = bounds.max_cores.map_or(num_cores, |max| max as usize);
+            Some((j, bounds.min_cores as f64, max as f64))
+        })
+        .collect_vec()
+}
+
+/// Cells `(i, j, k)` of the expected best qualities instances must not be assigned to, since
+/// they were imputed and [`SolverOptions::forbid_imputed`] is set
+fn forbidden_cells(
+    data: &Data,
+    options: &SolverOptions,
+) -> Vec<(usize, usize, usize)> {
+    match (&data.imputed, options.forbid_imputed) {
+        (Some(imputed), true) => imputed
+            .indexed_iter()
+            .filter(|(_, &imputed)| imputed)
+            .map(|(cell, _)| cell)
+            .collect_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check upfront that the algorithms forced into the portfolio are part of the data and fit on
+/// `num_cores` together
+fn check_algorithm_constraints(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<()> {
+    let constraints = &options.algorithm_constraints;
+    for algo in &constraints.include {
+        anyhow::ensure!(
+            data.algorithms.iter().any(|a| a == algo),
+            "{algo} is forced into the portfolio, but not part of the data"
+        );
+        anyhow::ensure!(
+            !constraints.exclude.contains(algo),
+            "{algo} is both forced into and out of the portfolio"
+        );
+    }
+    for algo in &constraints.exclude {
+        if !data.algorithms.iter().any(|a| a == algo) {
+            warn!(
+                "Excluded {algo} is not part of the data and will be ignored"
+            );
+        }
+    }
+    for bounds in &constraints.core_bounds {
+        let algo = &bounds.algorithm;
+        let max = bounds.max_cores.unwrap_or(u32::MAX);
+        anyhow::ensure!(
+            bounds.min_cores <= max,
+            "{algo} has more minimum cores ({}) than maximum cores ({max})",
+            bounds.min_cores
+        );
+        if bounds.min_cores == 0 {
+            if !data.algorithms.iter().any(|a| a == algo) {
+                warn!("Core bounds of {algo} are ignored, it is not part of the data");
+            }
+            continue;
+        }
+        anyhow::ensure!(
+            data.algorithms.iter().any(|a| a == algo),
+            "{algo} has minimum cores, but is not part of the data"
+        );
+        anyhow::ensure!(
+            !constraints.exclude.contains(algo),
+            "{algo} has minimum cores, but is forced out of the portfolio"
+        );
+    }
+    let included_cores = data
+        .algorithms
+        .iter()
+        .map(|algo| {
+            let threads = algo.num_threads as usize;
+            let included = match constraints.include.contains(algo) {
+                true => threads,
+                false => 0,
+            };
+            constraints
+                .core_bounds
+                .iter()
+                .filter(|bounds| bounds.algorithm == *algo)
+                .map(|bounds| {
+                    let min = bounds.min_cores as usize;
+                    (min + threads - 1) / threads * threads
+                })
+                .fold(included, usize::max)
+        })
+        .sum::<usize>();
+    anyhow::ensure!(
+        included_cores <= num_cores,
+        "The algorithms forced into the portfolio need {included_cores} cores, but only {num_cores} are available"
+    );
+    anyhow::ensure!(
+        data.algorithms
+            .iter()
+            .any(|algo| !constraints.exclude.contains(algo)),
+        "All algorithms are excluded from the portfolio"
+    );
+    if options.forbid_imputed && data.imputed.is_none() {
+        warn!("Imputed qualities are forbidden, but the data does not mark imputed qualities");
+    }
+    if let Some(budget) = options.memory_budget {
+        if data.algorithms.iter().all(|algo| algo.memory.is_none()) {
+            warn!("A memory budget is set, but no algorithm has a memory requirement");
+        }
+        let included_memory = forced_algorithms(data, options)
+            .into_iter()
+            .filter(|(_, include)| *include)
+            .map(|(j, _)| memory(data, j))
+            .sum::<f64>();
+        anyhow::ensure!(
+            included_memory <= budget,
+            "The algorithms forced into the portfolio need {included_memory} memory, but the budget is {budget}"
+        );
+    }
+    if let Some(budget) = options.cost_budget {
+        if data.algorithms.iter().all(|algo| algo.cost.is_none()) {
+            warn!("A cost budget is set, but no algorithm has a cost");
+        }
+        let included_cost = forced_algorithms(data, options)
+            .into_iter()
+            .filter(|(_, include)| *include)
+            .map(|(j, _)| cost(data, j))
+            .sum::<f64>();
+        anyhow::ensure!(
+            included_cost <= budget,
+            "The algorithms forced into the portfolio cost {included_cost}, but the budget is {budget}"
+        );
+    }
+    if let Some(topology) = &options.topology {
+        let node_cores = topology.numa_nodes.iter().sum::<u32>();
+        anyhow::ensure!(
+            node_cores as usize >= num_cores,
+            "The NUMA nodes have {node_cores} cores, but the portfolio uses {num_cores} cores"
+        );
+        let largest = topology.numa_nodes.iter().max().copied().unwrap_or(0);
+        for algo in &data.algorithms {
+            if algo.num_threads > largest {
+                warn!("{algo} doesn't fit on a single NUMA node and gets no cores");
+            }
+        }
+    }
+    if !options.core_classes.is_empty() {
+        let class_cores = CoreClass::total_cores(&options.core_classes)?;
+        if class_cores != num_cores as u32 {
+            warn!("The core classes have {class_cores} cores, but the portfolio uses {num_cores} cores");
+        }
+        if let Some(algo) = (0..data.num_algorithms)
+            .find(|&j| core_class(data, j, options).is_none())
+        {
+            warn!(
+                "{} runs on no core class, its cores are only limited by the total number of cores",
+                data.algorithms[algo]
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check upfront that every instance can meet the quality guarantee on its own
+fn check_quality_guarantee(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<()> {
+    if let Some(guarantee) = &options.quality_guarantee {
+        if let Some(i) = guarantee_instances(guarantee, data.num_instances)
+            .into_iter()
+            .find(|&i| i >= data.num_instances)
+        {
+            anyhow::bail!("Quality guarantee for unknown instance {i}");
+        }
+        let unsatisfiable =
+            unsatisfiable_instances(data, num_cores, guarantee);
+        if !unsatisfiable.is_empty() {
+            anyhow::bail!(
+                "No algorithm achieves a quality within {} * best on instances {:?}",
+                guarantee.factor,
+                unsatisfiable
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check the chance constraint of the `options` against the data
+///
+/// An instance is unsatisfiable if even spending all cores on the algorithm with the largest
+/// `-ln(1 - s_ij)` per core misses the required probability. The chance constraint is not written
+/// to checkpoints, so it can't be combined with them.
+fn check_chance_constraint(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<()> {
+    let Some(chance) = &options.chance_constraint else {
+        return Ok(());
+    };
+    anyhow::ensure!(
+        options.checkpoint.is_none(),
+        "The chance constraint can't be combined with checkpoints, a resumed solve would ignore it"
+    );
+    let QualityThreshold {
+        factor,
+        probability,
+    } = chance.threshold;
+    anyhow::ensure!(
+        probability > 0.0 && probability < 1.0,
+        "The probability of the chance constraint has to be between 0 and 1, got {probability}"
+    );
+    anyhow::ensure!(
+        chance.success.dim() == (data.num_instances, data.num_algorithms),
+        "The success rates have shape {:?}, but the data has {} instances and {} algorithms",
+        chance.success.dim(),
+        data.num_instances,
+        data.num_algorithms
+    );
+    let required = -(1.0 - probability).ln();
+    let unsatisfiable = chance
+        .success
+        .outer_iter()
+        .enumerate()
+        .filter(|(_, success)| {
+            let per_core = success
+                .iter()
+                .zip(&data.algorithms)
+                .map(|(s, algo)| -(1.0 - s).ln() / algo.num_threads as f64)
+                .fold(0.0, f64::max);
+            per_core * (num_cores as f64) < required
+        })
+        .map(|(i, _)| i)
+        .collect_vec();
+    if !unsatisfiable.is_empty() {
+        anyhow::bail!(
+            "No portfolio reaches a quality within {factor} * best with probability {probability} on instances {unsatisfiable:?}"
+        );
+    }
+    Ok(())
+}
+
+fn guarantee_instances(
+    guarantee: &QualityGuarantee,
+    num_instances: usize,
+) -> Vec<usize> {
+    match &guarantee.instances {
+        Some(instances) => instances.clone(),
+        None => (0..num_instances).collect_vec(),
+    }
+}
+
+/// Instances for which no single algorithm fitting on `num_cores` meets the quality guarantee
+fn unsatisfiable_instances(
+    data: &Data,
+    num_cores: usize,
+    guarantee: &QualityGuarantee,
+) -> Vec<usize> {
+    let e_min = &data.expected_best_quality;
+    guarantee_instances(guarantee, data.num_instances)
+        .into_iter()
+        .filter(|&i| {
+            !data.algorithms.iter().enumerate().any(|(j, algo)| {
+                (0..num_cores)
+                    .take_while(|k| {
+                        (k + 1) * algo.num_threads as usize <= num_cores
+                    })
+                    .any(|k| {
+                        e_min[(i, j, k)]
+                            <= guarantee.factor * data.best_per_instance[i]
+                    })
+            })
+        })
+        .collect_vec()
+}
+
+/// Objective value of a resource assignment (copies of each algorithm of the data)
+///
+/// Every instance is assigned to the selected algorithm with the best expected quality, the
+/// objective is the sum of the quality ratios to the best quality over all instances (see
+/// [`solve`]). Infinite if no algorithm is selected.
+pub fn objective(data: &Data, resource_assignment: &[f64]) -> f64 {
+    quality_ratios(data, resource_assignment).into_iter().sum()
+}
+
+/// Quality ratio of every instance of a resource assignment, see [`objective`]
+fn quality_ratios(data: &Data, resource_assignment: &[f64]) -> Vec<f64> {
+    expected_qualities(data, resource_assignment)
+        .into_iter()
+        .zip(&data.best_per_instance)
+        .map(|(quality, best)| quality / best)
+        .collect()
+}
+
+/// Expected quality of every instance of a resource assignment, the best expected quality of the
+/// selected algorithms (infinite if no algorithm is selected)
+fn expected_qualities(data: &Data, resource_assignment: &[f64]) -> Vec<f64> {
+    let e_min = &data.expected_best_quality;
+    let selected = resource_assignment
+        .iter()
+        .enumerate()
+        .filter(|(_, copies)| **copies >= 1.0)
+        .map(|(j, copies)| (j, *copies as usize - 1))
+        .collect_vec();
+    (0..data.num_instances)
+        .map(|i| {
+            selected
+                .iter()
+                .map(|&(j, k)| e_min[(i, j, k)])
+                .fold(f64::INFINITY, f64::min)
+        })
+        .collect()
+}
+
+/// Expected quality of the `portfolio` on every instance of the data and its ratio to the best
+/// quality, in order of the [instance ids](crate::csv_parser::InstanceId)
+///
+/// Every instance is assigned to the selected algorithm with the best expected quality like in
+/// the [`objective`].
+pub fn instance_qualities(
+    data: &Data,
+    portfolio: &Portfolio,
+) -> Vec<InstanceQuality> {
+    expected_qualities(data, &portfolio_copies(portfolio, data))
+        .into_iter()
+        .zip(data.iter_instances())
+        .map(|(expected_quality, (_, name, best))| InstanceQuality {
+            instance: name.map(String::from),
+            expected_quality,
+            ratio: expected_quality / best,
+        })
+        .collect()
+}
+
+/// Create a portfolio greedily, without any MIP solver.
+///
+/// Starting with the best single copy of any algorithm, a copy of the algorithm with the largest
+/// improvement of the [`objective`] per core is added until no further copy fits on the
+/// `num_cores` cores. The resource assignments contain every algorithm of the data in order, so
+/// the portfolio can be passed to [`solve`] as initial portfolio.
+pub fn greedy_solve(data: &Data, num_cores: usize) -> Portfolio {
+    let mut copies = vec![0.0; data.num_algorithms];
+    let mut current = f64::INFINITY;
+    let mut free_cores = num_cores as u32;
+    loop {
+        let candidates = data
+            .algorithms
+            .iter()
+            .enumerate()
+            .filter(|(_, algo)| algo.num_threads <= free_cores)
+            .map(|(j, algo)| {
+                let mut next = copies.clone();
+                next[j] += 1.0;
+                let value = objective(data, &next);
+                let improvement = match current.is_finite() {
+                    true => (current - value) / algo.num_threads as f64,
+                    false => -value,
+                };
+                (j, value, improvement)
+            })
+            .collect_vec();
+        let Some(&(j, value, _)) = candidates
+            .iter()
+            .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        else {
+            break;
+        };
+        debug!("Greedy: adding {}, objective {value}", data.algorithms[j]);
+        copies[j] += 1.0;
+        free_cores -= data.algorithms[j].num_threads;
+        current = value;
+    }
+    portfolio_from_copies(data, &copies, "greedy_portfolio")
+}
+
+/// Portfolio with the given number of copies of every algorithm of the data
+fn portfolio_from_copies(
+    data: &Data,
+    copies: &[f64],
+    name: &str,
+) -> Portfolio {
+    Portfolio {
+        name: name.to_string(),
+        resource_assignments: data
+            .algorithms
+            .iter()
+            .cloned()
+            .zip(copies.iter().copied())
+            .collect_vec(),
+    }
+}
+
+/// Copies of every algorithm of the data in `portfolio`, see [`previous_cores`]
+fn portfolio_copies(portfolio: &Portfolio, data: &Data) -> Vec<f64> {
+    previous_cores(portfolio, data)
+        .into_iter()
+        .zip(&data.algorithms)
+        .map(|(cores, algo)| cores / algo.num_threads as f64)
+        .collect_vec()
+}
+
+/// Fraction of the gap between the [single best](Data::sbs_objective) and the
+/// [virtual best](Data::vbs_objective) solver that is closed by `portfolio`
+///
+/// 1 if the portfolio is as good as the virtual best solver, 0 if it is as good as the single best
+/// solver and negative if it is worse. NaN if the single best solver is the virtual best solver.
+pub fn gap_closed(data: &Data, portfolio: &Portfolio) -> f64 {
+    let copies = portfolio_copies(portfolio, data);
+    let (sbs, vbs) = (data.sbs_objective(), data.vbs_objective());
+    if (sbs - vbs).abs() < f64::EPSILON {
+        return f64::NAN;
+    }
+    (sbs - objective(data, &copies)) / (sbs - vbs)
+}
+
+fn postprocess_solution(
+    solution: Vec<f64>,
+    n: usize,
+    num_cores: usize,
+    algorithms: &ndarray::Array1<Algorithm>,
+    portfolio_name: &str,
+    opt: bool,
+) -> Portfolio {
+    let mut portfolio_selection = vec![0.0; n];
+    for j in 0..n {
+        for k in 0..num_cores {
+            portfolio_selection[j] +=
+                solution[j * num_cores + k] * (k + 1) as f64;
+        }
+    }
+    let resource_assignments = algorithms
+        .iter()
+        .zip(portfolio_selection)
+        .map(|(algo, cores)| (algo.clone(), cores))
+        .collect_vec();
     let name = if opt {
         [portfolio_name, "opt"].join("_")
     } else {
@@ -229,6 +2782,91 @@ fn postprocess_solution(
     }
 }
 
+/// Copies of every algorithm of the data in the `initial` portfolio of [`solve`]
+///
+/// The algorithms are matched by name and number of threads, see [`solve`] for the mismatches that
+/// are rejected.
+fn initial_copies(
+    initial: &Portfolio,
+    data: &Data,
+    num_cores: usize,
+) -> Result<Vec<f64>> {
+    let mut copies = vec![0.0; data.num_algorithms];
+    for (algo, c) in &initial.resource_assignments {
+        let j = data
+            .algorithms
+            .iter()
+            .position(|a| a == algo)
+            .with_context(|| {
+                format!(
+                    "{algo} of the initial portfolio {} is not part of the data",
+                    initial.name
+                )
+            })?;
+        anyhow::ensure!(
+            *c >= 0.0 && c.fract() == 0.0,
+            "{algo} has {c} copies in the initial portfolio {}, expected a whole number",
+            initial.name
+        );
+        copies[j] += c;
+    }
+    let cores = copies
+        .iter()
+        .zip(&data.algorithms)
+        .map(|(c, a)| c * a.num_threads as f64)
+        .sum::<f64>();
+    anyhow::ensure!(
+        cores <= num_cores as f64,
+        "The initial portfolio {} uses {cores} cores, but only {num_cores} are available",
+        initial.name
+    );
+    Ok(copies)
+}
+
+/// Values of the `b` variables for an initial resource assignment
+#[cfg(any(feature = "highs", feature = "cbc", feature = "cpsat"))]
+fn assignment_to_b(
+    assignment: &[f64],
+    n: usize,
+    num_cores: usize,
+) -> Vec<f64> {
+    let mut b = vec![0.0; n * num_cores];
+    for (j, v) in assignment.iter().enumerate() {
+        if v.abs() > f64::EPSILON {
+            b[j * num_cores + *v as usize - 1] = 1.0;
+        }
+    }
+    b
+}
+
+/// Copies of the provided initial portfolio, the heuristic based on `best_per_instance_count` or
+/// the [`greedy_solve`] portfolio
+fn initial_assignment(
+    initial: Option<&Portfolio>,
+    data: &Data,
+    num_cores: usize,
+) -> Result<Option<Vec<f64>>> {
+    Ok(match (initial, &data.best_per_instance_count) {
+        (Some(portfolio), _) => {
+            Some(initial_copies(portfolio, data, num_cores)?)
+        }
+        (None, Some(counts)) => get_b_start(
+            counts,
+            &data.algorithms,
+            data.num_instances,
+            num_cores,
+        )
+        .ok(),
+        (None, None) => Some(
+            greedy_solve(data, num_cores)
+                .resource_assignments
+                .into_iter()
+                .map(|(_, copies)| copies)
+                .collect_vec(),
+        ),
+    })
+}
+
 fn get_b_start(
     counts: &ndarray::Array1<f64>,
     algorithms: &ndarray::Array1<Algorithm>,
@@ -0,0 +1,181 @@
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+
+use super::*;
+
+/// Start solving in the background and iterate over the improving portfolios as they are found.
+///
+/// The solver runs [`solve_with_progress`] on its own thread, the returned [`AnytimeSolve`] yields
+/// every incumbent (including a portfolio [refined](OptimizationResult::refined) by the local
+/// search) and ends once the solver is optimal or runs into the timeout. This way, a front-end
+/// can display or even start executing the current best portfolio while the solver continues.
+///
+/// Dropping the iterator [cancels](CancellationToken) the solver, a token in the `options` is
+/// cancelled as well.
+pub fn solve_anytime(
+    data: Arc<Data>,
+    num_cores: usize,
+    options: SolverOptions,
+    initial: Option<Portfolio>,
+) -> AnytimeSolve {
+    let (events, handle, cancellation) =
+        spawn(data, num_cores, options, initial);
+    AnytimeSolve {
+        events,
+        handle: Some(handle),
+        cancellation,
+    }
+}
+
+/// Start solving in the background and return a handle to poll, abort or join the solver.
+///
+/// Like [`solve_anytime`], the solver runs [`solve_with_progress`] on its own thread, but the
+/// [`SolveHandle`] never blocks except for [`join`](SolveHandle::join), e.g. for the event loop
+/// of a GUI or a service that checks on the solver periodically.
+///
+/// Dropping the handle [cancels](CancellationToken) the solver, a token in the `options` is
+/// cancelled as well.
+pub fn spawn_solve(
+    data: Arc<Data>,
+    num_cores: usize,
+    options: SolverOptions,
+    initial: Option<Portfolio>,
+) -> SolveHandle {
+    let (events, handle, cancellation) =
+        spawn(data, num_cores, options, initial);
+    SolveHandle {
+        events,
+        handle: Some(handle),
+        cancellation,
+        incumbent: None,
+    }
+}
+
+/// Run [`solve_with_progress`] on a new thread, returns the receiver of its events, its handle
+/// and the token that cancels it
+fn spawn(
+    data: Arc<Data>,
+    num_cores: usize,
+    mut options: SolverOptions,
+    initial: Option<Portfolio>,
+) -> (
+    mpsc::Receiver<ProgressEvent>,
+    JoinHandle<Result<OptimizationResult>>,
+    CancellationToken,
+) {
+    let cancellation = options
+        .cancellation
+        .get_or_insert_with(CancellationToken::new)
+        .clone();
+    let (sender, receiver) = mpsc::channel();
+    let handle = std::thread::spawn(move || {
+        solve_with_progress(
+            &data,
+            num_cores,
+            &options,
+            initial.as_ref(),
+            |event| {
+                // the receiver may be gone, the solver is cancelled then anyway
+                sender.send(event).ok();
+            },
+        )
+    });
+    (receiver, handle, cancellation)
+}
+
+/// Wait for the solver thread and return its result
+fn join(
+    handle: &mut Option<JoinHandle<Result<OptimizationResult>>>,
+) -> Result<OptimizationResult> {
+    handle
+        .take()
+        .context("the solver was already joined")?
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// Iterator over the improving portfolios of a running solver, see [`solve_anytime`]
+pub struct AnytimeSolve {
+    events: mpsc::Receiver<ProgressEvent>,
+    handle: Option<JoinHandle<Result<OptimizationResult>>>,
+    cancellation: CancellationToken,
+}
+
+impl AnytimeSolve {
+    /// Stop the solver, the iterator ends with the best portfolio found so far
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Wait for the solver to finish and return its result
+    ///
+    /// Incumbents that were not consumed by the iterator are part of the
+    /// [`history`](OptimizationResult::history).
+    pub fn result(mut self) -> Result<OptimizationResult> {
+        join(&mut self.handle)
+    }
+}
+
+impl Iterator for AnytimeSolve {
+    type Item = Incumbent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.events.recv().ok()? {
+            ProgressEvent::Incumbent(incumbent)
+            | ProgressEvent::Refined(incumbent)
+            | ProgressEvent::Pruned(incumbent) => Some(incumbent),
+        }
+    }
+}
+
+impl Drop for AnytimeSolve {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Handle of a solver running in the background, see [`spawn_solve`]
+pub struct SolveHandle {
+    events: mpsc::Receiver<ProgressEvent>,
+    handle: Option<JoinHandle<Result<OptimizationResult>>>,
+    cancellation: CancellationToken,
+    incumbent: Option<Incumbent>,
+}
+
+impl SolveHandle {
+    /// The best portfolio found so far without waiting for the solver, `None` if it has not found
+    /// any portfolio yet
+    pub fn poll_incumbent(&mut self) -> Option<&Incumbent> {
+        for event in self.events.try_iter() {
+            match event {
+                ProgressEvent::Incumbent(incumbent)
+                | ProgressEvent::Refined(incumbent)
+                | ProgressEvent::Pruned(incumbent) => {
+                    self.incumbent = Some(incumbent)
+                }
+            }
+        }
+        self.incumbent.as_ref()
+    }
+
+    /// Whether the solver has finished, [`join`](SolveHandle::join) does not block then
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+
+    /// Stop the solver, it finishes with the best portfolio found so far
+    pub fn abort(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// Wait for the solver to finish and return its result
+    pub fn join(mut self) -> Result<OptimizationResult> {
+        join(&mut self.handle)
+    }
+}
+
+impl Drop for SolveHandle {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
@@ -0,0 +1,414 @@
+use ndarray::ArrayView2;
+
+use super::*;
+
+/// Number of cells (instances times algorithms times repetitions) above which
+/// [`Decomposition::Auto`] solves the model with the Benders decomposition
+pub const BENDERS_CELLS: usize = 10_000_000;
+
+/// Whether the Gurobi backend solves the model of `data` with the Benders decomposition
+///
+/// [`Decomposition::Auto`] only decomposes large models with an objective that sums a score per
+/// instance and without checkpoints or a solution pool, which the decomposition does not support.
+pub(super) fn applies(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> bool {
+    match options.decomposition {
+        Decomposition::Monolithic => false,
+        Decomposition::Benders => true,
+        Decomposition::Auto => {
+            data.num_instances * data.num_algorithms * num_cores
+                > BENDERS_CELLS
+                && summing(&options.objective)
+                && options.checkpoint.is_none()
+                && options.pool_size.is_none()
+        }
+    }
+}
+
+/// Whether the `objective` is a sum of [scores](ObjectiveFunction::score) per instance
+pub(super) fn summing(objective: &Objective) -> bool {
+    !matches!(
+        objective,
+        Objective::WorstCase
+            | Objective::Cvar { .. }
+            | Objective::MeanVariance { .. }
+    )
+}
+
+/// Score of every cell of the data for the objective of the `options`, infinite for cells that
+/// instances must not be assigned to
+pub(super) fn cell_scores(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<Array3<f64>> {
+    let objective = &options.objective;
+    anyhow::ensure!(
+        summing(objective),
+        "The {objective:?} objective is not supported by the Benders decomposition"
+    );
+    let custom = match objective {
+        Objective::Custom(name) => {
+            Some(objectives::get(name).with_context(|| {
+                format!("No objective registered as {name}")
+            })?)
+        }
+        _ => None,
+    };
+    let e_min = &data.expected_best_quality;
+    let mut scores = Array3::from_shape_fn(
+        (data.num_instances, data.num_algorithms, num_cores),
+        |(i, j, k)| {
+            let ratio = e_min[(i, j, k)] / data.best_per_instance[i];
+            match &custom {
+                Some(custom) => custom.score(data, i, ratio),
+                None => objective.score(ratio),
+            }
+        },
+    );
+    for cell in forbidden_cells(data, options) {
+        scores[cell] = f64::INFINITY;
+    }
+    Ok(scores)
+}
+
+/// Score of the best selected cell of an instance and the terms `(cell, v - s)` of its Benders cut
+/// `theta >= v - sum (v - s) b` over the cells with a better score `s` than the best selected
+/// score `v`, `None` if no allowed cell is selected
+///
+/// The `scores` of the cells of the instance and the `selected` cells are in the order of `b`.
+fn optimality_cut(
+    scores: ArrayView2<f64>,
+    selected: &[bool],
+) -> Option<(f64, Vec<(usize, f64)>)> {
+    let value = scores
+        .iter()
+        .zip(selected)
+        .filter(|(_, &selected)| selected)
+        .map(|(&score, _)| score)
+        .fold(f64::INFINITY, f64::min);
+    if !value.is_finite() {
+        return None;
+    }
+    let terms = scores
+        .iter()
+        .enumerate()
+        .filter(|(_, &score)| score < value)
+        .map(|(cell, &score)| (cell, value - score))
+        .collect_vec();
+    Some((value, terms))
+}
+
+/// Create a portfolio with the Benders decomposition of the portfolio model, see
+/// [`Decomposition::Benders`]
+///
+/// The master model only selects the algorithms and their numbers of repetitions (`b`) and bounds
+/// the score of every instance `i` from below by a variable `theta_i`. For a selection, the
+/// subproblem of an instance assigns it to the selected cell with the best score `v_i`. Whenever
+/// `theta_i` underestimates `v_i` at a solution of the master, the lazy optimality cut
+/// `theta_i >= v_i - sum_jk max(0, v_i - s_ijk) b_jk` is added. It is tight at the selection and
+/// valid for every other selection, as selecting a better cell lowers the bound to its score. The
+/// master has `O(n k + m)` variables instead of the `O(m n k)` assignment variables of the
+/// monolithic model, so it still builds for tens of thousands of instances.
+///
+/// Only objectives that sum a score per instance are supported, the solution pool and
+/// checkpoints are not.
+pub(super) fn solve(
+    env: &grb::Env,
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+    progress: &mut dyn FnMut(ProgressEvent),
+) -> Result<OptimizationResult> {
+    let start = std::time::Instant::now();
+    check_quality_guarantee(data, num_cores, options)?;
+    check_chance_constraint(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    let scores = cell_scores(data, num_cores, options)?;
+    if options.checkpoint.is_some() {
+        warn!("Checkpoints are not supported by the Benders decomposition");
+    }
+    if options.pool_size.is_some() {
+        warn!(
+            "The solution pool is not supported by the Benders decomposition"
+        );
+    }
+    #[cfg(feature = "server")]
+    let _job = crate::metrics::JobGuard::start();
+    let mut model = Model::with_env("benders_master", env)?;
+    set_parameters(&mut model, options)?;
+    model.set_param(param::LazyConstraints, 1)?;
+    let n = data.num_algorithms;
+
+    let pruned = pruned_cells(data, num_cores, options);
+    let (b, cores) =
+        add_selection_model(&mut model, data, num_cores, pruned.as_ref(), "")?;
+    let b_flat = b.iter().copied().collect_vec();
+    let theta = scores
+        .outer_iter()
+        .enumerate()
+        .map(|(i, scores)| {
+            let lower = scores.iter().copied().fold(f64::INFINITY, f64::min);
+            let lower = match lower.is_finite() {
+                true => lower,
+                false => 0.0,
+            };
+            model.add_var(
+                format!("theta_{i}").as_str(),
+                Continuous,
+                0.0,
+                lower,
+                f64::INFINITY,
+                [],
+            )
+        })
+        .collect::<grb::Result<Vec<_>>>()?;
+    let allowed = |i: usize| {
+        b_flat
+            .iter()
+            .zip(scores.index_axis(Axis(0), i))
+            .filter(|(_, score)| score.is_finite())
+            .map(|(&var, _)| var)
+            .collect_vec()
+    };
+    model.add_constr("selected", c!(b_flat.iter().grb_sum() >= 1))?;
+    if !forbidden_cells(data, options).is_empty() {
+        for i in 0..data.num_instances {
+            model.add_constr(
+                format!("imputed_{i}").as_str(),
+                c!(allowed(i).into_iter().grb_sum() >= 1),
+            )?;
+        }
+    }
+    if let Some(guarantee) = &options.quality_guarantee {
+        let e_min = &data.expected_best_quality;
+        for i in guarantee_instances(guarantee, data.num_instances) {
+            let good = b
+                .indexed_iter()
+                .filter(|((j, k), _)| {
+                    scores[(i, *j, *k)].is_finite()
+                        && e_min[(i, *j, *k)]
+                            <= guarantee.factor * data.best_per_instance[i]
+                })
+                .map(|(_, &var)| var)
+                .grb_sum();
+            model.add_constr(
+                format!("guarantee_{i}").as_str(),
+                c!(good >= 1),
+            )?;
+        }
+    }
+    add_chance_constraints(&mut model, &b, options, "")?;
+    add_selection_constraints(&mut model, &b, &cores, data, options, "")?;
+    add_budgets(&mut model, &b, data, options, "")?;
+    add_symmetry_breaking(&mut model, &b, data, options, "")?;
+    let objective_function = theta.iter().grb_sum()
+        + option_penalties(
+            &mut model, &b, &cores, data, num_cores, options, "",
+        )?;
+    let cut = |i: usize, value: f64, terms: &[(usize, f64)]| {
+        let relaxed = terms
+            .iter()
+            .map(|&(cell, coefficient)| coefficient * b_flat[cell])
+            .grb_sum();
+        c!(theta[i] + relaxed >= value)
+    };
+
+    let initial_portfolio = match initial_assignment(initial, data, num_cores)?
+    {
+        Some(copies) => {
+            // an equivalent start that satisfies the symmetry breaking constraints
+            let start = selection(
+                &symmetry_canonical_copies(
+                    &copies,
+                    &interchangeable_algorithms(data, options),
+                ),
+                num_cores,
+            );
+            model.set_obj_attr_batch(
+                attr::Start,
+                b_flat.iter().copied().zip(start.iter().copied()),
+            )?;
+            // the cuts of the initial portfolio make it a feasible start
+            let selected = start.iter().map(|x| *x > 0.5).collect_vec();
+            for (i, scores) in scores.outer_iter().enumerate() {
+                if let Some((value, terms)) = optimality_cut(scores, &selected)
+                {
+                    model.add_constr(
+                        format!("cut_{i}").as_str(),
+                        cut(i, value, &terms),
+                    )?;
+                }
+            }
+            let initial_portfolio = postprocess_solution(
+                selection(&copies, num_cores),
+                n,
+                num_cores,
+                &data.algorithms,
+                "initial_portfolio",
+                false,
+            );
+            info!("Initial portfolio:\n{initial_portfolio}");
+            Some(initial_portfolio)
+        }
+        None => {
+            info!("No initial portfolio provided");
+            None
+        }
+    };
+
+    let validator = validation::Validator::new(data, options)?;
+    let mut history = Vec::new();
+    let mut num_cuts = 0;
+    let mut callback = |w: Where| {
+        if options.is_cancelled() {
+            terminate(&w);
+        }
+        if let Where::MIPSol(ctx) = w {
+            let solution = ctx.get_solution(b_flat.iter().chain(&theta))?;
+            let (sol_b, sol_theta) = solution.split_at(b_flat.len());
+            let selected = sol_b.iter().map(|x| *x > 0.5).collect_vec();
+            let mut cut_off = false;
+            for (i, &estimate) in sol_theta.iter().enumerate() {
+                let Some((value, terms)) =
+                    optimality_cut(scores.index_axis(Axis(0), i), &selected)
+                else {
+                    continue;
+                };
+                if estimate < value - 1e-6 * value.abs().max(1.0) {
+                    ctx.add_lazy(cut(i, value, &terms))?;
+                    num_cuts += 1;
+                    cut_off = true;
+                }
+            }
+            // the master underestimated the objective of the solution
+            if cut_off {
+                return Ok(());
+            }
+            let obj = ctx.obj()?;
+            let obj_bnd = ctx.obj_bnd()?;
+            let opt = (obj / obj_bnd).abs() < f64::EPSILON;
+            let res = postprocess_solution(
+                sol_b.to_vec(),
+                n,
+                num_cores,
+                &data.algorithms,
+                "intermediate_portfolio",
+                opt,
+            );
+            debug!("{res}");
+            debug!("Lower bound: {obj_bnd}\nCurrent objective value: {obj}");
+            #[cfg(feature = "server")]
+            crate::metrics::SOLVER_GAP.set((obj - obj_bnd).abs() / obj.abs());
+            let incumbent = Incumbent {
+                simulated: validator
+                    .as_ref()
+                    .map(|validator| validator.validate(&res))
+                    .transpose()?,
+                portfolio: res,
+                objective: obj,
+                bound: obj_bnd,
+                time: ctx.runtime()?,
+            };
+            progress(ProgressEvent::Incumbent(incumbent.clone()));
+            history.push(incumbent);
+        }
+        Ok(())
+    };
+
+    model.set_objective(objective_function, ModelSense::Minimize)?;
+    model
+        .optimize_with_callback(&mut callback)
+        .map_err(solve_error)?;
+    info!("The Benders decomposition added {num_cuts} cuts");
+    check_solve_status(&model, options)?;
+    if model.status()? == Status::Infeasible {
+        return Err(compute_infeasibility(
+            &mut model,
+            &[data],
+            num_cores,
+            options,
+        )?
+        .into());
+    }
+    if model.status()? == Status::Interrupted
+        && model.get_attr(attr::SolCount)? == 0
+    {
+        anyhow::bail!("The solver was cancelled before it found a portfolio");
+    }
+    let solution =
+        model.get_obj_attr_batch(attr::X, b_flat.iter().copied())?;
+    let gap = model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+    #[cfg(feature = "server")]
+    crate::metrics::SOLVER_GAP.set(gap);
+    let final_portfolio = postprocess_solution(
+        solution,
+        n,
+        num_cores,
+        &data.algorithms,
+        "final_portfolio",
+        gap.abs() < f64::EPSILON,
+    );
+    let stats = solve_stats(&model, start, history.len(), options)?;
+    Ok(OptimizationResult {
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: initial_portfolio
+            .as_ref()
+            .map(|portfolio| gap_closed(data, portfolio)),
+        initial_portfolio,
+        final_portfolio,
+        gap,
+        history,
+        refined: false,
+        pool: Vec::new(),
+        stats,
+        instance_qualities: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimality_cut() {
+        let scores = ndarray::arr2(&[[1.5, 1.2], [1.0, f64::INFINITY]]);
+        let (value, terms) =
+            optimality_cut(scores.view(), &[true, false, false, false])
+                .unwrap();
+        assert_eq!(value, 1.5);
+        assert_eq!(terms, vec![(1, 1.5 - 1.2), (2, 0.5)]);
+        // the best cell is selected, nothing is better
+        let (value, terms) =
+            optimality_cut(scores.view(), &[true, false, true, false])
+                .unwrap();
+        assert_eq!((value, terms.len()), (1.0, 0));
+        // only a forbidden cell is selected
+        assert!(optimality_cut(scores.view(), &[false, false, false, true])
+            .is_none());
+    }
+
+    #[test]
+    fn test_applies() {
+        let algorithms = [Algorithm::new("algo1".into(), 1)];
+        let data = Data::new(&algorithms, &[1.0], None, &[1.0], 1).unwrap();
+        let options = |decomposition| SolverOptions {
+            decomposition,
+            ..Default::default()
+        };
+        assert!(!applies(&data, 1, &options(Decomposition::Auto)));
+        assert!(applies(&data, 1, &options(Decomposition::Benders)));
+        assert!(!applies(&data, 1, &options(Decomposition::Monolithic)));
+        let worst_case = SolverOptions {
+            objective: Objective::WorstCase,
+            ..options(Decomposition::Benders)
+        };
+        assert!(cell_scores(&data, 1, &worst_case).is_err());
+        let scores = cell_scores(&data, 1, &options(Decomposition::Benders));
+        assert_eq!(scores.unwrap()[(0, 0, 0)], 1.0);
+    }
+}
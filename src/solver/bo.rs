@@ -0,0 +1,320 @@
+use std::{collections::HashSet, f64::consts::PI, time::Instant};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use log::debug;
+use ndarray::{Array1, Array2};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use super::racing::fill_copies;
+use super::{gap_closed, objective, portfolio_from_copies};
+use crate::csv_parser::Data;
+use crate::datastructures::*;
+
+#[derive(Debug, Clone)]
+/// Options for [`solve`]
+pub struct BoOptions {
+    /// Stop after this many seconds
+    pub timeout: Timeout,
+    /// Stop after evaluating this many allocations
+    pub max_evaluations: usize,
+    /// Number of random allocations evaluated before the surrogate model is used
+    pub initial_samples: usize,
+    /// Number of allocations the acquisition function is evaluated on in every iteration, half of
+    /// them random and half of them neighbors of the incumbent
+    pub num_candidates: usize,
+    /// Length scale of the squared exponential kernel on the fractions of cores per algorithm
+    pub length_scale: f64,
+    /// Seed for the random allocations
+    pub seed: u64,
+}
+
+impl Default for BoOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Timeout::default(),
+            max_evaluations: 200,
+            initial_samples: 20,
+            num_candidates: 256,
+            length_scale: 0.2,
+            seed: 42,
+        }
+    }
+}
+
+/// Create a portfolio using Bayesian optimization over the core allocations, without any MIP.
+///
+/// The [`objective`] is treated as a black box: a Gaussian process on the fractions of cores
+/// assigned to each algorithm models it from the allocations evaluated so far, and the allocation
+/// with the highest expected improvement among random allocations and neighbors of the incumbent
+/// is evaluated next. Every improvement is recorded in the history, so the search can be stopped
+/// at any time. The gap is relative to the trivial bound of one per instance.
+pub fn solve(
+    data: &Data,
+    num_cores: usize,
+    options: &BoOptions,
+) -> Result<OptimizationResult> {
+    let threads = data.algorithms.iter().map(|a| a.num_threads).collect_vec();
+    let fitting = (0..data.num_algorithms)
+        .filter(|&j| threads[j] as usize <= num_cores)
+        .collect_vec();
+    anyhow::ensure!(
+        !fitting.is_empty(),
+        "No algorithm fits on {num_cores} cores"
+    );
+    let encode = |copies: &[f64]| {
+        Array1::from_iter(
+            copies
+                .iter()
+                .zip(&threads)
+                .map(|(c, &t)| c * t as f64 / num_cores as f64),
+        )
+    };
+    let random = |rng: &mut ChaCha8Rng| {
+        let subset_size = rng.gen_range(1..=fitting.len());
+        let subset = fitting
+            .choose_multiple(rng, subset_size)
+            .copied()
+            .collect_vec();
+        let mut copies = vec![0.0; data.num_algorithms];
+        fill_copies(&mut copies, &subset, &threads, num_cores as u32, rng);
+        copies
+    };
+    let neighbor = |copies: &[f64], rng: &mut ChaCha8Rng| {
+        let mut copies = copies.to_vec();
+        let selected = (0..copies.len())
+            .filter(|&j| copies[j] >= 1.0)
+            .collect_vec();
+        if let Some(&j) = selected.choose(rng) {
+            copies[j] -= 1.0;
+        }
+        let added = *fitting.choose(rng).unwrap();
+        fill_copies(&mut copies, &[added], &threads, num_cores as u32, rng);
+        fill_copies(&mut copies, &fitting, &threads, num_cores as u32, rng);
+        copies
+    };
+
+    let bound = data.num_instances as f64;
+    let start = Instant::now();
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let mut evaluated = HashSet::new();
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+    let mut incumbent: Option<(Vec<f64>, f64)> = None;
+    let mut history = Vec::new();
+    while ys.len() < options.max_evaluations
+        && start.elapsed().as_secs_f64() < options.timeout.0
+    {
+        let candidates = (0..options.num_candidates)
+            .map(|c| match (&incumbent, c % 2) {
+                (Some((best, _)), 1) => neighbor(best, &mut rng),
+                _ => random(&mut rng),
+            })
+            .filter(|copies| !evaluated.contains(&key(copies)))
+            .unique_by(|copies| key(copies))
+            .collect_vec();
+        let next = match ys.len() < options.initial_samples.max(1) {
+            true => candidates.into_iter().next(),
+            false => {
+                let model =
+                    GaussianProcess::fit(&xs, &ys, options.length_scale);
+                let best = incumbent.as_ref().map_or(f64::INFINITY, |i| i.1);
+                candidates
+                    .into_iter()
+                    .map(|copies| {
+                        let ei =
+                            model.expected_improvement(&encode(&copies), best);
+                        (copies, ei)
+                    })
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(copies, _)| copies)
+            }
+        };
+        let Some(next) = next else {
+            debug!("No unevaluated allocations found");
+            break;
+        };
+        let value = objective(data, &next);
+        evaluated.insert(key(&next));
+        xs.push(encode(&next));
+        ys.push(value);
+        if incumbent.as_ref().map_or(true, |(_, best)| value < *best) {
+            debug!("New incumbent with objective {value}");
+            history.push(Incumbent {
+                portfolio: portfolio_from_copies(
+                    data,
+                    &next,
+                    "intermediate_portfolio",
+                ),
+                objective: value,
+                bound,
+                time: start.elapsed().as_secs_f64(),
+                simulated: None,
+            });
+            incumbent = Some((next, value));
+        }
+    }
+    let (best, value) = incumbent.context("No allocation was evaluated")?;
+    let final_portfolio =
+        portfolio_from_copies(data, &best, "final_portfolio");
+    Ok(OptimizationResult {
+        initial_portfolio: None,
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: None,
+        final_portfolio,
+        gap: (value - bound) / value,
+        stats: SolveStats {
+            wall_time: start.elapsed().as_secs_f64(),
+            node_count: None,
+            objective: value,
+            bound: Some(bound),
+            num_incumbents: history.len(),
+            termination: match start.elapsed().as_secs_f64()
+                >= options.timeout.0
+            {
+                true => Termination::Timeout,
+                false => Termination::Limit,
+            },
+            numeric_focus: None,
+        },
+        history,
+        refined: false,
+        pool: Vec::new(),
+        instance_qualities: None,
+    })
+}
+
+fn key(copies: &[f64]) -> Vec<u32> {
+    copies.iter().map(|&c| c as u32).collect_vec()
+}
+
+/// Gaussian process regression with a squared exponential kernel on standardized observations
+struct GaussianProcess {
+    xs: Vec<Array1<f64>>,
+    cholesky: Array2<f64>,
+    alpha: Array1<f64>,
+    mean: f64,
+    scale: f64,
+    length_scale: f64,
+}
+
+impl GaussianProcess {
+    fn fit(xs: &[Array1<f64>], ys: &[f64], length_scale: f64) -> Self {
+        let n = ys.len();
+        let mean = ys.iter().sum::<f64>() / n as f64;
+        let variance =
+            ys.iter().map(|y| (y - mean).powi(2)).sum::<f64>() / n as f64;
+        let scale = match variance.sqrt() {
+            s if s > f64::EPSILON => s,
+            _ => 1.0,
+        };
+        let kernel = Array2::from_shape_fn((n, n), |(i, j)| {
+            let noise = if i == j { 1e-6 } else { 0.0 };
+            kernel(&xs[i], &xs[j], length_scale) + noise
+        });
+        let cholesky = cholesky(&kernel);
+        let standardized =
+            Array1::from_iter(ys.iter().map(|y| (y - mean) / scale));
+        let alpha =
+            solve_upper(&cholesky, &solve_lower(&cholesky, &standardized));
+        Self {
+            xs: xs.to_vec(),
+            cholesky,
+            alpha,
+            mean,
+            scale,
+            length_scale,
+        }
+    }
+
+    /// Posterior mean and standard deviation at `x`
+    fn predict(&self, x: &Array1<f64>) -> (f64, f64) {
+        let k = Array1::from_iter(
+            self.xs.iter().map(|xi| kernel(xi, x, self.length_scale)),
+        );
+        let v = solve_lower(&self.cholesky, &k);
+        let variance = (1.0 - v.dot(&v)).max(1e-12);
+        (
+            self.mean + self.scale * k.dot(&self.alpha),
+            self.scale * variance.sqrt(),
+        )
+    }
+
+    /// Expected improvement over `best` when minimizing
+    fn expected_improvement(&self, x: &Array1<f64>, best: f64) -> f64 {
+        let (mean, std) = self.predict(x);
+        let z = (best - mean) / std;
+        let pdf = (-0.5 * z * z).exp() / (2.0 * PI).sqrt();
+        let cdf = 0.5 * erfc(-z / std::f64::consts::SQRT_2);
+        (best - mean) * cdf + std * pdf
+    }
+}
+
+fn kernel(a: &Array1<f64>, b: &Array1<f64>, length_scale: f64) -> f64 {
+    let distance = (a - b).mapv(|d| d * d).sum();
+    (-distance / (2.0 * length_scale * length_scale)).exp()
+}
+
+/// Lower triangular `L` with `L * L^T = a`
+fn cholesky(a: &Array2<f64>) -> Array2<f64> {
+    let n = a.nrows();
+    let mut l = Array2::zeros((n, n));
+    for i in 0..n {
+        for j in 0..=i {
+            let s = (0..j).map(|k| l[(i, k)] * l[(j, k)]).sum::<f64>();
+            l[(i, j)] = match i == j {
+                true => (a[(i, i)] - s).max(1e-12).sqrt(),
+                false => (a[(i, j)] - s) / l[(j, j)],
+            };
+        }
+    }
+    l
+}
+
+/// Solve `l * x = b` for lower triangular `l`
+fn solve_lower(l: &Array2<f64>, b: &Array1<f64>) -> Array1<f64> {
+    let mut x = Array1::zeros(b.len());
+    for i in 0..b.len() {
+        let s = (0..i).map(|k| l[(i, k)] * x[k]).sum::<f64>();
+        x[i] = (b[i] - s) / l[(i, i)];
+    }
+    x
+}
+
+/// Solve `l^T * x = b` for lower triangular `l`
+fn solve_upper(l: &Array2<f64>, b: &Array1<f64>) -> Array1<f64> {
+    let mut x = Array1::zeros(b.len());
+    for i in (0..b.len()).rev() {
+        let s = (i + 1..b.len()).map(|k| l[(k, i)] * x[k]).sum::<f64>();
+        x[i] = (b[i] - s) / l[(i, i)];
+    }
+    x
+}
+
+/// Complementary error function (Chebyshev approximation, relative error below 1.2e-7)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let coefficients = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ];
+    let polynomial = coefficients
+        .iter()
+        .rev()
+        .fold(0.0, |acc, coefficient| acc * t + coefficient);
+    let r = t * (-z * z + polynomial).exp();
+    match x >= 0.0 {
+        true => r,
+        false => 2.0 - r,
+    }
+}
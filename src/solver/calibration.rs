@@ -0,0 +1,164 @@
+use super::*;
+use crate::csv_parser::{self, DataOptions};
+use polars::prelude::{col, LazyFrame};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Fast solver of every slowdown ratio of [`calibrate_slowdown_ratio`]
+pub enum CalibrationSolver {
+    /// The [`greedy_solve`] portfolio, without a MIP solver
+    #[default]
+    Greedy,
+    /// The rounded LP relaxation of the Gurobi model, see [`relaxation::solve`]
+    Relaxation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A slowdown ratio with the quality and speed of its portfolio, see
+/// [`calibrate_slowdown_ratio`]
+pub struct SlowdownPoint {
+    /// The slowdown ratio the algorithms were filtered with
+    pub slowdown_ratio: f64,
+    /// Number of algorithms within the slowdown ratio
+    pub num_algorithms: usize,
+    /// The portfolio of the algorithms within the slowdown ratio
+    pub portfolio: Portfolio,
+    /// Sum of the quality ratios of the portfolio to the best qualities of all algorithms within
+    /// the largest slowdown ratio, comparable between the points
+    pub objective: f64,
+    /// Expected running time of the portfolio, see [`expected_runtime`]
+    pub runtime: f64,
+    /// Whether the portfolio is on the quality/speed frontier, i.e. no other point has a portfolio
+    /// that is better in one and at least as good in the other
+    pub on_frontier: bool,
+}
+
+/// `num_points` slowdown ratios from `min` to `max`, evenly spaced on a logarithmic scale, see
+/// [`calibrate_slowdown_ratio`]
+pub fn slowdown_ratio_grid(min: f64, max: f64, num_points: usize) -> Vec<f64> {
+    match num_points {
+        0 => Vec::new(),
+        1 => vec![max],
+        _ => (0..num_points)
+            .map(|p| {
+                let t = p as f64 / (num_points - 1) as f64;
+                min * (max / min).powf(t)
+            })
+            .collect_vec(),
+    }
+}
+
+/// Trade the quality of the portfolio off against its speed by solving the normalized data frame
+/// `df` for every slowdown ratio of `ratios` (e.g. a [`slowdown_ratio_grid`]).
+///
+/// A small slowdown ratio keeps only the fast algorithms (see
+/// [`Data::from_normalized_dataframe_with_options`]), so the portfolio is fast but may be far from
+/// the best quality. Every ratio is solved quickly by the `solver`, the `options` are only used by
+/// the [`CalibrationSolver::Relaxation`]. The portfolios are evaluated on the data of the largest
+/// ratio, so their objectives and [expected running times](expected_runtime) are comparable.
+/// Ratios that keep no algorithm are skipped, the points are sorted by their slowdown ratio.
+pub fn calibrate_slowdown_ratio(
+    df: LazyFrame,
+    num_cores: u32,
+    ratios: &[f64],
+    data_options: &DataOptions,
+    solver: CalibrationSolver,
+    options: &SolverOptions,
+) -> Result<Vec<SlowdownPoint>> {
+    anyhow::ensure!(
+        ratios.iter().all(|&ratio| ratio > 0.0),
+        "The slowdown ratios must be positive"
+    );
+    let ratios = ratios
+        .iter()
+        .copied()
+        .sorted_by(|a, b| a.total_cmp(b))
+        .dedup()
+        .collect_vec();
+    let Some(&largest) = ratios.last() else {
+        anyhow::bail!("No slowdown ratios provided");
+    };
+    anyhow::ensure!(
+        num_kept(df.clone(), largest)? > 0,
+        "No algorithm is within the largest slowdown ratio {largest}"
+    );
+    let data_options = DataOptions {
+        dump_normalized: None,
+        ..data_options.clone()
+    };
+    let reference = Data::from_normalized_dataframe_with_options(
+        df.clone(),
+        num_cores,
+        largest,
+        &data_options,
+    )?;
+    let times = csv_parser::expected_times(df.clone(), &reference)?;
+    let mut points = Vec::new();
+    for ratio in ratios {
+        if num_kept(df.clone(), ratio)? == 0 {
+            info!("No algorithm is within the slowdown ratio {ratio}");
+            continue;
+        }
+        let data = Data::from_normalized_dataframe_with_options(
+            df.clone(),
+            num_cores,
+            ratio,
+            &data_options,
+        )?;
+        let portfolio = match solver {
+            CalibrationSolver::Greedy => {
+                greedy_solve(&data, num_cores as usize)
+            }
+            CalibrationSolver::Relaxation => {
+                relaxation::solve(
+                    &data,
+                    num_cores as usize,
+                    options,
+                    &Default::default(),
+                )?
+                .result
+                .final_portfolio
+            }
+        };
+        let portfolio = Portfolio {
+            name: format!("slowdown_portfolio_{}", points.len()),
+            ..portfolio
+        };
+        points.push(SlowdownPoint {
+            slowdown_ratio: ratio,
+            num_algorithms: data.num_algorithms,
+            objective: objective(
+                &reference,
+                &portfolio_copies(&portfolio, &reference),
+            ),
+            runtime: expected_runtime(&reference, &times, &portfolio),
+            portfolio,
+            on_frontier: false,
+        });
+    }
+    let dominated = |point: &SlowdownPoint| {
+        points.iter().any(|other| {
+            other.objective <= point.objective
+                && other.runtime <= point.runtime
+                && (other.objective < point.objective
+                    || other.runtime < point.runtime)
+        })
+    };
+    let on_frontier =
+        points.iter().map(|point| !dominated(point)).collect_vec();
+    for (point, on_frontier) in points.iter_mut().zip(on_frontier) {
+        point.on_frontier = on_frontier;
+    }
+    Ok(points)
+}
+
+/// Number of algorithms with valid runs in `df` that are within the `slowdown_ratio`, see
+/// [`csv_parser::gmean_runtimes`]
+fn num_kept(df: LazyFrame, slowdown_ratio: f64) -> Result<usize> {
+    let runtimes =
+        csv_parser::gmean_runtimes(df.filter(col("valid")), slowdown_ratio)?;
+    Ok(runtimes["kept"]
+        .bool()?
+        .into_no_null_iter()
+        .filter(|&kept| kept)
+        .count())
+}
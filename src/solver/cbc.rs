@@ -0,0 +1,137 @@
+use coin_cbc::{Col, Model as CbcModel, Sense};
+use std::time::Instant;
+
+use super::linear::{self, LinearProblem, LinearSolution};
+use super::*;
+
+/// Objective value CBC reports without a solution
+const NO_SOLUTION: f64 = 1e50;
+
+impl LinearProblem for CbcModel {
+    type Col = Col;
+
+    fn add_binary(&mut self, objective: f64) -> Col {
+        let col = CbcModel::add_binary(self);
+        self.set_obj_coeff(col, objective);
+        col
+    }
+
+    fn add_continuous(&mut self, objective: f64) -> Col {
+        let col = self.add_col();
+        self.set_obj_coeff(col, objective);
+        col
+    }
+
+    fn add_row(
+        &mut self,
+        lower: Option<f64>,
+        upper: Option<f64>,
+        terms: impl IntoIterator<Item = (Col, f64)>,
+    ) {
+        let row = CbcModel::add_row(self);
+        if let Some(lower) = lower {
+            self.set_row_lower(row, lower);
+        }
+        if let Some(upper) = upper {
+            self.set_row_upper(row, upper);
+        }
+        for (col, weight) in terms {
+            self.set_weight(row, col, weight);
+        }
+    }
+}
+
+/// Create a portfolio from the input data using the open-source COIN-OR CBC solver.
+///
+/// Solves the same model as [`solve`] with the same timeout and initial solution semantics, but
+/// CBC reports no intermediate solutions (the history only contains the final portfolio) and
+/// does not explain infeasible models.
+pub fn solve_cbc(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+) -> Result<OptimizationResult> {
+    check_objective(options)?;
+    check_quality_guarantee(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    #[cfg(feature = "server")]
+    let _job = crate::metrics::JobGuard::start();
+    let start = Instant::now();
+
+    let mut model = CbcModel::default();
+    model.set_obj_sense(Sense::Minimize);
+    let linear_model =
+        linear::build_model(&mut model, data, num_cores, options);
+    if !options.solver_output() {
+        model.set_log_level(0);
+    }
+    model.set_parameter("sec", &options.timeout.0.to_string());
+    if let Some(mip_gap) = options.mip_gap {
+        model.set_parameter("ratioGap", &mip_gap.to_string());
+    }
+    if let Some(node_limit) = options.node_limit {
+        model.set_parameter("maxNodes", &node_limit.to_string());
+    }
+    if let Some(threads) = options.threads {
+        model.set_parameter("threads", &threads.to_string());
+    }
+    if let Some(seed) = options.seed {
+        model.set_parameter("randomCbcSeed", &seed.to_string());
+    }
+
+    let initial = linear::initial_selection(initial, data, num_cores)?;
+    if let Some((b_start, _)) = &initial {
+        if let Some(start) =
+            linear::start_solution(&linear_model, b_start, data, num_cores)
+        {
+            let cols = model.cols().collect_vec();
+            for (col, value) in cols.into_iter().zip(start) {
+                model.set_col_initial_solution(col, value);
+            }
+        }
+    }
+
+    let solution = model.solve();
+    let raw = solution.raw();
+    if raw.is_proven_infeasible() {
+        return Err(unexplained_infeasibility());
+    }
+    let objective = raw.obj_value();
+    if objective >= NO_SOLUTION {
+        if raw.is_seconds_limit_reached() {
+            return Err(SolverError::Timeout(options.timeout.0).into());
+        }
+        anyhow::bail!("CBC found no solution");
+    }
+    let b = linear_model
+        .b
+        .iter()
+        .map(|&var| solution.col(var).round())
+        .collect_vec();
+    let bound = raw.best_possible_value();
+    // CBC reports a solve stopped at the ratioGap as optimal
+    let gap = (objective - bound).abs() / objective.abs().max(1e-10);
+    let termination = match () {
+        _ if raw.is_proven_optimal() => {
+            gap_termination(Termination::Optimal, gap, options)
+        }
+        _ if raw.is_seconds_limit_reached() => Termination::Timeout,
+        _ if raw.is_node_limit_reached() => Termination::Limit,
+        _ => Termination::Finished,
+    };
+    Ok(linear::linear_result(
+        data,
+        num_cores,
+        options,
+        start,
+        initial.map(|(_, portfolio)| portfolio),
+        LinearSolution {
+            b,
+            objective,
+            bound,
+            gap,
+            termination,
+        },
+    ))
+}
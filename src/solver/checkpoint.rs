@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::*;
+
+/// File of a checkpoint with the data and options of the model, written when the solver starts
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+/// File of a checkpoint with the incumbents and the runtime of the current run
+const STATE_FILE: &str = "state.json";
+
+/// Continue an interrupted solve from the checkpoint in `dir`, see [`SolverOptions::checkpoint`].
+///
+/// The model is rebuilt from the data and options of the checkpoint and started from the last
+/// incumbent with the remaining timeout, the resumed solve writes its checkpoints to `dir` again.
+/// The branch-and-bound tree is not part of the checkpoint, so the lower bound starts over. The
+/// history of the result contains the incumbents of all runs with the time since the first run
+/// started.
+pub fn resume(dir: impl AsRef<Path>) -> Result<OptimizationResult> {
+    let dir = dir.as_ref();
+    let Checkpoint {
+        num_cores,
+        mut options,
+        data,
+        elapsed,
+        mut history,
+    } = read_json(&dir.join(CHECKPOINT_FILE))?;
+    let state = match dir.join(STATE_FILE).exists() {
+        true => read_json(&dir.join(STATE_FILE))?,
+        false => State::default(),
+    };
+    let data = data.into_data()?;
+    history.extend(state.history);
+    options.timeout = Timeout((options.timeout.0 - state.runtime).max(0.0));
+    // the directory may have been moved since the checkpoint was written
+    if let Some(checkpoint) = &mut options.checkpoint {
+        checkpoint.dir = dir.to_path_buf();
+    }
+    info!(
+        "Resuming after {:.1}s with {} incumbents and {:.1}s left",
+        elapsed + state.runtime,
+        history.len(),
+        options.timeout.0
+    );
+    // the incumbents of the callback are not rounded
+    let initial = history.last().map(|incumbent| Portfolio {
+        resource_assignments: incumbent
+            .portfolio
+            .resource_assignments
+            .iter()
+            .map(|(algo, copies)| (algo.clone(), copies.round()))
+            .collect(),
+        ..incumbent.portfolio.clone()
+    });
+    let resumed = Resumed {
+        elapsed: elapsed + state.runtime,
+        history,
+    };
+    let result = solve_with_env(
+        &Solver::new()?.env,
+        &data,
+        num_cores,
+        &options,
+        initial.as_ref(),
+        &mut |_| (),
+        Some(resumed),
+    )?;
+    Ok(refine(&data, num_cores, &options, result, &mut |_| ()))
+}
+
+#[derive(Clone)]
+/// Previous runs of a [resumed](resume) solve
+pub(super) struct Resumed {
+    /// Seconds the previous runs took
+    pub elapsed: f64,
+    /// Incumbents of the previous runs, with the time since the first run started
+    pub history: Vec<Incumbent>,
+}
+
+/// Writes the checkpoints of a running solve
+pub(super) struct Checkpointer {
+    dir: PathBuf,
+    interval: f64,
+    last_write: Instant,
+}
+
+impl Checkpointer {
+    /// Start checkpointing a solve of `data` to the directory of the `options` (if any), the
+    /// state of a previous checkpoint in the directory is replaced
+    pub fn start(
+        data: &Data,
+        num_cores: usize,
+        options: &SolverOptions,
+        resumed: Option<&Resumed>,
+    ) -> Result<Option<Self>> {
+        let Some(CheckpointOptions { dir, interval }) = &options.checkpoint
+        else {
+            return Ok(None);
+        };
+        fs::create_dir_all(dir).with_context(|| {
+            format!(
+                "Failed to create the checkpoint directory {}",
+                dir.display()
+            )
+        })?;
+        write_json(
+            &dir.join(CHECKPOINT_FILE),
+            &Checkpoint {
+                num_cores,
+                options: options.clone(),
+                data: DataSnapshot::new(data),
+                elapsed: resumed.map_or(0.0, |r| r.elapsed),
+                history: resumed.map_or_else(Vec::new, |r| r.history.clone()),
+            },
+        )?;
+        let mut checkpointer = Self {
+            dir: dir.clone(),
+            interval: *interval,
+            last_write: Instant::now(),
+        };
+        checkpointer.write(&[], 0.0)?;
+        Ok(Some(checkpointer))
+    }
+
+    /// Write the incumbents of the current run and its runtime in seconds
+    pub fn write(
+        &mut self,
+        history: &[Incumbent],
+        runtime: f64,
+    ) -> Result<()> {
+        write_json(
+            &self.dir.join(STATE_FILE),
+            &State {
+                history: history.to_vec(),
+                runtime,
+            },
+        )?;
+        self.last_write = Instant::now();
+        Ok(())
+    }
+
+    /// Write the runtime if the interval passed since the last checkpoint
+    pub fn update(
+        &mut self,
+        history: &[Incumbent],
+        runtime: f64,
+    ) -> Result<()> {
+        match self.last_write.elapsed().as_secs_f64() < self.interval {
+            true => Ok(()),
+            false => self.write(history, runtime),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// Everything needed to rebuild the model of a solve
+struct Checkpoint {
+    num_cores: usize,
+    /// Options of the solve, the timeout is the one of the current run
+    options: SolverOptions,
+    data: DataSnapshot,
+    /// Seconds the previous runs took
+    elapsed: f64,
+    /// Incumbents of the previous runs, with the time since the first run started
+    history: Vec<Incumbent>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+/// Progress of the current run
+struct State {
+    /// Incumbents of the current run, with the time since the first run started
+    history: Vec<Incumbent>,
+    /// Seconds the current run took so far
+    runtime: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+/// Serializable [`Data`], the arrays are stored in row-major order
+struct DataSnapshot {
+    algorithms: Vec<Algorithm>,
+    best_per_instance: Vec<f64>,
+    best_per_instance_count: Option<Vec<f64>>,
+    shape: (usize, usize, usize),
+    expected_best_quality: Vec<f64>,
+    imputed: Option<Vec<bool>>,
+    #[serde(default)]
+    instances: Option<Vec<String>>,
+    sampling_seed: Option<u64>,
+}
+
+impl DataSnapshot {
+    fn new(data: &Data) -> Self {
+        Self {
+            algorithms: data.algorithms.to_vec(),
+            best_per_instance: data.best_per_instance.to_vec(),
+            best_per_instance_count: data
+                .best_per_instance_count
+                .as_ref()
+                .map(|counts| counts.to_vec()),
+            shape: data.expected_best_quality.dim(),
+            expected_best_quality: data
+                .expected_best_quality
+                .iter()
+                .copied()
+                .collect(),
+            imputed: data
+                .imputed
+                .as_ref()
+                .map(|imputed| imputed.iter().copied().collect()),
+            instances: data.instances.clone(),
+            sampling_seed: data.sampling_seed,
+        }
+    }
+
+    fn into_data(self) -> Result<Data> {
+        Ok(Data {
+            num_instances: self.shape.0,
+            num_algorithms: self.shape.1,
+            algorithms: self.algorithms.into(),
+            best_per_instance: self.best_per_instance.into(),
+            best_per_instance_count: self
+                .best_per_instance_count
+                .map(Into::into),
+            expected_best_quality: Array3::from_shape_vec(
+                self.shape,
+                self.expected_best_quality,
+            )?,
+            imputed: self
+                .imputed
+                .map(|imputed| Array3::from_shape_vec(self.shape, imputed))
+                .transpose()?,
+            instances: self.instances,
+            sampling_seed: self.sampling_seed,
+        })
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file)).with_context(|| {
+        format!("Failed to read the checkpoint {}", path.display())
+    })
+}
+
+/// Write `value` to a temporary file first, so that a job killed while writing leaves the
+/// previous checkpoint intact
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    serde_json::to_writer(
+        std::io::BufWriter::new(fs::File::create(&tmp)?),
+        value,
+    )?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint() {
+        let algorithms = vec![
+            Algorithm::new("algo1".into(), 1),
+            Algorithm::new("algo2".into(), 2),
+        ];
+        let mut data = Data::new(
+            &algorithms,
+            &[1.0, 2.0],
+            Some(&[1.0, 1.0]),
+            &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0],
+            2,
+        )
+        .unwrap();
+        data.imputed = Some(data.expected_best_quality.mapv(|q| q > 4.0));
+        data.sampling_seed = Some(42);
+        data.instances = Some(vec!["graph1".into(), "graph2".into()]);
+        let dir = std::env::temp_dir().join("portfolio_solver_checkpoint");
+        let options =
+            SolverOptions::new(Timeout(10.0)).with_checkpoint(&dir, 1.0);
+        let mut checkpointer = Checkpointer::start(&data, 2, &options, None)
+            .unwrap()
+            .unwrap();
+        let checkpoint: Checkpoint =
+            read_json(&dir.join(CHECKPOINT_FILE)).unwrap();
+        assert_eq!(checkpoint.num_cores, 2);
+        assert_eq!(checkpoint.options.checkpoint, options.checkpoint);
+        let restored = checkpoint.data.into_data().unwrap();
+        assert_eq!(restored.algorithms, data.algorithms);
+        assert_eq!(restored.best_per_instance, data.best_per_instance);
+        assert_eq!(
+            restored.best_per_instance_count,
+            data.best_per_instance_count
+        );
+        assert_eq!(restored.expected_best_quality, data.expected_best_quality);
+        assert_eq!(restored.imputed, data.imputed);
+        assert_eq!(restored.sampling_seed, Some(42));
+        assert_eq!(restored.instances, data.instances);
+        assert_eq!((restored.num_instances, restored.num_algorithms), (2, 2));
+        let incumbent = Incumbent {
+            portfolio: Portfolio {
+                name: "intermediate_portfolio".into(),
+                resource_assignments: vec![(algorithms[0].clone(), 2.0)],
+            },
+            objective: 3.0,
+            bound: 2.0,
+            time: 0.5,
+            simulated: None,
+        };
+        checkpointer
+            .write(std::slice::from_ref(&incumbent), 0.5)
+            .unwrap();
+        checkpointer.update(&[], 0.7).unwrap();
+        let state: State = read_json(&dir.join(STATE_FILE)).unwrap();
+        assert_eq!(state.history, vec![incumbent]);
+        assert_eq!(state.runtime, 0.5);
+        assert!(Checkpointer::start(
+            &data,
+            2,
+            &SolverOptions::default(),
+            None
+        )
+        .unwrap()
+        .is_none());
+    }
+}
@@ -0,0 +1,619 @@
+use std::time::Instant;
+
+use ndarray::ArrayView1;
+
+use super::*;
+
+/// Create a portfolio with column generation over the algorithms and their numbers of repetitions
+/// using the Gurobi Optimizer.
+///
+/// Every cell `(j, k)` of the data, i.e. `k + 1` copies of algorithm `j`, is a column of the
+/// master problem with its selection `b_jk` and the assignments `a_ijk` of all instances to it.
+/// The restricted master problem starts with the columns of the [`greedy_solve`] portfolio and the
+/// best column of every instance, its LP relaxation is solved and the column with the most
+/// negative reduced cost is priced in for every algorithm until no column has a negative reduced
+/// cost. The restricted master problem is then solved as a MIP and all columns whose reduced cost
+/// is below the gap between the portfolio and the LP bound are added before it is solved again.
+/// No other column can be part of a better portfolio, so the portfolio is optimal if the solves
+/// finish within the timeout, which applies to the whole column generation. Only a fraction of
+/// the `n k` columns is usually part of the model, which suits data with many algorithms and
+/// thread configurations.
+///
+/// Only objectives that sum a score per instance are supported, as are the quality guarantee,
+/// the included and excluded algorithms, forbidden imputed cells and the budgets. Core bounds, the
+/// chance constraint and the penalties are not supported, the solution pool and checkpoints are
+/// ignored.
+pub fn solve_colgen(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<OptimizationResult> {
+    Solver::for_options(options)?.solve_colgen(data, num_cores, options)
+}
+
+impl Solver {
+    /// Create a portfolio with column generation, see [`solve_colgen`]
+    pub fn solve_colgen(
+        &self,
+        data: &Data,
+        num_cores: usize,
+        options: &SolverOptions,
+    ) -> Result<OptimizationResult> {
+        let start = Instant::now();
+        check_quality_guarantee(data, num_cores, options)?;
+        check_algorithm_constraints(data, num_cores, options)?;
+        anyhow::ensure!(
+            options.algorithm_constraints.core_bounds.is_empty()
+                && options.chance_constraint.is_none()
+                && options.stability_penalty.is_none()
+                && options.idle_core_penalty.is_none()
+                && options.complexity_penalty.is_none()
+                && options.core_classes.is_empty()
+                && options.topology.is_none(),
+            "Core bounds, core classes, the topology, the chance constraint and penalties are not supported by the column generation"
+        );
+        if options.checkpoint.is_some() || options.pool_size.is_some() {
+            warn!("Checkpoints and the solution pool are not supported by the column generation");
+        }
+        anyhow::ensure!(
+            benders::summing(&options.objective),
+            "The {:?} objective is not supported by the column generation",
+            options.objective
+        );
+        let scores = column_scores(data, num_cores, options)?;
+        let unassignable = scores
+            .outer_iter()
+            .enumerate()
+            .filter(|(_, scores)| scores.iter().all(|s| s.is_infinite()))
+            .map(|(i, _)| i)
+            .collect_vec();
+        anyhow::ensure!(
+            unassignable.is_empty(),
+            "No algorithm may be assigned to instances {unassignable:?}"
+        );
+        let mut master =
+            Master::new(&self.env, data, num_cores, options, &scores)?;
+        for cell in initial_columns(data, num_cores, options, &scores) {
+            master.add_column(cell, &scores, VarType::Continuous)?;
+        }
+        let remaining = || options.timeout.0 - start.elapsed().as_secs_f64();
+
+        // price in columns until the LP relaxation is optimal
+        let duals = loop {
+            master
+                .model
+                .set_param(param::TimeLimit, remaining().max(0.0))?;
+            master.model.optimize()?;
+            anyhow::ensure!(
+                master.model.status()? == Status::Optimal,
+                "The LP relaxation of the restricted master problem was not solved ({:?})",
+                master.model.status()?
+            );
+            let duals = master.duals()?;
+            let columns = master
+                .candidates(&scores)
+                .map(|cell| (cell, duals.reduced_cost(data, &scores, cell)))
+                .filter(|(_, reduced_cost)| *reduced_cost < -1e-9)
+                .into_group_map_by(|((j, _), _)| *j)
+                .into_values()
+                .filter_map(|columns| {
+                    columns.into_iter().min_by(|(_, a), (_, b)| a.total_cmp(b))
+                })
+                .map(|(cell, _)| cell)
+                .sorted()
+                .collect_vec();
+            if columns.is_empty()
+                || remaining() <= 0.0
+                || options.is_cancelled()
+            {
+                break duals;
+            }
+            debug!("Pricing in {} columns", columns.len());
+            for cell in columns {
+                master.add_column(cell, &scores, VarType::Continuous)?;
+            }
+        };
+        let lp_bound = master.model.get_attr(attr::ObjVal)?;
+        info!(
+            "LP bound {lp_bound} with {} of {} columns",
+            master.columns.len(),
+            data.num_algorithms * num_cores
+        );
+
+        // solve the restricted master MIP and add the columns that could improve its portfolio
+        master.set_binary()?;
+        let mut history = Vec::new();
+        master.solve(remaining(), options, &start, &mut history)?;
+        let objective = master.model.get_attr(attr::ObjVal)?;
+        let columns = master
+            .candidates(&scores)
+            .filter(|&cell| {
+                duals.reduced_cost(data, &scores, cell)
+                    < objective - lp_bound - 1e-9
+            })
+            .collect_vec();
+        if !columns.is_empty() {
+            info!("Adding {} columns within the gap", columns.len());
+            for cell in columns {
+                master.add_column(cell, &scores, VarType::Binary)?;
+            }
+            master.solve(remaining(), options, &start, &mut history)?;
+        }
+
+        anyhow::ensure!(
+            master.model.get_obj_attr(attr::X, &master.overflow)? < 0.5
+                && master.underflow.map_or(Ok(0.0), |var| master
+                    .model
+                    .get_obj_attr(attr::X, &var))?
+                    < 0.5,
+            "No portfolio satisfies the constraints on {num_cores} cores"
+        );
+        let gap = master.model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+        let final_portfolio = master.portfolio(
+            data,
+            num_cores,
+            "final_portfolio",
+            gap.abs() < f64::EPSILON,
+        )?;
+        let stats = solve_stats(&master.model, start, history.len(), options)?;
+        Ok(OptimizationResult {
+            gap_closed: gap_closed(data, &final_portfolio),
+            initial_gap_closed: None,
+            initial_portfolio: None,
+            final_portfolio,
+            gap,
+            history,
+            refined: false,
+            pool: Vec::new(),
+            stats: SolveStats {
+                wall_time: start.elapsed().as_secs_f64(),
+                ..stats
+            },
+            instance_qualities: None,
+        })
+    }
+}
+
+/// Score of assigning every instance to every cell for the objective of the `options`, infinite
+/// if the instance must not be assigned to the cell (forbidden, violating the quality guarantee,
+/// excluded algorithm or more cores than available)
+fn column_scores(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<Array3<f64>> {
+    let mut scores = benders::cell_scores(data, num_cores, options)?;
+    let e_min = &data.expected_best_quality;
+    if let Some(guarantee) = &options.quality_guarantee {
+        for i in guarantee_instances(guarantee, data.num_instances) {
+            let bound = guarantee.factor * data.best_per_instance[i];
+            for ((j, k), score) in
+                scores.index_axis_mut(Axis(0), i).indexed_iter_mut()
+            {
+                if e_min[(i, j, k)] > bound {
+                    *score = f64::INFINITY;
+                }
+            }
+        }
+    }
+    let excluded = forced_algorithms(data, options)
+        .into_iter()
+        .filter_map(|(j, include)| (!include).then_some(j))
+        .collect_vec();
+    for ((_, j, k), score) in scores.indexed_iter_mut() {
+        if excluded.contains(&j) || column_cores(data, (j, k)) > num_cores {
+            *score = f64::INFINITY;
+        }
+    }
+    Ok(scores)
+}
+
+/// Number of cores of the column `(j, k)`
+fn column_cores(data: &Data, (j, k): (usize, usize)) -> usize {
+    (k + 1) * data.algorithms[j].num_threads as usize
+}
+
+/// Columns of the [`greedy_solve`] portfolio, the best column of every instance and a single copy
+/// of every included algorithm, so the restricted master problem is feasible
+fn initial_columns(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    scores: &Array3<f64>,
+) -> Vec<(usize, usize)> {
+    let greedy = portfolio_copies(&greedy_solve(data, num_cores), data)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, copies)| *copies >= 1.0)
+        .map(|(j, copies)| (j, copies as usize - 1))
+        .filter(|&(j, k)| {
+            scores
+                .slice(ndarray::s![.., j, k])
+                .iter()
+                .any(|s| s.is_finite())
+        })
+        .collect_vec();
+    let best = scores.outer_iter().filter_map(|scores| {
+        scores
+            .indexed_iter()
+            .filter(|(_, score)| score.is_finite())
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(cell, _)| cell)
+    });
+    let included = forced_algorithms(data, options)
+        .into_iter()
+        .filter_map(|(j, include)| include.then_some((j, 0)));
+    greedy
+        .into_iter()
+        .chain(best)
+        .chain(included)
+        .unique()
+        .collect_vec()
+}
+
+/// Restricted master problem of the column generation
+struct Master<'a> {
+    model: Model,
+    data: &'a Data,
+    num_cores: usize,
+    /// Every instance is assigned exactly once
+    assign: Vec<grb::Constr>,
+    /// Every algorithm is selected at most (or exactly, if included) once
+    algorithms: Vec<grb::Constr>,
+    /// The portfolio uses at most (or exactly) the available cores
+    cores: grb::Constr,
+    memory: Option<grb::Constr>,
+    cost: Option<grb::Constr>,
+    /// Cores used beyond the available ones, heavily penalized to keep the model feasible
+    overflow: grb::Var,
+    /// Cores left idle if all cores have to be used, heavily penalized
+    underflow: Option<grb::Var>,
+    /// The cell `(j, k)` of every column with its selection `b`
+    columns: Vec<((usize, usize), grb::Var)>,
+    /// Assignments of the instances to the columns
+    assignments: Vec<grb::Var>,
+}
+
+/// Duals of the constraints of the [`Master`] at an optimal LP solution
+struct Duals {
+    assign: Vec<f64>,
+    algorithms: Vec<f64>,
+    cores: f64,
+    memory: f64,
+    cost: f64,
+}
+
+impl Duals {
+    /// Reduced cost of the column of cell `(j, k)`, selecting it and assigning every instance to
+    /// it whose score is below its dual
+    fn reduced_cost(
+        &self,
+        data: &Data,
+        scores: &Array3<f64>,
+        (j, k): (usize, usize),
+    ) -> f64 {
+        let copies = (k + 1) as f64;
+        let constant = self.algorithms[j]
+            + self.cores * column_cores(data, (j, k)) as f64
+            + self.memory * copies * memory(data, j)
+            + self.cost * cost(data, j);
+        reduced_cost(
+            scores.slice(ndarray::s![.., j, k]),
+            &self.assign,
+            constant,
+        )
+    }
+}
+
+/// Reduced cost `sum_i min(0, s_i - pi_i) - constant` of a column with the instance `scores`, the
+/// duals `pi` of the assignment constraints and the duals of the other constraints weighted by
+/// its coefficients in them (`constant`)
+fn reduced_cost(
+    scores: ArrayView1<f64>,
+    assign: &[f64],
+    constant: f64,
+) -> f64 {
+    scores
+        .iter()
+        .zip(assign)
+        .filter(|(score, _)| score.is_finite())
+        .map(|(score, dual)| (score - dual).min(0.0))
+        .sum::<f64>()
+        - constant
+}
+
+impl<'a> Master<'a> {
+    /// Restricted master problem without columns
+    fn new(
+        env: &grb::Env,
+        data: &'a Data,
+        num_cores: usize,
+        options: &SolverOptions,
+        scores: &Array3<f64>,
+    ) -> Result<Self> {
+        let mut model = Model::with_env("colgen_master", env)?;
+        set_parameters(&mut model, options)?;
+        // exceeds the objective of every portfolio, which is at most the worst score per instance
+        let penalty = 1.0
+            + scores
+                .outer_iter()
+                .map(|scores| {
+                    scores
+                        .iter()
+                        .copied()
+                        .filter(|score| score.is_finite())
+                        .fold(0.0, f64::max)
+                })
+                .sum::<f64>();
+        let overflow =
+            add_ctsvar!(model, name: "overflow", obj: penalty, bounds: 0..)?;
+        let assign = (0..data.num_instances)
+            .map(|i| {
+                model.add_constr(
+                    format!("c4_{i}").as_str(),
+                    c!(grb::Expr::default() == 1),
+                )
+            })
+            .collect::<grb::Result<Vec<_>>>()?;
+        let included = forced_algorithms(data, options)
+            .into_iter()
+            .filter_map(|(j, include)| include.then_some(j))
+            .collect_vec();
+        let algorithms = (0..data.num_algorithms)
+            .map(|j| match included.contains(&j) {
+                true => model.add_constr(
+                    format!("include_{j}").as_str(),
+                    c!(grb::Expr::default() == 1),
+                ),
+                false => model.add_constr(
+                    format!("c2_{j}").as_str(),
+                    c!(grb::Expr::default() <= 1),
+                ),
+            })
+            .collect::<grb::Result<Vec<_>>>()?;
+        let (cores, underflow) = match data
+            .algorithms
+            .iter()
+            .any(|a| a.num_threads == 1)
+        {
+            true => {
+                let underflow = add_ctsvar!(model, name: "underflow", obj: penalty, bounds: 0..)?;
+                let cores = model
+                    .add_constr("c3", c!(underflow - overflow == num_cores))?;
+                (cores, Some(underflow))
+            }
+            false => (
+                model.add_constr("c3", c!(-1.0 * overflow <= num_cores))?,
+                None,
+            ),
+        };
+        let memory = options
+            .memory_budget
+            .map(|budget| {
+                model.add_constr("memory", c!(grb::Expr::default() <= budget))
+            })
+            .transpose()?;
+        let cost = options
+            .cost_budget
+            .map(|budget| {
+                model.add_constr("cost", c!(grb::Expr::default() <= budget))
+            })
+            .transpose()?;
+        Ok(Self {
+            model,
+            data,
+            num_cores,
+            assign,
+            algorithms,
+            cores,
+            memory,
+            cost,
+            overflow,
+            underflow,
+            columns: Vec::new(),
+            assignments: Vec::new(),
+        })
+    }
+
+    /// Add the column of cell `(j, k)` with the assignments of all instances allowed on it
+    fn add_column(
+        &mut self,
+        (j, k): (usize, usize),
+        scores: &Array3<f64>,
+        vtype: VarType,
+    ) -> Result<()> {
+        let data = self.data;
+        let copies = (k + 1) as f64;
+        let coefficients = [
+            Some((self.algorithms[j], 1.0)),
+            Some((self.cores, column_cores(data, (j, k)) as f64)),
+            self.memory
+                .map(|memory| (memory, copies * super::memory(data, j))),
+            self.cost.map(|cost| (cost, super::cost(data, j))),
+        ];
+        let b = self.model.add_var(
+            format!("b_{j}_{k}").as_str(),
+            vtype,
+            0.0,
+            0.0,
+            1.0,
+            coefficients.into_iter().flatten(),
+        )?;
+        for (i, &score) in scores.slice(ndarray::s![.., j, k]).indexed_iter() {
+            if !score.is_finite() {
+                continue;
+            }
+            let a = self.model.add_var(
+                format!("a_{i}_{j}_{k}").as_str(),
+                vtype,
+                score,
+                0.0,
+                1.0,
+                [(self.assign[i], 1.0)],
+            )?;
+            self.model
+                .add_constr(format!("c5_{i}_{j}_{k}").as_str(), c!(a <= b))?;
+            self.assignments.push(a);
+        }
+        self.columns.push(((j, k), b));
+        Ok(())
+    }
+
+    /// Cells that can be priced in, i.e. that are not yet a column and can be assigned
+    fn candidates<'s>(
+        &'s self,
+        scores: &'s Array3<f64>,
+    ) -> impl Iterator<Item = (usize, usize)> + 's {
+        (0..self.data.num_algorithms)
+            .cartesian_product(0..scores.dim().2)
+            .filter(move |&(j, k)| {
+                !self.columns.iter().any(|(cell, _)| *cell == (j, k))
+                    && scores
+                        .slice(ndarray::s![.., j, k])
+                        .iter()
+                        .any(|score| score.is_finite())
+            })
+    }
+
+    /// Duals of the constraints of the solved LP relaxation
+    fn duals(&self) -> Result<Duals> {
+        let dual = |constr: Option<&grb::Constr>| {
+            constr.map_or(Ok(0.0), |constr| {
+                self.model.get_obj_attr(attr::Pi, constr)
+            })
+        };
+        Ok(Duals {
+            assign: self
+                .model
+                .get_obj_attr_batch(attr::Pi, self.assign.iter().copied())?,
+            algorithms: self.model.get_obj_attr_batch(
+                attr::Pi,
+                self.algorithms.iter().copied(),
+            )?,
+            cores: dual(Some(&self.cores))?,
+            memory: dual(self.memory.as_ref())?,
+            cost: dual(self.cost.as_ref())?,
+        })
+    }
+
+    /// Make all columns binary
+    fn set_binary(&mut self) -> Result<()> {
+        let vars = self
+            .columns
+            .iter()
+            .map(|(_, b)| *b)
+            .chain(self.assignments.iter().copied())
+            .collect_vec();
+        self.model.set_obj_attr_batch(
+            attr::VType,
+            vars.into_iter().map(|var| (var, VarType::Binary)),
+        )?;
+        Ok(())
+    }
+
+    /// Solve the restricted master MIP within `timeout` seconds and record its portfolio in the
+    /// `history`
+    fn solve(
+        &mut self,
+        timeout: f64,
+        options: &SolverOptions,
+        start: &Instant,
+        history: &mut Vec<Incumbent>,
+    ) -> Result<()> {
+        self.model.set_param(param::TimeLimit, timeout.max(0.0))?;
+        self.model.optimize_with_callback(&mut |w: Where| {
+            if options.is_cancelled() {
+                terminate(&w);
+            }
+            Ok(())
+        })?;
+        anyhow::ensure!(
+            self.model.get_attr(attr::SolCount)? > 0,
+            "No portfolio found within the timeout"
+        );
+        let portfolio = self.portfolio(
+            self.data,
+            self.num_cores,
+            "intermediate_portfolio",
+            false,
+        )?;
+        history.push(Incumbent {
+            portfolio,
+            objective: self.model.get_attr(attr::ObjVal)?,
+            bound: self.model.get_attr(attr::ObjBound)?,
+            time: start.elapsed().as_secs_f64(),
+            simulated: None,
+        });
+        Ok(())
+    }
+
+    /// Portfolio of the selected columns of the solved MIP
+    fn portfolio(
+        &self,
+        data: &Data,
+        num_cores: usize,
+        name: &str,
+        opt: bool,
+    ) -> Result<Portfolio> {
+        let values = self.model.get_obj_attr_batch(
+            attr::X,
+            self.columns.iter().map(|(_, b)| *b),
+        )?;
+        let mut solution = vec![0.0; data.num_algorithms * num_cores];
+        for (((j, k), _), value) in self.columns.iter().zip(values) {
+            solution[j * num_cores + k] = value;
+        }
+        Ok(postprocess_solution(
+            solution,
+            data.num_algorithms,
+            num_cores,
+            &data.algorithms,
+            name,
+            opt,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduced_cost() {
+        let scores = ndarray::arr1(&[1.0, 2.0, f64::INFINITY]);
+        // only the first instance improves on its dual
+        assert_eq!(reduced_cost(scores.view(), &[1.5, 1.5, 3.0], 0.0), -0.5);
+        // the cores are too expensive
+        assert_eq!(reduced_cost(scores.view(), &[1.5, 1.5, 3.0], -1.0), 0.5);
+        assert_eq!(reduced_cost(scores.view(), &[1.0, 2.0, 3.0], 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_initial_columns() {
+        let algorithms = [
+            Algorithm::new("algo1".into(), 1),
+            Algorithm::new("algo2".into(), 1),
+        ];
+        let stats = [1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 1.0, 1.0];
+        let data =
+            Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+        let scores =
+            column_scores(&data, 2, &SolverOptions::default()).unwrap();
+        let columns =
+            initial_columns(&data, 2, &SolverOptions::default(), &scores);
+        // the best columns of both instances
+        assert!(columns.contains(&(0, 0)) && columns.contains(&(1, 0)));
+        let options = SolverOptions {
+            algorithm_constraints: AlgorithmConstraints {
+                exclude: vec![algorithms[1].clone()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let scores = column_scores(&data, 2, &options).unwrap();
+        assert!(scores
+            .slice(ndarray::s![.., 1, ..])
+            .iter()
+            .all(|s| s.is_infinite()));
+    }
+}
@@ -0,0 +1,231 @@
+use cp_sat::builder::{CpModelBuilder, LinearExpr};
+use cp_sat::proto::{CpSolverStatus, SatParameters};
+use std::time::Instant;
+
+use super::*;
+
+/// Resolution of the quality ratios, which are scaled to integers for CP-SAT
+const SCALE: f64 = 1e6;
+
+fn scaled(value: f64) -> i64 {
+    (value * SCALE).round() as i64
+}
+
+/// Create a portfolio from the input data using the OR-Tools CP-SAT solver.
+///
+/// Solves the same model as [`crate::solver::solve`], but the quality ratio `q_i / best_i` of
+/// every instance is an integer variable in units of `1 / SCALE` (`1e6`), so the objective, the
+/// quality guarantees and the penalties are rounded to this resolution. The initial portfolio
+/// is passed as a hint for the selected algorithms. CP-SAT reports no intermediate solutions (the
+/// history only contains the final portfolio) and does not explain infeasible models.
+pub fn solve(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+) -> Result<OptimizationResult> {
+    check_objective(options)?;
+    check_quality_guarantee(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    #[cfg(feature = "server")]
+    let _job = crate::metrics::JobGuard::start();
+    let start = Instant::now();
+    let (n, m) = (data.num_algorithms, data.num_instances);
+    let ratio = |i: usize, j: usize, k: usize| {
+        scaled(
+            data.expected_best_quality[(i, j, k)] / data.best_per_instance[i],
+        )
+    };
+    let idle_core_penalty = options.idle_core_penalty.unwrap_or(0.0);
+    let threads = |j: usize| data.algorithms[j].num_threads as i64;
+
+    let mut model = CpModelBuilder::default();
+    let a = Array3::from_shape_fn((m, n, num_cores), |_| model.new_bool_var());
+    let b = Array2::from_shape_fn((n, num_cores), |_| model.new_bool_var());
+    let q = Array1::from_shape_fn(m, |i| {
+        let upper = (0..n)
+            .cartesian_product(0..num_cores)
+            .map(|(j, k)| ratio(i, j, k))
+            .max()
+            .unwrap_or(0);
+        model.new_int_var([(0, upper)])
+    });
+    let cores = |j: usize| {
+        (0..num_cores)
+            .map(|k| ((k + 1) as i64 * threads(j), b[(j, k)]))
+            .collect::<LinearExpr>()
+    };
+
+    // constraint 1
+    for ((i, j, k), &val_a) in a.indexed_iter() {
+        model.add_le(LinearExpr::from((ratio(i, j, k), val_a)), q[i]);
+    }
+    // constraint 2
+    for row in b.rows() {
+        model.add_at_most_one(row.iter().copied());
+    }
+    // constraint 3
+    let sums = (0..n).map(cores).collect::<LinearExpr>();
+    if data.algorithms.iter().any(|a| a.num_threads == 1) {
+        model.add_eq(sums, num_cores as i64);
+    } else {
+        model.add_le(sums, num_cores as i64);
+    }
+    // constraint 4
+    for row in a.outer_iter() {
+        model.add_exactly_one(row.iter().copied());
+    }
+    // constraint 5
+    for ((_, j, k), &val_a) in a.indexed_iter() {
+        model.add_le(val_a, b[(j, k)]);
+    }
+    if let Some(guarantee) = &options.quality_guarantee {
+        for i in guarantee_instances(guarantee, m) {
+            model.add_le(q[i], (guarantee.factor * SCALE).floor() as i64);
+        }
+    }
+    for (j, include) in forced_algorithms(data, options) {
+        let selected = b.row(j).iter().copied().collect::<LinearExpr>();
+        model.add_eq(selected, include as i64);
+    }
+    for (j, min, max) in core_bounds(data, num_cores, options) {
+        model.add_ge(cores(j), min as i64);
+        model.add_le(cores(j), max as i64);
+    }
+    for (i, j, k) in forbidden_cells(data, options) {
+        model.add_eq(LinearExpr::from(a[(i, j, k)]), 0);
+    }
+    if let Some(budget) = options.memory_budget {
+        // memory is scaled like the quality ratios, rounding the requirements up
+        let used = b
+            .indexed_iter()
+            .map(|((j, k), &var)| {
+                let required = (k + 1) as f64 * memory(data, j) * SCALE;
+                (required.ceil() as i64, var)
+            })
+            .collect::<LinearExpr>();
+        model.add_le(used, scaled(budget));
+    }
+    if let Some(budget) = options.cost_budget {
+        let spent = b
+            .indexed_iter()
+            .map(|((j, _), &var)| ((cost(data, j) * SCALE).ceil() as i64, var))
+            .collect::<LinearExpr>();
+        model.add_le(spent, scaled(budget));
+    }
+
+    let mut objective = q.iter().copied().collect::<LinearExpr>();
+    if idle_core_penalty != 0.0 {
+        for ((j, k), &val_b) in b.indexed_iter() {
+            let cores = (k + 1) as f64 * threads(j) as f64;
+            objective -= (scaled(idle_core_penalty * cores), val_b);
+        }
+    }
+    if let Some(penalty) = options.complexity_penalty {
+        for &val_b in &b {
+            objective += (scaled(penalty), val_b);
+        }
+    }
+    if let Some(stability) = &options.stability_penalty {
+        let previous_cores = previous_cores(&stability.previous, data);
+        for (j, previous) in previous_cores.into_iter().enumerate() {
+            let previous = previous.round() as i64;
+            let d = model.new_int_var([(0, num_cores as i64 + previous)]);
+            model.add_ge(d, cores(j) - previous);
+            model.add_ge(LinearExpr::from(d) + cores(j), previous);
+            objective += (scaled(stability.penalty), d);
+        }
+    }
+    model.minimize(objective);
+
+    let initial_portfolio = match initial_assignment(initial, data, num_cores)?
+    {
+        Some(initial_assignment) => {
+            let b_start = assignment_to_b(&initial_assignment, n, num_cores);
+            for (&var, &value) in b.iter().zip(&b_start) {
+                model.add_hint(var, value.round() as i64);
+            }
+            let initial_portfolio = postprocess_solution(
+                b_start,
+                n,
+                num_cores,
+                &data.algorithms,
+                "initial_portfolio",
+                false,
+            );
+            info!("Initial portfolio:\n{initial_portfolio}");
+            Some(initial_portfolio)
+        }
+        None => {
+            info!("No initial portfolio provided");
+            None
+        }
+    };
+
+    let parameters = SatParameters {
+        max_time_in_seconds: Some(options.timeout.0),
+        log_search_progress: Some(options.solver_output()),
+        relative_gap_limit: options.mip_gap,
+        num_search_workers: options.threads.map(i32::try_from).transpose()?,
+        random_seed: options.seed.map(i32::try_from).transpose()?,
+        ..Default::default()
+    };
+    let response = model.solve_with_parameters(&parameters);
+    let optimal = match response.status() {
+        CpSolverStatus::Optimal => true,
+        CpSolverStatus::Feasible => false,
+        CpSolverStatus::Infeasible => return Err(unexplained_infeasibility()),
+        status => anyhow::bail!("CP-SAT found no solution: {status:?}"),
+    };
+    let b_solution = b
+        .iter()
+        .map(|&var| var.solution_value(&response) as u8 as f64)
+        .collect_vec();
+    let offset = idle_core_penalty * num_cores as f64;
+    let objective = response.objective_value / SCALE + offset;
+    let bound = response.best_objective_bound / SCALE + offset;
+    // CP-SAT reports a solve stopped at the relative_gap_limit as optimal
+    let gap = (objective - bound).abs() / objective.abs().max(1e-10);
+    #[cfg(feature = "server")]
+    crate::metrics::SOLVER_GAP.set(gap);
+    let final_portfolio = postprocess_solution(
+        b_solution,
+        n,
+        num_cores,
+        &data.algorithms,
+        "final_portfolio",
+        gap.abs() < f64::EPSILON,
+    );
+    debug!("Final objective value: {objective}");
+    Ok(OptimizationResult {
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: initial_portfolio
+            .as_ref()
+            .map(|portfolio| gap_closed(data, portfolio)),
+        initial_portfolio,
+        final_portfolio: final_portfolio.clone(),
+        gap,
+        history: vec![Incumbent {
+            portfolio: final_portfolio,
+            objective,
+            bound,
+            time: start.elapsed().as_secs_f64(),
+            simulated: None,
+        }],
+        refined: false,
+        pool: Vec::new(),
+        stats: SolveStats {
+            wall_time: start.elapsed().as_secs_f64(),
+            node_count: u64::try_from(response.num_branches).ok(),
+            objective,
+            bound: Some(bound),
+            num_incumbents: 1,
+            termination: match optimal {
+                true => gap_termination(Termination::Optimal, gap, options),
+                false => Termination::Timeout,
+            },
+            numeric_focus: None,
+        },
+        instance_qualities: None,
+    })
+}
@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use polars::prelude::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use super::*;
+use crate::csv_parser::{self, DataOptions};
+use validation::Validator;
+
+#[derive(Debug, Clone)]
+/// Options for [`cross_validate`]
+pub struct CrossValidationOptions {
+    /// Number of folds the instances are split into, at least 2
+    pub num_folds: usize,
+    /// Number of simulated seeds per evaluated portfolio
+    pub num_seeds: u32,
+    /// Seed for the assignment of the instances to the folds
+    pub seed: u64,
+}
+
+impl Default for CrossValidationOptions {
+    fn default() -> Self {
+        Self {
+            num_folds: 5,
+            num_seeds: 10,
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A fold of a [`cross_validate`] run
+pub struct Fold {
+    /// Names of the held-out instances
+    pub instances: Vec<String>,
+    /// Portfolio trained on the instances of the other folds
+    pub portfolio: Portfolio,
+    /// Simulated objective per instance of the portfolio on the training instances
+    pub train_objective: f64,
+    /// Simulated objective per instance of the portfolio on the held-out instances
+    pub test_objective: f64,
+}
+
+impl Fold {
+    /// How much worse the portfolio is on the held-out instances than on the training instances
+    pub fn generalization_gap(&self) -> f64 {
+        self.test_objective - self.train_objective
+    }
+}
+
+/// Result of [`cross_validate`]
+pub struct CrossValidation {
+    /// The folds in order
+    pub folds: Vec<Fold>,
+    /// Mean [generalization gap](Fold::generalization_gap) over the folds
+    pub mean_gap: f64,
+    /// Sample standard deviation of the generalization gap over the folds
+    pub std_gap: f64,
+    /// Result of the solver on all instances
+    pub result: OptimizationResult,
+}
+
+/// Estimate how well portfolios generalize to unseen instances with k-fold cross-validation.
+///
+/// The instances of the normalized data frame `df` (see
+/// [`csv_parser::parse_normalized_csvs`]) are shuffled and split into
+/// [`num_folds`](CrossValidationOptions::num_folds) folds. For every fold, a portfolio is solved
+/// on the data of the other folds (built with `num_cores` repetitions, the `slowdown_ratio` and
+/// the `data_options`) and [simulated](crate::portfolio_simulator) on the training and on the
+/// held-out instances. The objective of the `options` is compared per instance, i.e. summing
+/// objectives (all but [`Objective::WorstCase`] and [`Objective::Cvar`]) are divided by the number
+/// of instances, relative to the best quality of any algorithm on the evaluated instances. Finally,
+/// the portfolio is solved on all instances.
+///
+/// Every solve uses the timeout of the `options` and one Gurobi environment (if the Gurobi
+/// [backend](SolverOptions::backend) is used), a
+/// [chance constraint](SolverOptions::chance_constraint) is estimated again from the runs of the
+/// training instances and the [validation](SolverOptions::validation) of the `options` is ignored.
+pub fn cross_validate(
+    df: &DataFrame,
+    num_cores: u32,
+    slowdown_ratio: f64,
+    data_options: &DataOptions,
+    options: &SolverOptions,
+    cv_options: &CrossValidationOptions,
+) -> Result<CrossValidation> {
+    let CrossValidationOptions {
+        num_folds,
+        num_seeds,
+        seed,
+    } = *cv_options;
+    let mut instances = df["instance"]
+        .unique()?
+        .sort(false)
+        .utf8()?
+        .into_no_null_iter()
+        .map(String::from)
+        .collect_vec();
+    anyhow::ensure!(
+        num_folds >= 2 && num_folds <= instances.len(),
+        "Cross-validation needs between 2 and {} folds, got {num_folds}",
+        instances.len()
+    );
+    instances.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+    let solver = match options.backend {
+        Backend::Gurobi => Solver::for_options(options).ok(),
+        _ => None,
+    };
+    let data_options = DataOptions {
+        dump_normalized: None,
+        ..data_options.clone()
+    };
+    let data = |df: &DataFrame, slowdown_ratio: f64| {
+        csv_parser::Data::from_normalized_dataframe_with_options(
+            df.clone().lazy(),
+            num_cores,
+            slowdown_ratio,
+            &data_options,
+        )
+    };
+    let train = |df: &DataFrame| -> Result<OptimizationResult> {
+        let data = data(df, slowdown_ratio)?;
+        let chance_constraint = match &options.chance_constraint {
+            Some(chance) => Some(ChanceConstraint {
+                success: csv_parser::success_rates(
+                    df.clone().lazy(),
+                    &data,
+                    chance.threshold.factor,
+                )?,
+                threshold: chance.threshold,
+            }),
+            None => None,
+        };
+        let options = SolverOptions {
+            chance_constraint,
+            validation: None,
+            ..options.clone()
+        };
+        match &solver {
+            Some(solver) => {
+                solver.solve(&data, num_cores as usize, &options, None)
+            }
+            None => solve(&data, num_cores as usize, &options, None),
+        }
+    };
+    let evaluate = |df: DataFrame, portfolio: &Portfolio| -> Result<f64> {
+        let df = Arc::new(df);
+        // the portfolio may contain algorithms filtered by the slowdown ratio of the fold
+        let data = data(&df, f64::MAX)?;
+        let options = SolverOptions {
+            objective: options.objective.clone(),
+            ..Default::default()
+        }
+        .with_validation(df, num_seeds, f64::INFINITY);
+        let validator = Validator::new(&data, &options)?
+            .context("The evaluation needs a validation")?;
+        let ratios = validator.simulated_ratios(portfolio)?;
+        let value = objectives::value(&options.objective, &data, &ratios);
+        Ok(match benders::summing(&options.objective) {
+            true => value / data.num_instances as f64,
+            false => value,
+        })
+    };
+
+    let mut folds = Vec::with_capacity(num_folds);
+    for fold in 0..num_folds {
+        let held_out = instances
+            .iter()
+            .skip(fold)
+            .step_by(num_folds)
+            .cloned()
+            .sorted()
+            .collect_vec();
+        info!("Fold {fold}: holding out {} instances", held_out.len());
+        let (train_df, test_df) = split_instances(df, &held_out)?;
+        let result = train(&train_df)
+            .with_context(|| format!("Solving fold {fold} failed"))?;
+        let portfolio = Portfolio {
+            name: format!("fold_{fold}_portfolio"),
+            ..result.final_portfolio
+        };
+        let train_objective = evaluate(train_df, &portfolio)?;
+        let test_objective = evaluate(test_df, &portfolio)?;
+        info!(
+            "Fold {fold}: objective {train_objective} on the training instances, {test_objective} on the held-out instances"
+        );
+        folds.push(Fold {
+            instances: held_out,
+            portfolio,
+            train_objective,
+            test_objective,
+        });
+    }
+    let gaps = folds.iter().map(Fold::generalization_gap).collect_vec();
+    let mean_gap = gaps.iter().sum::<f64>() / num_folds as f64;
+    let std_gap =
+        (gaps.iter().map(|gap| (gap - mean_gap).powi(2)).sum::<f64>()
+            / (num_folds - 1) as f64)
+            .sqrt();
+    info!("Generalization gap {mean_gap} +- {std_gap}");
+    Ok(CrossValidation {
+        folds,
+        mean_gap,
+        std_gap,
+        result: train(df)?,
+    })
+}
+
+/// Rows of `df` on other instances than the `held_out` ones and rows on the `held_out` instances
+fn split_instances(
+    df: &DataFrame,
+    held_out: &[String],
+) -> Result<(DataFrame, DataFrame)> {
+    let held_out = held_out.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mask = df
+        .column("instance")?
+        .utf8()?
+        .into_iter()
+        .map(|instance| instance.map_or(false, |i| held_out.contains(i)))
+        .collect::<BooleanChunked>();
+    Ok((df.filter(&!&mask)?, df.filter(&mask)?))
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A number of copies of an algorithm that the solver does not consider, since another selection
+/// is at least as good, see [`dominated_cells`]
+pub struct DominatedCell {
+    /// The dominated algorithm
+    pub algorithm: Algorithm,
+    /// The dominated number of copies
+    pub copies: usize,
+    /// Algorithm of the dominating selection, `algorithm` itself if fewer copies are as good
+    pub dominated_by: Algorithm,
+    /// Number of copies of the dominating selection
+    pub dominating_copies: usize,
+}
+
+impl fmt::Display for DominatedCell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            algorithm,
+            copies,
+            dominated_by,
+            dominating_copies,
+        } = self;
+        if algorithm == dominated_by {
+            write!(
+                f,
+                "{copies} copies of {algorithm}: {dominating_copies} copies are as good on every instance"
+            )
+        } else {
+            write!(
+                f,
+                "{copies} copies of {algorithm}: {dominating_copies} copies of {dominated_by} are as good on every instance with no more cores"
+            )
+        }
+    }
+}
+
+/// Selections of algorithms and numbers of repetitions (cells of the
+/// [`expected_best_quality`](Data::expected_best_quality)) that are dominated by another cell and
+/// dropped from the model of the Gurobi backend with
+/// [`prune_dominated`](SolverOptions::prune_dominated)
+///
+/// A cell is dominated by fewer copies of the same algorithm or by copies of another algorithm on
+/// no more cores if their expected best quality is at least as good on every instance. Replacing a
+/// dominated cell in a portfolio by its dominating cell (adding its copies to the ones of the
+/// dominating algorithm if it is already selected, whose qualities have to improve with more
+/// copies) keeps the portfolio feasible and doesn't worsen its objective:
+/// - If all cores have to be used (some algorithm is sequential) or idle cores are penalized,
+///   the dominating cell has to use exactly as many cores.
+/// - The dominating cell must not need more memory or cost more, be forbidden on more instances or
+///   be excluded, and an included algorithm is only dominated by fewer copies of itself.
+/// - Only algorithms on the same [core class](SolverOptions::core_classes) dominate each other,
+///   the capacity of another class may not suffice.
+/// - With a [`topology`](SolverOptions::topology), the dominating algorithm must not use more
+///   threads per copy, which may not fit on any NUMA node.
+/// - Algorithms with core bounds are never dominated or dominating, and nothing is dominated with a
+///   custom objective, a chance constraint or a stability penalty.
+///
+/// Cells with equal qualities on the same cores are only dominated by the one with the lowest
+/// algorithm id, in the order of the algorithms and copies.
+pub fn dominated_cells(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Vec<DominatedCell> {
+    dominated_mask(data, num_cores, options)
+        .indexed_iter()
+        .filter_map(|((j, k), dominating)| {
+            let &(l, m) = dominating.as_ref()?;
+            Some(DominatedCell {
+                algorithm: data.algorithms[j].clone(),
+                copies: k + 1,
+                dominated_by: data.algorithms[l].clone(),
+                dominating_copies: m + 1,
+            })
+        })
+        .collect_vec()
+}
+
+/// Dominating cell `(j, k)` of every cell of the selection `b` that is dominated, see
+/// [`dominated_cells`]
+pub(super) fn dominated_mask(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Array2<Option<(usize, usize)>> {
+    let mut dominated =
+        Array2::from_elem((data.num_algorithms, num_cores), None);
+    // the variance may grow if some instances improve more than others
+    if matches!(
+        options.objective,
+        Objective::Custom(_) | Objective::MeanVariance { .. }
+    ) || options.chance_constraint.is_some()
+        || options.stability_penalty.is_some()
+    {
+        return dominated;
+    }
+    let e_min = &data.expected_best_quality;
+    let forced = forced_algorithms(data, options);
+    let included = |j: usize| forced.contains(&(j, true));
+    let excluded = |j: usize| forced.contains(&(j, false));
+    let bounded = core_bounds(data, num_cores, options)
+        .into_iter()
+        .map(|(j, _, _)| j)
+        .collect_vec();
+    let mut forbidden = Array3::from_elem(e_min.dim(), false);
+    for cell in forbidden_cells(data, options) {
+        forbidden[cell] = true;
+    }
+    // the dominating cell must be allowed wherever the dominated cell is, with any number of
+    // copies if they are added to another selection of the algorithm
+    let allowed_where = |(j, k): (usize, usize), (l, m): (usize, usize)| {
+        (0..data.num_instances).all(|i| {
+            forbidden[(i, j, k)]
+                || match l == j {
+                    true => !forbidden[(i, l, m)],
+                    false => !forbidden
+                        .slice(ndarray::s![i, l, ..])
+                        .iter()
+                        .any(|&f| f),
+                }
+        })
+    };
+    let cores =
+        |j: usize, k: usize| (k + 1) * data.algorithms[j].num_threads as usize;
+    let monotone = (0..data.num_algorithms)
+        .map(|j| {
+            (1..num_cores)
+                .filter(|&k| cores(j, k) <= num_cores)
+                .all(|k| {
+                    (0..data.num_instances)
+                        .all(|i| e_min[(i, j, k)] <= e_min[(i, j, k - 1)])
+                })
+        })
+        .collect_vec();
+    let exact_cores = data.algorithms.iter().any(|a| a.num_threads == 1)
+        || options.idle_core_penalty.is_some();
+    let candidates = (0..data.num_algorithms)
+        .filter(|j| !bounded.contains(j))
+        .cartesian_product(0..num_cores)
+        .filter(|&(j, k)| cores(j, k) <= num_cores)
+        .collect_vec();
+    for &(j, k) in &candidates {
+        let dominating = candidates.iter().copied().find(|&(l, m)| {
+            let same = l == j;
+            if (l, m) == (j, k)
+                || excluded(l)
+                || (included(j) && !same)
+                || (same && m > k)
+                || (!same && !monotone[l])
+                || core_class(data, l, options) != core_class(data, j, options)
+                || (options.topology.is_some()
+                    && data.algorithms[l].num_threads
+                        > data.algorithms[j].num_threads)
+                || match exact_cores {
+                    true => cores(l, m) != cores(j, k),
+                    false => cores(l, m) > cores(j, k),
+                }
+                || memory(data, l) * (m + 1) as f64
+                    > memory(data, j) * (k + 1) as f64
+                || (!same && cost(data, l) > cost(data, j))
+            {
+                return false;
+            }
+            if !allowed_where((j, k), (l, m)) {
+                return false;
+            }
+            let at_least_as_good = (0..data.num_instances)
+                .all(|i| e_min[(i, l, m)] <= e_min[(i, j, k)]);
+            let strictly_better = (0..data.num_instances)
+                .any(|i| e_min[(i, l, m)] < e_min[(i, j, k)])
+                || cores(l, m) < cores(j, k);
+            // ties are broken by the order of the cells
+            at_least_as_good && (strictly_better || (l, m) < (j, k))
+        });
+        dominated[(j, k)] = dominating;
+    }
+    dominated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dominated_cells() {
+        let algorithms = [
+            Algorithm::new("algo1".into(), 2),
+            Algorithm::new("algo2".into(), 2),
+            Algorithm::new("algo3".into(), 4),
+        ];
+        // two instances, up to 4 cores
+        let mut stats = vec![f64::MAX; 2 * 3 * 4];
+        let mut set = |i: usize, j: usize, qualities: [f64; 2]| {
+            stats[i * 12 + j * 4] = qualities[0];
+            stats[i * 12 + j * 4 + 1] = qualities[1];
+        };
+        // algo1 doesn't improve with a second copy
+        set(0, 0, [2.0, 2.0]);
+        set(1, 0, [3.0, 3.0]);
+        // algo2 is worse than algo1
+        set(0, 1, [2.5, 2.0]);
+        set(1, 1, [3.0, 2.5]);
+        // algo3 is better than two copies of algo2 on the same cores
+        set(0, 2, [2.0, f64::MAX]);
+        set(1, 2, [2.4, f64::MAX]);
+        let data =
+            Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+        let dominated = dominated_cells(&data, 4, &Default::default())
+            .into_iter()
+            .map(|cell| {
+                (
+                    cell.algorithm.algorithm,
+                    cell.copies,
+                    cell.dominated_by.algorithm,
+                    cell.dominating_copies,
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            dominated,
+            vec![
+                ("algo1".into(), 2, "algo1".into(), 1),
+                ("algo2".into(), 1, "algo1".into(), 1),
+                ("algo2".into(), 2, "algo3".into(), 1),
+            ]
+        );
+
+        // nothing is dominated by an excluded algorithm
+        let options = SolverOptions {
+            algorithm_constraints: AlgorithmConstraints {
+                exclude: vec![algorithms[0].clone()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let dominated = dominated_cells(&data, 4, &options);
+        assert_eq!(dominated.len(), 2);
+        assert_eq!(dominated[0].dominated_by, algorithms[1]);
+        assert_eq!(
+            dominated[1].to_string(),
+            "2 copies of algo2 2: 1 copies of algo3 4 are as good on every instance with no more cores"
+        );
+    }
+
+    #[test]
+    fn test_dominated_core_classes() {
+        let algorithms = [
+            Algorithm::new("algo1@p".into(), 1),
+            Algorithm::new("algo1@e".into(), 1),
+        ];
+        // splitting by core class gives both algorithms the same qualities
+        let data =
+            Data::new(&algorithms, &[1.0, 1.0], None, &[1.0; 16], 4).unwrap();
+        let dominated = dominated_cells(&data, 4, &Default::default());
+        assert!(dominated.iter().any(|cell| cell.algorithm == algorithms[1]
+            && cell.dominated_by == algorithms[0]));
+
+        let class = |name: &str, cores: u32| CoreClass {
+            name: name.into(),
+            cores,
+            speed: 1.0,
+        };
+        let options = SolverOptions {
+            core_classes: vec![class("p", 1), class("e", 3)],
+            ..Default::default()
+        };
+        // the e cores are not dominated by the single p core
+        assert!(dominated_cells(&data, 4, &options).is_empty());
+    }
+
+    #[test]
+    fn test_dominated_topology() {
+        let algorithms = [
+            Algorithm::new("algo1".into(), 1),
+            Algorithm::new("algo2".into(), 4),
+        ];
+        let mut stats = vec![f64::MAX; 2 * 2 * 4];
+        for i in 0..2 {
+            stats[i * 8..i * 8 + 4].copy_from_slice(&[4.0, 3.0, 2.5, 2.0]);
+            stats[i * 8 + 4] = 1.0;
+        }
+        let data =
+            Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+        let dominated = dominated_cells(&data, 4, &Default::default());
+        assert_eq!(dominated.len(), 1);
+        assert_eq!(dominated[0].dominated_by, algorithms[1]);
+
+        // algo2 fits on no node, so the copies of algo1 are the only placeable choice
+        let options = SolverOptions {
+            topology: Some("2x3".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(dominated_cells(&data, 4, &options).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use itertools::Itertools;
+use log::debug;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use super::racing::fill_copies;
+use super::{gap_closed, objective, portfolio_from_copies};
+use crate::csv_parser::Data;
+use crate::datastructures::*;
+
+#[derive(Debug, Clone)]
+/// Options for [`solve`]
+pub struct GeneticOptions {
+    /// Stop after this many seconds
+    pub timeout: Timeout,
+    /// Number of portfolios in every generation
+    pub population_size: usize,
+    /// Stop after this many generations
+    pub generations: usize,
+    /// Probability that a child is mutated
+    pub mutation_rate: f64,
+    /// Number of portfolios competing for every parent
+    pub tournament_size: usize,
+    /// Number of best portfolios that are copied into the next generation unchanged
+    pub elitism: usize,
+    /// Seed for the initial population and the genetic operators
+    pub seed: u64,
+}
+
+impl Default for GeneticOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Timeout::default(),
+            population_size: 50,
+            generations: 200,
+            mutation_rate: 0.2,
+            tournament_size: 3,
+            elitism: 2,
+            seed: 42,
+        }
+    }
+}
+
+/// Create a portfolio with a genetic algorithm, without any MIP.
+///
+/// Portfolios are vectors of copies per algorithm, i.e. the cores of every algorithm change in
+/// steps of its `num_threads`. Children are created by uniform crossover of two parents chosen by
+/// tournament selection, mutated by moving a copy to a random algorithm and repaired to respect the
+/// core budget (surplus copies are removed, free cores are filled with copies of the algorithms
+/// of the child). Portfolios are scored by the [`objective`] on the `expected_best_quality`, every
+/// improvement is recorded in the history. The gap is relative to the trivial bound of one per
+/// instance.
+pub fn solve(
+    data: &Data,
+    num_cores: usize,
+    options: &GeneticOptions,
+) -> Result<OptimizationResult> {
+    let threads = data.algorithms.iter().map(|a| a.num_threads).collect_vec();
+    let fitting = (0..data.num_algorithms)
+        .filter(|&j| threads[j] as usize <= num_cores)
+        .collect_vec();
+    anyhow::ensure!(
+        !fitting.is_empty(),
+        "No algorithm fits on {num_cores} cores"
+    );
+    anyhow::ensure!(
+        options.population_size > options.elitism,
+        "The population has to be larger than the elite"
+    );
+    let num_cores = num_cores as u32;
+    let repair = |copies: &mut [f64], rng: &mut ChaCha8Rng| {
+        loop {
+            let used = copies
+                .iter()
+                .zip(&threads)
+                .map(|(c, &t)| *c as u32 * t)
+                .sum::<u32>();
+            let selected = (0..copies.len())
+                .filter(|&j| copies[j] >= 1.0)
+                .collect_vec();
+            match selected.choose(rng) {
+                Some(&j) if used > num_cores => copies[j] -= 1.0,
+                _ => break,
+            }
+        }
+        let selected = (0..copies.len())
+            .filter(|&j| copies[j] >= 1.0)
+            .collect_vec();
+        match selected.is_empty() {
+            true => fill_copies(copies, &fitting, &threads, num_cores, rng),
+            false => fill_copies(copies, &selected, &threads, num_cores, rng),
+        }
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let evaluate = |copies: Vec<f64>| {
+        let value = objective(data, &copies);
+        (copies, value)
+    };
+    let mut population = (0..options.population_size)
+        .map(|_| {
+            let subset_size = rng.gen_range(1..=fitting.len());
+            let subset = fitting
+                .choose_multiple(&mut rng, subset_size)
+                .copied()
+                .collect_vec();
+            let mut copies = vec![0.0; data.num_algorithms];
+            fill_copies(&mut copies, &subset, &threads, num_cores, &mut rng);
+            evaluate(copies)
+        })
+        .collect_vec();
+
+    let bound = data.num_instances as f64;
+    let start = Instant::now();
+    let mut best = f64::INFINITY;
+    let mut history = Vec::new();
+    for generation in 0..=options.generations {
+        population.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        let (copies, value) = &population[0];
+        if *value < best {
+            debug!("Generation {generation}: new incumbent with objective {value}");
+            best = *value;
+            history.push(Incumbent {
+                portfolio: portfolio_from_copies(
+                    data,
+                    copies,
+                    "intermediate_portfolio",
+                ),
+                objective: best,
+                bound,
+                time: start.elapsed().as_secs_f64(),
+                simulated: None,
+            });
+        }
+        if generation == options.generations
+            || start.elapsed().as_secs_f64() >= options.timeout.0
+        {
+            break;
+        }
+        let tournament = |rng: &mut ChaCha8Rng| {
+            (0..options.tournament_size.max(1))
+                .map(|_| rng.gen_range(0..population.len()))
+                .min()
+                .unwrap()
+        };
+        let children = (options.elitism..options.population_size)
+            .map(|_| {
+                let first = &population[tournament(&mut rng)].0;
+                let second = &population[tournament(&mut rng)].0;
+                let mut child = first
+                    .iter()
+                    .zip(second)
+                    .map(|(&a, &b)| if rng.gen_bool(0.5) { a } else { b })
+                    .collect_vec();
+                if rng.gen_bool(options.mutation_rate) {
+                    let selected = (0..child.len())
+                        .filter(|&j| child[j] >= 1.0)
+                        .collect_vec();
+                    if let Some(&j) = selected.choose(&mut rng) {
+                        child[j] -= 1.0;
+                    }
+                    let added = *fitting.choose(&mut rng).unwrap();
+                    fill_copies(
+                        &mut child,
+                        &[added],
+                        &threads,
+                        num_cores,
+                        &mut rng,
+                    );
+                }
+                repair(&mut child, &mut rng);
+                evaluate(child)
+            })
+            .collect_vec();
+        population.truncate(options.elitism);
+        population.extend(children);
+    }
+    let (copies, value) = &population[0];
+    let final_portfolio =
+        portfolio_from_copies(data, copies, "final_portfolio");
+    Ok(OptimizationResult {
+        initial_portfolio: None,
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: None,
+        final_portfolio,
+        gap: (value - bound) / value,
+        stats: SolveStats {
+            wall_time: start.elapsed().as_secs_f64(),
+            node_count: None,
+            objective: *value,
+            bound: Some(bound),
+            num_incumbents: history.len(),
+            termination: match start.elapsed().as_secs_f64()
+                >= options.timeout.0
+            {
+                true => Termination::Timeout,
+                false => Termination::Limit,
+            },
+            numeric_focus: None,
+        },
+        history,
+        refined: false,
+        pool: Vec::new(),
+        instance_qualities: None,
+    })
+}
@@ -0,0 +1,178 @@
+use super::*;
+use std::collections::BTreeMap;
+
+/// Create one portfolio per group of instances using the Gurobi Optimizer.
+///
+/// `groups` contains the input data of every group, e.g. split by instance family with
+/// [`crate::csv_parser::split_groups`]. Every portfolio gets `num_cores` cores, but all groups
+/// share the set of deployed algorithms: an algorithm with copies in any of the portfolios counts
+/// once towards the [`cost_budget`](SolverOptions::cost_budget) and the
+/// [`complexity_penalty`](SolverOptions::complexity_penalty), and at most `max_deployed`
+/// algorithms are deployed over all groups. All groups must contain the same algorithms.
+///
+/// The other `options` are applied to every group. The chance constraint, the stability penalty
+/// and quality guarantees for a subset of the instances are not supported.
+pub fn solve_grouped(
+    groups: &BTreeMap<String, Data>,
+    num_cores: usize,
+    max_deployed: Option<usize>,
+    options: &SolverOptions,
+) -> Result<BTreeMap<String, Portfolio>> {
+    Solver::for_options(options)?.solve_grouped(
+        groups,
+        num_cores,
+        max_deployed,
+        options,
+    )
+}
+
+impl Solver {
+    /// Create one portfolio per group of instances, see [`solve_grouped`]
+    pub fn solve_grouped(
+        &self,
+        groups: &BTreeMap<String, Data>,
+        num_cores: usize,
+        max_deployed: Option<usize>,
+        options: &SolverOptions,
+    ) -> Result<BTreeMap<String, Portfolio>> {
+        let Some(first) = groups.values().next() else {
+            anyhow::bail!("No groups provided");
+        };
+        anyhow::ensure!(
+            groups
+                .values()
+                .all(|data| data.algorithms == first.algorithms),
+            "All groups must contain the same algorithms"
+        );
+        anyhow::ensure!(
+            options.chance_constraint.is_none(),
+            "The chance constraint is not supported for groups"
+        );
+        anyhow::ensure!(
+            options.stability_penalty.is_none(),
+            "The stability penalty is not supported for groups"
+        );
+        anyhow::ensure!(
+            options
+                .quality_guarantee
+                .as_ref()
+                .map_or(true, |guarantee| guarantee.instances.is_none()),
+            "Quality guarantees for a subset of the instances are not supported for groups"
+        );
+        for data in groups.values() {
+            check_quality_guarantee(data, num_cores, options)?;
+            check_algorithm_constraints(data, num_cores, options)?;
+        }
+        let mut model = Model::with_env("grouped_portfolio_model", &self.env)?;
+        set_parameters(&mut model, options)?;
+
+        // the shared constraints are added once for the deployed algorithms
+        let group_options = SolverOptions {
+            cost_budget: None,
+            complexity_penalty: None,
+            ..options.clone()
+        };
+        let mut objective_function = grb::Expr::default();
+        let mut group_vars: Vec<PortfolioVars> = Vec::new();
+        for (g, data) in groups.values().enumerate() {
+            let prefix = format!("g{g}_");
+            let vars =
+                add_portfolio_model(&mut model, data, num_cores, &prefix)?;
+            add_quality_guarantees(
+                &mut model,
+                &vars.q,
+                data,
+                &group_options,
+                &prefix,
+            )?;
+            add_algorithm_constraints(
+                &mut model,
+                &vars,
+                data,
+                &group_options,
+                &prefix,
+            )?;
+            add_budgets(&mut model, &vars.b, data, &group_options, &prefix)?;
+            objective_function = objective_function
+                + quality_objective(
+                    &mut model,
+                    &vars,
+                    data,
+                    &options.objective,
+                    &prefix,
+                )?
+                + option_penalties(
+                    &mut model,
+                    &vars.b,
+                    &vars.cores,
+                    data,
+                    num_cores,
+                    &group_options,
+                    &prefix,
+                )?;
+            group_vars.push(vars);
+        }
+
+        // an algorithm is deployed if it has copies in any group
+        let deployed = add_vars(
+            first.num_algorithms,
+            |j| add_binvar!(model, name: format!("deployed_{j}").as_str()),
+        )?;
+        for (g, vars) in group_vars.iter().enumerate() {
+            for ((j, k), &var) in vars.b.indexed_iter() {
+                model.add_constr(
+                    format!("deploy_{g}_{j}_{k}").as_str(),
+                    c!(deployed[j] >= var),
+                )?;
+            }
+        }
+        if let Some(max_deployed) = max_deployed {
+            model.add_constr(
+                "max_deployed",
+                c!(deployed.iter().grb_sum() <= max_deployed),
+            )?;
+        }
+        if let Some(budget) = options.cost_budget {
+            let spent = deployed
+                .iter()
+                .enumerate()
+                .map(|(j, &var)| var * cost(first, j))
+                .grb_sum();
+            model.add_constr("cost", c!(spent <= budget))?;
+        }
+        if let Some(penalty) = options.complexity_penalty {
+            objective_function =
+                objective_function + penalty * deployed.iter().grb_sum();
+        }
+
+        model.set_objective(objective_function, ModelSense::Minimize)?;
+        model.optimize().map_err(solve_error)?;
+        check_solve_status(&model, options)?;
+        if model.status()? == Status::Infeasible {
+            return Err(compute_infeasibility(
+                &mut model,
+                &[first],
+                num_cores,
+                options,
+            )?
+            .into());
+        }
+        let gap = model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+        groups
+            .iter()
+            .zip(group_vars)
+            .map(|((name, data), vars)| {
+                let solution = model.get_obj_attr_batch(attr::X, vars.b)?;
+                let portfolio = postprocess_solution(
+                    solution,
+                    data.num_algorithms,
+                    num_cores,
+                    &data.algorithms,
+                    format!("group_{name}_portfolio").as_str(),
+                    gap.abs() < f64::EPSILON,
+                );
+                Ok((name.clone(), portfolio))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,133 @@
+use ::highs::{Col, HighsModelStatus, RowProblem, Sense};
+use std::ops::Bound;
+use std::time::Instant;
+
+use super::linear::{self, LinearProblem, LinearSolution};
+use super::*;
+
+impl LinearProblem for RowProblem {
+    type Col = Col;
+
+    fn add_binary(&mut self, objective: f64) -> Col {
+        self.add_integer_column(objective, 0..=1)
+    }
+
+    fn add_continuous(&mut self, objective: f64) -> Col {
+        self.add_column(objective, 0.0..)
+    }
+
+    fn add_row(
+        &mut self,
+        lower: Option<f64>,
+        upper: Option<f64>,
+        terms: impl IntoIterator<Item = (Col, f64)>,
+    ) {
+        let bound = |value: Option<f64>| {
+            value.map_or(Bound::Unbounded, Bound::Included)
+        };
+        RowProblem::add_row(self, (bound(lower), bound(upper)), terms);
+    }
+}
+
+/// Create a portfolio from the input data using the open-source HiGHS solver.
+///
+/// Solves the same model as [`solve`] with the same timeout and initial solution semantics, but
+/// HiGHS reports no intermediate solutions (the history only contains the final portfolio) and
+/// does not explain infeasible models.
+pub fn solve_highs(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    initial: Option<&Portfolio>,
+) -> Result<OptimizationResult> {
+    check_objective(options)?;
+    check_quality_guarantee(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    #[cfg(feature = "server")]
+    let _job = crate::metrics::JobGuard::start();
+    let start = Instant::now();
+    let (n, m) = (data.num_algorithms, data.num_instances);
+
+    let mut problem = RowProblem::default();
+    let linear_model =
+        linear::build_model(&mut problem, data, num_cores, options);
+    let mut model = problem.optimise(Sense::Minimise);
+    if !options.solver_output() {
+        model.make_quiet();
+    }
+    model.set_option("time_limit", options.timeout.0);
+    if let Some(mip_gap) = options.mip_gap {
+        model.set_option("mip_rel_gap", mip_gap);
+    }
+    if let Some(node_limit) = options.node_limit {
+        model.set_option(
+            "mip_max_nodes",
+            i32::try_from(node_limit).unwrap_or(i32::MAX),
+        );
+    }
+    if let Some(threads) = options.threads {
+        model.set_option("threads", i32::try_from(threads)?);
+    }
+    if let Some(seed) = options.seed {
+        model.set_option("random_seed", i32::try_from(seed)?);
+    }
+
+    let initial = linear::initial_selection(initial, data, num_cores)?;
+    if let Some((b_start, _)) = &initial {
+        if let Some(start) =
+            linear::start_solution(&linear_model, b_start, data, num_cores)
+        {
+            if model
+                .try_set_solution(Some(&start), None, None, None)
+                .is_err()
+            {
+                warn!("HiGHS rejected the initial solution");
+            }
+        }
+    }
+
+    let solved = model
+        .try_solve()
+        .map_err(|status| anyhow::anyhow!("HiGHS failed: {status:?}"))?;
+    let status = solved.status();
+    if status == HighsModelStatus::Infeasible {
+        return Err(unexplained_infeasibility());
+    }
+    let gap = solved.mip_gap();
+    let solution = solved.get_solution();
+    let b_offset = m * n * num_cores;
+    // HiGHS reports an infinite gap without a primal feasible solution
+    if !gap.is_finite() || solution.columns().len() < b_offset + n * num_cores
+    {
+        if status == HighsModelStatus::ReachedTimeLimit {
+            return Err(SolverError::Timeout(options.timeout.0).into());
+        }
+        anyhow::bail!("HiGHS found no solution: {status:?}");
+    }
+    let b = solution.columns()[b_offset..b_offset + n * num_cores]
+        .iter()
+        .map(|x| x.round())
+        .collect_vec();
+    let objective = solved.objective_value();
+    let termination = match status {
+        HighsModelStatus::Optimal => {
+            gap_termination(Termination::Optimal, gap, options)
+        }
+        HighsModelStatus::ReachedTimeLimit => Termination::Timeout,
+        _ => Termination::Limit,
+    };
+    Ok(linear::linear_result(
+        data,
+        num_cores,
+        options,
+        start,
+        initial.map(|(_, portfolio)| portfolio),
+        LinearSolution {
+            b,
+            objective,
+            bound: objective - gap * objective.abs(),
+            gap,
+            termination,
+        },
+    ))
+}
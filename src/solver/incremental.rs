@@ -0,0 +1,407 @@
+use std::time::Instant;
+
+use super::*;
+
+/// A portfolio model that is kept alive between solves on growing data.
+///
+/// Re-solving a benchmark that gained a few instances or algorithms from scratch rebuilds the
+/// whole model and proves its structure again. Instead, every [`solve`](Incremental::solve) only
+/// adds the variables and constraints of the new instances and algorithms to the existing model,
+/// updates the objective coefficients of the cells, whose expected best qualities and the best
+/// quality per instance may change with the data, and starts from the portfolio of the previous
+/// solve. Instances are matched by their names (by their ids if the data has no names) and
+/// algorithms by their name and number of threads. Removing instances or algorithms is not
+/// supported.
+///
+/// The model assigns every instance to a selected cell and minimizes the sum of the
+/// [scores](ObjectiveFunction::score) of the assigned cells, so only objectives that sum a score
+/// per instance are supported, as is forbidding imputed cells. Quality guarantees, the chance
+/// constraint, algorithm constraints, budgets, core classes, the topology and penalties are not
+/// supported, the solution pool and checkpoints are ignored.
+pub struct Incremental {
+    model: Model,
+    num_cores: usize,
+    options: SolverOptions,
+    /// Instances of the model, in the order they were added
+    instances: Vec<String>,
+    /// Algorithms of the model, in the order they were added
+    algorithms: Vec<Algorithm>,
+    /// Assignment of instance `i` to algorithm `j` with `k + 1` repetitions, indexed `[i][j][k]`
+    a: Vec<Vec<Vec<grb::Var>>>,
+    /// Selection of algorithm `j` with `k + 1` repetitions, indexed `[j][k]`
+    b: Vec<Vec<grb::Var>>,
+    /// Every instance is assigned exactly once (constraint 4)
+    assigned: Vec<Constr>,
+    /// The cores of the selection (constraint 3)
+    cores: Option<Constr>,
+    /// Final portfolio of the previous solve
+    incumbent: Option<Portfolio>,
+}
+
+impl Solver {
+    /// Create an incremental solver with the Gurobi environment of this solver, see
+    /// [`Incremental`]
+    pub fn incremental(
+        &self,
+        num_cores: usize,
+        options: &SolverOptions,
+    ) -> Result<Incremental> {
+        Incremental::new(&self.env, num_cores, options)
+    }
+}
+
+impl Incremental {
+    /// Create an empty model for portfolios on `num_cores` cores, which is built by the first
+    /// [`solve`](Incremental::solve)
+    pub fn new(
+        env: &grb::Env,
+        num_cores: usize,
+        options: &SolverOptions,
+    ) -> Result<Self> {
+        anyhow::ensure!(
+            options.quality_guarantee.is_none()
+                && options.chance_constraint.is_none()
+                && options.algorithm_constraints.include.is_empty()
+                && options.algorithm_constraints.exclude.is_empty()
+                && options.algorithm_constraints.core_bounds.is_empty()
+                && options.memory_budget.is_none()
+                && options.cost_budget.is_none()
+                && options.core_classes.is_empty()
+                && options.topology.is_none()
+                && options.stability_penalty.is_none()
+                && options.idle_core_penalty.is_none()
+                && options.complexity_penalty.is_none(),
+            "Quality guarantees, the chance constraint, algorithm constraints, budgets, core classes, the topology and penalties are not supported by the incremental solver"
+        );
+        anyhow::ensure!(
+            benders::summing(&options.objective),
+            "The {:?} objective is not supported by the incremental solver",
+            options.objective
+        );
+        if options.checkpoint.is_some() || options.pool_size.is_some() {
+            warn!("Checkpoints and the solution pool are not supported by the incremental solver");
+        }
+        let mut model = Model::with_env("incremental_portfolio_model", env)
+            .map_err(|err| gurobi_error(err, SolverError::ModelBuild))?;
+        set_parameters(&mut model, options)?;
+        model.set_attr(attr::ModelSense, ModelSense::Minimize)?;
+        Ok(Self {
+            model,
+            num_cores,
+            options: options.clone(),
+            instances: Vec::new(),
+            algorithms: Vec::new(),
+            a: Vec::new(),
+            b: Vec::new(),
+            assigned: Vec::new(),
+            cores: None,
+            incumbent: None,
+        })
+    }
+
+    /// Number of instances and algorithms of the model
+    pub fn size(&self) -> (usize, usize) {
+        (self.instances.len(), self.algorithms.len())
+    }
+
+    /// Create a portfolio for `data`, which contains all instances and algorithms of the previous
+    /// solves and possibly new ones
+    ///
+    /// The first solve starts from the initial portfolio of [`solve`](crate::solver::solve),
+    /// every later one from the final portfolio of the previous solve.
+    pub fn solve(&mut self, data: &Data) -> Result<OptimizationResult> {
+        let start = Instant::now();
+        let (rows, cols) = self.update(data).map_err(model_build_error)?;
+        let num_cores = self.num_cores;
+        let n = data.num_algorithms;
+        // the selection in the order of the algorithms of the data
+        let b = cols
+            .iter()
+            .flat_map(|&j| self.b[j].iter().copied())
+            .collect_vec();
+
+        let initial_portfolio = match initial_assignment(
+            self.incumbent.as_ref(),
+            data,
+            num_cores,
+        )? {
+            Some(copies) => {
+                let initial_selection = selection(&copies, num_cores);
+                self.model.set_obj_attr_batch(
+                    attr::Start,
+                    b.iter().copied().zip(initial_selection.iter().copied()),
+                )?;
+                Some(postprocess_solution(
+                    initial_selection,
+                    n,
+                    num_cores,
+                    &data.algorithms,
+                    "initial_portfolio",
+                    false,
+                ))
+            }
+            None => None,
+        };
+        info!(
+            "Solving the incremental model with {} instances and {} algorithms",
+            rows.len(),
+            cols.len()
+        );
+
+        let options = &self.options;
+        let mut history = Vec::new();
+        let mut callback = |w: Where| {
+            if options.is_cancelled() {
+                terminate(&w);
+            }
+            if let Where::MIPSol(ctx) = w {
+                let portfolio = postprocess_solution(
+                    ctx.get_solution(b.iter())?,
+                    n,
+                    num_cores,
+                    &data.algorithms,
+                    "intermediate_portfolio",
+                    false,
+                );
+                history.push(Incumbent {
+                    portfolio,
+                    objective: ctx.obj()?,
+                    bound: ctx.obj_bnd()?,
+                    time: ctx.runtime()?,
+                    simulated: None,
+                });
+            }
+            Ok(())
+        };
+        export_and_import(&mut self.model, options)?;
+        self.model
+            .optimize_with_callback(&mut callback)
+            .map_err(solve_error)?;
+        check_solve_status(&self.model, options)?;
+        match self.model.status()? {
+            Status::Infeasible => {
+                anyhow::bail!("No portfolio fits on {num_cores} cores")
+            }
+            Status::Interrupted
+                if self.model.get_attr(attr::SolCount)? == 0 =>
+            {
+                anyhow::bail!(
+                    "The solver was cancelled before it found a portfolio"
+                )
+            }
+            _ => {}
+        }
+        let solution =
+            self.model.get_obj_attr_batch(attr::X, b.iter().copied())?;
+        let gap = self.model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+        let final_portfolio = postprocess_solution(
+            solution,
+            n,
+            num_cores,
+            &data.algorithms,
+            "final_portfolio",
+            gap.abs() < f64::EPSILON,
+        );
+        let stats =
+            solve_stats(&self.model, start, history.len(), &self.options)?;
+        self.incumbent = Some(final_portfolio.clone());
+        Ok(OptimizationResult {
+            gap_closed: gap_closed(data, &final_portfolio),
+            initial_gap_closed: initial_portfolio
+                .as_ref()
+                .map(|portfolio| gap_closed(data, portfolio)),
+            initial_portfolio,
+            final_portfolio,
+            gap,
+            history,
+            refined: false,
+            pool: Vec::new(),
+            stats,
+            instance_qualities: None,
+        })
+    }
+
+    /// Add the new instances and algorithms of `data` to the model and update the objective
+    /// coefficients, returns the indices in the model of the instances and algorithms of the data
+    fn update(&mut self, data: &Data) -> Result<(Vec<usize>, Vec<usize>)> {
+        let num_cores = self.num_cores;
+        anyhow::ensure!(
+            data.expected_best_quality.dim().2 >= num_cores,
+            "The data has expected best qualities for up to {} repetitions, but the portfolio has {num_cores} cores",
+            data.expected_best_quality.dim().2
+        );
+        let names = data.instances.clone().unwrap_or_else(|| {
+            (0..data.num_instances).map(|i| i.to_string()).collect_vec()
+        });
+        if let Some(removed) =
+            self.instances.iter().find(|name| !names.contains(name))
+        {
+            anyhow::bail!("Instance {removed} was removed from the data, the incremental solver only supports adding instances");
+        }
+        if let Some(removed) = self
+            .algorithms
+            .iter()
+            .find(|algo| !data.algorithms.iter().any(|a| a == *algo))
+        {
+            anyhow::bail!("{removed} was removed from the data, the incremental solver only supports adding algorithms");
+        }
+        let scores = benders::cell_scores(data, num_cores, &self.options)?;
+        let unassignable = scores
+            .outer_iter()
+            .enumerate()
+            .filter(|(_, scores)| scores.iter().all(|s| s.is_infinite()))
+            .map(|(i, _)| i)
+            .collect_vec();
+        anyhow::ensure!(
+            unassignable.is_empty(),
+            "No algorithm may be assigned to instances {unassignable:?}"
+        );
+
+        let new_algorithms = data
+            .algorithms
+            .iter()
+            .filter(|algo| !self.algorithms.contains(algo))
+            .cloned()
+            .collect_vec();
+        let new_instances = names
+            .iter()
+            .filter(|name| !self.instances.contains(name))
+            .cloned()
+            .collect_vec();
+        debug!(
+            "Adding {} instances and {} algorithms to the incremental model",
+            new_instances.len(),
+            new_algorithms.len()
+        );
+
+        // selection of the new algorithms with constraint 2 and their cores in constraint 3
+        for algo in new_algorithms {
+            let j = self.algorithms.len();
+            let row = (0..num_cores)
+                .map(|k| {
+                    let threads = (k + 1) as f64 * algo.num_threads as f64;
+                    let cores = self.cores.map(|c| (c, threads));
+                    self.model.add_var(
+                        &format!("b_{j}_{k}"),
+                        VarType::Binary,
+                        0.0,
+                        0.0,
+                        1.0,
+                        cores,
+                    )
+                })
+                .collect::<grb::Result<Vec<_>>>()?;
+            self.model.add_constr(
+                &format!("c2_{j}"),
+                c!(row.iter().grb_sum() <= 1),
+            )?;
+            self.b.push(row);
+            self.algorithms.push(algo);
+            // assignments of the existing instances to the new algorithm
+            for i in 0..self.instances.len() {
+                let row = self.assignment_vars(i, j)?;
+                self.a[i].push(row);
+            }
+        }
+        let sense = match self.algorithms.iter().any(|a| a.num_threads == 1) {
+            true => ConstrSense::Equal,
+            false => ConstrSense::Less,
+        };
+        match self.cores {
+            Some(cores) => {
+                self.model.set_obj_attr(attr::Sense, &cores, sense)?
+            }
+            None => {
+                let sum = self
+                    .b
+                    .iter()
+                    .zip(&self.algorithms)
+                    .flat_map(|(row, algo)| {
+                        row.iter()
+                            .zip(1..=num_cores)
+                            .map(|(var, k)| *var * k * algo.num_threads)
+                    })
+                    .grb_sum();
+                let constraint = match sense {
+                    ConstrSense::Equal => c!(sum == num_cores),
+                    _ => c!(sum <= num_cores),
+                };
+                self.cores = Some(self.model.add_constr("c3", constraint)?);
+            }
+        }
+
+        // assignments of the new instances with constraint 4
+        for name in new_instances {
+            let i = self.instances.len();
+            let assigned = self.model.add_constr(
+                &format!("c4_{i}"),
+                c!(grb::Expr::default() == 1),
+            )?;
+            self.assigned.push(assigned);
+            self.instances.push(name);
+            let rows = (0..self.algorithms.len())
+                .map(|j| self.assignment_vars(i, j))
+                .collect::<Result<Vec<_>>>()?;
+            self.a.push(rows);
+        }
+
+        let rows = names
+            .iter()
+            .map(|name| self.instances.iter().position(|n| n == name).unwrap())
+            .collect_vec();
+        let cols = data
+            .algorithms
+            .iter()
+            .map(|algo| {
+                self.algorithms.iter().position(|a| a == algo).unwrap()
+            })
+            .collect_vec();
+        let cells = scores
+            .indexed_iter()
+            .map(|((i, j, k), &score)| (self.a[rows[i]][cols[j]][k], score));
+        let (vars, scores): (Vec<_>, Vec<_>) = cells.unzip();
+        self.model.set_obj_attr_batch(
+            attr::Obj,
+            vars.iter().copied().zip(scores.iter().map(|s| {
+                if s.is_finite() {
+                    *s
+                } else {
+                    0.0
+                }
+            })),
+        )?;
+        self.model.set_obj_attr_batch(
+            attr::UB,
+            vars.iter()
+                .copied()
+                .zip(scores.iter().map(|s| s.is_finite() as u8 as f64)),
+        )?;
+        Ok((rows, cols))
+    }
+
+    /// Add the assignments of instance `i` to algorithm `j` with constraint 5 and their terms of
+    /// constraint 4, the objective coefficients are set by [`update`](Incremental::update)
+    fn assignment_vars(
+        &mut self,
+        i: usize,
+        j: usize,
+    ) -> Result<Vec<grb::Var>> {
+        let mut row = Vec::with_capacity(self.num_cores);
+        for k in 0..self.num_cores {
+            let var = self.model.add_var(
+                &format!("a_{i}_{j}_{k}"),
+                VarType::Binary,
+                0.0,
+                0.0,
+                1.0,
+                [(self.assigned[i], 1.0)],
+            )?;
+            self.model.add_constr(
+                &format!("c5_{i}_{j}_{k}"),
+                c!(var <= self.b[j][k]),
+            )?;
+            row.push(var);
+        }
+        Ok(row)
+    }
+}
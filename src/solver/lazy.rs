@@ -0,0 +1,92 @@
+use grb::callback::MIPSolCtx;
+
+use super::*;
+
+/// Linking constraint of the portfolio model, see [`add_linking_constraints`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    /// Constraint 1, an instance has at least the expected quality of the cell it is assigned to
+    Quality,
+    /// Constraint 5, an instance can only be assigned to a selected cell
+    Selection,
+}
+
+/// Linking constraints of the cells `(i, j, k)` violated by the integral solution `a`, `b` and `q`
+/// (in the order of the variables) of a model without them
+///
+/// Only the cell an instance is assigned to can violate its linking constraints.
+fn violated_links(
+    e_min: &Array3<f64>,
+    a: &[f64],
+    b: &[f64],
+    q: &[f64],
+) -> Vec<(Link, (usize, usize, usize))> {
+    let (_, n, num_cores) = e_min.dim();
+    a.iter()
+        .enumerate()
+        .filter(|(_, &assigned)| assigned > 0.5)
+        .flat_map(|(cell, _)| {
+            let (i, j, k) = (
+                cell / (n * num_cores),
+                cell / num_cores % n,
+                cell % num_cores,
+            );
+            let e = e_min[(i, j, k)];
+            let quality = (q[i] < e - 1e-6 * e.abs().max(1.0))
+                .then_some((Link::Quality, (i, j, k)));
+            let selection = (b[j * num_cores + k] < 0.5)
+                .then_some((Link::Selection, (i, j, k)));
+            quality.into_iter().chain(selection)
+        })
+        .collect_vec()
+}
+
+/// Add the linking constraints violated by the solution of `ctx` as lazy constraints and return
+/// their number, the solution is rejected if any were added
+pub(super) fn separate(
+    ctx: &MIPSolCtx,
+    vars: &PortfolioVars,
+    data: &Data,
+) -> Result<usize> {
+    let PortfolioVars { a, b, q, .. } = vars;
+    let e_min = &data.expected_best_quality;
+    let links = violated_links(
+        e_min,
+        &ctx.get_solution(a.iter())?,
+        &ctx.get_solution(b.iter())?,
+        &ctx.get_solution(q.iter())?,
+    );
+    for &(link, (i, j, k)) in &links {
+        let constraint = match link {
+            Link::Quality => c!(a[(i, j, k)] * e_min[(i, j, k)] <= q[i]),
+            Link::Selection => c!(a[(i, j, k)] <= b[(j, k)]),
+        };
+        ctx.add_lazy(constraint)?;
+    }
+    Ok(links.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violated_links() {
+        // two instances, two algorithms, one repetition
+        let e_min =
+            Array3::from_shape_vec((2, 2, 1), vec![1.0, 2.0, 3.0, 4.0])
+                .unwrap();
+        let b = [1.0, 0.0];
+        assert!(violated_links(
+            &e_min,
+            &[1.0, 0.0, 1.0, 0.0],
+            &b,
+            &[1.0, 3.0]
+        )
+        .is_empty());
+        assert_eq!(
+            violated_links(&e_min, &[0.0, 1.0, 1.0, 0.0], &b, &[2.0, 0.0]),
+            vec![(Link::Selection, (0, 1, 0)), (Link::Quality, (1, 0, 0))]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use std::time::Instant;
+
+use super::*;
+
+/// A mixed-integer program of an open-source backend that [`build_model`] adds the model to
+pub(super) trait LinearProblem {
+    /// Handle of a column of the problem
+    type Col: Copy;
+
+    /// Add a binary column with the `objective` coefficient
+    fn add_binary(&mut self, objective: f64) -> Self::Col;
+
+    /// Add a non-negative continuous column with the `objective` coefficient
+    fn add_continuous(&mut self, objective: f64) -> Self::Col;
+
+    /// Add the row `lower <= sum(weight * col) <= upper`, a missing bound is unbounded
+    fn add_row(
+        &mut self,
+        lower: Option<f64>,
+        upper: Option<f64>,
+        terms: impl IntoIterator<Item = (Self::Col, f64)>,
+    );
+}
+
+/// Columns of the model added by [`build_model`]
+pub(super) struct LinearModel<C> {
+    /// Selection of the algorithms and their numbers of repetitions
+    pub b: Array2<C>,
+    /// Previous cores of every algorithm and the weight of the stability penalty
+    pub previous: Option<(Vec<f64>, f64)>,
+}
+
+/// Add the model of [`solve`] to the `problem` of an open-source backend
+///
+/// The columns are added in the order a, b, q, d, which [`start_solution`] relies on. The
+/// constant offset of the idle core penalty is not part of the objective, see [`linear_result`].
+pub(super) fn build_model<P: LinearProblem>(
+    problem: &mut P,
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> LinearModel<P::Col> {
+    let (n, m) = (data.num_algorithms, data.num_instances);
+    let e_min = &data.expected_best_quality;
+    let idle_core_penalty = options.idle_core_penalty.unwrap_or(0.0);
+    let complexity_penalty = options.complexity_penalty.unwrap_or(0.0);
+    let threads = |j: usize| data.algorithms[j].num_threads as f64;
+
+    let a =
+        Array3::from_shape_fn((m, n, num_cores), |_| problem.add_binary(0.0));
+    let b = Array2::from_shape_fn((n, num_cores), |(j, k)| {
+        let cores = (k + 1) as f64 * threads(j);
+        problem.add_binary(complexity_penalty - idle_core_penalty * cores)
+    });
+    let q = Array1::from_shape_fn(m, |i| {
+        problem.add_continuous(1.0 / data.best_per_instance[i])
+    });
+    let cores = |j: usize| {
+        (0..num_cores)
+            .map(|k| (b[(j, k)], (k + 1) as f64 * threads(j)))
+            .collect_vec()
+    };
+
+    // constraint 1
+    for ((i, j, k), &val_a) in a.indexed_iter() {
+        problem.add_row(
+            None,
+            Some(0.0),
+            [(val_a, e_min[(i, j, k)]), (q[i], -1.0)],
+        );
+    }
+    // constraint 2
+    for row in b.rows() {
+        problem.add_row(None, Some(1.0), row.iter().map(|&var| (var, 1.0)));
+    }
+    // constraint 3
+    let sums = (0..n).flat_map(cores).collect_vec();
+    let lower = data
+        .algorithms
+        .iter()
+        .any(|a| a.num_threads == 1)
+        .then_some(num_cores as f64);
+    problem.add_row(lower, Some(num_cores as f64), sums);
+    // constraint 4
+    for row in a.outer_iter() {
+        problem.add_row(
+            Some(1.0),
+            Some(1.0),
+            row.iter().map(|&var| (var, 1.0)),
+        );
+    }
+    // constraint 5
+    for ((_, j, k), &val_a) in a.indexed_iter() {
+        problem.add_row(None, Some(0.0), [(val_a, 1.0), (b[(j, k)], -1.0)]);
+    }
+    if let Some(guarantee) = &options.quality_guarantee {
+        for i in guarantee_instances(guarantee, m) {
+            problem.add_row(
+                None,
+                Some(guarantee.factor * data.best_per_instance[i]),
+                [(q[i], 1.0)],
+            );
+        }
+    }
+    for (j, include) in forced_algorithms(data, options) {
+        let selected = match include {
+            true => 1.0,
+            false => 0.0,
+        };
+        problem.add_row(
+            Some(selected),
+            Some(selected),
+            b.row(j).iter().map(|&var| (var, 1.0)),
+        );
+    }
+    for (j, min, max) in core_bounds(data, num_cores, options) {
+        problem.add_row(Some(min), Some(max), cores(j));
+    }
+    for (i, j, k) in forbidden_cells(data, options) {
+        problem.add_row(None, Some(0.0), [(a[(i, j, k)], 1.0)]);
+    }
+    if let Some(budget) = options.memory_budget {
+        let used = b
+            .indexed_iter()
+            .map(|((j, k), &var)| (var, (k + 1) as f64 * memory(data, j)))
+            .collect_vec();
+        problem.add_row(None, Some(budget), used);
+    }
+    if let Some(budget) = options.cost_budget {
+        let spent = b
+            .indexed_iter()
+            .map(|((j, _), &var)| (var, cost(data, j)))
+            .collect_vec();
+        problem.add_row(None, Some(budget), spent);
+    }
+    let previous = options.stability_penalty.as_ref().map(|stability| {
+        (previous_cores(&stability.previous, data), stability.penalty)
+    });
+    if let Some((previous_cores, penalty)) = &previous {
+        for (j, previous) in previous_cores.iter().enumerate() {
+            let d = problem.add_continuous(*penalty);
+            let cores = cores(j);
+            problem.add_row(
+                Some(-previous),
+                None,
+                cores.iter().map(|&(var, c)| (var, -c)).chain([(d, 1.0)]),
+            );
+            problem.add_row(
+                Some(*previous),
+                None,
+                cores.into_iter().chain([(d, 1.0)]),
+            );
+        }
+    }
+    LinearModel { b, previous }
+}
+
+/// Selection `b` of the initial portfolio (see [`initial_assignment`]) and the portfolio itself
+pub(super) fn initial_selection(
+    initial: Option<&Portfolio>,
+    data: &Data,
+    num_cores: usize,
+) -> Result<Option<(Vec<f64>, Portfolio)>> {
+    let Some(initial_assignment) =
+        initial_assignment(initial, data, num_cores)?
+    else {
+        info!("No initial portfolio provided");
+        return Ok(None);
+    };
+    let b_start =
+        assignment_to_b(&initial_assignment, data.num_algorithms, num_cores);
+    let initial_portfolio = postprocess_solution(
+        b_start.clone(),
+        data.num_algorithms,
+        num_cores,
+        &data.algorithms,
+        "initial_portfolio",
+        false,
+    );
+    info!("Initial portfolio:\n{initial_portfolio}");
+    Ok(Some((b_start, initial_portfolio)))
+}
+
+/// Complete initial solution (in column order a, b, q, d) of the `model` for the selection
+/// `b_start`
+///
+/// Every instance is assigned to the selected algorithm with the best expected quality, `None` if
+/// no algorithm is selected.
+pub(super) fn start_solution<C>(
+    model: &LinearModel<C>,
+    b_start: &[f64],
+    data: &Data,
+    num_cores: usize,
+) -> Option<Vec<f64>> {
+    let (n, m) = (data.num_algorithms, data.num_instances);
+    let e_min = &data.expected_best_quality;
+    let selected = (0..n)
+        .cartesian_product(0..num_cores)
+        .filter(|&(j, k)| b_start[j * num_cores + k] > 0.5)
+        .collect_vec();
+    let mut a = Array3::<f64>::zeros((m, n, num_cores));
+    let mut q = Vec::with_capacity(m);
+    for i in 0..m {
+        let &(j, k) = selected.iter().min_by(|&&(j1, k1), &&(j2, k2)| {
+            e_min[(i, j1, k1)].total_cmp(&e_min[(i, j2, k2)])
+        })?;
+        a[(i, j, k)] = 1.0;
+        q.push(e_min[(i, j, k)]);
+    }
+    let d = match &model.previous {
+        Some((previous_cores, _)) => previous_cores
+            .iter()
+            .enumerate()
+            .map(|(j, previous)| {
+                let cores = selected
+                    .iter()
+                    .filter(|&&(selected, _)| selected == j)
+                    .map(|&(_, k)| {
+                        (k + 1) as f64 * data.algorithms[j].num_threads as f64
+                    })
+                    .sum::<f64>();
+                (cores - previous).abs()
+            })
+            .collect_vec(),
+        None => Vec::new(),
+    };
+    Some(
+        a.iter()
+            .copied()
+            .chain(b_start.iter().copied())
+            .chain(q)
+            .chain(d)
+            .collect_vec(),
+    )
+}
+
+/// Final solution of an open-source backend, see [`linear_result`]
+pub(super) struct LinearSolution {
+    /// Values of the selection `b`
+    pub b: Vec<f64>,
+    /// Objective value of the model of [`build_model`]
+    pub objective: f64,
+    /// Lower bound on the objective value of the model of [`build_model`]
+    pub bound: f64,
+    /// Relative gap between the objective value and the bound
+    pub gap: f64,
+    /// Why the backend stopped
+    pub termination: Termination,
+}
+
+/// Result of an open-source backend that started at `start` and stopped with the `solution`
+///
+/// The history only contains the final portfolio, the backends report no intermediate solutions.
+pub(super) fn linear_result(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    start: Instant,
+    initial_portfolio: Option<Portfolio>,
+    solution: LinearSolution,
+) -> OptimizationResult {
+    let LinearSolution {
+        b,
+        objective,
+        bound,
+        gap,
+        termination,
+    } = solution;
+    #[cfg(feature = "server")]
+    crate::metrics::SOLVER_GAP.set(gap);
+    let final_portfolio = postprocess_solution(
+        b,
+        data.num_algorithms,
+        num_cores,
+        &data.algorithms,
+        "final_portfolio",
+        gap.abs() < f64::EPSILON,
+    );
+    let offset = options.idle_core_penalty.unwrap_or(0.0) * num_cores as f64;
+    debug!("Final objective value: {}", objective + offset);
+    OptimizationResult {
+        gap_closed: gap_closed(data, &final_portfolio),
+        initial_gap_closed: initial_portfolio
+            .as_ref()
+            .map(|portfolio| gap_closed(data, portfolio)),
+        initial_portfolio,
+        final_portfolio: final_portfolio.clone(),
+        gap,
+        history: vec![Incumbent {
+            portfolio: final_portfolio,
+            objective: objective + offset,
+            bound: bound + offset,
+            time: start.elapsed().as_secs_f64(),
+            simulated: None,
+        }],
+        refined: false,
+        pool: Vec::new(),
+        stats: SolveStats {
+            wall_time: start.elapsed().as_secs_f64(),
+            node_count: None,
+            objective: objective + offset,
+            bound: Some(bound + offset),
+            num_incumbents: 1,
+            termination,
+            numeric_focus: None,
+        },
+        instance_qualities: None,
+    }
+}
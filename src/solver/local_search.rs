@@ -0,0 +1,412 @@
+use itertools::Itertools;
+use log::{debug, info};
+
+use super::{
+    core_bounds, core_class, cost, fits_topology, forced_algorithms,
+    gap_closed, guarantee_instances, memory, portfolio_copies,
+    portfolio_from_copies, previous_cores, quality_ratios,
+};
+use crate::csv_parser::Data;
+use crate::datastructures::*;
+
+/// Improve the final portfolio of a `result` with a nonzero gap by local search
+///
+/// Starting from the final portfolio, the best improving move is applied until no move improves
+/// the objective of the model (including the penalties of the `options`). A move either shifts a
+/// single copy of one algorithm to (as many copies as fit of) another algorithm or swaps all
+/// copies of an algorithm for another algorithm. Moves that violate a constraint of the model are
+/// skipped. The search is deterministic, an improved portfolio is appended to the history. A
+/// cancelled solve is not refined.
+pub(super) fn refine(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    mut result: OptimizationResult,
+) -> OptimizationResult {
+    if result.gap.abs() < f64::EPSILON
+        || result.gap.is_nan()
+        || options.is_cancelled()
+    {
+        return result;
+    }
+    let threads = data
+        .algorithms
+        .iter()
+        .map(|a| a.num_threads as usize)
+        .collect_vec();
+    let mut copies = portfolio_copies(&result.final_portfolio, data);
+    let evaluate = |copies: &[f64]| match feasible(
+        data, num_cores, options, &threads, copies,
+    ) {
+        true => penalized_objective(data, num_cores, options, copies),
+        false => f64::INFINITY,
+    };
+    let initial = evaluate(&copies);
+    let mut current = initial;
+    while let Some((next, value)) = moves(&copies, &threads, num_cores)
+        .map(|next| {
+            let value = evaluate(&next);
+            (next, value)
+        })
+        .filter(|(_, value)| *value < current - 1e-9)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    {
+        debug!("Local search: objective {current} -> {value}");
+        copies = next;
+        current = value;
+    }
+    if current >= initial {
+        return result;
+    }
+    info!("Local search improved the objective from {initial} to {current}");
+    let final_portfolio =
+        portfolio_from_copies(data, &copies, "final_portfolio");
+    let (bound, time) = result
+        .history
+        .last()
+        .map_or((f64::NEG_INFINITY, 0.0), |last| (last.bound, last.time));
+    result.history.push(Incumbent {
+        portfolio: final_portfolio.clone(),
+        objective: current,
+        bound,
+        time,
+        simulated: None,
+    });
+    result.stats.objective = current;
+    result.stats.num_incumbents = result.history.len();
+    if bound.is_finite() {
+        result.gap = (current - bound).abs() / current.abs();
+    }
+    result.gap_closed = gap_closed(data, &final_portfolio);
+    result.final_portfolio = final_portfolio;
+    result.refined = true;
+    result
+}
+
+/// Remove algorithms from the `portfolio` as long as the objective of the model (including the
+/// penalties of the `options`) degrades by at most the relative `tolerance`, for leaner portfolios
+/// that are easier to operate.
+///
+/// In every round, each algorithm of the portfolio is removed on its own and its cores are
+/// reallocated greedily to the remaining algorithms, adding the copy with the best objective until
+/// no further copy fits. The removal with the best objective is kept if the portfolio still
+/// satisfies the constraints of the model and its objective is within the tolerance of the
+/// original portfolio, so the degradation does not add up over the rounds. At least one algorithm
+/// is always kept.
+pub fn prune(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    portfolio: &Portfolio,
+    tolerance: f64,
+) -> Portfolio {
+    let threads = data
+        .algorithms
+        .iter()
+        .map(|a| a.num_threads as usize)
+        .collect_vec();
+    let mut copies = portfolio_copies(portfolio, data)
+        .into_iter()
+        .map(f64::round)
+        .collect_vec();
+    let objective =
+        |copies: &[f64]| penalized_objective(data, num_cores, options, copies);
+    let initial = objective(&copies);
+    let limit = initial + tolerance * initial.abs();
+    loop {
+        let selected = (0..copies.len())
+            .filter(|&j| copies[j] >= 1.0)
+            .collect_vec();
+        if selected.len() <= 1 {
+            break;
+        }
+        let Some((removed, next, value)) = selected
+            .iter()
+            .filter_map(|&removed| {
+                let mut next = copies.clone();
+                next[removed] = 0.0;
+                let remaining = selected
+                    .iter()
+                    .copied()
+                    .filter(|&j| j != removed)
+                    .collect_vec();
+                reallocate(
+                    &mut next, &remaining, &threads, num_cores, objective,
+                );
+                feasible(data, num_cores, options, &threads, &next).then(
+                    || {
+                        let value = objective(&next);
+                        (removed, next, value)
+                    },
+                )
+            })
+            .filter(|(_, _, value)| *value <= limit)
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        else {
+            break;
+        };
+        debug!(
+            "Pruning {}: objective {} -> {value}",
+            data.algorithms[removed],
+            objective(&copies)
+        );
+        copies = next;
+    }
+    let pruned = portfolio_from_copies(data, &copies, &portfolio.name);
+    let removed = portfolio
+        .resource_assignments
+        .iter()
+        .filter(|(algo, c)| {
+            *c >= 1.0
+                && !pruned
+                    .resource_assignments
+                    .iter()
+                    .any(|(a, c)| a == algo && *c >= 1.0)
+        })
+        .map(|(algo, _)| algo.to_string())
+        .collect_vec();
+    if !removed.is_empty() {
+        info!(
+            "Pruned {} from the portfolio, the objective degraded from {initial} to {}",
+            removed.join(", "),
+            objective(&copies)
+        );
+    }
+    pruned
+}
+
+/// Fill the free cores of `copies` greedily with copies of the algorithms that are not excluded,
+/// e.g. to start the solver for more cores from the portfolio for fewer cores
+///
+/// The `copies` are returned unchanged if the filled ones violate a constraint of the model.
+pub(super) fn fill(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    copies: &[f64],
+) -> Vec<f64> {
+    let threads = data
+        .algorithms
+        .iter()
+        .map(|a| a.num_threads as usize)
+        .collect_vec();
+    let excluded = forced_algorithms(data, options)
+        .into_iter()
+        .filter_map(|(j, include)| (!include).then_some(j))
+        .collect_vec();
+    let candidates = (0..data.num_algorithms)
+        .filter(|j| !excluded.contains(j))
+        .collect_vec();
+    let mut filled = copies.to_vec();
+    reallocate(&mut filled, &candidates, &threads, num_cores, |copies| {
+        penalized_objective(data, num_cores, options, copies)
+    });
+    match feasible(data, num_cores, options, &threads, &filled) {
+        true => filled,
+        false => copies.to_vec(),
+    }
+}
+
+/// Add copies of the `candidates` to `copies` until none of them fits on the free cores, always
+/// adding the copy with the best `objective`
+fn reallocate(
+    copies: &mut [f64],
+    candidates: &[usize],
+    threads: &[usize],
+    num_cores: usize,
+    objective: impl Fn(&[f64]) -> f64,
+) {
+    loop {
+        let used = copies
+            .iter()
+            .zip(threads)
+            .map(|(c, &t)| *c as usize * t)
+            .sum::<usize>();
+        let Some((j, _)) = candidates
+            .iter()
+            .filter(|&&j| used + threads[j] <= num_cores)
+            .map(|&j| {
+                let mut next = copies.to_vec();
+                next[j] += 1.0;
+                (j, objective(&next))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        else {
+            break;
+        };
+        copies[j] += 1.0;
+    }
+}
+
+/// All resource assignments reachable from `copies` with a single shift or swap
+fn moves<'a>(
+    copies: &'a [f64],
+    threads: &'a [usize],
+    num_cores: usize,
+) -> impl Iterator<Item = Vec<f64>> + 'a {
+    let n = copies.len();
+    let used = copies
+        .iter()
+        .zip(threads)
+        .map(|(c, &t)| *c as usize * t)
+        .sum::<usize>();
+    (0..n)
+        .filter(|&from| copies[from] >= 1.0)
+        .cartesian_product(0..n)
+        .filter(|&(from, to)| from != to)
+        .flat_map(move |(from, to)| {
+            let shift = {
+                let free = num_cores - used + threads[from];
+                let mut next = copies.to_vec();
+                next[from] -= 1.0;
+                next[to] += (free / threads[to]) as f64;
+                next
+            };
+            let swap = {
+                let free =
+                    num_cores - used + copies[from] as usize * threads[from];
+                let mut next = copies.to_vec();
+                next[from] = 0.0;
+                next[to] += (free / threads[to]) as f64;
+                next
+            };
+            [shift, swap]
+        })
+        .filter(move |next| next.iter().zip(copies).any(|(a, b)| a != b))
+}
+
+/// Whether `copies` satisfy the core, memory and cost budgets, the core classes, the topology, the
+/// algorithm constraints (including the core bounds and forbidden imputed qualities) and the
+/// quality guarantees of the model
+pub(super) fn feasible(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    threads: &[usize],
+    copies: &[f64],
+) -> bool {
+    let used = copies
+        .iter()
+        .zip(threads)
+        .map(|(c, &t)| *c as usize * t)
+        .sum::<usize>();
+    let all_cores = threads.contains(&1);
+    if used > num_cores || (all_cores && used < num_cores) {
+        return false;
+    }
+    if forced_algorithms(data, options)
+        .into_iter()
+        .any(|(j, include)| (copies[j] >= 1.0) != include)
+    {
+        return false;
+    }
+    if core_bounds(data, num_cores, options).into_iter().any(
+        |(j, min, max)| {
+            let cores = copies[j] * threads[j] as f64;
+            cores < min || cores > max
+        },
+    ) {
+        return false;
+    }
+    if options.forbid_imputed
+        && (0..data.num_instances).any(|i| {
+            copies.iter().enumerate().all(|(j, &c)| {
+                c < 1.0 || data.is_imputed(i, j, c as usize - 1)
+            })
+        })
+    {
+        return false;
+    }
+    if let Some(budget) = options.memory_budget {
+        let used = copies
+            .iter()
+            .enumerate()
+            .map(|(j, c)| c * memory(data, j))
+            .sum::<f64>();
+        if used > budget {
+            return false;
+        }
+    }
+    for (c, class) in options.core_classes.iter().enumerate() {
+        let used = copies
+            .iter()
+            .zip(threads)
+            .enumerate()
+            .filter(|(j, _)| core_class(data, *j, options) == Some(c))
+            .map(|(_, (copies, &t))| copies * t as f64)
+            .sum::<f64>();
+        if used > class.cores as f64 {
+            return false;
+        }
+    }
+    if let Some(topology) = &options.topology {
+        if !fits_topology(data, topology, copies) {
+            return false;
+        }
+    }
+    if let Some(budget) = options.cost_budget {
+        let spent = copies
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c >= 1.0)
+            .map(|(j, _)| cost(data, j))
+            .sum::<f64>();
+        if spent > budget {
+            return false;
+        }
+    }
+    if options
+        .chance_constraint
+        .as_ref()
+        .map_or(false, |chance| !chance.satisfied(copies))
+    {
+        return false;
+    }
+    let Some(guarantee) = &options.quality_guarantee else {
+        return true;
+    };
+    let e_min = &data.expected_best_quality;
+    guarantee_instances(guarantee, data.num_instances)
+        .into_iter()
+        .all(|i| {
+            copies.iter().enumerate().filter(|(_, c)| **c >= 1.0).any(
+                |(j, c)| {
+                    e_min[(i, j, *c as usize - 1)]
+                        <= guarantee.factor * data.best_per_instance[i]
+                },
+            )
+        })
+}
+
+/// [`objectives::value`](super::objectives::value) of `copies` including the idle core, stability
+/// and complexity penalties of the `options`
+pub(super) fn penalized_objective(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    copies: &[f64],
+) -> f64 {
+    let cores = copies
+        .iter()
+        .zip(&data.algorithms)
+        .map(|(c, a)| c * a.num_threads as f64)
+        .collect_vec();
+    let idle = options.idle_core_penalty.unwrap_or(0.0)
+        * (num_cores as f64 - cores.iter().sum::<f64>());
+    let stability = options.stability_penalty.as_ref().map_or(0.0, |s| {
+        let previous = previous_cores(&s.previous, data);
+        s.penalty
+            * cores
+                .iter()
+                .zip(previous)
+                .map(|(c, p)| (c - p).abs())
+                .sum::<f64>()
+    });
+    let complexity = options.complexity_penalty.unwrap_or(0.0)
+        * copies.iter().filter(|&&c| c >= 1.0).count() as f64;
+    let ratios = quality_ratios(data, copies);
+    super::objectives::value(&options.objective, data, &ratios)
+        + idle
+        + stability
+        + complexity
+}
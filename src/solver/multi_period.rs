@@ -0,0 +1,167 @@
+use super::*;
+
+/// Create one portfolio per period using the Gurobi Optimizer.
+///
+/// `periods[t]` is the input data for period `t` and its quality objective is weighted with
+/// `weights[t]` (see [`recency_weights`]). Changing the number of cores of an algorithm between
+/// two consecutive periods costs `switching_cost` per core. All periods must contain the same
+/// algorithms.
+///
+/// The `options` are applied to every period, except for the stability penalty, which only
+/// applies to the first period.
+pub fn solve_multi_period(
+    periods: &[Data],
+    num_cores: usize,
+    weights: &[f64],
+    switching_cost: f64,
+    options: &SolverOptions,
+) -> Result<Vec<Portfolio>> {
+    Solver::for_options(options)?.solve_multi_period(
+        periods,
+        num_cores,
+        weights,
+        switching_cost,
+        options,
+    )
+}
+
+/// Weights `decay^(num_periods - 1 - t)` for `num_periods` periods in chronological order, the
+/// last period being the most recent one
+pub fn recency_weights(num_periods: usize, decay: f64) -> Vec<f64> {
+    (0..num_periods)
+        .map(|t| decay.powi((num_periods - 1 - t) as i32))
+        .collect_vec()
+}
+
+impl Solver {
+    /// Create one portfolio per period, see [`solve_multi_period`]
+    pub fn solve_multi_period(
+        &self,
+        periods: &[Data],
+        num_cores: usize,
+        weights: &[f64],
+        switching_cost: f64,
+        options: &SolverOptions,
+    ) -> Result<Vec<Portfolio>> {
+        anyhow::ensure!(!periods.is_empty(), "No periods provided");
+        anyhow::ensure!(
+            periods.len() == weights.len(),
+            "Got {} weights for {} periods",
+            weights.len(),
+            periods.len()
+        );
+        anyhow::ensure!(
+            periods
+                .iter()
+                .all(|data| data.algorithms == periods[0].algorithms),
+            "All periods must contain the same algorithms"
+        );
+        anyhow::ensure!(
+            options.chance_constraint.is_none(),
+            "The chance constraint is not supported for multiple periods"
+        );
+        for data in periods {
+            check_quality_guarantee(data, num_cores, options)?;
+            check_algorithm_constraints(data, num_cores, options)?;
+        }
+        let mut model =
+            Model::with_env("multi_period_portfolio_model", &self.env)?;
+        set_parameters(&mut model, options)?;
+
+        let later_options = SolverOptions {
+            stability_penalty: None,
+            ..options.clone()
+        };
+        let mut objective_function = grb::Expr::default();
+        let mut period_vars: Vec<PortfolioVars> = Vec::new();
+        for (t, (data, &weight)) in periods.iter().zip(weights).enumerate() {
+            let prefix = format!("p{t}_");
+            let period_options = match t {
+                0 => options,
+                _ => &later_options,
+            };
+            let vars =
+                add_portfolio_model(&mut model, data, num_cores, &prefix)?;
+            add_quality_guarantees(
+                &mut model,
+                &vars.q,
+                data,
+                period_options,
+                &prefix,
+            )?;
+            add_algorithm_constraints(
+                &mut model,
+                &vars,
+                data,
+                period_options,
+                &prefix,
+            )?;
+            add_budgets(&mut model, &vars.b, data, period_options, &prefix)?;
+            objective_function = objective_function
+                + weight
+                    * quality_objective(
+                        &mut model,
+                        &vars,
+                        data,
+                        &period_options.objective,
+                        &prefix,
+                    )?
+                + option_penalties(
+                    &mut model,
+                    &vars.b,
+                    &vars.cores,
+                    data,
+                    num_cores,
+                    period_options,
+                    &prefix,
+                )?;
+            if let Some(previous) = period_vars.last() {
+                objective_function = objective_function
+                    + switching_cost
+                        * add_l1_distance(
+                            &mut model,
+                            &vars.cores,
+                            &previous.cores,
+                            &format!("switch_{t}"),
+                        )?;
+            }
+            period_vars.push(vars);
+        }
+
+        model.set_objective(objective_function, ModelSense::Minimize)?;
+        model.optimize()?;
+        check_solve_status(&model, options)?;
+        if model.status()? == Status::Infeasible {
+            return Err(compute_infeasibility(
+                &mut model,
+                &periods.iter().collect_vec(),
+                num_cores,
+                options,
+            )?
+            .into());
+        }
+        if model.status()? == Status::Interrupted
+            && model.get_attr(attr::SolCount)? == 0
+        {
+            anyhow::bail!(
+                "The solver was cancelled before it found a portfolio"
+            );
+        }
+        let gap = model.get_attr(attr::MIPGap).unwrap_or(f64::MAX);
+        period_vars
+            .into_iter()
+            .enumerate()
+            .map(|(t, vars)| {
+                let solution = model.get_obj_attr_batch(attr::X, vars.b)?;
+                Ok(postprocess_solution(
+                    solution,
+                    periods[t].num_algorithms,
+                    num_cores,
+                    &periods[t].algorithms,
+                    format!("period_{t}_portfolio").as_str(),
+                    gap.abs() < f64::EPSILON,
+                ))
+            })
+            .collect()
+    }
+}
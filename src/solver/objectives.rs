@@ -0,0 +1,227 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use grb::prelude::*;
+use polars::prelude::*;
+
+use super::PortfolioVars;
+use crate::csv_parser::Data;
+use crate::datastructures::Objective;
+
+/// Objective of the portfolio model that can be [registered](register) under a name and selected
+/// with [`Objective::Custom`] without changing the model
+///
+/// Unless [`build`](ObjectiveFunction::build) and [`value`](ObjectiveFunction::value) are
+/// overridden, the objective is the sum of the [`score`](ObjectiveFunction::score)s of the quality
+/// ratios `q_i / best_i` of all instances.
+pub trait ObjectiveFunction: Send + Sync {
+    /// Contribution of `instance` with the quality ratio `ratio` to the objective, must not
+    /// decrease with the ratio
+    fn score(&self, data: &Data, instance: usize, ratio: f64) -> f64;
+
+    /// Objective value of the quality ratios of all instances, used by the local search
+    fn value(&self, data: &Data, ratios: &[f64]) -> f64 {
+        ratios
+            .iter()
+            .enumerate()
+            .map(|(i, &ratio)| self.score(data, i, ratio))
+            .sum()
+    }
+
+    /// Objective term of the model with the variables `vars` for `data`, the names of additional
+    /// variables and constraints have to start with `prefix`
+    ///
+    /// By default, every assignment of an instance is scored with [`assignment_objective`].
+    fn build(
+        &self,
+        model: &mut Model,
+        vars: &PortfolioVars,
+        data: &Data,
+        prefix: &str,
+    ) -> Result<grb::Expr> {
+        let _ = (model, prefix);
+        Ok(assignment_objective(vars, data, |i, ratio| {
+            self.score(data, i, ratio)
+        }))
+    }
+}
+
+/// Sum of the `score`s of the assignments of all instances to an algorithm and number of
+/// repetitions
+///
+/// Exactly one assignment per instance is chosen, which selects the best algorithm for the
+/// instance as long as the scores are monotone in the quality ratio.
+pub fn assignment_objective(
+    vars: &PortfolioVars,
+    data: &Data,
+    score: impl Fn(usize, f64) -> f64,
+) -> grb::Expr {
+    let e_min = &data.expected_best_quality;
+    vars.a
+        .indexed_iter()
+        .map(|((i, j, k), &val_a)| {
+            val_a * score(i, e_min[(i, j, k)] / data.best_per_instance[i])
+        })
+        .grb_sum()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Sum of the quality ratios, see [`Objective::QualityRatio`]
+pub struct RatioSum;
+
+impl ObjectiveFunction for RatioSum {
+    fn score(&self, _data: &Data, _instance: usize, ratio: f64) -> f64 {
+        ratio
+    }
+
+    /// Uses the expected best quality `q` of every instance directly
+    fn build(
+        &self,
+        _model: &mut Model,
+        vars: &PortfolioVars,
+        data: &Data,
+        _prefix: &str,
+    ) -> Result<grb::Expr> {
+        Ok(vars
+            .q
+            .iter()
+            .zip(data.best_per_instance.iter())
+            .map(|(&var, &best)| var * (1.0 / best))
+            .grb_sum())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Penalized quality ratio, see [`Objective::Penalized`]
+pub struct Penalized {
+    /// Quality ratio above which an instance counts as failed
+    pub cutoff: f64,
+    /// Failed instances contribute `factor * cutoff`
+    pub factor: f64,
+}
+
+impl Penalized {
+    /// PAR10 score with the quality ratio `cutoff`
+    pub fn par10(cutoff: f64) -> Self {
+        Self {
+            cutoff,
+            factor: 10.0,
+        }
+    }
+}
+
+impl ObjectiveFunction for Penalized {
+    fn score(&self, _data: &Data, _instance: usize, ratio: f64) -> f64 {
+        match ratio > self.cutoff {
+            true => self.factor * self.cutoff,
+            false => ratio,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Rank of the portfolio among the single algorithms, i.e. the fraction of the algorithms whose
+/// single run has a better expected quality than the portfolio on every instance
+///
+/// Ignores how much better the algorithms are, so a few instances with large quality ratios do not
+/// dominate the objective.
+pub struct RankBased;
+
+impl ObjectiveFunction for RankBased {
+    fn score(&self, data: &Data, instance: usize, ratio: f64) -> f64 {
+        let best = data.best_per_instance[instance];
+        let better = (0..data.num_algorithms)
+            .filter(|&j| {
+                data.expected_best_quality[(instance, j, 0)] / best
+                    < ratio - 1e-9
+            })
+            .count();
+        better as f64 / data.num_algorithms.max(1) as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+/// Sum of the quality ratios weighted per instance, e.g. by
+/// [running time](TimeWeighted::from_times)
+pub struct TimeWeighted {
+    /// Weight of every instance in the order of the instances of the data, instances without a
+    /// weight have weight one
+    pub weights: Vec<f64>,
+}
+
+impl TimeWeighted {
+    /// Weight every instance with `weights`
+    pub fn new(weights: Vec<f64>) -> Self {
+        Self { weights }
+    }
+
+    /// Weight every instance with the fastest valid running time of the normalized data frame
+    /// `df`, relative to the mean over all instances, so that instances which take long to solve
+    /// count more and the weights sum up to the number of instances
+    ///
+    /// `df` has to contain the instances of the data, e.g. the data frame the data was created
+    /// from. Startup times are only included if they were added with
+    /// [`add_startup_times`](crate::csv_parser::add_startup_times).
+    pub fn from_times(df: LazyFrame) -> Result<Self> {
+        let times = df
+            .filter(col("valid"))
+            .groupby([col("instance")])
+            .agg([col("time").min()])
+            .sort("instance", Default::default())
+            .collect()?;
+        let times = times
+            .column("time")?
+            .cast(&DataType::Float64)?
+            .f64()?
+            .into_no_null_iter()
+            .collect::<Vec<_>>();
+        let mean = times.iter().sum::<f64>() / times.len().max(1) as f64;
+        anyhow::ensure!(mean > 0.0, "No positive running times to weight by");
+        Ok(Self::new(times.into_iter().map(|t| t / mean).collect()))
+    }
+}
+
+impl ObjectiveFunction for TimeWeighted {
+    fn score(&self, _data: &Data, instance: usize, ratio: f64) -> f64 {
+        self.weights.get(instance).copied().unwrap_or(1.0) * ratio
+    }
+}
+
+/// Objectives registered with [`register`], in addition to the built-in ones of [`get`]
+static REGISTRY: Mutex<Vec<(String, Arc<dyn ObjectiveFunction>)>> =
+    Mutex::new(Vec::new());
+
+/// Register `objective` under `name` (case insensitive) for [`Objective::Custom`], replacing any
+/// objective registered under the same name before
+pub fn register(name: &str, objective: impl ObjectiveFunction + 'static) {
+    let name = name.to_lowercase();
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    registry.retain(|(n, _)| *n != name);
+    registry.push((name, Arc::new(objective)));
+}
+
+/// The objective registered under `name`, the built-in objectives `ratio-sum` ([`RatioSum`]) and
+/// `rank` ([`RankBased`]) are always available unless they are replaced
+pub fn get(name: &str) -> Option<Arc<dyn ObjectiveFunction>> {
+    let name = name.to_lowercase();
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, objective)) = registry.iter().find(|(n, _)| *n == name) {
+        return Some(objective.clone());
+    }
+    match name.as_str() {
+        "ratio-sum" => Some(Arc::new(RatioSum)),
+        "rank" => Some(Arc::new(RankBased)),
+        _ => None,
+    }
+}
+
+/// Objective value of the quality `ratios` of all instances, i.e. [`Objective::value`] or the
+/// value of the registered objective for [`Objective::Custom`] (infinite if it is not registered)
+pub fn value(objective: &Objective, data: &Data, ratios: &[f64]) -> f64 {
+    match objective {
+        Objective::Custom(name) => {
+            get(name).map_or(f64::INFINITY, |o| o.value(data, ratios))
+        }
+        objective => objective.value(ratios.iter().copied()),
+    }
+}
@@ -0,0 +1,276 @@
+use super::*;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A portfolio on the Pareto front of quality and running time, see [`solve_pareto`]
+pub struct ParetoPoint {
+    /// The portfolio
+    pub portfolio: Portfolio,
+    /// Value of the quality [objective](SolverOptions::objective) of the portfolio
+    pub objective: f64,
+    /// Expected running time of the portfolio, see [`expected_runtime`]
+    pub runtime: f64,
+}
+
+/// Enumerate the Pareto front of the expected quality and the expected running time of the
+/// portfolios using the Gurobi Optimizer.
+///
+/// `times[(i, j, k)]` is the expected running time of `k + 1` runs of algorithm `j` on instance
+/// `i` (see [`csv_parser::expected_times`](crate::csv_parser::expected_times)), all copies of a
+/// portfolio run until they finish, so the running time on an instance is the one of its slowest
+/// selected algorithm. With the epsilon-constraint method, the fastest portfolio and the portfolio
+/// of the best quality are solved first and the best quality is solved for `num_points` running
+/// time limits evenly spaced between their running times. The points are sorted by running time,
+/// points that are dominated by another point or were not solved within the timeout (which applies
+/// to every solve) are dropped.
+///
+/// The constraints of the `options` apply to every point, the penalties are ignored.
+pub fn solve_pareto(
+    data: &Data,
+    times: &Array3<f64>,
+    num_cores: usize,
+    num_points: usize,
+    options: &SolverOptions,
+) -> Result<Vec<ParetoPoint>> {
+    Solver::for_options(options)?
+        .solve_pareto(data, times, num_cores, num_points, options)
+}
+
+/// Mean over all instances of the expected running time of the slowest algorithm of the
+/// `portfolio`, see [`solve_pareto`]
+pub fn expected_runtime(
+    data: &Data,
+    times: &Array3<f64>,
+    portfolio: &Portfolio,
+) -> f64 {
+    let selected = portfolio_copies(portfolio, data)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, copies)| copies.round() >= 1.0)
+        .map(|(j, copies)| (j, copies.round() as usize - 1))
+        .collect_vec();
+    let total = (0..data.num_instances)
+        .map(|i| {
+            selected
+                .iter()
+                .map(|&(j, k)| times[(i, j, k)])
+                .fold(0.0, f64::max)
+        })
+        .sum::<f64>();
+    total / data.num_instances.max(1) as f64
+}
+
+impl Solver {
+    /// Enumerate the Pareto front of quality and running time, see [`solve_pareto`]
+    pub fn solve_pareto(
+        &self,
+        data: &Data,
+        times: &Array3<f64>,
+        num_cores: usize,
+        num_points: usize,
+        options: &SolverOptions,
+    ) -> Result<Vec<ParetoPoint>> {
+        anyhow::ensure!(num_points > 0, "The Pareto front needs a point");
+        anyhow::ensure!(
+            times.dim() == data.expected_best_quality.dim(),
+            "The running times have shape {:?}, but the data has shape {:?}",
+            times.dim(),
+            data.expected_best_quality.dim()
+        );
+        check_quality_guarantee(data, num_cores, options)?;
+        check_chance_constraint(data, num_cores, options)?;
+        check_algorithm_constraints(data, num_cores, options)?;
+        let mut model = Model::with_env("pareto_model", &self.env)?;
+        set_parameters(&mut model, options)?;
+
+        let vars = add_portfolio_model(&mut model, data, num_cores, "")?;
+        add_quality_guarantees(&mut model, &vars.q, data, options, "")?;
+        add_chance_constraints(&mut model, &vars.b, options, "")?;
+        add_algorithm_constraints(&mut model, &vars, data, options, "")?;
+        add_budgets(&mut model, &vars.b, data, options, "")?;
+        let quality = quality_objective(
+            &mut model,
+            &vars,
+            data,
+            &options.objective,
+            "",
+        )?;
+        // running time of the slowest selected algorithm per instance
+        let slowest = (0..data.num_instances)
+            .map(|i| {
+                add_ctsvar!(model, name: format!("t_{i}").as_str(), bounds: 0..)
+            })
+            .collect::<grb::Result<Vec<_>>>()?;
+        for ((j, k), &b) in vars.b.indexed_iter() {
+            for (i, &t) in slowest.iter().enumerate() {
+                model.add_constr(
+                    format!("runtime_{i}_{j}_{k}").as_str(),
+                    c!(t >= times[(i, j, k)] * b),
+                )?;
+            }
+        }
+        let runtime = (1.0 / data.num_instances.max(1) as f64)
+            * slowest.iter().grb_sum();
+        let max_time = times.iter().copied().fold(0.0, f64::max);
+        let limit = model
+            .add_constr("runtime_limit", c!(runtime.clone() <= max_time))?;
+
+        let solve = |model: &mut Model| -> Result<Option<Portfolio>> {
+            model.optimize()?;
+            if model.status()? == Status::Infeasible {
+                return Err(compute_infeasibility(
+                    model,
+                    &[data],
+                    num_cores,
+                    options,
+                )?
+                .into());
+            }
+            if model.get_attr(attr::SolCount)? == 0 {
+                return Ok(None);
+            }
+            let solution =
+                model.get_obj_attr_batch(attr::X, vars.b.iter().copied())?;
+            Ok(Some(postprocess_solution(
+                solution,
+                data.num_algorithms,
+                num_cores,
+                &data.algorithms,
+                "pareto_portfolio",
+                false,
+            )))
+        };
+        model.set_objective(runtime.clone(), ModelSense::Minimize)?;
+        let fastest = solve(&mut model)?
+            .context("No portfolio found within the timeout")?;
+        // the running time only breaks ties between portfolios of the same quality
+        let tie_break = 1e-6 * data.vbs_objective() / max_time.max(1.0);
+        model.set_objective(
+            quality + tie_break * runtime,
+            ModelSense::Minimize,
+        )?;
+        let best = solve(&mut model)?
+            .context("No portfolio found within the timeout")?;
+        let (min_runtime, max_runtime) = (
+            expected_runtime(data, times, &fastest),
+            expected_runtime(data, times, &best),
+        );
+        let mut portfolios = vec![best];
+        for p in 0..num_points {
+            let step = match num_points {
+                1 => 0.0,
+                _ => p as f64 / (num_points - 1) as f64,
+            };
+            let bound = min_runtime + step * (max_runtime - min_runtime);
+            model.set_obj_attr(attr::RHS, &limit, bound)?;
+            match solve(&mut model)? {
+                Some(portfolio) => portfolios.push(portfolio),
+                None => warn!(
+                    "No portfolio within the running time {bound} found within the timeout"
+                ),
+            }
+        }
+
+        let points = portfolios
+            .into_iter()
+            .map(|portfolio| {
+                let copies = portfolio_copies(&portfolio, data)
+                    .into_iter()
+                    .map(f64::round)
+                    .collect_vec();
+                ParetoPoint {
+                    objective: objectives::value(
+                        &options.objective,
+                        data,
+                        &quality_ratios(data, &copies),
+                    ),
+                    runtime: expected_runtime(data, times, &portfolio),
+                    portfolio,
+                }
+            })
+            .collect_vec();
+        Ok(pareto_front(points))
+    }
+}
+
+/// Non-dominated `points` with distinct portfolios, sorted by running time and named
+/// `pareto_portfolio_<i>`
+fn pareto_front(points: Vec<ParetoPoint>) -> Vec<ParetoPoint> {
+    let dominates = |a: &ParetoPoint, b: &ParetoPoint| {
+        a.objective <= b.objective
+            && a.runtime <= b.runtime
+            && (a.objective < b.objective || a.runtime < b.runtime)
+    };
+    let mut front = points
+        .iter()
+        .filter(|point| !points.iter().any(|other| dominates(other, point)))
+        .cloned()
+        .collect_vec();
+    front.sort_by(|a, b| {
+        a.runtime
+            .total_cmp(&b.runtime)
+            .then(a.objective.total_cmp(&b.objective))
+    });
+    front.dedup_by(|a, b| {
+        a.portfolio.resource_assignments == b.portfolio.resource_assignments
+    });
+    for (i, point) in front.iter_mut().enumerate() {
+        point.portfolio.name = format!("pareto_portfolio_{i}");
+    }
+    front
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pareto_front() {
+        let algorithms = [
+            Algorithm::new("algo1".into(), 1),
+            Algorithm::new("algo2".into(), 1),
+        ];
+        let data = Data::new(
+            &algorithms,
+            &[1.0, 1.0],
+            None,
+            &[1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 1.0, 1.0],
+            2,
+        )
+        .unwrap();
+        let times = Array3::from_shape_vec(
+            (2, 2, 2),
+            vec![1.0, 2.0, 4.0, 5.0, 3.0, 3.0, 1.0, 2.0],
+        )
+        .unwrap();
+        let point = |copies: [f64; 2], objective, runtime| ParetoPoint {
+            portfolio: portfolio_from_copies(&data, &copies, "candidate"),
+            objective,
+            runtime,
+        };
+        let both = point([1.0, 1.0], 2.0, 0.0);
+        assert_eq!(
+            expected_runtime(&data, &times, &both.portfolio),
+            (4.0 + 3.0) / 2.0
+        );
+        assert_eq!(
+            expected_runtime(
+                &data,
+                &times,
+                &portfolio_from_copies(&data, &[2.0, 0.0], "candidate")
+            ),
+            (2.0 + 3.0) / 2.0
+        );
+        let front = pareto_front(vec![
+            point([1.0, 1.0], 2.0, 3.5),
+            point([2.0, 0.0], 3.0, 2.5),
+            // dominated by the first point
+            point([0.0, 2.0], 3.0, 3.5),
+            point([1.0, 1.0], 2.0, 3.5),
+        ]);
+        assert_eq!(front.len(), 2);
+        assert_eq!(front[0].runtime, 2.5);
+        assert_eq!(front[0].portfolio.name, "pareto_portfolio_0");
+        assert_eq!(front[1].objective, 2.0);
+        assert_eq!(front[1].portfolio.name, "pareto_portfolio_1");
+    }
+}
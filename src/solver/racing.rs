@@ -0,0 +1,221 @@
+use anyhow::Result;
+use itertools::Itertools;
+use log::debug;
+use polars::prelude::*;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::csv_parser::extract_algorithm_columns;
+use crate::datastructures::*;
+use crate::portfolio_simulator::{portfolio_run_from_samples, simulate};
+
+#[derive(Debug, Clone)]
+/// Options for [`solve`]
+pub struct RacingOptions {
+    /// Number of random candidate allocations in the first round, in addition to one candidate per
+    /// algorithm using all cores
+    pub num_candidates: usize,
+    /// Number of simulated seeds per candidate in the first round
+    pub min_seeds: u32,
+    /// Only the best `1 / eta` candidates advance to the next round, which simulates `eta` times
+    /// as many seeds
+    pub eta: usize,
+    /// Seed for the random candidate allocations
+    pub seed: u64,
+}
+
+impl Default for RacingOptions {
+    fn default() -> Self {
+        Self {
+            num_candidates: 64,
+            min_seeds: 1,
+            eta: 2,
+            seed: 42,
+        }
+    }
+}
+
+/// Create a portfolio by racing candidate allocations against each other, without any MIP.
+///
+/// Candidate allocations are evaluated by [simulating](crate::portfolio_simulator) their execution
+/// on the normalized data frame `df` (see [`crate::csv_parser::parse_normalized_csvs`]) and scored
+/// by the sum of the quality ratios to the best quality over all instances, like the objective of
+/// [`crate::solver::solve`]. Successive halving discards the worse half (see
+/// [`RacingOptions::eta`]) of the candidates after every round and evaluates the remaining ones
+/// with more seeds, until only one candidate is left.
+pub fn solve(
+    df: &DataFrame,
+    num_cores: u32,
+    options: &RacingOptions,
+) -> Result<Portfolio> {
+    anyhow::ensure!(options.eta >= 2, "eta has to be at least 2");
+    let df = df
+        .clone()
+        .lazy()
+        .filter(col("valid"))
+        .select([
+            col("algorithm"),
+            col("num_threads"),
+            col("instance"),
+            col("quality"),
+            col("time"),
+            col("valid"),
+        ])
+        .collect()?;
+    let algorithms = extract_algorithm_columns(&df)?
+        .into_iter()
+        .filter(|algo| algo.num_threads <= num_cores)
+        .collect_vec();
+    anyhow::ensure!(
+        !algorithms.is_empty(),
+        "No algorithm fits on {num_cores} cores"
+    );
+    let best = df
+        .clone()
+        .lazy()
+        .groupby([col("instance")])
+        .agg([min("quality").alias("best")]);
+
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let mut candidates = algorithms
+        .iter()
+        .map(|algo| {
+            vec![(algo.clone(), (num_cores / algo.num_threads) as f64)]
+        })
+        .chain(
+            (0..options.num_candidates)
+                .map(|_| random_allocation(&algorithms, num_cores, &mut rng)),
+        )
+        .unique_by(|assignments| {
+            assignments
+                .iter()
+                .map(|(algo, copies)| (algo.clone(), *copies as u32))
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let mut num_seeds = options.min_seeds.max(1);
+    while candidates.len() > 1 {
+        let scores = candidates
+            .iter()
+            .map(|assignments| {
+                evaluate(&df, &best, assignments, num_seeds, num_cores)
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        let num_advancing = (candidates.len() / options.eta).max(1);
+        debug!(
+            "Racing {} candidates with {num_seeds} seeds, best score {}",
+            candidates.len(),
+            scores.iter().copied().fold(f64::INFINITY, f64::min)
+        );
+        candidates = candidates
+            .into_iter()
+            .zip(scores)
+            .sorted_by(|(_, a), (_, b)| a.total_cmp(b))
+            .take(num_advancing)
+            .map(|(assignments, _)| assignments)
+            .collect_vec();
+        num_seeds *= options.eta as u32;
+    }
+    Ok(Portfolio {
+        name: String::from("racing_portfolio"),
+        resource_assignments: candidates.remove(0),
+    })
+}
+
+/// A random allocation of all cores to copies of a random subset of the `algorithms`
+fn random_allocation(
+    algorithms: &[Algorithm],
+    num_cores: u32,
+    rng: &mut ChaCha8Rng,
+) -> Vec<(Algorithm, f64)> {
+    let threads = algorithms.iter().map(|a| a.num_threads).collect_vec();
+    algorithms
+        .iter()
+        .cloned()
+        .zip(random_copies(&threads, num_cores, rng))
+        .filter(|(_, copies)| *copies > 0.0)
+        .sorted_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
+        .collect_vec()
+}
+
+/// Random number of copies per algorithm (with `threads` threads each), such that no further
+/// copy of a random subset of the algorithms fits on the remaining cores
+fn random_copies<R: Rng>(
+    threads: &[u32],
+    num_cores: u32,
+    rng: &mut R,
+) -> Vec<f64> {
+    let subset_size = rng.gen_range(1..=threads.len());
+    let subset =
+        rand::seq::index::sample(rng, threads.len(), subset_size).into_vec();
+    let mut copies = vec![0.0; threads.len()];
+    fill_copies(&mut copies, &subset, threads, num_cores, rng);
+    copies
+}
+
+/// Add random copies of the `candidates` until none of them fits on the remaining cores
+pub(super) fn fill_copies<R: Rng>(
+    copies: &mut [f64],
+    candidates: &[usize],
+    threads: &[u32],
+    num_cores: u32,
+    rng: &mut R,
+) {
+    let used = copies
+        .iter()
+        .zip(threads)
+        .map(|(copies, &threads)| *copies as u32 * threads)
+        .sum::<u32>();
+    let mut free_cores = num_cores.saturating_sub(used);
+    loop {
+        let fitting = candidates
+            .iter()
+            .filter(|&&j| threads[j] <= free_cores)
+            .collect_vec();
+        let Some(&&j) = fitting.choose(rng) else {
+            break;
+        };
+        copies[j] += 1.0;
+        free_cores -= threads[j];
+    }
+}
+
+/// Sum of the quality ratios over all instances, averaged over `num_seeds` simulated runs
+///
+/// Instances without any run of the portfolio have an infinite ratio.
+fn evaluate(
+    df: &DataFrame,
+    best: &LazyFrame,
+    resource_assignments: &[(Algorithm, f64)],
+    num_seeds: u32,
+    num_cores: u32,
+) -> Result<f64> {
+    let portfolio = Portfolio {
+        name: String::from("candidate"),
+        resource_assignments: resource_assignments.to_vec(),
+    };
+    let samples = (0..num_seeds)
+        .map(|seed| simulate(df, &portfolio, seed as u64))
+        .collect::<Result<Vec<LazyFrame>>>()?;
+    let runs = portfolio_run_from_samples(
+        concat(samples, false, false)?,
+        &["instance", "seed"],
+        &["algorithm", "num_threads"],
+        num_cores,
+        &portfolio.name,
+    );
+    let score = best
+        .clone()
+        .join(runs, [col("instance")], [col("instance")], JoinType::Left)
+        .select([(col("quality") / col("best"))
+            .fill_null(lit(f64::INFINITY))
+            .sum()])
+        .collect()?;
+    Ok(score
+        .column("quality")?
+        .f64()?
+        .get(0)
+        .unwrap_or(f64::INFINITY)
+        / num_seeds as f64)
+}
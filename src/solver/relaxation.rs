@@ -0,0 +1,245 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use grb::prelude::*;
+use itertools::Itertools;
+use log::{debug, info};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use super::local_search::{feasible, penalized_objective};
+use super::{
+    add_algorithm_constraints, add_budgets, add_chance_constraints,
+    add_portfolio_model, add_quality_guarantees, check_algorithm_constraints,
+    check_chance_constraint, check_quality_guarantee, gap_closed,
+    option_penalties, portfolio_from_copies, quality_objective, round_to_sum,
+    set_parameters, PortfolioVars, Solver,
+};
+use crate::csv_parser::Data;
+use crate::datastructures::*;
+
+#[derive(Debug, Clone)]
+/// Options for the randomized rounding of [`solve`]
+pub struct RoundingOptions {
+    /// Number of rounded portfolios, the best one is the final portfolio
+    pub samples: usize,
+    /// Seed for the randomized rounding
+    pub seed: u64,
+}
+
+impl Default for RoundingOptions {
+    fn default() -> Self {
+        Self {
+            samples: 100,
+            seed: 42,
+        }
+    }
+}
+
+/// Result of [`solve`]
+pub struct RelaxationResult {
+    /// Optimal solution of the LP relaxation with fractional copies of every algorithm
+    pub fractional_portfolio: Portfolio,
+    /// Best rounded portfolio, the gap is relative to the objective value of the LP relaxation
+    pub result: OptimizationResult,
+    /// How much worse the objective of the rounded portfolio is than the objective value of the
+    /// LP relaxation (including the penalties of the options)
+    pub rounding_loss: f64,
+}
+
+/// Solve the LP relaxation of the portfolio model without rounding, see [`solve`].
+///
+/// Returns the fractional copies of every algorithm as portfolio `fractional_portfolio` and the
+/// objective value of the relaxation, a lower bound of the objective of every portfolio. The
+/// fractional copies are meaningful on their own if the cores are time-shared, e.g. 1.5 copies of
+/// an algorithm run on one core and for half of the time on another one.
+pub fn solve_fractional(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<(Portfolio, f64)> {
+    check_quality_guarantee(data, num_cores, options)?;
+    check_chance_constraint(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    let (fractions, bound) = relax(data, num_cores, options)?;
+    Ok((
+        portfolio_from_copies(data, &fractions, "fractional_portfolio"),
+        bound,
+    ))
+}
+
+/// Fractional copies of every algorithm and objective value of the LP relaxation
+fn relax(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+) -> Result<(Vec<f64>, f64)> {
+    let solver = Solver::for_options(options)?;
+    let mut model = Model::with_env("portfolio_relaxation", solver.env())?;
+    set_parameters(&mut model, options)?;
+
+    let vars = add_portfolio_model(&mut model, data, num_cores, "")?;
+    add_quality_guarantees(&mut model, &vars.q, data, options, "")?;
+    add_chance_constraints(&mut model, &vars.b, options, "")?;
+    add_algorithm_constraints(&mut model, &vars, data, options, "")?;
+    add_budgets(&mut model, &vars.b, data, options, "")?;
+    let objective_function =
+        quality_objective(&mut model, &vars, data, &options.objective, "")?
+            + option_penalties(
+                &mut model,
+                &vars.b,
+                &vars.cores,
+                data,
+                num_cores,
+                options,
+                "",
+            )?;
+    let PortfolioVars { b, .. } = vars;
+    // relax all integer variables, including those of the options
+    model.update()?;
+    let all_vars = model.get_vars()?.to_vec();
+    model.set_obj_attr_batch(
+        attr::VType,
+        all_vars.into_iter().map(|var| (var, VarType::Continuous)),
+    )?;
+    model.set_objective(objective_function, ModelSense::Minimize)?;
+    model.optimize()?;
+    match model.status()? {
+        Status::Optimal => (),
+        Status::Infeasible => anyhow::bail!(
+            "The LP relaxation is infeasible, solve the MIP for the conflicting constraints"
+        ),
+        status => anyhow::bail!("The LP relaxation was not solved: {status:?}"),
+    }
+    let bound = model.get_attr(attr::ObjVal)?;
+    info!("Objective value of the LP relaxation: {bound}");
+    let fractions = model
+        .get_obj_attr_batch(attr::X, b)?
+        .chunks(num_cores)
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(k, x)| (k + 1) as f64 * x)
+                .sum::<f64>()
+        })
+        .collect_vec();
+    Ok((fractions, bound))
+}
+
+/// Create an approximate portfolio by rounding the LP relaxation of the portfolio model.
+///
+/// Solves the model of [`super::solve`] with all integer variables relaxed, which takes seconds on
+/// models where branch-and-bound takes hours. The fractional copies `sum_k (k + 1) b_jk` of every
+/// algorithm are rounded to integer copies filling the core budget, the first sample rounds up the
+/// largest fractional parts, all further samples round up at random. The best sample satisfying the
+/// quality guarantees (including the penalties of the `options`) is the final portfolio, every
+/// improvement is recorded in the history. The objective value of the relaxation is the lower bound
+/// of all incumbents. Use [`solve_fractional`] for the relaxation without rounding.
+pub fn solve(
+    data: &Data,
+    num_cores: usize,
+    options: &SolverOptions,
+    rounding: &RoundingOptions,
+) -> Result<RelaxationResult> {
+    check_quality_guarantee(data, num_cores, options)?;
+    check_chance_constraint(data, num_cores, options)?;
+    check_algorithm_constraints(data, num_cores, options)?;
+    let start = Instant::now();
+    let (fractions, bound) = relax(data, num_cores, options)?;
+    let fractional_portfolio =
+        portfolio_from_copies(data, &fractions, "fractional_portfolio");
+    debug!("{fractional_portfolio}");
+
+    let steps = data.algorithms.iter().map(|a| a.num_threads).collect_vec();
+    let threads = steps.iter().map(|&t| t as usize).collect_vec();
+    let mut rng = ChaCha8Rng::seed_from_u64(rounding.seed);
+    let mut best: Option<(Vec<f64>, f64)> = None;
+    let mut history = Vec::new();
+    for sample in 0..rounding.samples.max(1) {
+        let copies = match sample {
+            0 => round_to_sum(&fractions, &steps, num_cores as u32)?,
+            _ => randomized_rounding(
+                &fractions,
+                &steps,
+                num_cores as u32,
+                &mut rng,
+            )?,
+        };
+        if !feasible(data, num_cores, options, &threads, &copies) {
+            continue;
+        }
+        let value = penalized_objective(data, num_cores, options, &copies);
+        if best.as_ref().map_or(true, |(_, best)| value < *best) {
+            debug!("Sample {sample}: new incumbent with objective {value}");
+            history.push(Incumbent {
+                portfolio: portfolio_from_copies(
+                    data,
+                    &copies,
+                    "intermediate_portfolio",
+                ),
+                objective: value,
+                bound,
+                time: start.elapsed().as_secs_f64(),
+                simulated: None,
+            });
+            best = Some((copies, value));
+        }
+    }
+    let Some((copies, value)) = best else {
+        anyhow::bail!(
+            "No rounded portfolio satisfies the quality guarantees, solve the MIP instead"
+        );
+    };
+    let final_portfolio =
+        portfolio_from_copies(data, &copies, "final_portfolio");
+    Ok(RelaxationResult {
+        fractional_portfolio,
+        rounding_loss: value - bound,
+        result: OptimizationResult {
+            initial_portfolio: None,
+            gap_closed: gap_closed(data, &final_portfolio),
+            initial_gap_closed: None,
+            final_portfolio,
+            gap: (value - bound).abs() / value.abs(),
+            stats: SolveStats {
+                wall_time: start.elapsed().as_secs_f64(),
+                node_count: None,
+                objective: value,
+                bound: Some(bound),
+                num_incumbents: history.len(),
+                termination: Termination::Finished,
+                numeric_focus: None,
+            },
+            history,
+            refined: false,
+            pool: Vec::new(),
+            instance_qualities: None,
+        },
+    })
+}
+
+/// Round the fractional copies of every algorithm to integer copies that fill the core budget
+/// `sum` like [`round_to_sum`], but round up in random order
+///
+/// Fractional parts are rounded up in order of a random key `u^(1 / fractional part)` with `u`
+/// uniform in `[0, 1)`, so algorithms with larger fractional parts are more likely to get another
+/// copy.
+pub(super) fn randomized_rounding(
+    fractions: &[f64],
+    steps: &Vec<u32>,
+    sum: u32,
+    rng: &mut impl Rng,
+) -> Result<Vec<f64>> {
+    let keys = fractions
+        .iter()
+        .map(|&f| {
+            let fraction = f - f.floor();
+            let key = match fraction > f64::EPSILON {
+                true => rng.gen::<f64>().powf(1.0 / fraction),
+                false => 0.0,
+            };
+            f.floor() + key
+        })
+        .collect_vec();
+    round_to_sum(&keys, steps, sum)
+}
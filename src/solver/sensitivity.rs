@@ -0,0 +1,79 @@
+use super::*;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Objective of a portfolio with additional cores, see [`marginal_cores`]
+pub struct MarginalCores {
+    /// Number of cores on top of the ones of the original portfolio
+    pub additional_cores: usize,
+    /// The portfolio on the additional cores
+    pub portfolio: Portfolio,
+    /// Objective of the portfolio (including the penalties of the options)
+    pub objective: f64,
+    /// Improvement of the objective over the original portfolio
+    pub improvement: f64,
+    /// Improvement of the objective over the portfolio with one core fewer
+    pub marginal: f64,
+    /// Remaining gap of the solve, see [`OptimizationResult::gap`]
+    pub gap: f64,
+}
+
+/// How much the objective of `portfolio` on `num_cores` improves with `1..=additional` more cores,
+/// e.g. to decide whether a larger machine is worth it.
+///
+/// Every number of cores is solved with the timeout of the `options`, starting from the
+/// portfolio with one core fewer whose free cores are filled greedily (see
+/// [`solve_for_budgets`]). The data has to cover `num_cores + additional` repetitions, all
+/// objectives are relative to its best qualities.
+pub fn marginal_cores(
+    data: &Data,
+    num_cores: usize,
+    additional: usize,
+    options: &SolverOptions,
+    portfolio: &Portfolio,
+) -> Result<Vec<MarginalCores>> {
+    let max_cores = data.expected_best_quality.shape()[2];
+    anyhow::ensure!(
+        num_cores + additional <= max_cores,
+        "{additional} additional cores exceed the {max_cores} repetitions of the data"
+    );
+    let copies = portfolio_copies(portfolio, data);
+    let mut previous =
+        local_search::penalized_objective(data, num_cores, options, &copies);
+    let original = previous;
+    let budgets = (1..=additional).map(|c| num_cores + c).collect_vec();
+    let results = solve_budgets(
+        data,
+        &budgets,
+        (0..additional).collect_vec(),
+        options,
+        Some(copies),
+    )?;
+    Ok(results
+        .into_iter()
+        .zip(budgets)
+        .map(|(result, cores)| {
+            let objective = local_search::penalized_objective(
+                data,
+                cores,
+                options,
+                &portfolio_copies(&result.final_portfolio, data),
+            );
+            let marginal = MarginalCores {
+                additional_cores: cores - num_cores,
+                portfolio: result.final_portfolio,
+                objective,
+                improvement: original - objective,
+                marginal: previous - objective,
+                gap: result.gap,
+            };
+            info!(
+                "{} additional cores improve the objective by {} ({} over one core fewer)",
+                marginal.additional_cores,
+                marginal.improvement,
+                marginal.marginal
+            );
+            previous = objective;
+            marginal
+        })
+        .collect_vec())
+}
@@ -1,4 +1,16 @@
-use super::round_to_sum;
+use super::local_search::{feasible, fill, penalized_objective, refine};
+use super::relaxation::randomized_rounding;
+use super::{
+    auto, check_algorithm_constraints, check_chance_constraint,
+    constraint_option, constraint_options, core_bounds, describe_conflict,
+    describe_constraint, forced_algorithms, gap_closed, gap_termination,
+    greedy_solve, gurobi_error, initial_copies, instance_qualities,
+    interchangeable_algorithms, model_build_error, objective,
+    portfolio_from_copies, previous_cores, prune, racing, recency_weights,
+    rescaled, round_to_sum, solve_racing, symmetry_canonical_copies,
+    unsatisfiable_instances, validation::Validator,
+};
+use crate::{csv_parser::Data, datastructures::*};
 
 #[test]
 fn test_round_to_sum() {
@@ -10,3 +22,1245 @@ fn test_round_to_sum() {
         vec![2.0, 2.0, 1.0, 1.0, 2.0]
     );
 }
+
+#[test]
+fn test_randomized_rounding() {
+    use rand::SeedableRng;
+    let fractions = vec![2.4, 1.6, 0.8, 1.9, 1.6];
+    let steps = vec![1, 2, 4, 8, 1];
+    let sum = 20;
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(42);
+    for _ in 0..100 {
+        let rounded =
+            randomized_rounding(&fractions, &steps, sum, &mut rng).unwrap();
+        assert_eq!(
+            rounded
+                .iter()
+                .zip(&steps)
+                .map(|(r, &s)| *r as u32 * s)
+                .sum::<u32>(),
+            sum
+        );
+        for (r, f) in rounded.iter().zip(&fractions) {
+            assert!(*r >= f.floor());
+        }
+    }
+    // integral fractions are never rounded up
+    let rounded =
+        randomized_rounding(&[2.0, 1.0], &vec![1, 2], 4, &mut rng).unwrap();
+    assert_eq!(rounded, vec![2.0, 1.0]);
+}
+
+#[test]
+fn test_unsatisfiable_instances() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 2),
+    ];
+    // 2 instances, 2 algorithms, 4 repetitions
+    let stats = [
+        4.0, 3.0, 3.0, 3.0, 2.0, 1.0, 1.0, 1.0, //
+        2.0, 2.0, 2.0, 2.0, 3.0, 3.0, 3.0, 3.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 2.0], None, &stats, 4).unwrap();
+    let guarantee = QualityGuarantee {
+        factor: 1.5,
+        instances: None,
+    };
+    // algo2 needs 4 cores for 2 repetitions, so instance 0 can only reach 2.0
+    assert_eq!(unsatisfiable_instances(&data, 2, &guarantee), vec![0]);
+    assert!(unsatisfiable_instances(&data, 4, &guarantee).is_empty());
+}
+
+#[test]
+fn test_describe_constraint() {
+    let algorithms = [Algorithm::new("algo1".into(), 1)];
+    let data = Data::new(&algorithms, &[1.0], None, &[1.0, 1.0], 2).unwrap();
+    let options = SolverOptions::default();
+    assert_eq!(
+        describe_constraint("c3", &data, 2, &options),
+        "the portfolio uses exactly 2 cores"
+    );
+    assert_eq!(
+        describe_constraint("c2_0", &data, 2, &options),
+        "algo1 1 is selected with at most one number of repetitions"
+    );
+    // the conflicts of the multi-period model are described with the data of their period
+    let later = Data::new(&algorithms, &[2.0], None, &[2.0, 2.0], 2).unwrap();
+    let guaranteed = SolverOptions {
+        quality_guarantee: Some(QualityGuarantee {
+            factor: 1.5,
+            instances: None,
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        describe_conflict("p1_include_0", &[&data, &later], 2, &options),
+        "in period 1, algo1 1 is forced into the portfolio"
+    );
+    assert_eq!(
+        describe_conflict("p1_guarantee_0", &[&data, &later], 2, &guaranteed),
+        "in period 1, the expected quality of instance 0 is at most 1.5 * 2"
+    );
+    assert_eq!(
+        describe_conflict("guarantee_0", &[&data], 2, &guaranteed),
+        "the expected quality of instance 0 is at most 1.5 * 1"
+    );
+    assert_eq!(
+        describe_constraint("unknown", &data, 2, &options),
+        "unknown"
+    );
+    assert_eq!(constraint_option("c3"), Some("num_cores"));
+    assert_eq!(
+        constraint_option("p0_maxcores_0"),
+        Some("algorithm_constraints.core_bounds")
+    );
+    assert_eq!(constraint_option("quality_target"), Some("quality_target"));
+    assert_eq!(constraint_option("chance_1"), Some("chance_constraint"));
+    assert_eq!(constraint_option("c1_0_0_0"), None);
+    assert!(constraint_options(&SolverOptions::default()).is_empty());
+    let constrained = SolverOptions {
+        memory_budget: Some(1.0),
+        quality_guarantee: Some(QualityGuarantee {
+            factor: 1.1,
+            instances: None,
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        constraint_options(&constrained),
+        ["memory_budget", "quality_guarantee"]
+    );
+}
+
+#[test]
+fn test_chance_constraint() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 2),
+    ];
+    let data =
+        Data::new(&algorithms, &[1.0, 1.0], None, &[1.0; 8], 2).unwrap();
+    let options = |probability: f64| SolverOptions {
+        chance_constraint: Some(ChanceConstraint {
+            threshold: QualityThreshold {
+                factor: 1.1,
+                probability,
+            },
+            success: ndarray::arr2(&[[0.5, 0.0], [0.0, 0.9]]),
+        }),
+        ..Default::default()
+    };
+    assert!(check_chance_constraint(&data, 2, &options(0.7)).is_ok());
+    // two copies of algo1 reach 0.75 on instance 0
+    let err = check_chance_constraint(&data, 2, &options(0.8)).unwrap_err();
+    assert!(err.to_string().ends_with("on instances [0]"));
+    assert!(check_chance_constraint(&data, 2, &options(1.0)).is_err());
+    assert!(check_chance_constraint(&data, 3, &Default::default()).is_ok());
+    let checkpointed = SolverOptions {
+        checkpoint: Some(CheckpointOptions {
+            dir: std::env::temp_dir().join("portfolio_solver_chance"),
+            interval: 60.0,
+        }),
+        ..options(0.7)
+    };
+    assert!(check_chance_constraint(&data, 2, &checkpointed).is_err());
+    // one copy of each algorithm only reaches 0.5 on instance 0
+    assert!(!feasible(&data, 3, &options(0.7), &[1, 2], &[1.0, 1.0]));
+    assert!(feasible(&data, 4, &options(0.7), &[1, 2], &[2.0, 1.0]));
+    assert_eq!(
+        describe_constraint("chance_1", &data, 2, &options(0.8)),
+        "a run of the portfolio reaches a quality of at most 1.1 * 1 on instance 1 with probability 0.8"
+    );
+}
+
+#[test]
+fn test_infeasibility_display() {
+    let infeasibility = Infeasibility {
+        conflicts: vec!["algo1 1 is forced into the portfolio".into()],
+        options: vec!["algorithm_constraints.include".into()],
+    };
+    assert_eq!(
+        infeasibility.to_string(),
+        "The model is infeasible, conflicting constraints:\n  - algo1 1 is forced into the portfolio\nRelax one of: algorithm_constraints.include\n"
+    );
+}
+
+#[test]
+fn test_previous_cores() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 4),
+    ];
+    let data = Data::new(&algorithms, &[1.0], None, &[1.0; 2], 1).unwrap();
+    let previous = Portfolio {
+        name: "previous".into(),
+        resource_assignments: vec![
+            (Algorithm::new("algo2".into(), 4), 2.0),
+            (Algorithm::new("algo3".into(), 1), 1.0),
+        ],
+    };
+    assert_eq!(previous_cores(&previous, &data), vec![0.0, 8.0]);
+}
+
+#[test]
+fn test_recency_weights() {
+    // the last period is the most recent one
+    assert_eq!(recency_weights(3, 0.5), vec![0.25, 0.5, 1.0]);
+}
+
+#[cfg(feature = "highs")]
+#[test]
+fn test_solve_highs() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        1.0, 1.0, 3.0, 3.0, //
+        3.0, 3.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let initial = Portfolio {
+        name: String::from("initial"),
+        resource_assignments: vec![(algorithms[0].clone(), 2.0)],
+    };
+    let result = super::solve_highs(
+        &data,
+        2,
+        &SolverOptions::default(),
+        Some(&initial),
+    )
+    .unwrap();
+    assert_eq!(
+        result.initial_portfolio.unwrap().resource_assignments,
+        vec![(algorithms[0].clone(), 2.0), (algorithms[1].clone(), 0.0)]
+    );
+    assert_eq!(
+        result.final_portfolio.resource_assignments,
+        vec![(algorithms[0].clone(), 1.0), (algorithms[1].clone(), 1.0)]
+    );
+    assert!(result.gap.abs() < f64::EPSILON);
+}
+
+#[cfg(feature = "cbc")]
+#[test]
+fn test_solve_cbc() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        1.0, 1.0, 3.0, 3.0, //
+        3.0, 3.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let options = SolverOptions {
+        backend: Backend::Cbc,
+        ..Default::default()
+    };
+    let initial = Portfolio {
+        name: String::from("initial"),
+        resource_assignments: vec![(algorithms[0].clone(), 2.0)],
+    };
+    let result = super::solve(&data, 2, &options, Some(&initial)).unwrap();
+    assert_eq!(
+        result.final_portfolio.resource_assignments,
+        vec![(algorithms[0].clone(), 1.0), (algorithms[1].clone(), 1.0)]
+    );
+    assert!(result.gap.abs() < f64::EPSILON);
+}
+
+#[cfg(feature = "cpsat")]
+#[test]
+fn test_solve_cpsat() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        1.0, 1.0, 3.0, 3.0, //
+        3.0, 3.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let initial = Portfolio {
+        name: String::from("initial"),
+        resource_assignments: vec![(algorithms[0].clone(), 2.0)],
+    };
+    let result = super::cpsat::solve(
+        &data,
+        2,
+        &SolverOptions::default(),
+        Some(&initial),
+    )
+    .unwrap();
+    assert_eq!(
+        result.final_portfolio.resource_assignments,
+        vec![(algorithms[0].clone(), 1.0), (algorithms[1].clone(), 1.0)]
+    );
+    assert!((result.history[0].objective - 2.0).abs() < 1e-6);
+    assert!(result.gap.abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_racing() {
+    use polars::prelude::*;
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2", "algo3", "algo3"],
+        "num_threads" => vec![1i64; 6],
+        "instance" => ["graph1", "graph2", "graph1", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 3.0, 3.0, 1.0, 2.0, 2.0],
+        "time" => vec![1.0; 6],
+        "valid" => vec![true; 6],
+    }
+    .unwrap();
+    let portfolio =
+        racing::solve(&df, 2, &racing::RacingOptions::default()).unwrap();
+    assert_eq!(
+        portfolio.resource_assignments,
+        vec![
+            (Algorithm::new("algo1".into(), 1), 1.0),
+            (Algorithm::new("algo2".into(), 1), 1.0)
+        ]
+    );
+}
+
+#[test]
+fn test_prune() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // algo2 only improves instance 1 from 1.05 to 1.0 with two cores
+    let stats = [1.0, 1.0, 1.5, 1.4, 1.1, 1.05, 1.0, 1.0];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let portfolio = Portfolio {
+        name: String::from("final_portfolio"),
+        resource_assignments: vec![
+            (algorithms[0].clone(), 1.0),
+            (algorithms[1].clone(), 1.0),
+        ],
+    };
+    let options = SolverOptions::default();
+    let pruned = prune(&data, 2, &options, &portfolio, 0.05);
+    assert_eq!(
+        pruned.resource_assignments,
+        vec![(algorithms[0].clone(), 2.0), (algorithms[1].clone(), 0.0)]
+    );
+    // removing algo2 degrades the objective by 2.5%
+    let pruned = prune(&data, 2, &options, &portfolio, 0.01);
+    assert_eq!(pruned.resource_assignments, portfolio.resource_assignments);
+    // constraints of the model are kept
+    let options = SolverOptions {
+        algorithm_constraints: AlgorithmConstraints {
+            include: vec![algorithms[1].clone()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let pruned = prune(&data, 2, &options, &portfolio, 0.05);
+    assert_eq!(pruned.resource_assignments, portfolio.resource_assignments);
+}
+
+#[test]
+fn test_validation() {
+    use polars::prelude::*;
+    use std::sync::Arc;
+    let df = df! {
+        "algorithm" => ["algo1", "algo1", "algo2", "algo2"],
+        "num_threads" => vec![1i64; 4],
+        "instance" => ["graph1", "graph2", "graph1", "graph2"],
+        "quality" => [1.0, 3.0, 3.0, 1.0],
+        "time" => vec![1.0; 4],
+        "valid" => vec![true; 4],
+    }
+    .unwrap();
+    let data =
+        Data::from_normalized_dataframe(df.clone().lazy(), 2, f64::MAX, 0)
+            .unwrap();
+    let portfolio = Portfolio {
+        name: String::from("portfolio"),
+        resource_assignments: vec![
+            (Algorithm::new("algo1".into(), 1), 1.0),
+            (Algorithm::new("algo2".into(), 1), 0.0),
+        ],
+    };
+    // a single run per instance is simulated exactly
+    let options =
+        SolverOptions::default().with_validation(Arc::new(df.clone()), 3, 0.1);
+    let validator = Validator::new(&data, &options).unwrap().unwrap();
+    assert!((validator.validate(&portfolio).unwrap() - 4.0).abs() < 1e-9);
+    // the simulation does not match qualities measured on other runs
+    let mut other = df.clone();
+    other
+        .replace("quality", Series::new("quality", [2.0, 6.0, 6.0, 2.0]))
+        .unwrap();
+    let options =
+        SolverOptions::default().with_validation(Arc::new(other), 3, 0.1);
+    let validator = Validator::new(&data, &options).unwrap().unwrap();
+    assert!((validator.validate(&portfolio).unwrap() - 8.0).abs() < 1e-9);
+    let single = df.head(Some(1));
+    let options =
+        SolverOptions::default().with_validation(Arc::new(single), 3, 0.1);
+    assert!(Validator::new(&data, &options).is_err());
+    assert!(Validator::new(&data, &SolverOptions::default())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_objective() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        2.0, 1.0, 4.0, 3.0, //
+        4.0, 3.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    assert_eq!(objective(&data, &[2.0, 0.0]), 4.0);
+    assert_eq!(objective(&data, &[1.0, 1.0]), 4.0);
+    assert_eq!(objective(&data, &[0.0, 1.0]), 6.0);
+    assert_eq!(objective(&data, &[0.0, 0.0]), f64::INFINITY);
+}
+
+#[test]
+fn test_custom_objectives() {
+    use super::objectives::{self, ObjectiveFunction};
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        2.0, 1.0, 4.0, 3.0, //
+        4.0, 3.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    // single runs have the ratios 2 and 4 on instance 0
+    let rank = objectives::RankBased;
+    assert_eq!(rank.score(&data, 0, 1.0), 0.0);
+    assert_eq!(rank.score(&data, 0, 3.0), 0.5);
+    assert_eq!(rank.score(&data, 0, 4.0), 0.5);
+    assert_eq!(rank.score(&data, 1, 5.0), 1.0);
+    assert_eq!(rank.value(&data, &[3.0, 5.0]), 1.5);
+    let par10 = objectives::Penalized::par10(2.0);
+    let penalized = Objective::Penalized {
+        cutoff: 2.0,
+        factor: 10.0,
+    };
+    assert_eq!(par10.value(&data, &[1.5, 3.0]), penalized.value([1.5, 3.0]));
+    let weighted = objectives::TimeWeighted::new(vec![0.5, 1.5]);
+    assert_eq!(weighted.value(&data, &[2.0, 2.0]), 4.0);
+    assert_eq!(weighted.value(&data, &[1.0, 2.0]), 3.5);
+
+    assert!("rank".parse::<Objective>().is_ok());
+    assert!("weighted".parse::<Objective>().is_err());
+    objectives::register("Weighted", weighted);
+    let custom = "weighted".parse::<Objective>().unwrap();
+    assert_eq!(custom, Objective::Custom("weighted".into()));
+    assert_eq!(objectives::value(&custom, &data, &[1.0, 2.0]), 3.5);
+    let unknown = Objective::Custom("unknown".into());
+    assert_eq!(objectives::value(&unknown, &data, &[1.0]), f64::INFINITY);
+}
+
+#[test]
+fn test_initial_copies() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 2),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        2.0, 1.0, 4.0, 3.0, //
+        4.0, 3.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let initial = |resource_assignments: Vec<(Algorithm, f64)>| Portfolio {
+        name: String::from("initial"),
+        resource_assignments,
+    };
+    // the order of the portfolio does not matter
+    assert_eq!(
+        initial_copies(
+            &initial(vec![
+                (algorithms[1].clone(), 1.0),
+                (algorithms[0].clone(), 2.0)
+            ]),
+            &data,
+            4
+        )
+        .unwrap(),
+        vec![2.0, 1.0]
+    );
+    assert_eq!(
+        initial_copies(&initial(vec![]), &data, 4).unwrap(),
+        vec![0.0, 0.0]
+    );
+    // different number of threads
+    let err = initial_copies(
+        &initial(vec![(Algorithm::new("algo2".into(), 1), 1.0)]),
+        &data,
+        4,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("not part of the data"));
+    assert!(initial_copies(
+        &initial(vec![(algorithms[0].clone(), 1.5)]),
+        &data,
+        4
+    )
+    .is_err());
+    // 2 + 2 * 2 cores
+    assert!(initial_copies(
+        &initial(vec![
+            (algorithms[0].clone(), 2.0),
+            (algorithms[1].clone(), 2.0)
+        ]),
+        &data,
+        4
+    )
+    .is_err());
+}
+
+#[test]
+fn test_gap_closed() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        1.0, 1.0, 4.0, 4.0, //
+        4.0, 4.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let portfolio = |copies: [f64; 2]| Portfolio {
+        name: String::from("portfolio"),
+        resource_assignments: algorithms.iter().cloned().zip(copies).collect(),
+    };
+    // single best solver: 5 (either algorithm with 2 copies), virtual best solver: 2
+    assert_eq!(gap_closed(&data, &portfolio([0.0, 2.0])), 0.0);
+    assert_eq!(gap_closed(&data, &portfolio([2.0, 0.0])), 0.0);
+    assert_eq!(gap_closed(&data, &portfolio([1.0, 1.0])), 2.0 / 3.0);
+    assert_eq!(gap_closed(&data, &portfolio([0.0, 1.0])), -1.0 / 3.0);
+}
+
+#[test]
+fn test_refine() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // 2 instances, 2 algorithms, 2 repetitions
+    let stats = [
+        1.0, 1.0, 4.0, 4.0, //
+        4.0, 4.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let portfolio = |copies: [f64; 2]| Portfolio {
+        name: String::from("final_portfolio"),
+        resource_assignments: algorithms.iter().cloned().zip(copies).collect(),
+    };
+    let result = |gap: f64| OptimizationResult {
+        initial_portfolio: None,
+        final_portfolio: portfolio([0.0, 2.0]),
+        gap,
+        history: vec![Incumbent {
+            portfolio: portfolio([0.0, 2.0]),
+            objective: 5.0,
+            bound: 2.0,
+            time: 1.0,
+            simulated: None,
+        }],
+        gap_closed: 0.0,
+        initial_gap_closed: None,
+        refined: false,
+        pool: Vec::new(),
+        stats: Default::default(),
+        instance_qualities: None,
+    };
+    let options = SolverOptions::default();
+    // optimal results are not touched
+    let optimal = refine(&data, 2, &options, result(0.0));
+    assert!(!optimal.refined);
+    assert_eq!(optimal.history.len(), 1);
+
+    let refined = refine(&data, 2, &options, result(0.6));
+    assert!(refined.refined);
+    assert_eq!(refined.final_portfolio, portfolio([1.0, 1.0]));
+    assert_eq!(refined.history.len(), 2);
+    assert_eq!(refined.history[1].objective, 3.0);
+    assert_eq!(refined.history[1].bound, 2.0);
+    assert_eq!(refined.gap, 1.0 / 3.0);
+    assert_eq!(refined.gap_closed, 2.0 / 3.0);
+}
+
+#[test]
+fn test_fill() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 4.0, 4.0, //
+        4.0, 4.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let options = SolverOptions::default();
+    // a second copy of algo1 doesn't help the second instance
+    assert_eq!(fill(&data, 2, &options, &[1.0, 0.0]), vec![1.0, 1.0]);
+    assert_eq!(fill(&data, 2, &options, &[0.0, 2.0]), vec![0.0, 2.0]);
+    let options = SolverOptions {
+        algorithm_constraints: AlgorithmConstraints {
+            exclude: vec![algorithms[1].clone()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(fill(&data, 2, &options, &[1.0, 0.0]), vec![2.0, 0.0]);
+}
+
+#[test]
+fn test_complexity_penalty() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 4.0, 4.0, //
+        4.0, 4.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let options = SolverOptions {
+        complexity_penalty: Some(0.5),
+        ..Default::default()
+    };
+    assert_eq!(penalized_objective(&data, 2, &options, &[1.0, 1.0]), 4.0);
+    assert_eq!(penalized_objective(&data, 2, &options, &[0.0, 2.0]), 5.5);
+}
+
+#[test]
+fn test_algorithm_constraints() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 4.0, 4.0, //
+        4.0, 4.0, 2.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let options = |include: &[usize], exclude: &[usize]| SolverOptions {
+        algorithm_constraints: AlgorithmConstraints {
+            include: include.iter().map(|&j| algorithms[j].clone()).collect(),
+            exclude: exclude.iter().map(|&j| algorithms[j].clone()).collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(
+        forced_algorithms(&data, &options(&[1], &[0])),
+        vec![(0, false), (1, true)]
+    );
+    assert!(
+        check_algorithm_constraints(&data, 2, &options(&[1], &[0])).is_ok()
+    );
+    assert!(
+        check_algorithm_constraints(&data, 1, &options(&[0, 1], &[])).is_err()
+    );
+    assert!(
+        check_algorithm_constraints(&data, 2, &options(&[0], &[0])).is_err()
+    );
+    assert!(
+        check_algorithm_constraints(&data, 2, &options(&[], &[0, 1])).is_err()
+    );
+    let unknown = SolverOptions {
+        algorithm_constraints: AlgorithmConstraints {
+            include: vec![Algorithm::new("algo3".into(), 1)],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(check_algorithm_constraints(&data, 2, &unknown).is_err());
+    assert_eq!(
+        describe_constraint("include_1", &data, 2, &options(&[1], &[])),
+        "algo2 1 is forced into the portfolio"
+    );
+
+    let portfolio = |copies: [f64; 2]| Portfolio {
+        name: String::from("final_portfolio"),
+        resource_assignments: algorithms.iter().cloned().zip(copies).collect(),
+    };
+    let result = || OptimizationResult {
+        initial_portfolio: None,
+        final_portfolio: portfolio([0.0, 2.0]),
+        gap: 0.6,
+        history: Vec::new(),
+        gap_closed: 0.0,
+        initial_gap_closed: None,
+        refined: false,
+        pool: Vec::new(),
+        stats: Default::default(),
+        instance_qualities: None,
+    };
+    // the local search keeps excluded algorithms out of the portfolio
+    let refined = refine(&data, 2, &options(&[], &[0]), result());
+    assert_eq!(refined.final_portfolio, portfolio([0.0, 2.0]));
+    let refined = refine(&data, 2, &options(&[0], &[]), result());
+    assert_eq!(refined.final_portfolio, portfolio([1.0, 1.0]));
+}
+
+#[test]
+fn test_core_bounds() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 2),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 4.0, 4.0, 4.0, 4.0, //
+        4.0, 4.0, 4.0, 4.0, 2.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = |min_cores: u32, max_cores: Option<u32>| SolverOptions {
+        algorithm_constraints: AlgorithmConstraints {
+            core_bounds: vec![CoreBounds {
+                algorithm: algorithms[1].clone(),
+                min_cores,
+                max_cores,
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert_eq!(
+        core_bounds(&data, 4, &options(1, None)),
+        vec![(1, 1.0, 4.0)]
+    );
+    assert!(
+        check_algorithm_constraints(&data, 4, &options(1, Some(2))).is_ok()
+    );
+    assert!(
+        check_algorithm_constraints(&data, 4, &options(3, Some(2))).is_err()
+    );
+    // 3 cores of algo2 need 2 copies
+    assert!(check_algorithm_constraints(&data, 3, &options(3, None)).is_err());
+    assert_eq!(
+        describe_constraint("maxcores_1", &data, 4, &options(0, Some(2))),
+        "algo2 2 gets at most 2 cores"
+    );
+
+    let portfolio = |copies: [f64; 2]| Portfolio {
+        name: String::from("final_portfolio"),
+        resource_assignments: algorithms.iter().cloned().zip(copies).collect(),
+    };
+    let result = OptimizationResult {
+        initial_portfolio: None,
+        final_portfolio: portfolio([0.0, 2.0]),
+        gap: 0.6,
+        history: Vec::new(),
+        gap_closed: 0.0,
+        initial_gap_closed: None,
+        refined: false,
+        pool: Vec::new(),
+        stats: Default::default(),
+        instance_qualities: None,
+    };
+    // the local search moves the cores above the bound of algo2 to algo1
+    let refined = refine(&data, 4, &options(0, Some(2)), result);
+    assert_eq!(refined.final_portfolio, portfolio([2.0, 1.0]));
+}
+
+#[test]
+fn test_memory_budget() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1).with_memory(3.0),
+        Algorithm::new("algo2".into(), 1).with_memory(1.0),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 4.0, 4.0, 4.0, 4.0, //
+        4.0, 4.0, 4.0, 4.0, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let mut data =
+        Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = |memory_budget: f64| SolverOptions {
+        memory_budget: Some(memory_budget),
+        algorithm_constraints: AlgorithmConstraints {
+            include: vec![Algorithm::new("algo1".into(), 1)],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(check_algorithm_constraints(&data, 4, &options(4.0)).is_ok());
+    assert!(check_algorithm_constraints(&data, 4, &options(2.0)).is_err());
+    assert_eq!(
+        describe_constraint("memory", &data, 4, &options(4.0)),
+        "the portfolio uses at most 4 memory"
+    );
+    assert!(feasible(&data, 2, &options(4.0), &[1, 1], &[1.0, 1.0]));
+    assert!(!feasible(&data, 3, &options(4.0), &[1, 1], &[1.0, 2.0]));
+
+    // memory requirements are matched by algorithm and number of threads
+    data.set_requirements(&[
+        Algorithm::new("algo2".into(), 1).with_memory(0.5)
+    ]);
+    assert_eq!(data.algorithms[0].memory, Some(3.0));
+    assert_eq!(data.algorithms[1].memory, Some(0.5));
+    assert!(feasible(&data, 3, &options(4.0), &[1, 1], &[1.0, 2.0]));
+}
+
+#[test]
+fn test_core_classes() {
+    let algorithms = [
+        Algorithm::new("algo1@p".into(), 1),
+        Algorithm::new("algo1@e".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let class = |name: &str, cores: u32| CoreClass {
+        name: name.into(),
+        cores,
+        speed: 1.0,
+    };
+    let options = SolverOptions {
+        core_classes: vec![class("p", 1), class("e", 3)],
+        ..Default::default()
+    };
+    assert_eq!(
+        CoreClass::of(&algorithms[1], &options.core_classes),
+        Some(1)
+    );
+    assert!(check_algorithm_constraints(&data, 4, &options).is_ok());
+    assert_eq!(constraint_option("class_0"), Some("core_classes"));
+    assert_eq!(
+        describe_constraint("class_0", &data, 4, &options),
+        "the algorithms on the p cores use at most 1 cores"
+    );
+    assert!(feasible(&data, 4, &options, &[1, 1], &[1.0, 3.0]));
+    assert!(!feasible(&data, 4, &options, &[1, 1], &[2.0, 2.0]));
+    // the same algorithm on classes of unequal capacity is not interchangeable, ordering its
+    // copies would cut off the portfolio above
+    let same = [1.0; 16];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &same, 4).unwrap();
+    assert_eq!(
+        interchangeable_algorithms(&data, &Default::default()),
+        vec![vec![0, 1]]
+    );
+    assert!(interchangeable_algorithms(&data, &options).is_empty());
+
+    let duplicate = SolverOptions {
+        core_classes: vec![class("p", 1), class("p", 3)],
+        ..Default::default()
+    };
+    assert!(check_algorithm_constraints(&data, 4, &duplicate).is_err());
+}
+
+#[test]
+fn test_topology() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 4),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let topology: Topology = "2x6".parse().unwrap();
+    assert_eq!(topology.numa_nodes, vec![6, 6]);
+    assert_eq!("8,4".parse::<Topology>().unwrap().numa_nodes, vec![8, 4]);
+    assert!("2x0".parse::<Topology>().is_err());
+    let options = SolverOptions {
+        topology: Some(topology),
+        ..Default::default()
+    };
+    assert!(check_algorithm_constraints(&data, 12, &options).is_ok());
+    assert!(check_algorithm_constraints(&data, 16, &options).is_err());
+    assert_eq!(constraint_option("node_1"), Some("topology"));
+    assert_eq!(
+        describe_constraint("node_1", &data, 12, &options),
+        "the copies on NUMA node 1 use at most 6 cores"
+    );
+    // one copy of algo2 per node and the single-threaded copies around them
+    assert!(feasible(&data, 12, &options, &[1, 4], &[4.0, 2.0]));
+    // three copies of algo2 would need a node with 8 cores
+    assert!(!feasible(&data, 12, &options, &[1, 4], &[0.0, 3.0]));
+}
+
+#[test]
+fn test_cost_budget() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1).with_cost(2.0),
+        Algorithm::new("algo2".into(), 1).with_cost(3.0),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 4.0, 4.0, 4.0, 4.0, //
+        4.0, 4.0, 4.0, 4.0, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = |cost_budget: f64| SolverOptions {
+        cost_budget: Some(cost_budget),
+        algorithm_constraints: AlgorithmConstraints {
+            include: vec![Algorithm::new("algo1".into(), 1)],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(check_algorithm_constraints(&data, 4, &options(4.0)).is_ok());
+    assert!(check_algorithm_constraints(&data, 4, &options(1.0)).is_err());
+    assert_eq!(
+        describe_constraint("cost", &data, 4, &options(4.0)),
+        "the algorithms of the portfolio cost at most 4"
+    );
+    // every selected algorithm costs once, independent of its copies
+    assert!(feasible(&data, 4, &options(4.0), &[1, 1], &[4.0, 0.0]));
+    assert!(!feasible(&data, 4, &options(4.0), &[1, 1], &[3.0, 1.0]));
+    assert!(feasible(&data, 4, &options(5.0), &[1, 1], &[1.0, 3.0]));
+}
+
+#[test]
+fn test_forbid_imputed() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0,
+        1.0,
+        2.0,
+        2.0, //
+        f64::MAX,
+        f64::MAX,
+        1.0,
+        1.0,
+    ];
+    let mut data =
+        Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let options = SolverOptions {
+        forbid_imputed: true,
+        ..Default::default()
+    };
+    // without a mask, nothing is known to be imputed
+    assert!(feasible(&data, 2, &options, &[1, 1], &[2.0, 0.0]));
+    let mut imputed = ndarray::Array3::from_elem((2, 2, 2), false);
+    imputed.slice_mut(ndarray::s![1, 0, ..]).fill(true);
+    data.imputed = Some(imputed);
+    assert!(!feasible(&data, 2, &options, &[1, 1], &[2.0, 0.0]));
+    assert!(feasible(&data, 2, &options, &[1, 1], &[1.0, 1.0]));
+    assert!(feasible(
+        &data,
+        2,
+        &Default::default(),
+        &[1, 1],
+        &[2.0, 0.0]
+    ));
+    assert_eq!(
+        describe_constraint("imputed_1_0_1", &data, 2, &options),
+        "instance 1 is not assigned to 2 copies of algo1 1, whose quality was imputed"
+    );
+}
+
+#[test]
+fn test_greedy_solve() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 2),
+    ];
+    // 2 instances, 3 algorithms, 3 repetitions
+    let stats = [
+        1.5, 1.5, 1.5, 5.0, 5.0, 5.0, 2.0, 2.0, 2.0, //
+        5.0, 4.0, 4.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.5, 1.0], None, &stats, 3).unwrap();
+    let portfolio = greedy_solve(&data, 3);
+    // algo3 is the best single copy, algo2 complements it better than algo1
+    assert_eq!(
+        portfolio.resource_assignments,
+        vec![
+            (algorithms[0].clone(), 0.0),
+            (algorithms[1].clone(), 1.0),
+            (algorithms[2].clone(), 1.0),
+        ]
+    );
+}
+
+#[cfg(not(feature = "highs"))]
+#[test]
+fn test_solve_racing() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 2),
+    ];
+    // 2 instances, 3 algorithms, 3 repetitions
+    let stats = [
+        1.5, 1.5, 1.5, 5.0, 5.0, 5.0, 2.0, 2.0, 2.0, //
+        5.0, 4.0, 4.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.5, 1.0], None, &stats, 3).unwrap();
+    // the MIP fails without the HiGHS backend, the heuristics still find a portfolio
+    let options = SolverOptions {
+        backend: Backend::Highs,
+        timeout: Timeout(1.0),
+        ..Default::default()
+    };
+    let (result, method) = solve_racing(&data, 3, &options, None).unwrap();
+    assert_ne!(method, SolveMethod::Mip);
+    assert_eq!(result.final_portfolio.name, "final_portfolio");
+    assert_eq!(
+        objective(&data, &[1.0, 1.0, 0.0]),
+        objective(
+            &data,
+            &result
+                .final_portfolio
+                .resource_assignments
+                .iter()
+                .map(|(_, copies)| *copies)
+                .collect::<Vec<_>>()
+        )
+    );
+}
+
+#[test]
+fn test_auto() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 2),
+    ];
+    let stats = [
+        1.5, 1.5, 1.5, 5.0, 5.0, 5.0, 2.0, 2.0, 2.0, //
+        5.0, 4.0, 4.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.5, 1.0], None, &stats, 3).unwrap();
+    // without any budget left for the MIP, the best heuristic portfolio is kept
+    let (result, method) = auto(&data, 3, Timeout(0.0)).unwrap();
+    assert_ne!(method, SolveMethod::Mip);
+    assert_eq!(result.final_portfolio.name, "final_portfolio");
+    let copies = result
+        .final_portfolio
+        .resource_assignments
+        .iter()
+        .map(|(_, copies)| *copies)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        objective(&data, &copies),
+        objective(&data, &[1.0, 1.0, 0.0])
+    );
+}
+
+#[cfg(feature = "bo")]
+#[test]
+fn test_bo() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 2),
+    ];
+    // 2 instances, 3 algorithms, 4 repetitions
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 4.0, 4.0, 4.0, 4.0, 3.0, 2.0, 2.0, 2.0, //
+        4.0, 4.0, 4.0, 4.0, 1.0, 1.0, 1.0, 1.0, 3.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = super::bo::BoOptions {
+        max_evaluations: 30,
+        initial_samples: 5,
+        ..Default::default()
+    };
+    let result = super::bo::solve(&data, 4, &options).unwrap();
+    use itertools::Itertools;
+    assert_eq!(result.history.last().unwrap().objective, 2.0);
+    assert!(result
+        .history
+        .iter()
+        .tuple_windows()
+        .all(|(a, b)| a.objective > b.objective));
+    let copies = result
+        .final_portfolio
+        .resource_assignments
+        .iter()
+        .map(|(_, copies)| *copies)
+        .collect_vec();
+    assert_eq!(objective(&data, &copies), 2.0);
+}
+
+#[test]
+fn test_genetic() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 2),
+    ];
+    // 2 instances, 3 algorithms, 4 repetitions
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 4.0, 4.0, 4.0, 4.0, 3.0, 2.0, 2.0, 2.0, //
+        4.0, 4.0, 4.0, 4.0, 1.0, 1.0, 1.0, 1.0, 3.0, 2.0, 2.0, 2.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = super::genetic::GeneticOptions {
+        population_size: 10,
+        generations: 20,
+        ..Default::default()
+    };
+    let result = super::genetic::solve(&data, 4, &options).unwrap();
+    use itertools::Itertools;
+    assert_eq!(result.history.last().unwrap().objective, 2.0);
+    assert!(result
+        .history
+        .iter()
+        .tuple_windows()
+        .all(|(a, b)| a.objective > b.objective));
+    let copies = result
+        .final_portfolio
+        .resource_assignments
+        .iter()
+        .map(|(_, copies)| *copies)
+        .collect_vec();
+    assert_eq!(objective(&data, &copies), 2.0);
+    assert!(result.gap.abs() < f64::EPSILON);
+    assert_eq!(result.stats.objective, 2.0);
+    assert_eq!(result.stats.bound, Some(2.0));
+    assert_eq!(result.stats.num_incumbents, result.history.len());
+    assert_eq!(result.stats.node_count, None);
+    assert_eq!(result.stats.termination, Termination::Limit);
+}
+
+#[test]
+fn test_interchangeable_algorithms() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 1),
+        Algorithm::new("algo4".into(), 2),
+    ];
+    // algo1, algo2 and algo4 have the same qualities, algo4 uses more threads
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 1.0, 1.0, //
+        3.0, 3.0, 3.0, 3.0, 1.0, 1.0, 3.0, 3.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let classes = interchangeable_algorithms(&data, &Default::default());
+    assert_eq!(classes, vec![vec![0, 1]]);
+    assert_eq!(
+        symmetry_canonical_copies(&[0.0, 2.0, 0.0, 0.0], &classes),
+        vec![2.0, 0.0, 0.0, 0.0]
+    );
+    assert_eq!(
+        describe_constraint("symmetry_0_1", &data, 2, &Default::default()),
+        "algo1 1 gets at least as many copies as the interchangeable algo2 1"
+    );
+    // bounded algorithms are distinguished from the others
+    let options = SolverOptions {
+        algorithm_constraints: AlgorithmConstraints {
+            include: vec![algorithms[1].clone()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(interchangeable_algorithms(&data, &options).is_empty());
+}
+
+#[test]
+fn test_solver_error() {
+    let license = grb::Error::FromAPI("No Gurobi license found".into(), 10009);
+    assert!(matches!(
+        gurobi_error(license, SolverError::ModelBuild),
+        SolverError::Environment(_)
+    ));
+    let memory = grb::Error::FromAPI("Out of memory".into(), 10001);
+    let err = model_build_error(memory.into());
+    assert!(matches!(
+        err.downcast_ref::<SolverError>(),
+        Some(SolverError::ModelBuild(_))
+    ));
+    // invalid options are not a failure of Gurobi
+    let err = model_build_error(anyhow::anyhow!("No objective registered"));
+    assert!(err.downcast_ref::<SolverError>().is_none());
+}
+
+#[test]
+fn test_rescaled() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    // the best qualities span many orders of magnitude
+    let stats = [1e-6, 2e-6, 3e-6, 1e-6, 2e6, 1e6, 4e6, 2e6];
+    let data = Data::new(&algorithms, &[1e-6, 1e6], None, &stats, 2).unwrap();
+    let scaled = rescaled(&data);
+    assert_eq!(scaled.best_per_instance.to_vec(), vec![1.0, 1.0]);
+    assert_eq!(scaled.expected_best_quality[[1, 1, 0]], 4.0);
+    for copies in [[1.0, 0.0], [0.0, 2.0], [1.0, 1.0]] {
+        assert!(
+            (objective(&data, &copies) - objective(&scaled, &copies)).abs()
+                < 1e-9
+        );
+    }
+}
+
+#[test]
+fn test_instance_qualities() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 4.0, 3.0, //
+        6.0, 5.0, 2.0, 2.0,
+    ];
+    let mut data =
+        Data::new(&algorithms, &[1.0, 2.0], None, &stats, 2).unwrap();
+    data.instances = Some(vec!["graph1".into(), "graph2".into()]);
+    let portfolio = portfolio_from_copies(&data, &[0.0, 2.0], "portfolio");
+    let qualities = instance_qualities(&data, &portfolio);
+    assert_eq!(
+        qualities,
+        vec![
+            InstanceQuality {
+                instance: Some("graph1".into()),
+                expected_quality: 3.0,
+                ratio: 3.0,
+            },
+            InstanceQuality {
+                instance: Some("graph2".into()),
+                expected_quality: 2.0,
+                ratio: 1.0,
+            },
+        ]
+    );
+    let total = qualities.iter().map(|quality| quality.ratio).sum::<f64>();
+    assert_eq!(total, objective(&data, &[0.0, 2.0]));
+}
+
+#[test]
+fn test_gap_termination() {
+    let options = SolverOptions::default().with_mip_gap(0.01);
+    assert_eq!(
+        gap_termination(Termination::Optimal, 0.005, &options),
+        Termination::GapReached
+    );
+    // proven optimal within the gap
+    assert_eq!(
+        gap_termination(Termination::Optimal, 0.0, &options),
+        Termination::Optimal
+    );
+    assert_eq!(
+        gap_termination(Termination::Timeout, 0.005, &options),
+        Termination::Timeout
+    );
+    // without a gap, the solver stops at its own tolerance
+    assert_eq!(
+        gap_termination(Termination::Optimal, 0.00005, &Default::default()),
+        Termination::Optimal
+    );
+}
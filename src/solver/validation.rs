@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use itertools::Itertools;
+use log::{info, warn};
+use polars::prelude::*;
+
+use super::{objectives, portfolio_copies, quality_ratios};
+use crate::csv_parser::Data;
+use crate::datastructures::*;
+use crate::portfolio_simulator::simulate;
+
+/// Simulates the incumbents of a solve, see [`SimulationValidation`]
+pub(super) struct Validator<'a> {
+    data: &'a Data,
+    objective: &'a Objective,
+    /// Valid runs of the data frame of the validation
+    df: DataFrame,
+    /// Instance names in the order of the instances of the data
+    instances: Vec<String>,
+    num_seeds: u32,
+    tolerance: f64,
+}
+
+impl<'a> Validator<'a> {
+    /// Validate the incumbents of a solve of `data` with the `options` (if any)
+    pub fn new(
+        data: &'a Data,
+        options: &'a SolverOptions,
+    ) -> Result<Option<Self>> {
+        let Some(validation) = &options.validation else {
+            return Ok(None);
+        };
+        anyhow::ensure!(
+            validation.num_seeds > 0,
+            "The validation needs at least one seed"
+        );
+        let df = validation
+            .df
+            .as_ref()
+            .clone()
+            .lazy()
+            .filter(col("valid"))
+            .collect()?;
+        let instances = df["instance"].unique()?.sort(false);
+        anyhow::ensure!(
+            instances.len() == data.num_instances,
+            "The data has {} instances, but the data frame of the validation has {}",
+            data.num_instances,
+            instances.len()
+        );
+        let instances = instances
+            .utf8()?
+            .into_no_null_iter()
+            .map(String::from)
+            .collect_vec();
+        Ok(Some(Self {
+            data,
+            objective: &options.objective,
+            df,
+            instances,
+            num_seeds: validation.num_seeds,
+            tolerance: validation.tolerance,
+        }))
+    }
+
+    /// Objective of the `portfolio` in the simulation, without the penalties of the options, see
+    /// [`Validator::simulated_ratios`]
+    pub fn validate(&self, portfolio: &Portfolio) -> Result<f64> {
+        let copies = portfolio_copies(portfolio, self.data)
+            .into_iter()
+            .map(f64::round)
+            .collect_vec();
+        let ratios = self.simulated_ratios(portfolio)?;
+        let expected_ratios = quality_ratios(self.data, &copies);
+        let expected =
+            objectives::value(self.objective, self.data, &expected_ratios);
+        let simulated = objectives::value(self.objective, self.data, &ratios);
+        let deviation =
+            (simulated - expected).abs() / expected.abs().max(f64::EPSILON);
+        match deviation > self.tolerance {
+            true => warn!(
+                "The simulated objective {simulated} of {} deviates by {:.1}% from the expected objective {expected}",
+                portfolio.name,
+                deviation * 100.0
+            ),
+            false => info!(
+                "Simulated objective {simulated}, expected objective {expected}"
+            ),
+        }
+        Ok(simulated)
+    }
+
+    /// Quality ratio of every instance of the data in the simulation of the `portfolio`
+    ///
+    /// The quality ratio of an instance is the mean best quality of the simulated runs relative
+    /// to the best quality of the data. Instances without a valid run of the portfolio keep the
+    /// expected quality ratio of the data, as their quality is imputed there.
+    pub fn simulated_ratios(&self, portfolio: &Portfolio) -> Result<Vec<f64>> {
+        let copies = portfolio_copies(portfolio, self.data)
+            .into_iter()
+            .map(f64::round)
+            .collect_vec();
+        let portfolio = Portfolio {
+            name: portfolio.name.clone(),
+            resource_assignments: self
+                .data
+                .algorithms
+                .iter()
+                .cloned()
+                .zip(copies.iter().copied())
+                .filter(|(_, copies)| *copies >= 1.0)
+                .collect(),
+        };
+        let runs = (0..self.num_seeds)
+            .map(|seed| {
+                Ok(simulate(&self.df, &portfolio, seed as u64)?
+                    .groupby([col("instance"), col("seed")])
+                    .agg([min("quality")]))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let means = concat(runs, false, false)?
+            .groupby([col("instance")])
+            .agg([col("quality").cast(DataType::Float64).mean()])
+            .collect()?;
+        let simulated: HashMap<&str, f64> = means["instance"]
+            .utf8()?
+            .into_no_null_iter()
+            .zip(means["quality"].f64()?.into_no_null_iter())
+            .collect();
+        let expected_ratios = quality_ratios(self.data, &copies);
+        Ok(self
+            .instances
+            .iter()
+            .zip(&expected_ratios)
+            .enumerate()
+            .map(|(i, (instance, &expected))| {
+                simulated
+                    .get(instance.as_str())
+                    .map_or(expected, |quality| {
+                        quality / self.data.best_per_instance[i]
+                    })
+            })
+            .collect_vec())
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use itertools::Itertools;
+use log::{info, warn};
+use polars::prelude::{
+    concat, DataFrame, DataType, IntoLazy, LazyFrame, NamedFrom, Series,
+};
+
+use crate::csv_parser::{self, Data};
+use crate::datastructures::*;
+use crate::solver::Solver;
+
+type Parser = Box<dyn Fn(&[PathBuf]) -> Result<LazyFrame>>;
+
+/// Number of chunks parsed by all watchers, which keeps their temporary files apart
+static CHUNKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Watches csv files that are still written to and re-solves whenever they change.
+///
+/// Files are polled for changes of their size and modification time and tailed like a log: on
+/// every change, only the complete lines appended since the last change are parsed (a partially
+/// written last line waits for the next change) and added to the rows of the file. The input data
+/// is then built from the rows of all files and the model is solved again, starting from the
+/// previous final portfolio. A file that shrinks is read again from the start.
+///
+/// The parser is called with a temporary file of the header and the new lines of a file, which
+/// keeps the file name, so every row must parse independently of the other rows.
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    parser: Parser,
+    num_cores: u32,
+    slowdown_ratio: f64,
+    data_options: csv_parser::DataOptions,
+    comment_char: Option<u8>,
+    file_states: HashMap<PathBuf, (u64, SystemTime)>,
+    tails: HashMap<PathBuf, Tail>,
+}
+
+#[derive(Default)]
+/// Rows parsed so far from a watched file
+struct Tail {
+    /// Bytes of the file up to the end of the last parsed line
+    offset: u64,
+    /// Header line of the file, which is prepended to the appended lines
+    header: Option<Vec<u8>>,
+    /// All rows parsed so far
+    rows: Option<DataFrame>,
+}
+
+impl Watcher {
+    /// Watch normalized csv files, see [`csv_parser::parse_normalized_csvs`]
+    pub fn normalized(
+        paths: Vec<PathBuf>,
+        desired_instances: Option<PathBuf>,
+        num_cores: u32,
+        slowdown_ratio: f64,
+    ) -> Self {
+        Self::with_parser(
+            paths,
+            move |paths| {
+                csv_parser::parse_normalized_csvs(
+                    paths,
+                    desired_instances.clone(),
+                    num_cores,
+                )
+            },
+            num_cores,
+            slowdown_ratio,
+        )
+    }
+
+    /// Watch files that are parsed into a normalized data frame by `parser`
+    pub fn with_parser<F>(
+        paths: Vec<PathBuf>,
+        parser: F,
+        num_cores: u32,
+        slowdown_ratio: f64,
+    ) -> Self
+    where
+        F: Fn(&[PathBuf]) -> Result<LazyFrame> + 'static,
+    {
+        Self {
+            paths,
+            parser: Box::new(parser),
+            num_cores,
+            slowdown_ratio,
+            data_options: csv_parser::DataOptions::default(),
+            comment_char: Some(b'#'),
+            file_states: HashMap::new(),
+            tails: HashMap::new(),
+        }
+    }
+
+    /// Build the input data from the parsed data frames with `options`, see
+    /// [`Data::from_normalized_dataframe_with_options`]
+    pub fn with_data_options(
+        mut self,
+        options: csv_parser::DataOptions,
+    ) -> Self {
+        self.data_options = options;
+        self
+    }
+
+    /// Skip lines starting with `comment_char` before the header of the files (defaults to `#`)
+    pub fn with_comment_char(mut self, comment_char: Option<u8>) -> Self {
+        self.comment_char = comment_char;
+        self
+    }
+
+    /// Check whether any watched file changed since the last call
+    ///
+    /// Always true on the first call if any file exists.
+    pub fn poll_changes(&mut self) -> bool {
+        let mut changed = false;
+        for path in &self.paths {
+            let Ok(metadata) = path.metadata() else {
+                continue;
+            };
+            let state = (
+                metadata.len(),
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            );
+            if self.file_states.insert(path.clone(), state) != Some(state) {
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Parse the lines appended to the watched files since the last call and build the input data
+    /// from all rows parsed so far
+    ///
+    /// Fails if the new lines of any file could not be parsed, they are parsed again on the next
+    /// call. The new lines of the other files are kept.
+    pub fn data(&mut self) -> Result<Data> {
+        let mut failed = None;
+        for path in self.paths.clone() {
+            if let Err(err) = self.tail(&path) {
+                failed.get_or_insert(err.context(format!(
+                    "Failed to parse the new lines of {}",
+                    path.display()
+                )));
+            }
+        }
+        if let Some(err) = failed {
+            return Err(err);
+        }
+        let frames = self
+            .paths
+            .iter()
+            .filter_map(|path| self.tails.get(path)?.rows.clone())
+            .collect_vec();
+        anyhow::ensure!(!frames.is_empty(), "No rows were parsed yet");
+        Data::from_normalized_dataframe_with_options(
+            concat(align_columns(frames)?, true, true)?,
+            self.num_cores,
+            self.slowdown_ratio,
+            &self.data_options,
+        )
+    }
+
+    /// Parse the complete lines appended to the file at `path` since the last call and add them
+    /// to its rows
+    fn tail(&mut self, path: &Path) -> Result<()> {
+        let Ok(metadata) = path.metadata() else {
+            return Ok(());
+        };
+        let tail = self.tails.entry(path.to_path_buf()).or_default();
+        if metadata.len() < tail.offset {
+            info!("{} was truncated, reading it again", path.display());
+            *tail = Tail::default();
+        }
+        let (offset, header) = (tail.offset, tail.header.clone());
+        let mut lines = Vec::new();
+        let mut file = fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_to_end(&mut lines)?;
+        let Some(end) = lines.iter().rposition(|&byte| byte == b'\n') else {
+            return Ok(());
+        };
+        lines.truncate(end + 1);
+        let Some(header) =
+            header.or_else(|| header_line(&lines, self.comment_char))
+        else {
+            return Ok(());
+        };
+        let chunk = match offset {
+            0 => lines.clone(),
+            _ => [header.as_slice(), &lines].concat(),
+        };
+        let rows = self.parse_chunk(path, &chunk)?;
+        let tail = self.tails.entry(path.to_path_buf()).or_default();
+        tail.rows = Some(match tail.rows.take() {
+            Some(previous) => previous.vstack(&rows)?,
+            None => rows,
+        });
+        tail.offset = offset + lines.len() as u64;
+        tail.header = Some(header);
+        Ok(())
+    }
+
+    /// Parse the `chunk` of lines of the file at `path` with the parser
+    fn parse_chunk(&self, path: &Path, chunk: &[u8]) -> Result<DataFrame> {
+        let dir = std::env::temp_dir().join(format!(
+            "portfolio_solver_watch_{}_{}",
+            std::process::id(),
+            CHUNKS.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        // the chunk keeps the file name, which the parser may extract columns from
+        let chunk_path =
+            dir.join(path.file_name().unwrap_or("chunk.csv".as_ref()));
+        fs::write(&chunk_path, chunk)?;
+        let rows =
+            (self.parser)(&[chunk_path]).and_then(|df| Ok(df.collect()?));
+        if let Err(err) = fs::remove_dir_all(&dir) {
+            warn!("Failed to remove {}: {err}", dir.display());
+        }
+        let mut rows = rows?;
+        // rows tagged with their source file name the watched file, not the chunk
+        if rows.get_column_names().contains(&"file") {
+            let file = vec![path.display().to_string(); rows.height()];
+            rows.with_column(Series::new("file", file))?;
+        }
+        Ok(rows)
+    }
+
+    /// Poll the files every `interval` and solve again if they changed.
+    ///
+    /// `on_update` is called with the new data and result after every solve and stops watching
+    /// if it returns `false`. The previous portfolio is the initial portfolio of the next solve,
+    /// without the algorithms that are no longer part of the data. A failed solve is logged and
+    /// the watcher waits for the next change.
+    pub fn run<F>(
+        &mut self,
+        solver: &Solver,
+        options: &SolverOptions,
+        interval: Duration,
+        on_update: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&Data, &OptimizationResult) -> Result<bool>,
+    {
+        let num_cores = self.num_cores as usize;
+        self.run_with(
+            interval,
+            |data, initial| solver.solve(data, num_cores, options, initial),
+            on_update,
+        )
+    }
+
+    /// Poll the files every `interval` and solve again with `solve` if they changed, see
+    /// [`Watcher::run`]
+    ///
+    /// `solve` is called with the new data and the initial portfolio.
+    pub fn run_with<S, F>(
+        &mut self,
+        interval: Duration,
+        mut solve: S,
+        mut on_update: F,
+    ) -> Result<()>
+    where
+        S: FnMut(&Data, Option<&Portfolio>) -> Result<OptimizationResult>,
+        F: FnMut(&Data, &OptimizationResult) -> Result<bool>,
+    {
+        let mut previous: Option<Portfolio> = None;
+        loop {
+            if self.poll_changes() {
+                match self.data() {
+                    Ok(data) => {
+                        info!("Input changed, solving again: {data}");
+                        let initial =
+                            previous.as_ref().map(|portfolio| Portfolio {
+                                resource_assignments: portfolio
+                                    .resource_assignments
+                                    .iter()
+                                    .filter(|(a, _)| {
+                                        data.algorithms.iter().any(|b| a == b)
+                                    })
+                                    .cloned()
+                                    .collect(),
+                                ..portfolio.clone()
+                            });
+                        match solve(&data, initial.as_ref()) {
+                            Ok(result) => {
+                                previous =
+                                    Some(result.final_portfolio.clone());
+                                if !on_update(&data, &result)? {
+                                    return Ok(());
+                                }
+                            }
+                            Err(err) => {
+                                warn!("Failed to solve, waiting for the next change: {err:#}");
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        warn!("Failed to parse the input, waiting for the next change: {err:#}");
+                    }
+                }
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+/// The first line of `lines` (including its line break) that is neither empty nor a comment
+fn header_line(lines: &[u8], comment_char: Option<u8>) -> Option<Vec<u8>> {
+    lines
+        .split_inclusive(|&byte| byte == b'\n')
+        .find(|line| {
+            !line.iter().all(u8::is_ascii_whitespace)
+                && line.first() != comment_char.as_ref()
+        })
+        .map(<[u8]>::to_vec)
+}
+
+/// The `frames` with the union of their columns (in the order they first appear), missing
+/// columns are null
+fn align_columns(frames: Vec<DataFrame>) -> Result<Vec<LazyFrame>> {
+    let mut columns: Vec<(String, DataType)> = Vec::new();
+    for series in frames.iter().flat_map(|df| df.get_columns()) {
+        if !columns.iter().any(|(name, _)| name == series.name()) {
+            columns.push((series.name().to_string(), series.dtype().clone()));
+        }
+    }
+    frames
+        .into_iter()
+        .map(|mut df| {
+            for (name, dtype) in &columns {
+                if df.column(name).is_err() {
+                    let nulls = Series::full_null(name, df.height(), dtype);
+                    df.with_column(nulls)?;
+                }
+            }
+            Ok(df.select(columns.iter().map(|(name, _)| name))?.lazy())
+        })
+        .collect()
+}
@@ -11,7 +11,7 @@ fn test_missing_algo_for_instance() {
     let k = 2;
     let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
     let data =
-        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX)
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
             .unwrap();
     assert_eq!(data.num_instances, 4);
     assert_eq!(data.num_algorithms, 2);
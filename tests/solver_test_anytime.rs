@@ -0,0 +1,28 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver::solve_anytime};
+use std::{path::PathBuf, sync::Arc};
+
+#[test]
+fn test_anytime() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    let mut solve = solve_anytime(
+        Arc::new(data),
+        k as usize,
+        SolverOptions::default(),
+        None,
+    );
+    let incumbents = solve.by_ref().collect::<Vec<_>>();
+    let result = solve.result().unwrap();
+    assert_eq!(incumbents, result.history);
+    assert_eq!(
+        incumbents.last().unwrap().portfolio.resource_assignments,
+        result.final_portfolio.resource_assignments
+    );
+}
@@ -0,0 +1,44 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver::solve};
+use std::path::PathBuf;
+
+#[test]
+fn test_benders_decomposition() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    for objective in [Objective::QualityRatio, Objective::GeometricMean] {
+        let options = |decomposition| SolverOptions {
+            objective: objective.clone(),
+            decomposition,
+            ..Default::default()
+        };
+        let monolithic = solve(
+            &data,
+            k as usize,
+            &options(Decomposition::Monolithic),
+            None,
+        )
+        .unwrap();
+        let benders =
+            solve(&data, k as usize, &options(Decomposition::Benders), None)
+                .unwrap();
+        assert_eq!(
+            benders.final_portfolio.resource_assignments,
+            monolithic.final_portfolio.resource_assignments
+        );
+        assert!(benders.gap_closed >= monolithic.gap_closed - 1e-9);
+    }
+
+    let worst_case = SolverOptions {
+        objective: Objective::WorstCase,
+        decomposition: Decomposition::Benders,
+        ..Default::default()
+    };
+    assert!(solve(&data, k as usize, &worst_case, None).is_err());
+}
@@ -0,0 +1,42 @@
+use portfolio_solver::{
+    csv_parser::Data,
+    datastructures::*,
+    solver::{solve, solve_for_budgets},
+};
+
+#[test]
+fn test_solve_for_budgets() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        3.0, 2.0, 1.5, 1.2, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = SolverOptions::default();
+    let budgets = [4, 1, 2];
+    let results = solve_for_budgets(&data, &budgets, &options).unwrap();
+    assert_eq!(results.len(), budgets.len());
+    for (result, &num_cores) in results.iter().zip(&budgets) {
+        let single = solve(&data, num_cores, &options, None).unwrap();
+        assert!(
+            (result.stats.objective - single.stats.objective).abs() < 1e-9
+        );
+        assert_eq!(
+            result
+                .final_portfolio
+                .resource_assignments
+                .iter()
+                .map(|(_, copies)| copies)
+                .sum::<f64>(),
+            num_cores as f64
+        );
+    }
+    // larger budgets start from the portfolio of the smaller ones
+    assert!(results[0].initial_portfolio.is_some());
+
+    assert!(solve_for_budgets(&data, &[2, 2], &options).is_err());
+    assert!(solve_for_budgets(&data, &[8], &options).is_err());
+}
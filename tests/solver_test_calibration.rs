@@ -0,0 +1,44 @@
+use polars::prelude::*;
+use portfolio_solver::{
+    csv_parser::DataOptions,
+    datastructures::*,
+    solver::{
+        calibrate_slowdown_ratio, slowdown_ratio_grid, CalibrationSolver,
+    },
+};
+
+#[test]
+fn test_calibrate_slowdown_ratio() {
+    // the fast algorithm is twice as bad as the slow one on both instances
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph2", "graph2"],
+        "algorithm" => ["fast", "slow", "fast", "slow"],
+        "num_threads" => [1i64; 4],
+        "quality" => [2.0, 1.0, 2.0, 1.0],
+        "time" => [1.0, 100.0, 1.0, 100.0],
+        "valid" => [true; 4],
+    }
+    .unwrap();
+    let points = calibrate_slowdown_ratio(
+        df.lazy(),
+        1,
+        &[10.0, 1.0],
+        &DataOptions::default(),
+        CalibrationSolver::Greedy,
+        &SolverOptions::default(),
+    )
+    .unwrap();
+    let ratios = points.iter().map(|p| p.slowdown_ratio).collect::<Vec<_>>();
+    assert_eq!(ratios, vec![1.0, 10.0]);
+    let algorithms =
+        points.iter().map(|p| p.num_algorithms).collect::<Vec<_>>();
+    assert_eq!(algorithms, vec![1, 2]);
+    // both objectives are relative to the slow algorithm
+    let objectives = points.iter().map(|p| p.objective).collect::<Vec<_>>();
+    assert_eq!(objectives, vec![4.0, 2.0]);
+    let runtimes = points.iter().map(|p| p.runtime).collect::<Vec<_>>();
+    assert_eq!(runtimes, vec![1.0, 100.0]);
+    assert!(points.iter().all(|p| p.on_frontier));
+
+    assert_eq!(slowdown_ratio_grid(1.0, 100.0, 3), vec![1.0, 10.0, 100.0]);
+}
@@ -0,0 +1,27 @@
+use portfolio_solver::{
+    csv_parser, datastructures::*, solver::solve_with_progress,
+};
+use std::path::PathBuf;
+
+#[test]
+fn test_cancellation() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    let token = CancellationToken::new();
+    let options = SolverOptions::default().with_cancellation(token.clone());
+    // cancel as soon as the first portfolio is found
+    let result =
+        solve_with_progress(&data, k as usize, &options, None, |_| {
+            token.cancel()
+        })
+        .unwrap();
+    assert!(!result.history.is_empty());
+    assert!(!result.refined);
+}
@@ -0,0 +1,61 @@
+use polars::prelude::*;
+use portfolio_solver::{csv_parser, datastructures::*, solver};
+
+#[test]
+fn test_chance_constraint() {
+    // algo1 reaches 1.1 * best on graph1 in 3 of 4 runs, only algo2 on graph2
+    let df = df! {
+        "instance" => ["graph1", "graph1", "graph1", "graph1", "graph1", "graph2", "graph2", "graph2"],
+        "algorithm" => ["algo1", "algo1", "algo1", "algo1", "algo2", "algo1", "algo2", "algo2"],
+        "num_threads" => [1i64, 1, 1, 1, 1, 1, 1, 1],
+        "quality" => [1.0, 1.0, 1.0, 5.0, 1.5, 3.0, 1.0, 1.05],
+        "time" => [1.0; 8],
+        "valid" => [true; 8],
+    }
+    .unwrap()
+    .lazy();
+    let k = 3;
+    let data = csv_parser::Data::from_normalized_dataframe(
+        df.clone(),
+        k,
+        f64::MAX,
+        0,
+    )
+    .unwrap();
+    let chance = ChanceConstraint {
+        threshold: QualityThreshold {
+            factor: 1.1,
+            probability: 0.9,
+        },
+        success: csv_parser::success_rates(df, &data, 1.1).unwrap(),
+    };
+    let options = SolverOptions {
+        chance_constraint: Some(chance.clone()),
+        ..Default::default()
+    };
+
+    // graph1 needs two copies of algo1 and graph2 one copy of algo2
+    let result = solver::solve(&data, k as usize, &options, None).unwrap();
+    let copies = data
+        .algorithms
+        .iter()
+        .map(|algo| {
+            result
+                .final_portfolio
+                .resource_assignments
+                .iter()
+                .find(|(a, _)| a == algo)
+                .map_or(0.0, |(_, c)| *c)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(copies, vec![2.0, 1.0]);
+    assert!(chance.satisfied(&copies));
+
+    let Err(err) = solver::solve(&data, 2, &options, None) else {
+        panic!("two cores cannot satisfy both instances");
+    };
+    let infeasibility = err.downcast_ref::<Infeasibility>().unwrap();
+    assert!(infeasibility
+        .options
+        .contains(&String::from("chance_constraint")));
+}
@@ -0,0 +1,37 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver};
+use std::path::PathBuf;
+
+#[test]
+fn test_checkpoint_resume() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    let dir = std::env::temp_dir().join("portfolio_solver_resume");
+    std::fs::remove_dir_all(&dir).ok();
+    let options = SolverOptions::default().with_checkpoint(&dir, 0.1);
+    let result = solver::solve(&data, k as usize, &options, None).unwrap();
+    assert!(dir.join("checkpoint.json").exists());
+    assert!(dir.join("state.json").exists());
+    let resumed = solver::resume(&dir).unwrap();
+    // the resumed solve starts from the optimal portfolio of the first run
+    let objective = |result: &OptimizationResult| {
+        result
+            .history
+            .last()
+            .map(|incumbent| incumbent.objective)
+            .unwrap()
+    };
+    assert!(objective(&resumed) <= objective(&result) + 1e-6);
+    assert!(resumed.history.len() >= result.history.len());
+    assert!(resumed
+        .history
+        .windows(2)
+        .all(|pair| pair[0].time <= pair[1].time));
+    assert!(solver::resume(dir.join("missing")).is_err());
+}
@@ -0,0 +1,46 @@
+use portfolio_solver::{
+    csv_parser,
+    datastructures::*,
+    solver::{solve, solve_colgen},
+};
+use std::path::PathBuf;
+
+#[test]
+fn test_column_generation() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 3;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    for objective in [Objective::QualityRatio, Objective::GeometricMean] {
+        let options = SolverOptions {
+            objective,
+            ..Default::default()
+        };
+        let monolithic = solve(&data, k as usize, &options, None).unwrap();
+        let colgen = solve_colgen(&data, k as usize, &options).unwrap();
+        assert!(
+            (colgen.stats.objective - monolithic.stats.objective).abs() < 1e-6
+        );
+        assert!(colgen.gap < 1e-4);
+        assert!(
+            colgen
+                .final_portfolio
+                .resource_assignments
+                .iter()
+                .map(|(_, cores)| cores)
+                .sum::<f64>()
+                <= k as f64
+        );
+    }
+
+    let worst_case = SolverOptions {
+        objective: Objective::WorstCase,
+        ..Default::default()
+    };
+    assert!(solve_colgen(&data, k as usize, &worst_case).is_err());
+}
@@ -0,0 +1,34 @@
+use portfolio_solver::{csv_parser::Data, datastructures::*, solver::solve};
+
+#[test]
+fn test_complexity_penalty() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        3.0, 2.0, 1.5, 1.2, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let num_algorithms = |options: &SolverOptions| {
+        let result = solve(&data, 2, options, None).unwrap();
+        let selected = result
+            .final_portfolio
+            .resource_assignments
+            .iter()
+            .filter(|(_, copies)| *copies >= 1.0)
+            .count();
+        (selected, result.stats.objective)
+    };
+    // both algorithms reach the virtual best
+    assert_eq!(num_algorithms(&SolverOptions::default()), (2, 2.0));
+    // a second algorithm improves the objective by 1, less than its penalty
+    let options = SolverOptions {
+        complexity_penalty: Some(1.5),
+        ..Default::default()
+    };
+    let (selected, objective) = num_algorithms(&options);
+    assert_eq!(selected, 1);
+    assert!((objective - 4.5).abs() < 1e-9);
+}
@@ -0,0 +1,36 @@
+use portfolio_solver::{csv_parser::Data, datastructures::*, solver::solve};
+
+#[test]
+fn test_core_classes() {
+    let algorithms = [
+        Algorithm::new("algo1@e".into(), 1),
+        Algorithm::new("algo1@p".into(), 1),
+    ];
+    // algo1 reaches better qualities on the faster performance cores
+    let stats = [
+        2.0, 1.9, 1.8, 1.7, 1.6, 1.3, 1.1, 1.0, //
+        2.0, 1.9, 1.8, 1.7, 1.6, 1.3, 1.1, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let class = |name: &str, cores: u32| CoreClass {
+        name: name.into(),
+        cores,
+        speed: 1.0,
+    };
+    let copies = |options: &SolverOptions| {
+        solve(&data, 4, options, None)
+            .unwrap()
+            .final_portfolio
+            .resource_assignments
+            .iter()
+            .map(|(_, copies)| *copies)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(copies(&SolverOptions::default()), vec![0.0, 4.0]);
+    // only one performance core is available
+    let options = SolverOptions {
+        core_classes: vec![class("p", 1), class("e", 3)],
+        ..Default::default()
+    };
+    assert_eq!(copies(&options), vec![3.0, 1.0]);
+}
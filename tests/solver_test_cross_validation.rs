@@ -0,0 +1,60 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver};
+use std::path::PathBuf;
+
+#[test]
+fn test_cross_validation() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k)
+        .unwrap()
+        .collect()
+        .unwrap();
+    let cv_options = solver::CrossValidationOptions {
+        num_folds: 2,
+        num_seeds: 2,
+        ..Default::default()
+    };
+    let cv = solver::cross_validate(
+        &df,
+        k,
+        std::f64::MAX,
+        &Default::default(),
+        &SolverOptions::default(),
+        &cv_options,
+    )
+    .unwrap();
+    assert_eq!(cv.folds.len(), 2);
+    // every instance is held out exactly once
+    let mut held_out = cv
+        .folds
+        .iter()
+        .flat_map(|fold| fold.instances.clone())
+        .collect::<Vec<_>>();
+    held_out.sort();
+    held_out.dedup();
+    assert_eq!(held_out.len(), df["instance"].n_unique().unwrap());
+    let gaps = cv
+        .folds
+        .iter()
+        .map(|fold| fold.generalization_gap())
+        .collect::<Vec<_>>();
+    assert!((cv.mean_gap - (gaps[0] + gaps[1]) / 2.0).abs() < 1e-9);
+    assert!(cv.std_gap >= 0.0);
+
+    let too_many = solver::CrossValidationOptions {
+        num_folds: 1000,
+        ..Default::default()
+    };
+    assert!(solver::cross_validate(
+        &df,
+        k,
+        std::f64::MAX,
+        &Default::default(),
+        &SolverOptions::default(),
+        &too_many,
+    )
+    .is_err());
+}
@@ -0,0 +1,26 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver::solve};
+use std::path::PathBuf;
+
+#[test]
+fn test_prune_dominated() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    for objective in [Objective::QualityRatio, Objective::WorstCase] {
+        let options = |prune_dominated| SolverOptions {
+            objective: objective.clone(),
+            decomposition: Decomposition::Monolithic,
+            prune_dominated,
+            ..Default::default()
+        };
+        let full = solve(&data, k as usize, &options(false), None).unwrap();
+        let pruned = solve(&data, k as usize, &options(true), None).unwrap();
+        assert!((pruned.stats.objective - full.stats.objective).abs() < 1e-6);
+    }
+}
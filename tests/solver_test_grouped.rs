@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+
+use portfolio_solver::{
+    csv_parser::Data, datastructures::*, solver::solve_grouped,
+};
+
+#[test]
+fn test_grouped() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 1),
+    ];
+    // algo1 is best on the first group, algo2 on the second, algo3 is good on both
+    let groups = BTreeMap::from([
+        (
+            String::from("a"),
+            Data::new(&algorithms, &[1.0], None, &[1.0, 3.0, 1.2], 1).unwrap(),
+        ),
+        (
+            String::from("b"),
+            Data::new(&algorithms, &[1.0], None, &[3.0, 1.0, 1.2], 1).unwrap(),
+        ),
+    ]);
+    let copies = |max_deployed: Option<usize>| {
+        solve_grouped(&groups, 1, max_deployed, &SolverOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|(group, portfolio)| {
+                let copies = portfolio
+                    .resource_assignments
+                    .iter()
+                    .map(|(_, copies)| *copies)
+                    .collect::<Vec<_>>();
+                (group, copies)
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let separate = copies(None);
+    assert_eq!(separate["a"], vec![1.0, 0.0, 0.0]);
+    assert_eq!(separate["b"], vec![0.0, 1.0, 0.0]);
+    // a single algorithm for both groups
+    let shared = copies(Some(1));
+    assert_eq!(shared["a"], vec![0.0, 0.0, 1.0]);
+    assert_eq!(shared["b"], vec![0.0, 0.0, 1.0]);
+}
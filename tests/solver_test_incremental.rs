@@ -0,0 +1,55 @@
+use portfolio_solver::{
+    csv_parser::Data,
+    datastructures::*,
+    solver::{solve, Solver},
+};
+
+fn copies(result: &OptimizationResult) -> Vec<f64> {
+    result
+        .final_portfolio
+        .resource_assignments
+        .iter()
+        .map(|(_, copies)| *copies)
+        .collect()
+}
+
+#[test]
+fn test_incremental() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+        Algorithm::new("algo3".into(), 1),
+    ];
+    // algo1 is best on the first instance, algo2 on the second
+    let stats = [
+        1.0, 1.0, 2.0, 2.0, //
+        2.0, 2.0, 1.0, 1.0,
+    ];
+    let data =
+        Data::new(&algorithms[..2], &[1.0, 1.0], None, &stats, 2).unwrap();
+    // algo3 is best on both instances and on the new third one
+    let grown_stats = [
+        1.0, 1.0, 2.0, 2.0, 0.5, 0.5, //
+        2.0, 2.0, 1.0, 1.0, 0.5, 0.5, //
+        2.0, 2.0, 2.0, 2.0, 0.5, 0.5,
+    ];
+    let grown =
+        Data::new(&algorithms, &[0.5, 0.5, 0.5], None, &grown_stats, 2)
+            .unwrap();
+
+    let options = SolverOptions::default();
+    let mut incremental =
+        Solver::new().unwrap().incremental(2, &options).unwrap();
+    let result = incremental.solve(&data).unwrap();
+    assert_eq!(copies(&result), vec![1.0, 1.0]);
+    assert_eq!(incremental.size(), (2, 2));
+    let result = incremental.solve(&grown).unwrap();
+    assert_eq!(incremental.size(), (3, 3));
+    assert_eq!(copies(&result), vec![0.0, 0.0, 2.0]);
+    assert_eq!(
+        copies(&result),
+        copies(&solve(&grown, 2, &options, None).unwrap())
+    );
+    // removing an algorithm is not supported
+    assert!(incremental.solve(&data).is_err());
+}
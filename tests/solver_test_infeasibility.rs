@@ -0,0 +1,38 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver};
+use std::path::PathBuf;
+
+#[test]
+fn test_infeasibility() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let mut data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    data.algorithms[0] = data.algorithms[0].clone().with_memory(2.0);
+    let mut options = SolverOptions {
+        memory_budget: Some(1.0),
+        ..Default::default()
+    };
+    options
+        .algorithm_constraints
+        .include
+        .push(data.algorithms[0].clone());
+    let Err(err) = solver::solve(&data, k as usize, &options, None) else {
+        panic!("the included algorithm exceeds the memory budget");
+    };
+    let infeasibility = err.downcast_ref::<Infeasibility>().unwrap();
+    assert!(infeasibility.conflicts.contains(&format!(
+        "{} is forced into the portfolio",
+        data.algorithms[0]
+    )));
+    assert!(infeasibility
+        .options
+        .contains(&String::from("algorithm_constraints.include")));
+    assert!(infeasibility
+        .options
+        .contains(&String::from("memory_budget")));
+}
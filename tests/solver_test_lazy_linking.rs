@@ -0,0 +1,26 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver::solve};
+use std::path::PathBuf;
+
+#[test]
+fn test_lazy_linking() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    for objective in [Objective::QualityRatio, Objective::WorstCase] {
+        let options = |lazy_linking| SolverOptions {
+            objective: objective.clone(),
+            decomposition: Decomposition::Monolithic,
+            lazy_linking,
+            ..Default::default()
+        };
+        let eager = solve(&data, k as usize, &options(false), None).unwrap();
+        let lazy = solve(&data, k as usize, &options(true), None).unwrap();
+        assert!((lazy.stats.objective - eager.stats.objective).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,45 @@
+use portfolio_solver::{
+    csv_parser::Data,
+    datastructures::*,
+    solver::{marginal_cores, solve},
+};
+
+#[test]
+fn test_marginal_cores() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        3.0, 2.0, 1.5, 1.2, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = SolverOptions::default();
+    let result = solve(&data, 1, &options, None).unwrap();
+    let marginal =
+        marginal_cores(&data, 1, 3, &options, &result.final_portfolio)
+            .unwrap();
+    assert_eq!(
+        marginal
+            .iter()
+            .map(|m| m.additional_cores)
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+    // a second core reaches the virtual best, more cores don't help
+    assert!(marginal[0].improvement > 0.0);
+    assert!((marginal[0].objective - data.vbs_objective()).abs() < 1e-9);
+    assert!(marginal[1].marginal.abs() < 1e-9);
+    assert!(
+        (marginal.iter().map(|m| m.marginal).sum::<f64>()
+            - marginal[2].improvement)
+            .abs()
+            < 1e-9
+    );
+
+    assert!(
+        marginal_cores(&data, 1, 4, &options, &result.final_portfolio)
+            .is_err()
+    );
+}
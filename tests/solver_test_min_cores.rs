@@ -0,0 +1,33 @@
+use portfolio_solver::{
+    csv_parser::Data, datastructures::*, solver::solve_min_cores,
+};
+
+#[test]
+fn test_min_cores() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        3.0, 2.0, 1.5, 1.2, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let cores = |target: f64| {
+        solve_min_cores(&data, target, Timeout::default())
+            .unwrap()
+            .final_portfolio
+            .resource_assignments
+    };
+    // a single copy of algo1 has quality ratio sum 4
+    assert_eq!(
+        cores(2.0),
+        vec![(algorithms[0].clone(), 1.0), (algorithms[1].clone(), 0.0)]
+    );
+    // the virtual best needs both algorithms
+    assert_eq!(
+        cores(1.0),
+        vec![(algorithms[0].clone(), 1.0), (algorithms[1].clone(), 1.0)]
+    );
+    assert!(solve_min_cores(&data, 0.5, Timeout::default()).is_err());
+}
@@ -0,0 +1,47 @@
+use portfolio_solver::{csv_parser::Data, datastructures::*, solver::solve};
+
+/// Copies per algorithm of the portfolio for `objective` on one core, algo1 is better on
+/// average, but bad on the last instance, algo2 is consistent
+fn copies(objective: Objective) -> Vec<f64> {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.6, //
+        1.0, 1.6, //
+        1.0, 1.6, //
+        3.0, 1.6,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0, 1.0, 1.0], None, &stats, 1)
+        .unwrap();
+    let options = SolverOptions {
+        objective,
+        ..Default::default()
+    };
+    solve(&data, 1, &options, None)
+        .unwrap()
+        .final_portfolio
+        .resource_assignments
+        .iter()
+        .map(|(_, copies)| *copies)
+        .collect()
+}
+
+#[test]
+fn test_mean_variance() {
+    assert_eq!(copies(Objective::QualityRatio), vec![1.0, 0.0]);
+    // mean 1.5 and variance 0.75 of algo1 against mean 1.6 and variance 0 of algo2
+    assert_eq!(
+        copies(Objective::MeanVariance { lambda: 1.0 }),
+        vec![0.0, 1.0]
+    );
+}
+
+#[test]
+fn test_cvar() {
+    // the mean 1.5 of algo1 against 1.6 of algo2
+    assert_eq!(copies(Objective::Cvar { alpha: 1.0 }), vec![1.0, 0.0]);
+    // the worst instance 3.0 of algo1 against 1.6 of algo2
+    assert_eq!(copies(Objective::Cvar { alpha: 0.25 }), vec![0.0, 1.0]);
+}
@@ -0,0 +1,51 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver};
+use std::path::PathBuf;
+
+#[test]
+fn test_pareto() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data = csv_parser::Data::from_normalized_dataframe(
+        df.clone(),
+        k,
+        std::f64::MAX,
+        0,
+    )
+    .unwrap();
+    let times = csv_parser::expected_times(df, &data).unwrap();
+    let front = solver::solve_pareto(
+        &data,
+        &times,
+        k as usize,
+        3,
+        &SolverOptions::default(),
+    )
+    .unwrap();
+    assert!(!front.is_empty());
+    // faster portfolios have a worse quality
+    assert!(front
+        .windows(2)
+        .all(|pair| pair[0].runtime < pair[1].runtime
+            && pair[0].objective > pair[1].objective));
+    let best =
+        solver::solve(&data, k as usize, &Default::default(), None).unwrap();
+    let last = front.last().unwrap();
+    assert!(
+        (last.objective
+            - solver::objective(
+                &data,
+                &best
+                    .final_portfolio
+                    .resource_assignments
+                    .iter()
+                    .map(|(_, copies)| *copies)
+                    .collect::<Vec<_>>()
+            ))
+        .abs()
+            < 1e-6
+    );
+}
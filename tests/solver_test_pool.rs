@@ -0,0 +1,33 @@
+use portfolio_solver::{csv_parser, datastructures::*, solver::solve};
+use std::path::PathBuf;
+
+#[test]
+fn test_solution_pool() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    let options = SolverOptions::default().with_pool(3, None);
+    let result = solve(&data, k as usize, &options, None).unwrap();
+    assert!(!result.pool.is_empty() && result.pool.len() <= 3);
+    assert_eq!(
+        result.pool[0].resource_assignments,
+        result.final_portfolio.resource_assignments
+    );
+    for (i, portfolio) in result.pool.iter().enumerate() {
+        assert!(
+            result.pool[..i]
+                .iter()
+                .all(|p| p.resource_assignments
+                    != portfolio.resource_assignments)
+        );
+    }
+    let result =
+        solve(&data, k as usize, &SolverOptions::default(), None).unwrap();
+    assert!(result.pool.is_empty());
+}
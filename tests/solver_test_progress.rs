@@ -0,0 +1,36 @@
+use portfolio_solver::{
+    csv_parser, datastructures::*, solver::solve_with_progress,
+};
+use std::path::PathBuf;
+
+#[test]
+fn test_progress_callback() {
+    let files = vec![
+        PathBuf::from("data/test/algo1.csv"),
+        "data/test/algo2.csv".into(),
+    ];
+    let k = 2;
+    let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
+    let data =
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
+            .unwrap();
+    let mut events = Vec::new();
+    let result = solve_with_progress(
+        &data,
+        k as usize,
+        &SolverOptions::default(),
+        None,
+        |event| events.push(event),
+    )
+    .unwrap();
+    assert!(!events.is_empty());
+    // without a timeout, every event is an incumbent of the history
+    assert_eq!(
+        events,
+        result
+            .history
+            .into_iter()
+            .map(ProgressEvent::Incumbent)
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,34 @@
+use portfolio_solver::{
+    csv_parser::Data, datastructures::*, solver::relaxation,
+};
+
+#[test]
+fn test_relaxation() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, //
+        2.0, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let options = SolverOptions::default();
+    let (fractional, bound) =
+        relaxation::solve_fractional(&data, 2, &options).unwrap();
+    let copies = fractional
+        .resource_assignments
+        .iter()
+        .map(|(_, copies)| copies)
+        .sum::<f64>();
+    assert!((copies - 2.0).abs() < 1e-6);
+    assert!((bound - 2.0).abs() < 1e-6);
+
+    let result =
+        relaxation::solve(&data, 2, &options, &Default::default()).unwrap();
+    assert!(result.rounding_loss >= -1e-6);
+    assert!(
+        (result.rounding_loss - (result.result.stats.objective - bound)).abs()
+            < 1e-6
+    );
+}
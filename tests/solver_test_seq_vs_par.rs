@@ -10,10 +10,10 @@ fn test_seq_vs_par() {
     let k = 8;
     let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
     let data =
-        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX)
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
             .unwrap();
     assert_eq!(
-        solve(&data, k as usize, Timeout::default(), None)
+        solve(&data, k as usize, &SolverOptions::default(), None)
             .unwrap()
             .final_portfolio,
         Portfolio {
@@ -22,14 +22,20 @@ fn test_seq_vs_par() {
                 (
                     Algorithm {
                         algorithm: "algo1".into(),
-                        num_threads: 1
+                        num_threads: 1,
+                        memory: None,
+                        cost: None,
+                        startup: None
                     },
                     4.0
                 ),
                 (
                     Algorithm {
                         algorithm: "algo7".into(),
-                        num_threads: 4
+                        num_threads: 4,
+                        memory: None,
+                        cost: None,
+                        startup: None
                     },
                     1.0
                 ),
@@ -10,26 +10,39 @@ fn test_simple_model() {
     let k = 2;
     let df = csv_parser::parse_normalized_csvs(&files, None, k).unwrap();
     let data =
-        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX)
+        csv_parser::Data::from_normalized_dataframe(df, k, std::f64::MAX, 0)
             .unwrap();
+    let result =
+        solve(&data, k as usize, &SolverOptions::default(), None).unwrap();
+    assert_eq!(result.stats.termination, Termination::Optimal);
+    assert_eq!(result.stats.num_incumbents, result.history.len());
+    assert!(result.stats.node_count.is_some());
+    assert!(
+        (result.stats.objective - result.stats.bound.unwrap()).abs() < 1e-6
+    );
+    assert!(result.stats.wall_time > 0.0);
     assert_eq!(
-        solve(&data, k as usize, Timeout::default(), None)
-            .unwrap()
-            .final_portfolio,
+        result.final_portfolio,
         Portfolio {
             name: "final_portfolio_opt".to_string(),
             resource_assignments: vec![
                 (
                     Algorithm {
                         algorithm: "algo1".into(),
-                        num_threads: 1
+                        num_threads: 1,
+                        memory: None,
+                        cost: None,
+                        startup: None
                     },
                     1.0
                 ),
                 (
                     Algorithm {
                         algorithm: "algo2".into(),
-                        num_threads: 1
+                        num_threads: 1,
+                        memory: None,
+                        cost: None,
+                        startup: None
                     },
                     1.0
                 ),
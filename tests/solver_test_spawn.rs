@@ -0,0 +1,28 @@
+use portfolio_solver::{
+    csv_parser::Data, datastructures::*, solver::spawn_solve,
+};
+use std::{sync::Arc, time::Duration};
+
+#[test]
+fn test_spawn_solve() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 1),
+    ];
+    let stats = [
+        1.0, 1.0, 2.0, 2.0, //
+        2.0, 2.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 2).unwrap();
+    let mut handle =
+        spawn_solve(Arc::new(data), 2, SolverOptions::default(), None);
+    while !handle.is_finished() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let incumbent = handle.poll_incumbent().cloned().unwrap();
+    let result = handle.join().unwrap();
+    assert_eq!(
+        incumbent.portfolio.resource_assignments,
+        result.final_portfolio.resource_assignments
+    );
+}
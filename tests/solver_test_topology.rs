@@ -0,0 +1,31 @@
+use portfolio_solver::{csv_parser::Data, datastructures::*, solver::solve};
+
+#[test]
+fn test_topology() {
+    let algorithms = [
+        Algorithm::new("algo1".into(), 1),
+        Algorithm::new("algo2".into(), 2),
+    ];
+    // two copies of algo2 are the best portfolio on 4 cores
+    let stats = [
+        2.0, 2.0, 2.0, 2.0, 1.5, 1.0, 1.0, 1.0, //
+        2.0, 2.0, 2.0, 2.0, 1.5, 1.0, 1.0, 1.0,
+    ];
+    let data = Data::new(&algorithms, &[1.0, 1.0], None, &stats, 4).unwrap();
+    let copies = |options: &SolverOptions| {
+        solve(&data, 4, options, None)
+            .unwrap()
+            .final_portfolio
+            .resource_assignments
+            .iter()
+            .map(|(_, copies)| *copies)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(copies(&SolverOptions::default()), vec![0.0, 2.0]);
+    // only one copy of algo2 fits on the nodes of three and one cores
+    let options = SolverOptions {
+        topology: Some("3,1".parse().unwrap()),
+        ..Default::default()
+    };
+    assert_eq!(copies(&options), vec![2.0, 1.0]);
+}
@@ -0,0 +1,107 @@
+use portfolio_solver::{csv_parser, solver::genetic, watch::Watcher};
+use std::{cell::Cell, fs, io::Write, path::PathBuf, rc::Rc, time::Duration};
+
+const HEADER: &str = "algorithm,num_threads,instance,quality,time,valid\n";
+
+fn append(path: &PathBuf, lines: &str) {
+    fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .write_all(lines.as_bytes())
+        .unwrap();
+}
+
+/// Lines of a run of both algorithms on `instance`
+fn runs(instance: &str) -> String {
+    format!(
+        "algo1,1,{instance},2.0,1.0,true\nalgo2,1,{instance},1.0,1.0,true\n"
+    )
+}
+
+#[test]
+fn test_watch_tail() {
+    let path = std::env::temp_dir().join("portfolio_solver_watch_tail.csv");
+    fs::write(
+        &path,
+        format!(
+            "# machine: test\n{HEADER}{}{}algo1,1,gra",
+            runs("graph1"),
+            runs("graph2")
+        ),
+    )
+    .unwrap();
+    // count the rows the parser sees
+    let parsed = Rc::new(Cell::new(0));
+    let counter = parsed.clone();
+    let mut watcher = Watcher::with_parser(
+        vec![path.clone()],
+        move |paths| {
+            let df = csv_parser::parse_normalized_csvs(paths, None, 2)?;
+            counter.set(counter.get() + df.clone().collect()?.height());
+            Ok(df)
+        },
+        2,
+        f64::MAX,
+    );
+    assert!(watcher.poll_changes());
+    assert!(!watcher.poll_changes());
+    let data = watcher.data().unwrap();
+    assert_eq!(data.num_instances, 2);
+    assert_eq!(parsed.get(), 4);
+
+    // the partial line is completed, only the new lines are parsed
+    append(&path, "ph3,1.0,1.0,true\nalgo2,1,graph3,4.0,1.0,true\n");
+    assert!(watcher.poll_changes());
+    let data = watcher.data().unwrap();
+    assert_eq!(data.num_instances, 3);
+    assert_eq!(data.num_algorithms, 2);
+    assert_eq!(parsed.get(), 6);
+
+    // a truncated file is read again from the start
+    fs::write(
+        &path,
+        format!("{HEADER}{}{}", runs("graph4"), runs("graph5")),
+    )
+    .unwrap();
+    assert!(watcher.poll_changes());
+    let data = watcher.data().unwrap();
+    assert_eq!(data.num_instances, 2);
+    assert_eq!(parsed.get(), 10);
+}
+
+#[test]
+fn test_watch_failed_solve() {
+    let path = std::env::temp_dir().join("portfolio_solver_watch_run.csv");
+    fs::write(
+        &path,
+        format!("{HEADER}{}{}", runs("graph1"), runs("graph2")),
+    )
+    .unwrap();
+    let mut watcher =
+        Watcher::normalized(vec![path.clone()], None, 2, f64::MAX);
+    let mut solves = 0;
+    let mut updates = Vec::new();
+    watcher
+        .run_with(
+            Duration::from_millis(1),
+            |data, initial| {
+                solves += 1;
+                if solves == 1 {
+                    // the input grows while the first solve fails
+                    append(&path, &runs("graph3"));
+                    anyhow::bail!("The solver failed");
+                }
+                // the failed solve left no previous portfolio
+                assert!(initial.is_none());
+                genetic::solve(data, 2, &Default::default())
+            },
+            |data, _| {
+                updates.push(data.num_instances);
+                Ok(false)
+            },
+        )
+        .unwrap();
+    assert_eq!(solves, 2);
+    assert_eq!(updates, vec![3]);
+}